@@ -472,6 +472,10 @@ impl BuilderAPIProvider for ArtifactoryClient {
                     channel: &ChannelIdent,
                     token: Option<&str>)
                     -> Result<PackageIdent> {
+        if package.version_constraint.is_some() {
+            return Err(Error::NotSupported);
+        }
+
         let path = self.api_path_for_latest_package(package, target);
 
         let mut res = self.add_authz(self.0.get_with_custom_url(&path, |u| {
@@ -121,10 +121,10 @@ mod json {
 
     impl From<PackageIdent> for super::PackageIdent {
         fn from(ident: PackageIdent) -> Self {
-            super::PackageIdent { origin:  ident.origin,
-                                  name:    ident.name,
-                                  version: Some(ident.version),
-                                  release: Some(ident.release), }
+            super::PackageIdent::new(ident.origin,
+                                     ident.name,
+                                     Some(ident.version),
+                                     Some(ident.release))
         }
     }
 }
@@ -248,6 +248,52 @@ impl BuilderAPIClient {
         }
     }
 
+    /// Returns the latest release in `channel` whose version satisfies the version constraint of
+    /// `package`, by walking every release of the package in that channel.
+    fn show_package_with_constraint(&self,
+                                    (package, target): (&PackageIdent, PackageTarget),
+                                    channel: &ChannelIdent,
+                                    token: Option<&str>)
+                                    -> Result<PackageIdent> {
+        let url = channel_package_path(channel, package);
+        let mut packages: Vec<PackageIdent> = Vec::new();
+        loop {
+            let range = packages.len();
+            let req = self.0.get_with_custom_url(&url, |u| {
+                                u.set_query(Some(&format!("target={}&range={}", target, range)))
+                            });
+            let mut res = self.maybe_add_authz(req, token).send()?;
+            let more_to_come = match res.status {
+                StatusCode::Ok => false,
+                StatusCode::PartialContent => true,
+                _ => return Err(err_from_response(res)),
+            };
+            let mut encoded = String::new();
+            res.read_to_string(&mut encoded)
+               .map_err(Error::BadResponseBody)?;
+            debug!("Body: {:?}", encoded);
+            let mut package_results: PackageResults<PackageIdent> =
+                serde_json::from_str(&encoded)?;
+            if package_results.data.is_empty() {
+                break;
+            }
+            packages.append(&mut package_results.data);
+            if !more_to_come {
+                break;
+            }
+        }
+
+        packages.into_iter()
+                .filter(|p| p.fully_qualified() && p.satisfies(package))
+                .max()
+                .ok_or_else(|| {
+                    Error::APIError(StatusCode::NotFound,
+                                    format!("No release of {} in the '{}' channel satisfies \
+                                             the version constraint",
+                                            package, channel))
+                })
+    }
+
     fn search_package_impl(&self,
                            search_term: &str,
                            limit: usize,
@@ -833,7 +879,8 @@ impl BuilderAPIProvider for BuilderAPIClient {
     /// Returns a package struct for the latest package.
     ///
     /// An optional version can be specified which will scope the release returned to the latest
-    /// release of that package.
+    /// release of that package. If the package identifier carries a version constraint, the
+    /// latest release satisfying it is returned instead.
     ///
     /// # Failures
     ///
@@ -844,6 +891,10 @@ impl BuilderAPIProvider for BuilderAPIClient {
                     channel: &ChannelIdent,
                     token: Option<&str>)
                     -> Result<PackageIdent> {
+        if package.version_constraint.is_some() {
+            return self.show_package_with_constraint((package, target), channel, token);
+        }
+
         let mut url = channel_package_path(channel, package);
 
         if !package.fully_qualified() {
@@ -1247,10 +1298,7 @@ mod tests {
     }
 
     fn get_test_ident(name: &str) -> PackageIdent {
-        PackageIdent { origin:  String::from("test"),
-                       name:    String::from(name),
-                       version: None,
-                       release: None, }
+        PackageIdent::new("test", name, None, None)
     }
 
     fn seach_generator<'a>(
@@ -29,6 +29,7 @@ pub enum Error {
     PackageReadError(PathBuf, io::Error),
     ParseIntError(num::ParseIntError),
     IdentNotFullyQualified,
+    InvalidFileUrl(String),
     UploadFailed(String),
     UrlParseError(url::ParseError),
     WriteSyncFailed,
@@ -66,6 +67,9 @@ impl fmt::Display for Error {
                  identifier (ex: core/busybox-static/1.42.2/20170513215502)"
                                                                             .to_string()
             }
+            Error::InvalidFileUrl(ref u) => {
+                format!("Invalid depot URL, {}: a file URL must be an absolute path", u)
+            }
             Error::UploadFailed(ref s) => format!("Upload failed: {}", s),
             Error::UrlParseError(ref e) => format!("{}", e),
             Error::WriteSyncFailed => {
@@ -98,6 +102,7 @@ impl error::Error for Error {
                 "Cannot perform the specified operation. Specify a fully qualifed package \
                  identifier (ex: core/busybox-static/1.42.2/20170513215502)"
             }
+            Error::InvalidFileUrl(_) => "Invalid depot URL, a file URL must be an absolute path",
             Error::UploadFailed(_) => "Upload failed",
             Error::UrlParseError(ref err) => err.description(),
             Error::WriteSyncFailed => {
@@ -0,0 +1,489 @@
+//! A depot in a directory on the local file system, used in place of Builder when the Builder URL
+//! is a `file://` URL such as `file:///srv/hab-depot`. This lets disconnected sites install and
+//! update packages from HARTs which are copied into a directory.
+//!
+//! Each subdirectory of the depot is a channel holding the HARTs promoted to it. HARTs directly in
+//! the depot directory are in the `stable` channel. A HART is only indexed once its signature has
+//! been verified against the local key cache, so the public key of each origin has to be imported
+//! with `hab origin key import` before its packages can be installed from the depot.
+
+use broadcast::BroadcastWriter;
+use hyper::{status::StatusCode,
+            Url};
+use std::{collections::HashMap,
+          ffi::OsStr,
+          fs::{self,
+               File},
+          io,
+          path::{Path,
+                 PathBuf},
+          sync::Mutex,
+          time::SystemTime};
+
+use crate::{error::{Error,
+                    Result},
+            hab_core::{crypto::keys::box_key_pair::WrappedSealedBox,
+                       fs::{cache_key_path,
+                            AtomicWriter},
+                       package::{Identifiable,
+                                 PackageArchive,
+                                 PackageIdent,
+                                 PackageTarget},
+                       ChannelIdent},
+            BoxedClient,
+            BuilderAPIProvider,
+            DisplayProgress,
+            OriginKeyIdent,
+            SchedulerResponse};
+
+lazy_static! {
+    /// The HARTs which were indexed so far, keyed by path. Indexing a HART means reading its
+    /// metadata and verifying its signature, so a HART is only indexed again once it changes.
+    static ref INDEX: Mutex<HashMap<PathBuf, Artifact>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Clone, Debug)]
+struct Artifact {
+    ident:    PackageIdent,
+    target:   PackageTarget,
+    path:     PathBuf,
+    size:     u64,
+    modified: Option<SystemTime>,
+}
+
+pub struct FileSystemClient {
+    depot_path:     PathBuf,
+    key_cache_path: PathBuf,
+}
+
+impl FileSystemClient {
+    pub fn new(endpoint: &Url, fs_root_path: Option<&Path>) -> Result<Self> {
+        let depot_path = endpoint.to_file_path()
+                                 .map_err(|_| Error::InvalidFileUrl(endpoint.to_string()))?;
+        debug!("FileSystemClient::new, depot_path = {:?}", depot_path);
+        Ok(FileSystemClient { depot_path,
+                              key_cache_path: cache_key_path(fs_root_path) })
+    }
+
+    pub fn create(endpoint: &Url, fs_root_path: Option<&Path>) -> Result<BoxedClient> {
+        Self::new(endpoint, fs_root_path).map(|c| Box::new(c) as _)
+    }
+
+    /// The channels of the depot, which are its subdirectories and the `stable` channel.
+    fn channels(&self) -> Result<Vec<ChannelIdent>> {
+        let mut channels = vec![ChannelIdent::stable()];
+        for entry in fs::read_dir(&self.depot_path)? {
+            let path = entry?.path();
+            if !path.is_dir() {
+                continue;
+            }
+            if let Some(name) = path.file_name().and_then(OsStr::to_str) {
+                let channel = ChannelIdent::from(name);
+                if !channels.contains(&channel) {
+                    channels.push(channel);
+                }
+            }
+        }
+        Ok(channels)
+    }
+
+    /// The directories holding the HARTs of the channel.
+    fn channel_paths(&self, channel: &ChannelIdent) -> Vec<PathBuf> {
+        let mut paths = vec![self.depot_path.join(channel.as_str())];
+        if *channel == ChannelIdent::stable() {
+            paths.push(self.depot_path.clone());
+        }
+        paths
+    }
+
+    /// The verified HARTs of the channel.
+    fn artifacts(&self, channel: &ChannelIdent) -> Result<Vec<Artifact>> {
+        let mut artifacts = Vec::new();
+        for path in self.channel_paths(channel) {
+            if path.is_dir() {
+                artifacts.extend(self.index(&path)?);
+            }
+        }
+        Ok(artifacts)
+    }
+
+    /// The verified HART of a fully qualified package in any channel.
+    fn artifact(&self, (ident, target): (&PackageIdent, PackageTarget)) -> Result<Artifact> {
+        for channel in self.channels()? {
+            if let Some(artifact) = self.artifacts(&channel)?
+                                        .into_iter()
+                                        .find(|a| &a.ident == ident && a.target == target)
+            {
+                return Ok(artifact);
+            }
+        }
+        Err(self.not_found(ident))
+    }
+
+    /// Indexes the HARTs in a directory. HARTs which cannot be read or verified are skipped.
+    fn index(&self, dir: &Path) -> Result<Vec<Artifact>> {
+        let mut index = INDEX.lock().expect("Depot index lock poisoned");
+        let mut artifacts = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(OsStr::to_str) != Some("hart") {
+                continue;
+            }
+            let metadata = fs::metadata(&path)?;
+            let size = metadata.len();
+            let modified = metadata.modified().ok();
+            match index.get(&path) {
+                Some(artifact) if artifact.size == size && artifact.modified == modified => {
+                    artifacts.push(artifact.clone());
+                    continue;
+                }
+                _ => {}
+            }
+            match self.read_artifact(&path, size, modified) {
+                Ok(artifact) => {
+                    index.insert(path, artifact.clone());
+                    artifacts.push(artifact);
+                }
+                Err(err) => warn!("Skipping {}, {}", path.display(), err),
+            }
+        }
+        Ok(artifacts)
+    }
+
+    fn read_artifact(&self,
+                     path: &Path,
+                     size: u64,
+                     modified: Option<SystemTime>)
+                     -> Result<Artifact> {
+        let mut archive = PackageArchive::new(path);
+        archive.verify(&self.key_cache_path)?;
+        Ok(Artifact { ident: archive.ident()?,
+                      target: archive.target()?,
+                      path: path.to_path_buf(),
+                      size,
+                      modified })
+    }
+
+    fn not_found(&self, ident: &PackageIdent) -> Error {
+        Error::APIError(StatusCode::NotFound,
+                        format!("No verified artifact for {} in {}",
+                                ident,
+                                self.depot_path.display()))
+    }
+}
+
+impl BuilderAPIProvider for FileSystemClient {
+    type Progress = Box<dyn DisplayProgress>;
+
+    fn fetch_package(&self,
+                     (ident, target): (&PackageIdent, PackageTarget),
+                     _token: Option<&str>,
+                     dst_path: &Path,
+                     progress: Option<Self::Progress>)
+                     -> Result<PackageArchive> {
+        if !ident.fully_qualified() {
+            return Err(Error::IdentNotFullyQualified);
+        }
+
+        let artifact = self.artifact((ident, target))?;
+        let path = &artifact.path;
+        let mut src = File::open(path).map_err(|e| Error::PackageReadError(path.clone(), e))?;
+
+        fs::create_dir_all(&dst_path)?;
+        let dst_file_path = dst_path.join(ident.archive_name_with_target(target)?);
+        let w = AtomicWriter::new(&dst_file_path)?;
+        w.with_writer(|mut f| {
+             match progress {
+                 Some(mut progress) => {
+                     progress.size(artifact.size);
+                     let mut writer = BroadcastWriter::new(&mut f, progress);
+                     io::copy(&mut src, &mut writer)
+                 }
+                 None => io::copy(&mut src, &mut f),
+             }
+         })
+         .map_err(|e| Error::DownloadWrite(dst_file_path.clone(), e))?;
+        Ok(PackageArchive::new(dst_file_path))
+    }
+
+    fn check_package(&self,
+                     (package, target): (&PackageIdent, PackageTarget),
+                     _token: Option<&str>)
+                     -> Result<()> {
+        if !package.fully_qualified() {
+            return Err(Error::IdentNotFullyQualified);
+        }
+
+        self.artifact((package, target)).map(|_| ())
+    }
+
+    fn show_package(&self,
+                    (package, target): (&PackageIdent, PackageTarget),
+                    channel: &ChannelIdent,
+                    _token: Option<&str>)
+                    -> Result<PackageIdent> {
+        self.artifacts(channel)?
+            .into_iter()
+            .filter(|a| a.target == target && a.ident.satisfies(package))
+            .map(|a| a.ident)
+            .max()
+            .ok_or_else(|| self.not_found(package))
+    }
+
+    fn package_channels(&self,
+                        (ident, target): (&PackageIdent, PackageTarget),
+                        _token: Option<&str>)
+                        -> Result<Vec<String>> {
+        if !ident.fully_qualified() {
+            return Err(Error::IdentNotFullyQualified);
+        }
+
+        let mut channels = Vec::new();
+        for channel in self.channels()? {
+            if self.artifacts(&channel)?
+                   .iter()
+                   .any(|a| &a.ident == ident && a.target == target)
+            {
+                channels.push(channel.to_string());
+            }
+        }
+        Ok(channels)
+    }
+
+    fn list_channels(&self, _origin: &str, _include_sandbox_channels: bool) -> Result<Vec<String>> {
+        Ok(self.channels()?.iter().map(ToString::to_string).collect())
+    }
+
+    fn fetch_origin_key(&self,
+                        origin: &str,
+                        revision: &str,
+                        _token: Option<&str>,
+                        _dst_path: &Path,
+                        _progress: Option<Self::Progress>)
+                        -> Result<PathBuf> {
+        // Artifacts are verified against the local key cache, so keys are never taken from the
+        // depot.
+        Err(Error::APIError(StatusCode::NotFound,
+                            format!("Origin key {}-{} is not in the local key cache, import it \
+                                     with `hab origin key import`",
+                                    origin, revision)))
+    }
+
+    // Not supported functions - return error
+
+    fn show_origin_keys(&self, _origin: &str) -> Result<Vec<OriginKeyIdent>> {
+        Err(Error::NotSupported)
+    }
+
+    fn put_origin_key(&self,
+                      _origin: &str,
+                      _revision: &str,
+                      _src_path: &Path,
+                      _token: &str,
+                      _progress: Option<Self::Progress>)
+                      -> Result<()> {
+        Err(Error::NotSupported)
+    }
+
+    fn put_origin_secret_key(&self,
+                             _origin: &str,
+                             _revision: &str,
+                             _src_path: &Path,
+                             _token: &str,
+                             _progress: Option<Self::Progress>)
+                             -> Result<()> {
+        Err(Error::NotSupported)
+    }
+
+    fn fetch_secret_origin_key(&self,
+                               _origin: &str,
+                               _token: &str,
+                               _dst_path: &Path,
+                               _progress: Option<Self::Progress>)
+                               -> Result<PathBuf> {
+        Err(Error::NotSupported)
+    }
+
+    fn put_package(&self,
+                   _pa: &mut PackageArchive,
+                   _token: &str,
+                   _force_upload: bool,
+                   _progress: Option<Self::Progress>)
+                   -> Result<()> {
+        Err(Error::NotSupported)
+    }
+
+    fn x_put_package(&self, _pa: &mut PackageArchive, _token: &str) -> Result<()> {
+        Err(Error::NotSupported)
+    }
+
+    fn delete_package(&self,
+                      (_ident, _target): (&PackageIdent, PackageTarget),
+                      _token: &str)
+                      -> Result<()> {
+        Err(Error::NotSupported)
+    }
+
+    fn promote_package(&self,
+                       (_ident, _target): (&PackageIdent, PackageTarget),
+                       _channel: &ChannelIdent,
+                       _token: &str)
+                       -> Result<()> {
+        Err(Error::NotSupported)
+    }
+
+    fn demote_package(&self,
+                      (_ident, _target): (&PackageIdent, PackageTarget),
+                      _channel: &ChannelIdent,
+                      _token: &str)
+                      -> Result<()> {
+        Err(Error::NotSupported)
+    }
+
+    fn search_package(&self,
+                      _search_term: &str,
+                      _limit: usize,
+                      _token: Option<&str>)
+                      -> Result<(Vec<PackageIdent>, usize)> {
+        Err(Error::NotSupported)
+    }
+
+    fn create_channel(&self, _origin: &str, _channel: &ChannelIdent, _token: &str) -> Result<()> {
+        Err(Error::NotSupported)
+    }
+
+    fn delete_channel(&self, _origin: &str, _channel: &ChannelIdent, _token: &str) -> Result<()> {
+        Err(Error::NotSupported)
+    }
+
+    fn get_origin_schedule(&self, _origin: &str, _limit: usize) -> Result<Vec<SchedulerResponse>> {
+        Err(Error::NotSupported)
+    }
+
+    fn get_schedule(&self, _group_id: i64, _include_projects: bool) -> Result<SchedulerResponse> {
+        Err(Error::NotSupported)
+    }
+
+    fn schedule_job(&self,
+                    (_ident, _target): (&PackageIdent, PackageTarget),
+                    _package_only: bool,
+                    _token: &str)
+                    -> Result<(String)> {
+        Err(Error::NotSupported)
+    }
+
+    fn fetch_rdeps(&self,
+                   (_ident, _target): (&PackageIdent, PackageTarget))
+                   -> Result<Vec<String>> {
+        Err(Error::NotSupported)
+    }
+
+    fn job_group_promote_or_demote(&self,
+                                   _group_id: u64,
+                                   _idents: &[String],
+                                   _channel: &ChannelIdent,
+                                   _token: &str,
+                                   _promote: bool)
+                                   -> Result<()> {
+        Err(Error::NotSupported)
+    }
+
+    fn job_group_cancel(&self, _group_id: u64, _token: &str) -> Result<()> {
+        Err(Error::NotSupported)
+    }
+
+    fn fetch_origin_public_encryption_key(&self,
+                                          _origin: &str,
+                                          _token: &str,
+                                          _dst_path: &Path,
+                                          _progress: Option<Self::Progress>)
+                                          -> Result<PathBuf> {
+        Err(Error::NotSupported)
+    }
+
+    fn create_origin(&self, _origin: &str, _token: &str) -> Result<()> { Err(Error::NotSupported) }
+
+    fn create_origin_secret(&self,
+                            _origin: &str,
+                            _token: &str,
+                            _key: &str,
+                            _secret: &WrappedSealedBox)
+                            -> Result<()> {
+        Err(Error::NotSupported)
+    }
+
+    fn delete_origin_secret(&self, _origin: &str, _token: &str, _key: &str) -> Result<()> {
+        Err(Error::NotSupported)
+    }
+
+    fn delete_origin(&self, _origin: &str, _token: &str) -> Result<()> { Err(Error::NotSupported) }
+
+    fn list_origin_secrets(&self, _origin: &str, _token: &str) -> Result<Vec<String>> {
+        Err(Error::NotSupported)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use tempfile::Builder;
+
+    const HART: &str = "happyhumans-possums-8.1.4-20160427165340-x86_64-linux.hart";
+    const UNVERIFIED_HART: &str = "unhappyhumans-possums-8.1.4-20160427165340-x86_64-linux.hart";
+
+    fn fixture(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../core/tests/fixtures")
+                                                 .join(name)
+    }
+
+    fn client(fs_root: &Path, depot: &Path) -> FileSystemClient {
+        let key_cache_path = cache_key_path(Some(fs_root));
+        fs::create_dir_all(&key_cache_path).unwrap();
+        fs::copy(fixture("happyhumans-20160424223347.pub"),
+                 key_cache_path.join("happyhumans-20160424223347.pub")).unwrap();
+        let endpoint = Url::from_file_path(depot).unwrap();
+        FileSystemClient::new(&endpoint, Some(fs_root)).unwrap()
+    }
+
+    #[test]
+    fn show_package_finds_the_verified_artifacts_of_a_channel() {
+        let fs_root = Builder::new().prefix("fs_root").tempdir().unwrap();
+        let depot = Builder::new().prefix("depot").tempdir().unwrap();
+        fs::create_dir(depot.path().join("unstable")).unwrap();
+        fs::copy(fixture(HART), depot.path().join(HART)).unwrap();
+        fs::copy(fixture(UNVERIFIED_HART), depot.path().join("unstable").join(UNVERIFIED_HART))
+            .unwrap();
+        let client = client(fs_root.path(), depot.path());
+        let target = PackageTarget::from_str("x86_64-linux").unwrap();
+
+        let ident = PackageIdent::from_str("happyhumans/possums").unwrap();
+        assert_eq!(client.show_package((&ident, target), &ChannelIdent::stable(), None)
+                         .unwrap(),
+                   PackageIdent::from_str("happyhumans/possums/8.1.4/20160427165340").unwrap());
+        assert!(client.show_package((&ident, target), &ChannelIdent::unstable(), None)
+                      .is_err());
+
+        let ident = PackageIdent::from_str("unhappyhumans/possums").unwrap();
+        assert!(client.show_package((&ident, target), &ChannelIdent::unstable(), None)
+                      .is_err());
+    }
+
+    #[test]
+    fn fetch_package_copies_the_artifact() {
+        let fs_root = Builder::new().prefix("fs_root").tempdir().unwrap();
+        let depot = Builder::new().prefix("depot").tempdir().unwrap();
+        let cache = Builder::new().prefix("cache").tempdir().unwrap();
+        fs::create_dir(depot.path().join("unstable")).unwrap();
+        fs::copy(fixture(HART), depot.path().join("unstable").join(HART)).unwrap();
+        let client = client(fs_root.path(), depot.path());
+        let target = PackageTarget::from_str("x86_64-linux").unwrap();
+
+        let ident = PackageIdent::from_str("happyhumans/possums/8.1.4/20160427165340").unwrap();
+        let mut archive = client.fetch_package((&ident, target), None, cache.path(), None)
+                                .unwrap();
+        assert_eq!(archive.ident().unwrap(), ident);
+        assert_eq!(client.package_channels((&ident, target), None).unwrap(),
+                   vec!["unstable".to_string()]);
+    }
+}
@@ -3,6 +3,9 @@ use habitat_http_client as hab_http;
 #[macro_use]
 extern crate hyper;
 
+#[macro_use]
+extern crate lazy_static;
+
 #[macro_use]
 extern crate log;
 
@@ -15,6 +18,7 @@ extern crate serde_json;
 pub mod artifactory;
 pub mod builder;
 pub mod error;
+pub mod filesystem;
 
 use std::str::FromStr;
 
@@ -34,6 +38,7 @@ pub use crate::error::{Error,
 
 use crate::{artifactory::ArtifactoryClient,
             builder::BuilderAPIClient,
+            filesystem::FileSystemClient,
             hab_core::{crypto::keys::box_key_pair::WrappedSealedBox,
                        env,
                        package::{PackageArchive,
@@ -183,10 +188,10 @@ mod json {
 
     impl From<PackageIdent> for super::PackageIdent {
         fn from(ident: PackageIdent) -> Self {
-            super::PackageIdent { origin:  ident.origin,
-                                  name:    ident.name,
-                                  version: Some(ident.version),
-                                  release: Some(ident.release), }
+            super::PackageIdent::new(ident.origin,
+                                     ident.name,
+                                     Some(ident.version),
+                                     Some(ident.release))
         }
     }
 }
@@ -400,6 +405,11 @@ impl Client {
     {
         let endpoint = endpoint.into_url().map_err(Error::UrlParseError)?;
 
+        // A `file://` URL points to a depot in a local directory
+        if endpoint.scheme() == "file" {
+            return FileSystemClient::create(&endpoint, fs_root_path);
+        }
+
         match &env::var("HAB_BLDR_PROVIDER").unwrap_or_else(|_| "builder".to_string())[..] {
             "artifactory" => ArtifactoryClient::create(endpoint, product, version, fs_root_path),
             _ => BuilderAPIClient::create(endpoint, product, version, fs_root_path),
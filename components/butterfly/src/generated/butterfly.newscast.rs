@@ -13,6 +13,9 @@ pub struct Election {
     pub status: ::std::option::Option<i32>,
     #[prost(string, repeated, tag="6")]
     pub votes: ::std::vec::Vec<std::string::String>,
+    /// Only used in update elections.
+    #[prost(message, optional, tag="7")]
+    pub canary: ::std::option::Option<CanaryRollout>,
 }
 pub mod election {
     #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
@@ -24,6 +27,31 @@ pub mod election {
         Finished = 3,
     }
 }
+/// The progress of a canary update of a service group, published by the winner of its update
+/// election.
+#[derive(Clone, PartialEq, ::prost::Message)]
+#[derive(Serialize, Deserialize)]
+pub struct CanaryRollout {
+    #[prost(string, optional, tag="1")]
+    pub pkg: ::std::option::Option<std::string::String>,
+    #[prost(enumeration="canary_rollout::Stage", optional, tag="2")]
+    pub stage: ::std::option::Option<i32>,
+    /// The members which are updated first.
+    #[prost(string, repeated, tag="3")]
+    pub members: ::std::vec::Vec<std::string::String>,
+    #[prost(uint64, optional, tag="4")]
+    pub incarnation: ::std::option::Option<u64>,
+}
+pub mod canary_rollout {
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+    #[repr(i32)]
+    #[derive(Serialize, Deserialize)]
+    pub enum Stage {
+        Baking = 1,
+        Promoted = 2,
+        Halted = 3,
+    }
+}
 #[derive(Clone, PartialEq, ::prost::Message)]
 #[derive(Serialize, Deserialize)]
 pub struct Service {
@@ -41,6 +69,24 @@ pub struct Service {
     pub cfg: ::std::option::Option<std::vec::Vec<u8>>,
     #[prost(message, optional, tag="12")]
     pub sys: ::std::option::Option<SysInfo>,
+    #[prost(enumeration="service::Health", optional, tag="13")]
+    pub health: ::std::option::Option<i32>,
+    /// Releases of the package which the service group must not run, because updating to them was
+    /// rolled back.
+    #[prost(string, repeated, tag="14")]
+    pub blocked_releases: ::std::vec::Vec<std::string::String>,
+}
+pub mod service {
+    /// The result of the latest health check of the service.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+    #[repr(i32)]
+    #[derive(Serialize, Deserialize)]
+    pub enum Health {
+        Ok = 1,
+        Warning = 2,
+        Critical = 3,
+        Unknown = 4,
+    }
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 #[derive(Serialize, Deserialize)]
@@ -47,7 +47,8 @@ impl From<CElection> for Rumor {
                                  term:          Some(value.term),
                                  suitability:   Some(value.suitability),
                                  status:        Some(value.status as i32),
-                                 votes:         value.votes.clone(), };
+                                 votes:         value.votes.clone(),
+                                 canary:        value.canary.clone().map(Into::into), };
         Rumor { r#type:  RumorType::Election as i32,
                 tag:     Vec::default(),
                 from_id: Some(value.member_id),
@@ -62,7 +63,8 @@ impl From<CElectionUpdate> for Rumor {
                                  term:          Some(value.term),
                                  suitability:   Some(value.suitability),
                                  status:        Some(value.status as i32),
-                                 votes:         value.votes.clone(), };
+                                 votes:         value.votes.clone(),
+                                 canary:        value.canary.clone().map(Into::into), };
         Rumor { r#type:  RumorType::ElectionUpdate as i32,
                 tag:     Vec::default(),
                 from_id: Some(value.member_id.clone()),
@@ -72,13 +74,15 @@ impl From<CElectionUpdate> for Rumor {
 
 impl From<CService> for Rumor {
     fn from(value: CService) -> Self {
-        let payload = Service { member_id:     Some(value.member_id.clone()),
-                                service_group: Some(value.service_group.to_string()),
-                                incarnation:   Some(value.incarnation),
-                                initialized:   Some(value.initialized),
-                                pkg:           Some(value.pkg),
-                                cfg:           Some(value.cfg),
-                                sys:           Some(value.sys.into()), };
+        let payload = Service { member_id:        Some(value.member_id.clone()),
+                                service_group:    Some(value.service_group.to_string()),
+                                incarnation:      Some(value.incarnation),
+                                initialized:      Some(value.initialized),
+                                pkg:              Some(value.pkg),
+                                cfg:              Some(value.cfg),
+                                sys:              Some(value.sys.into()),
+                                health:           Some(value.health as i32),
+                                blocked_releases: value.blocked_releases, };
         Rumor { r#type:  RumorType::Service as i32,
                 tag:     Vec::default(),
                 from_id: Some(value.member_id),
@@ -8,7 +8,9 @@
 //! devolve to a single, universal rumor, which when it is received by the winner will result in
 //! the election finishing. There can, in the end, be only one.
 
-pub use crate::protocol::newscast::{election::Status as ElectionStatus,
+pub use crate::protocol::newscast::{canary_rollout::Stage as CanaryStage,
+                                    election::Status as ElectionStatus,
+                                    CanaryRollout as ProtoCanaryRollout,
                                     Election as ProtoElection};
 use crate::{error::{Error,
                     Result},
@@ -33,6 +35,46 @@ pub trait ElectionRumor {
 
 pub type Term = u64;
 
+/// The progress of a canary update of a service group. Only the winner of the update election
+/// of the group publishes it, as part of its `ElectionUpdate` rumor.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CanaryRollout {
+    /// The release the service group is updated to.
+    pub pkg:         String,
+    pub stage:       CanaryStage,
+    /// The members which are updated first.
+    pub members:     Vec<String>,
+    /// Incremented with each change to the rollout, so the latest change wins.
+    pub incarnation: u64,
+}
+
+impl CanaryRollout {
+    pub fn new(pkg: String, stage: CanaryStage, members: Vec<String>) -> Self {
+        CanaryRollout { pkg,
+                        stage,
+                        members,
+                        incarnation: 0 }
+    }
+
+    fn from_proto(proto: ProtoCanaryRollout) -> Result<Self> {
+        Ok(CanaryRollout { pkg:         proto.pkg.ok_or(Error::ProtocolMismatch("pkg"))?,
+                           stage:       proto.stage
+                                             .and_then(CanaryStage::from_i32)
+                                             .ok_or(Error::ProtocolMismatch("stage"))?,
+                           members:     proto.members,
+                           incarnation: proto.incarnation.unwrap_or(0), })
+    }
+}
+
+impl From<CanaryRollout> for ProtoCanaryRollout {
+    fn from(value: CanaryRollout) -> Self {
+        ProtoCanaryRollout { pkg:         Some(value.pkg),
+                             stage:       Some(value.stage as i32),
+                             members:     value.members,
+                             incarnation: Some(value.incarnation), }
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct Election {
     pub member_id:     String,
@@ -41,6 +83,8 @@ pub struct Election {
     pub suitability:   u64,
     pub status:        ElectionStatus,
     pub votes:         Vec<String>,
+    /// The canary update the winner of an update election is running, if any.
+    pub canary:        Option<CanaryRollout>,
 }
 
 impl fmt::Display for Election {
@@ -72,7 +116,8 @@ impl Election {
                    } else {
                        ElectionStatus::NoQuorum
                    },
-                   votes: vec![from_id] }
+                   votes: vec![from_id],
+                   canary: None }
     }
 
     /// Insert a vote for the election.
@@ -97,6 +142,14 @@ impl Election {
 
     /// Sets the status of the election to "NoQuorum"
     pub fn no_quorum(&mut self) { self.status = ElectionStatus::NoQuorum; }
+
+    /// Replaces the canary rollout with `canary`, as a newer incarnation than the current one.
+    pub fn set_canary(&mut self, mut canary: CanaryRollout) {
+        canary.incarnation = self.canary_incarnation() + 1;
+        self.canary = Some(canary);
+    }
+
+    fn canary_incarnation(&self) -> u64 { self.canary.as_ref().map_or(0, |c| c.incarnation) }
 }
 
 impl ElectionRumor for Election {
@@ -116,6 +169,7 @@ impl PartialEq for Election {
         && self.votes == other.votes
         && self.status == other.status
         && self.term == other.term
+        && self.canary == other.canary
     }
 }
 
@@ -130,6 +184,10 @@ impl FromProto<ProtoRumor> for Election {
             _ => panic!("from-bytes election"),
         };
         let from_id = rumor.from_id.ok_or(Error::ProtocolMismatch("from-id"))?;
+        let canary = match payload.canary {
+            Some(canary) => Some(CanaryRollout::from_proto(canary)?),
+            None => None,
+        };
         Ok(Election { member_id:     from_id.clone(),
                       service_group: payload.service_group
                                             .ok_or(Error::ProtocolMismatch("service-group"))?,
@@ -138,7 +196,8 @@ impl FromProto<ProtoRumor> for Election {
                       status:        payload.status
                                             .and_then(ElectionStatus::from_i32)
                                             .unwrap_or(ElectionStatus::Running),
-                      votes:         payload.votes, })
+                      votes:         payload.votes,
+                      canary, })
     }
 }
 
@@ -149,7 +208,8 @@ impl From<Election> for newscast::Election {
                              term:          Some(value.term),
                              suitability:   Some(value.suitability),
                              status:        Some(value.status as i32),
-                             votes:         value.votes, }
+                             votes:         value.votes,
+                             canary:        value.canary.map(Into::into), }
     }
 }
 
@@ -161,6 +221,13 @@ impl Rumor for Election {
         if *self == other {
             debug!("stored and received rumors are equal; nothing to do");
             false
+        } else if other.term == self.term
+                  && self.status == ElectionStatus::Finished
+                  && other.status == ElectionStatus::Finished
+                  && self.canary_incarnation() > other.canary_incarnation()
+        {
+            debug!("stored rumor is finished and has a newer canary rollout; keep sharing it");
+            true
         } else if other.term >= self.term && other.status == ElectionStatus::Finished {
             debug!("received is finished and represents a newer term; replace stored and share");
             *self = other;
@@ -280,7 +347,9 @@ impl Rumor for ElectionUpdate {
 
 #[cfg(test)]
 mod tests {
-    use crate::rumor::{election::{Election,
+    use crate::rumor::{election::{CanaryRollout,
+                                  CanaryStage,
+                                  Election,
                                   ElectionUpdate,
                                   Term},
                        Rumor,
@@ -371,4 +440,28 @@ mod tests {
         assert_eq!(e1.member_id, "d");
         assert_eq!(e1.votes.len(), 4);
     }
+
+    #[test]
+    fn merge_keeps_the_newest_canary_rollout() {
+        let mut stored = create_election_update("a", 0);
+        stored.finish();
+        let mut received = stored.clone();
+        received.set_canary(CanaryRollout::new("core/redis/4.0.14/20190319155852".to_string(),
+                                               CanaryStage::Baking,
+                                               vec!["a".to_string()]));
+        assert_eq!(stored.merge(received.clone()), true);
+        assert_eq!(stored.canary, received.canary);
+
+        let mut stale = create_election_update("a", 0);
+        stale.finish();
+        assert_eq!(stored.merge(stale), true);
+        assert_eq!(stored.canary.as_ref().unwrap().incarnation, 1);
+
+        received.set_canary(CanaryRollout::new("core/redis/4.0.14/20190319155852".to_string(),
+                                               CanaryStage::Promoted,
+                                               vec!["a".to_string()]));
+        assert_eq!(stored.merge(received), true);
+        assert_eq!(stored.canary.as_ref().unwrap().stage, CanaryStage::Promoted);
+        assert_eq!(stored.canary.as_ref().unwrap().incarnation, 2);
+    }
 }
@@ -2,6 +2,8 @@
 //!
 //! Service rumors declare that a given `Server` is running this Service.
 
+pub use crate::protocol::newscast::service::Health as ServiceHealth;
+
 use crate::{error::{Error,
                     Result},
             protocol::{self,
@@ -24,13 +26,19 @@ use toml;
 
 #[derive(Debug, Clone)]
 pub struct Service {
-    pub member_id:     String,
-    pub service_group: ServiceGroup,
-    pub incarnation:   u64,
-    pub initialized:   bool,
-    pub pkg:           String,
-    pub cfg:           Vec<u8>,
-    pub sys:           SysInfo,
+    pub member_id:        String,
+    pub service_group:    ServiceGroup,
+    pub incarnation:      u64,
+    pub initialized:      bool,
+    pub pkg:              String,
+    pub cfg:              Vec<u8>,
+    pub sys:              SysInfo,
+    /// The result of the latest health check of the service. Unknown for services gossiped by
+    /// Supervisors which predate health in the Service rumor.
+    pub health:           ServiceHealth,
+    /// Releases of the package which the service group must not run, because updating to them
+    /// was rolled back.
+    pub blocked_releases: Vec<String>,
 }
 
 impl fmt::Display for Service {
@@ -46,7 +54,7 @@ impl Serialize for Service {
     fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
         where S: Serializer
     {
-        let mut strukt = serializer.serialize_struct("service", 7)?;
+        let mut strukt = serializer.serialize_struct("service", 9)?;
         let cfg: toml::value::Table = toml::from_slice(&self.cfg).unwrap_or_default();
         strukt.serialize_field("member_id", &self.member_id)?;
         strukt.serialize_field("service_group", &self.service_group)?;
@@ -55,6 +63,8 @@ impl Serialize for Service {
         strukt.serialize_field("cfg", &cfg)?;
         strukt.serialize_field("sys", &self.sys)?;
         strukt.serialize_field("initialized", &self.initialized)?;
+        strukt.serialize_field("health", &self.health)?;
+        strukt.serialize_field("blocked_releases", &self.blocked_releases)?;
         strukt.end()
     }
 }
@@ -100,6 +110,8 @@ impl Service {
                   initialized: false,
                   pkg: package.to_string(),
                   sys,
+                  health: ServiceHealth::Unknown,
+                  blocked_releases: Vec::new(),
                   cfg: cfg.map(|v| {
                               // Directly serializing a toml::value::Table can lead to an error
                               // Wrapping it in a toml::value::Value makes this operation safe
@@ -121,31 +133,37 @@ impl FromProto<newscast::Rumor> for Service {
             RumorPayload::Service(payload) => payload,
             _ => panic!("from-bytes service"),
         };
-        Ok(Service { member_id:     payload.member_id
-                                           .ok_or(Error::ProtocolMismatch("member-id"))?,
+        Ok(Service { member_id:        payload.member_id
+                                              .ok_or(Error::ProtocolMismatch("member-id"))?,
                      service_group:
                          payload.service_group
                                 .ok_or(Error::ProtocolMismatch("service-group"))
                                 .and_then(|s| ServiceGroup::from_str(&s).map_err(Error::from))?,
-                     incarnation:   payload.incarnation.unwrap_or(0),
-                     initialized:   payload.initialized.unwrap_or(false),
-                     pkg:           payload.pkg.ok_or(Error::ProtocolMismatch("pkg"))?,
-                     cfg:           payload.cfg.unwrap_or_default(),
-                     sys:           payload.sys
-                                           .ok_or(Error::ProtocolMismatch("sys"))
-                                           .and_then(SysInfo::from_proto)?, })
+                     incarnation:      payload.incarnation.unwrap_or(0),
+                     initialized:      payload.initialized.unwrap_or(false),
+                     pkg:              payload.pkg.ok_or(Error::ProtocolMismatch("pkg"))?,
+                     cfg:              payload.cfg.unwrap_or_default(),
+                     sys:              payload.sys
+                                              .ok_or(Error::ProtocolMismatch("sys"))
+                                              .and_then(SysInfo::from_proto)?,
+                     health:           payload.health
+                                              .and_then(ServiceHealth::from_i32)
+                                              .unwrap_or(ServiceHealth::Unknown),
+                     blocked_releases: payload.blocked_releases, })
     }
 }
 
 impl From<Service> for newscast::Service {
     fn from(value: Service) -> Self {
-        newscast::Service { member_id:     Some(value.member_id),
-                            service_group: Some(value.service_group.to_string()),
-                            incarnation:   Some(value.incarnation),
-                            initialized:   Some(value.initialized),
-                            pkg:           Some(value.pkg),
-                            cfg:           Some(value.cfg),
-                            sys:           Some(value.sys.into()), }
+        newscast::Service { member_id:        Some(value.member_id),
+                            service_group:    Some(value.service_group.to_string()),
+                            incarnation:      Some(value.incarnation),
+                            initialized:      Some(value.initialized),
+                            pkg:              Some(value.pkg),
+                            cfg:              Some(value.cfg),
+                            sys:              Some(value.sys.into()),
+                            health:           Some(value.health as i32),
+                            blocked_releases: value.blocked_releases, }
     }
 }
 
@@ -229,9 +247,11 @@ mod tests {
                                  PackageIdent},
                        service::ServiceGroup};
 
-    use super::Service;
-    use crate::rumor::{service::SysInfo,
-                       Rumor};
+    use super::{Service,
+                ServiceHealth};
+    use crate::{protocol::Message,
+                rumor::{service::SysInfo,
+                        Rumor}};
 
     fn create_service(member_id: &str) -> Service {
         let pkg = PackageIdent::from_str("core/neurosis/1.2.3/20161208121212").unwrap();
@@ -317,6 +337,24 @@ mod tests {
         assert_eq!(s1, s1_check);
     }
 
+    #[test]
+    fn health_survives_the_wire() {
+        let mut s1 = create_service("adam");
+        s1.health = ServiceHealth::Critical;
+        let bytes = s1.write_to_bytes().unwrap();
+        let s2 = Service::from_bytes(&bytes).unwrap();
+        assert_eq!(s2.health, ServiceHealth::Critical);
+    }
+
+    #[test]
+    fn blocked_releases_survive_the_wire() {
+        let mut s1 = create_service("adam");
+        s1.blocked_releases = vec!["core/neurosis/1.2.4/20161209121212".to_string()];
+        let bytes = s1.write_to_bytes().unwrap();
+        let s2 = Service::from_bytes(&bytes).unwrap();
+        assert_eq!(s2.blocked_releases, s1.blocked_releases);
+    }
+
     #[test]
     #[should_panic]
     fn service_package_name_mismatch() {
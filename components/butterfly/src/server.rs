@@ -25,7 +25,8 @@ use crate::{error::{Error,
             rumor::{dat_file::{DatFileReader,
                                DatFileWriter},
                     departure::Departure,
-                    election::{Election,
+                    election::{CanaryRollout,
+                               Election,
                                ElectionRumor,
                                ElectionUpdate},
                     heat::RumorHeat,
@@ -866,6 +867,28 @@ impl Server {
         self.update_store.insert(e);
     }
 
+    /// Publishes the progress of a canary update of `service_group` in its update election
+    /// rumor. Only the winner of a finished update election can do so; returns whether the
+    /// rollout was published.
+    pub fn publish_canary_rollout(&self, service_group: &str, canary: CanaryRollout) -> bool {
+        let mut won_election = None;
+        self.update_store.with_rumor(service_group, "election", |e| {
+                             if e.is_finished() && e.member_id == self.member_id() {
+                                 won_election = Some(e.clone());
+                             }
+                         });
+        match won_election {
+            Some(mut e) => {
+                e.set_canary(canary);
+                debug!("publish_canary_rollout: {:?}", e);
+                self.rumor_heat.start_hot_rumor(RumorKey::from(&e));
+                self.update_store.insert(e);
+                true
+            }
+            None => false,
+        }
+    }
+
     /// # Locking
     /// * `MemberList::entries` (read) This method must not be called while any MemberList::entries
     ///   lock is held.
@@ -1344,20 +1367,23 @@ mod election_trigger {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::rumor::election::Term;
+    use crate::rumor::{election::Term,
+                       service::ServiceHealth};
     use habitat_core::service::ServiceGroup;
     use std::str::FromStr;
 
     fn check_quorum_returns(val: bool) -> impl Fn(&str) -> bool { move |_: &str| val }
 
     fn mock_service(member: &Member) -> Service {
-        Service { member_id:     member.id.clone(),
-                  service_group: ServiceGroup::from_str("group.default").unwrap(),
-                  incarnation:   Default::default(),
-                  initialized:   Default::default(),
-                  pkg:           Default::default(),
-                  cfg:           Default::default(),
-                  sys:           Default::default(), }
+        Service { member_id:        member.id.clone(),
+                  service_group:    ServiceGroup::from_str("group.default").unwrap(),
+                  incarnation:      Default::default(),
+                  initialized:      Default::default(),
+                  pkg:              Default::default(),
+                  cfg:              Default::default(),
+                  sys:              Default::default(),
+                  health:           ServiceHealth::Unknown,
+                  blocked_releases: Vec::new(), }
     }
 
     #[test]
@@ -1,3 +1,4 @@
 pub mod binds;
 pub mod config;
+pub mod gc;
 pub mod install;
@@ -0,0 +1,379 @@
+//! Removes installed packages which are no longer needed.
+//!
+//! # Examples
+//!
+//! ```bash
+//! $ hab pkg gc --keep 2
+//! ```
+//!
+//! Will remove every installed release except the 2 newest releases of each package, along with
+//! anything those releases, a loaded service spec, or a running service depend on.
+//!
+//! # Internals
+//!
+//! * Build a `PackageGraph` of everything installed under the Habitat root
+//! * Mark the newest releases of each package, the protected packages, and all of their
+//!   dependencies as in use
+//! * Delete everything else, removing a package only once nothing left on disk depends on it
+
+use std::{collections::{HashMap,
+                        HashSet},
+          fs,
+          path::Path,
+          str::FromStr};
+
+use crate::{error::Result,
+            hcore::{fs as hfs,
+                    package::{self,
+                              list::temp_package_directory,
+                              Identifiable,
+                              PackageIdent,
+                              PackageInstall}},
+            package_graph::PackageGraph,
+            ui::{Status,
+                 UIWriter}};
+
+/// The number of releases of each package kept when no other policy is given.
+pub const DEFAULT_KEEP_RELEASES: usize = 2;
+
+/// Packages of which every release is kept, along with their dependencies. The hab CLI may be
+/// running from any of its releases, just like the Launcher, which keeps running the release it
+/// was started from when the Supervisor updates and loads libraries (such as glibc's NSS modules)
+/// from its dependencies at runtime.
+const ALWAYS_KEPT: &[&str] = &["core/hab", "core/hab-launcher"];
+
+/// Determines which installed packages survive a garbage collection.
+#[derive(Clone, Debug)]
+pub struct GcPolicy {
+    /// The number of releases of each package to keep, newest first. At least one release of
+    /// every package is always kept.
+    pub keep_releases: usize,
+    /// Packages which must never be removed, such as the idents of loaded service specs and
+    /// running services. A fuzzy ident protects the newest installed release which satisfies it,
+    /// which is the release a Supervisor would load.
+    pub protected:     Vec<PackageIdent>,
+}
+
+impl Default for GcPolicy {
+    fn default() -> Self {
+        GcPolicy { keep_releases: DEFAULT_KEEP_RELEASES,
+                   protected:     Vec::new(), }
+    }
+}
+
+/// Whether a garbage collection removes packages or only reports what it would remove.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum GcMode {
+    DryRun,
+    Run,
+}
+
+/// The outcome of a garbage collection.
+#[derive(Clone, Debug, Default)]
+pub struct GcSummary {
+    /// The packages which were (or, in a dry run, would have been) removed
+    pub removed:     Vec<PackageIdent>,
+    /// The disk space reclaimed by removing those packages, in bytes
+    pub bytes_freed: u64,
+}
+
+/// Remove every installed package under `fs_root_path` which the given policy does not keep.
+pub fn start<U>(ui: &mut U,
+                fs_root_path: &Path,
+                policy: &GcPolicy,
+                mode: GcMode)
+                -> Result<GcSummary>
+    where U: UIWriter
+{
+    ui.begin(format!("Collecting unused packages, keeping the {} newest releases of each",
+                     policy.keep_releases.max(1)))?;
+
+    let pkg_root_path = hfs::pkg_root_path(Some(fs_root_path));
+    if !pkg_root_path.is_dir() {
+        ui.end("No installed packages found")?;
+        return Ok(GcSummary::default());
+    }
+    let installed = package::all_packages(&pkg_root_path)?;
+    let mut graph = PackageGraph::from_root_path(fs_root_path)?;
+    let mut remaining = collectable(&graph, &installed, policy);
+
+    let mut summary = GcSummary::default();
+    // Only remove packages nothing else on disk depends on, which also makes sure an interrupted
+    // collection never leaves a kept package without its dependencies.
+    loop {
+        let (ready, blocked): (Vec<_>, Vec<_>) =
+            remaining.into_iter()
+                     .partition(|p| graph.count_rdeps(p).map_or(true, |c| c == 0));
+        if ready.is_empty() {
+            remaining = blocked;
+            break;
+        }
+        for ident in ready {
+            let install = PackageInstall::load(&ident, Some(fs_root_path))?;
+            let bytes = disk_usage(install.installed_path())?;
+            match mode {
+                GcMode::DryRun => ui.status(Status::DryRunDeleting, &ident)?,
+                GcMode::Run => {
+                    ui.status(Status::Deleting, &ident)?;
+                    remove_package_dir(&pkg_root_path, install.installed_path())?;
+                }
+            }
+            graph.remove(&ident);
+            summary.bytes_freed += bytes;
+            summary.removed.push(ident);
+        }
+        remaining = blocked;
+    }
+    for ident in remaining {
+        ui.status(Status::Skipping,
+                  format!("{}. Another unused package depends on it", &ident))?;
+    }
+
+    match mode {
+        GcMode::DryRun => {
+            ui.end(format!("Would remove {} packages, freeing {} (Dry run)",
+                           summary.removed.len(),
+                           human_bytes(summary.bytes_freed)))?;
+        }
+        GcMode::Run => {
+            ui.end(format!("Removed {} packages, freed {}",
+                           summary.removed.len(),
+                           human_bytes(summary.bytes_freed)))?;
+        }
+    }
+    Ok(summary)
+}
+
+/// Returns the installed packages which the policy does not keep, in no particular order.
+fn collectable(graph: &PackageGraph,
+               installed: &[PackageIdent],
+               policy: &GcPolicy)
+               -> Vec<PackageIdent> {
+    let always_kept: Vec<PackageIdent> =
+        ALWAYS_KEPT.iter()
+                   .map(|i| PackageIdent::from_str(i).expect("ALWAYS_KEPT has valid idents"))
+                   .collect();
+
+    let mut releases: HashMap<(&str, &str), Vec<&PackageIdent>> = HashMap::new();
+    for ident in installed {
+        releases.entry((ident.origin.as_str(), ident.name.as_str()))
+                .or_insert_with(Vec::new)
+                .push(ident);
+    }
+
+    let mut keep: HashSet<&PackageIdent> = HashSet::new();
+    for idents in releases.values_mut() {
+        idents.sort_by(|a, b| b.cmp(a));
+        keep.extend(idents.iter().take(policy.keep_releases.max(1)));
+    }
+    keep.extend(installed.iter()
+                         .filter(|i| always_kept.iter().any(|kept| i.satisfies(kept))));
+    for protected in &policy.protected {
+        if let Some(newest) = installed.iter().filter(|i| i.satisfies(protected)).max() {
+            keep.insert(newest);
+        }
+    }
+
+    let mut in_use: HashSet<&PackageIdent> = HashSet::new();
+    for ident in keep {
+        in_use.extend(graph.ordered_deps(ident));
+        in_use.insert(ident);
+    }
+
+    installed.iter()
+             .filter(|i| !in_use.contains(i))
+             .cloned()
+             .collect()
+}
+
+/// Delete an installed package directory, along with any parent directories which are left empty.
+/// Never removes anything at or above `pkg_root_path`.
+pub fn remove_package_dir(pkg_root_path: &Path, real_install_path: &Path) -> Result<()> {
+    // The install path is always 4 levels below the pkg_root_path, so it always has a parent
+    let real_install_base = real_install_path.parent()
+                                             .expect("Install path doesn't have a parent");
+    // Move the package out of the way first so a partially removed package is never visible
+    let temp_install_path = temp_package_directory(real_install_path)?.path()
+                                                                      .to_path_buf();
+    fs::rename(&real_install_path, &temp_install_path)?;
+    fs::remove_dir_all(&temp_install_path)?;
+
+    for p in real_install_base.ancestors() {
+        // Let's be safe and not rm below the package directories
+        if p == pkg_root_path {
+            break;
+        }
+        match p.read_dir()?.count() {
+            0 => fs::remove_dir(&p)?,
+            _ => break,
+        }
+    }
+    Ok(())
+}
+
+/// The size of every file below `path`, in bytes. Symlinks are not followed.
+fn disk_usage(path: &Path) -> Result<u64> {
+    let metadata = fs::symlink_metadata(path)?;
+    if !metadata.is_dir() {
+        return Ok(metadata.len());
+    }
+    let mut total = 0;
+    for entry in fs::read_dir(path)? {
+        total += disk_usage(&entry?.path())?;
+    }
+    Ok(total)
+}
+
+fn human_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[unit])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ui::UI;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    fn ident(s: &str) -> PackageIdent { PackageIdent::from_str(s).unwrap() }
+
+    /// Lay out an installed package with the given dependencies under `fs_root`.
+    fn install(fs_root: &Path, pkg: &str, deps: &[&str]) {
+        let path = hfs::pkg_install_path(&ident(pkg), Some(fs_root));
+        fs::create_dir_all(&path).unwrap();
+        fs::write(path.join("IDENT"), pkg).unwrap();
+        let mut deps_file = fs::File::create(path.join("DEPS")).unwrap();
+        for dep in deps {
+            writeln!(deps_file, "{}", dep).unwrap();
+        }
+    }
+
+    fn installed(fs_root: &Path) -> Vec<PackageIdent> {
+        let mut idents = package::all_packages(&hfs::pkg_root_path(Some(fs_root))).unwrap();
+        idents.sort();
+        idents
+    }
+
+    fn fixture() -> TempDir {
+        let root = TempDir::new().unwrap();
+        install(root.path(), "core/glibc/2.27/20190101000000", &[]);
+        install(root.path(), "core/glibc/2.29/20190601000000", &[]);
+        install(root.path(),
+                "core/redis/4.0.14/20190101000000",
+                &["core/glibc/2.27/20190101000000"]);
+        install(root.path(),
+                "core/redis/4.0.14/20190301000000",
+                &["core/glibc/2.29/20190601000000"]);
+        install(root.path(),
+                "core/redis/5.0.5/20190601000000",
+                &["core/glibc/2.29/20190601000000"]);
+        root
+    }
+
+    fn gc(root: &TempDir, policy: &GcPolicy, mode: GcMode) -> GcSummary {
+        start(&mut UI::with_sinks(), root.path(), policy, mode).unwrap()
+    }
+
+    #[test]
+    fn keeps_newest_releases_and_their_deps() {
+        let root = fixture();
+        let policy = GcPolicy { keep_releases: 1,
+                                protected:     vec![], };
+        let summary = gc(&root, &policy, GcMode::Run);
+
+        assert_eq!(summary.removed.len(), 3);
+        assert!(summary.bytes_freed > 0);
+        assert_eq!(installed(root.path()),
+                   vec![ident("core/glibc/2.29/20190601000000"),
+                        ident("core/redis/5.0.5/20190601000000")]);
+        // Emptied version directories are cleaned up too
+        assert!(!root.path().join("hab/pkgs/core/glibc/2.27").exists());
+    }
+
+    #[test]
+    fn protected_packages_are_kept_with_their_deps() {
+        let root = fixture();
+        let policy = GcPolicy { keep_releases: 1,
+                                protected:     vec![ident("core/redis/4.0.14/20190101000000")], };
+        gc(&root, &policy, GcMode::Run);
+
+        assert_eq!(installed(root.path()),
+                   vec![ident("core/glibc/2.27/20190101000000"),
+                        ident("core/glibc/2.29/20190601000000"),
+                        ident("core/redis/4.0.14/20190101000000"),
+                        ident("core/redis/5.0.5/20190601000000")]);
+    }
+
+    #[test]
+    fn fuzzy_protected_idents_keep_the_newest_match() {
+        let root = fixture();
+        let policy = GcPolicy { keep_releases: 1,
+                                protected:     vec![ident("core/redis/4.0.14")], };
+        gc(&root, &policy, GcMode::Run);
+
+        assert!(installed(root.path()).contains(&ident("core/redis/4.0.14/20190301000000")));
+        assert!(!installed(root.path()).contains(&ident("core/redis/4.0.14/20190101000000")));
+    }
+
+    #[test]
+    fn dry_run_removes_nothing() {
+        let root = fixture();
+        let policy = GcPolicy { keep_releases: 1,
+                                protected:     vec![], };
+        let summary = gc(&root, &policy, GcMode::DryRun);
+
+        assert_eq!(summary.removed.len(), 3);
+        assert!(summary.bytes_freed > 0);
+        assert_eq!(installed(root.path()).len(), 5);
+    }
+
+    #[test]
+    fn at_least_one_release_is_kept() {
+        let root = fixture();
+        let policy = GcPolicy { keep_releases: 0,
+                                protected:     vec![], };
+        gc(&root, &policy, GcMode::Run);
+
+        assert_eq!(installed(root.path()).len(), 2);
+    }
+
+    #[test]
+    fn every_hab_and_launcher_release_is_kept_with_its_deps() {
+        let root = fixture();
+        install(root.path(), "core/hab/0.80.0/20190101000000", &[]);
+        install(root.path(), "core/hab/0.81.0/20190601000000", &[]);
+        install(root.path(),
+                "core/hab-launcher/10000/20190101000000",
+                &["core/glibc/2.27/20190101000000"]);
+        install(root.path(),
+                "core/hab-launcher/11000/20190601000000",
+                &["core/glibc/2.29/20190601000000"]);
+        let policy = GcPolicy { keep_releases: 1,
+                                protected:     vec![], };
+        gc(&root, &policy, GcMode::Run);
+
+        let installed = installed(root.path());
+        assert!(installed.contains(&ident("core/hab/0.80.0/20190101000000")));
+        assert!(installed.contains(&ident("core/hab-launcher/10000/20190101000000")));
+        assert!(installed.contains(&ident("core/glibc/2.27/20190101000000")));
+        assert!(!installed.contains(&ident("core/redis/4.0.14/20190101000000")));
+    }
+
+    #[test]
+    fn human_bytes_formatting() {
+        assert_eq!(human_bytes(512), "512 B");
+        assert_eq!(human_bytes(1536), "1.5 KiB");
+        assert_eq!(human_bytes(5 * 1024 * 1024 * 1024), "5.0 GiB");
+    }
+}
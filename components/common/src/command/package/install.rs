@@ -62,7 +62,8 @@ use crate::{error::{Error,
             templating::{self,
                          hooks::{Hook,
                                  InstallHook},
-                         package::Pkg},
+                         package::Pkg,
+                         TemplateEngine},
             ui::{Status,
                  UIWriter}};
 
@@ -400,7 +401,8 @@ fn run_install_hook<T>(ui: &mut T, package: &PackageInstall) -> Result<()>
 {
     if let Some(ref hook) = InstallHook::load(&package.ident.name,
                                               &svc_hooks_path(package.ident.name.clone()),
-                                              &package.installed_path.join("hooks"))
+                                              &package.installed_path.join("hooks"),
+                                              TemplateEngine::for_package(package)?)
     {
         ui.status(Status::Executing,
                   format!("install hook for '{}'", &package.ident(),))?;
@@ -790,7 +792,7 @@ impl<'a> InstallTask<'a> {
                 continue;
             }
             let artifact_ident = artifact_ident.unwrap();
-            if artifact_ident.origin == ident.origin && artifact_ident.name == ident.name {
+            if artifact_ident.satisfies(ident) {
                 if latest.is_empty() {
                     latest.push((artifact_ident, artifact));
                 } else if artifact_ident > latest[0].0 {
@@ -22,8 +22,12 @@ pub enum Error {
     /// Occurs when there is no valid toml of json in the environment variable
     BadEnvConfig(String),
     BadGlyphStyle(String),
+    /// Occurs when a package names a template engine we don't know about
+    BadTemplateEngine(String),
     CantUploadGossipToml,
     ChannelNotFound,
+    /// When a JSON Patch cannot be parsed or applied to a configuration
+    ConfigPatchError(String),
     CryptoKeyError(String),
     DownloadFailed(String),
     EditorEnv(env::VarError),
@@ -33,9 +37,13 @@ pub enum Error {
     FileNotFound(String),
     GossipFileRelativePath(String),
     HabitatCore(hcore::Error),
+    /// Occurs when the run history of a hook can't be decoded.
+    HookHistoryCorrupt(PathBuf, serde_json::Error),
     InstallHookFailed(PackageIdent),
     InvalidEventStreamToken(String),
     InvalidInstallHookMode(String),
+    /// Occurs when a secret reference can't be split into a provider, path and key.
+    InvalidSecretReference(String),
     /// Occurs when making lower level IO calls.
     IO(io::Error),
     /// Errors when joining paths :)
@@ -51,6 +59,9 @@ pub enum Error {
     /// When an error occurs serializing rendering context
     RenderContextSerialization(serde_json::Error),
     RootRequired,
+    SecretNotFound(String),
+    /// Occurs when a secret provider fails to look up or renew a secret.
+    SecretProviderError(String),
     StatusFileCorrupt(PathBuf),
     StrFromUtf8Error(str::Utf8Error),
     StringFromUtf8Error(string::FromUtf8Error),
@@ -65,11 +76,14 @@ pub enum Error {
     /// failure crate. We should change this to a RenderError after we update the
     /// handlebars crate. See https://github.com/sunng87/handlebars-rust/issues/194
     TemplateRenderError(String),
+    /// When a Tera template cannot be parsed
+    TeraTemplateError(String),
     /// When an error occurs merging toml
     TomlMergeError(String),
     /// When an error occurs parsing toml
     TomlParser(toml::de::Error),
     TomlSerializeError(toml::ser::Error),
+    UnknownSecretProvider(String),
     WireDecode(String),
 }
 
@@ -85,10 +99,15 @@ impl fmt::Display for Error {
                 format!("Unable to find valid TOML or JSON in {} ENVVAR", varname)
             }
             Error::BadGlyphStyle(ref style) => format!("Unknown symbol style '{}'", style),
+            Error::BadTemplateEngine(ref engine) => {
+                format!("Unknown template engine '{}'; expected 'handlebars' or 'tera'",
+                        engine)
+            }
             Error::CantUploadGossipToml => {
                 "Can't upload gossip.toml, it's a reserved file name".to_string()
             }
             Error::ChannelNotFound => "Channel not found".to_string(),
+            Error::ConfigPatchError(ref e) => format!("Failed to patch configuration: {}", e),
             Error::CryptoKeyError(ref s) => format!("Missing or invalid key: {}", s),
             Error::DownloadFailed(ref msg) => msg.to_string(),
             Error::EditorEnv(ref e) => format!("Missing EDITOR environment variable: {}", e),
@@ -100,6 +119,9 @@ impl fmt::Display for Error {
                         s)
             }
             Error::HabitatCore(ref e) => format!("{}", e),
+            Error::HookHistoryCorrupt(ref path, ref e) => {
+                format!("Unable to decode hook history {}, {}", path.display(), e)
+            }
             Error::MissingCLIInputError(ref arg) => {
                 format!("Missing required CLI argument!: {}", arg)
             }
@@ -112,6 +134,10 @@ impl fmt::Display for Error {
             Error::InvalidInstallHookMode(ref e) => {
                 format!("Invalid InstallHookMode conversion from {}", e)
             }
+            Error::InvalidSecretReference(ref r) => {
+                format!("Invalid secret reference '{}', expected [provider:]path/key",
+                        r)
+            }
             Error::IO(ref err) => format!("{}", err),
             Error::JoinPathsError(ref err) => format!("{}", err),
             Error::NetParseError(ref err) => format!("{}", err),
@@ -135,6 +161,8 @@ impl fmt::Display for Error {
             Error::RootRequired => {
                 "Root or administrator permissions required to complete operation".to_string()
             }
+            Error::SecretNotFound(ref s) => format!("Secret not found: {}", s),
+            Error::SecretProviderError(ref e) => format!("Secret provider error: {}", e),
             Error::StatusFileCorrupt(ref path) => {
                 format!("Unable to decode contents of INSTALL_STATUS file, {}",
                         path.display())
@@ -143,9 +171,13 @@ impl fmt::Display for Error {
             Error::StringFromUtf8Error(ref e) => format!("{}", e),
             Error::TemplateFileError(ref err) => format!("{:?}", err),
             Error::TemplateRenderError(ref err) => err.to_string(),
+            Error::TeraTemplateError(ref err) => format!("Invalid Tera template: {}", err),
             Error::TomlMergeError(ref e) => format!("Failed to merge TOML: {}", e),
             Error::TomlParser(ref err) => format!("Failed to parse TOML: {}", err),
             Error::TomlSerializeError(ref e) => format!("Can't serialize TOML: {}", e),
+            Error::UnknownSecretProvider(ref p) => {
+                format!("Unknown or unconfigured secret provider '{}'", p)
+            }
             Error::WireDecode(ref m) => format!("Failed to decode wire message: {}", m),
         };
         write!(f, "{}", msg)
@@ -159,8 +191,10 @@ impl error::Error for Error {
             Error::ArtifactIdentMismatch((..)) => "Artifact ident does not match expected ident",
             Error::BadEnvConfig(_) => "Unknown syntax in Env Configuration",
             Error::BadGlyphStyle(_) => "Unknown symbol style",
+            Error::BadTemplateEngine(_) => "Unknown template engine",
             Error::CantUploadGossipToml => "Can't upload gossip.toml, it's a reserved filename",
             Error::ChannelNotFound => "Channel not found",
+            Error::ConfigPatchError(_) => "Failed to patch configuration",
             Error::CryptoKeyError(_) => "Missing or invalid key",
             Error::DownloadFailed(_) => "Failed to download from remote",
             Error::EditorEnv(_) => "Missing EDITOR environment variable",
@@ -171,9 +205,11 @@ impl error::Error for Error {
                 "Path for gossip file cannot have relative components (eg: ..)"
             }
             Error::HabitatCore(ref err) => err.description(),
+            Error::HookHistoryCorrupt(..) => "Unable to decode hook history",
             Error::InstallHookFailed(_) => "Install hook exited unsuccessfully",
             Error::InvalidEventStreamToken(_) => "Invalid event stream token provided",
             Error::InvalidInstallHookMode(_) => "Invalid InstallHookMode",
+            Error::InvalidSecretReference(_) => "Invalid secret reference",
             Error::IO(ref err) => err.description(),
             Error::JoinPathsError(ref err) => err.description(),
             Error::MissingCLIInputError(_) => "Missing required CLI argument!",
@@ -189,14 +225,18 @@ impl error::Error for Error {
             Error::RootRequired => {
                 "Root or administrator permissions required to complete operation"
             }
+            Error::SecretNotFound(_) => "Secret not found",
+            Error::SecretProviderError(_) => "Secret provider error",
             Error::StatusFileCorrupt(_) => "Unable to decode contents of INSTALL_STATUS file",
             Error::StrFromUtf8Error(_) => "Failed to convert a string as UTF-8",
             Error::StringFromUtf8Error(_) => "Failed to convert a string as UTF-8",
             Error::TemplateFileError(ref err) => err.description(),
             Error::TemplateRenderError(_) => "Failed to render template",
+            Error::TeraTemplateError(_) => "Invalid Tera template",
             Error::TomlMergeError(_) => "Failed to merge TOML!",
             Error::TomlParser(_) => "Failed to parse TOML!",
             Error::TomlSerializeError(_) => "Can't serialize TOML",
+            Error::UnknownSecretProvider(_) => "Unknown or unconfigured secret provider",
             Error::WireDecode(_) => "Failed to decode wire message",
         }
     }
@@ -1,14 +1,22 @@
 pub mod config;
 mod context;
+mod engine;
 pub mod helpers;
 pub mod hooks;
+pub mod lint;
 pub mod package;
+pub mod secrets;
 pub mod test_helpers;
 
-use std::{fmt,
+use std::{collections::BTreeSet,
+          error::Error as StdError,
+          fmt,
           ops::{Deref,
                 DerefMut},
-          result};
+          path::{Path,
+                 PathBuf},
+          result,
+          sync::Arc};
 
 use regex::Regex;
 
@@ -17,15 +25,20 @@ use handlebars::{Handlebars,
                  TemplateFileError};
 use serde::Serialize;
 use serde_json;
+use tera::{self,
+           Tera};
 
 use crate::{error::{Error,
                     Result},
             hcore::{fs,
                     package::PackageInstall},
-            templating::hooks::{Hook,
-                                InstallHook}};
+            templating::{hooks::{Hook,
+                                 InstallHook},
+                         secrets::SecretLeases}};
 
-pub use self::context::RenderContext;
+pub use self::{context::RenderContext,
+               engine::{TemplateEngine,
+                        TERA_EXTENSION}};
 
 // This is specifically for finding syntax violations to object access in handlebars templates.
 // This should eventually be removed when we have upgraded the handlebars library and provided
@@ -45,12 +58,14 @@ pub fn compile_for_package_install(package: &PackageInstall) -> Result<()> {
 
     let cfg = config::Cfg::new(&pkg, None)?;
     let ctx = RenderContext::new(&pkg, &cfg);
-    let cfg_renderer = config::CfgRenderer::new(pkg.path.join("config_install"))?;
+    let cfg_renderer =
+        config::CfgRenderer::new(pkg.path.join("config_install"), pkg.template_engine)?;
     cfg_renderer.compile(&pkg.name, &pkg, &pkg.svc_config_install_path, &ctx)?;
 
     if let Some(ref hook) = InstallHook::load(&pkg.name,
                                               &fs::svc_hooks_path(&pkg.name),
-                                              &package.installed_path.join("hooks"))
+                                              &package.installed_path.join("hooks"),
+                                              pkg.template_engine)
     {
         hook.compile(&pkg.name, &ctx)?;
     };
@@ -60,52 +75,179 @@ pub fn compile_for_package_install(package: &PackageInstall) -> Result<()> {
 
 pub type RenderResult<T> = result::Result<T, RenderError>;
 
-pub struct TemplateRenderer(Handlebars);
+/// Renders templates written for any of the template engines. Templates are rendered by the
+/// engine they were registered with, and all of them see the same data.
+pub struct TemplateRenderer {
+    handlebars: Handlebars,
+    tera:       Tera,
+    // Tera 1.0 has no public way to list its templates, so their names are kept here.
+    tera_names: BTreeSet<String>,
+    engine:     TemplateEngine,
+    leases:     Arc<SecretLeases>,
+}
 
 impl TemplateRenderer {
-    pub fn new() -> Self {
+    pub fn new() -> Self { Self::with_engine(TemplateEngine::default()) }
+
+    /// Creates a renderer which registers template files for `engine` unless their extension
+    /// says otherwise.
+    pub fn with_engine(engine: TemplateEngine) -> Self {
         let mut handlebars = Handlebars::new();
+        let leases = Arc::new(SecretLeases::default());
+        handlebars.register_helper("base64Decode", Box::new(helpers::BASE64_DECODE));
+        handlebars.register_helper("base64Encode", Box::new(helpers::BASE64_ENCODE));
+        handlebars.register_helper("cidrHost", Box::new(helpers::CIDR_HOST));
+        handlebars.register_helper("cidrNetmask", Box::new(helpers::CIDR_NETMASK));
+        handlebars.register_helper("cidrNetwork", Box::new(helpers::CIDR_NETWORK));
+        handlebars.register_helper("coalesce", Box::new(helpers::COALESCE));
+        handlebars.register_helper("default", Box::new(helpers::DEFAULT));
         handlebars.register_helper("eachAlive", Box::new(helpers::EACH_ALIVE));
+        handlebars.register_helper("eachHealthy", Box::new(helpers::EACH_HEALTHY));
+        handlebars.register_helper("eachSorted", Box::new(helpers::EACH_SORTED));
+        handlebars.register_helper("eachWhere", Box::new(helpers::EACH_WHERE));
+        handlebars.register_helper("fileContents", Box::new(helpers::FILE_CONTENTS));
+        handlebars.register_helper("intAdd", Box::new(helpers::INT_ADD));
+        handlebars.register_helper("intDiv", Box::new(helpers::INT_DIV));
+        handlebars.register_helper("intMod", Box::new(helpers::INT_MOD));
+        handlebars.register_helper("intMul", Box::new(helpers::INT_MUL));
+        handlebars.register_helper("intSub", Box::new(helpers::INT_SUB));
+        handlebars.register_helper("ipInCidr", Box::new(helpers::IP_IN_CIDR));
         handlebars.register_helper("pkgPathFor", Box::new(helpers::PKG_PATH_FOR));
+        handlebars.register_helper("secret",
+                                   Box::new(helpers::SecretHelper::new(Arc::clone(&leases))));
+        handlebars.register_helper("sha256", Box::new(helpers::SHA256));
         handlebars.register_helper("strConcat", Box::new(helpers::STR_CONCAT));
+        handlebars.register_helper("strContains", Box::new(helpers::STR_CONTAINS));
         handlebars.register_helper("strJoin", Box::new(helpers::STR_JOIN));
         handlebars.register_helper("strReplace", Box::new(helpers::STR_REPLACE));
+        handlebars.register_helper("strSplit", Box::new(helpers::STR_SPLIT));
+        handlebars.register_helper("strTrim", Box::new(helpers::STR_TRIM));
         handlebars.register_helper("toUppercase", Box::new(helpers::TO_UPPERCASE));
         handlebars.register_helper("toLowercase", Box::new(helpers::TO_LOWERCASE));
+        handlebars.register_helper("toIni", Box::new(helpers::TO_INI));
         handlebars.register_helper("toJson", Box::new(helpers::TO_JSON));
         handlebars.register_helper("toToml", Box::new(helpers::TO_TOML));
         handlebars.register_helper("toYaml", Box::new(helpers::TO_YAML));
 
         handlebars.register_escape_fn(never_escape);
-        TemplateRenderer(handlebars)
+
+        let mut tera = Tera::default();
+        tera.autoescape_on(Vec::new());
+        tera.register_function("secret", helpers::SecretFunction::new(Arc::clone(&leases)));
+        helpers::tera_filters::register(&mut tera);
+
+        TemplateRenderer { handlebars,
+                           tera,
+                           tera_names: BTreeSet::new(),
+                           engine,
+                           leases }
     }
 
+    /// Returns `true` once the lease of a secret used while rendering needs to be renewed, which
+    /// is done by rendering the templates again.
+    pub fn secrets_expired(&self) -> bool { self.leases.expired() }
+
+    /// The names of all registered templates, whichever engine they are written for.
+    pub fn template_names(&self) -> Vec<String> {
+        let mut names = self.handlebars
+                            .get_templates()
+                            .keys()
+                            .cloned()
+                            .chain(self.tera_names.iter().cloned())
+                            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    /// The names of the registered templates which are rendered to files of their own, which are
+    /// all of them but the Tera partials, see `TemplateEngine::is_partial`.
+    pub fn rendered_template_names(&self) -> Vec<String> {
+        self.template_names()
+            .into_iter()
+            .filter(|name| {
+                !(self.is_tera_template(name) && TemplateEngine::is_partial(Path::new(name)))
+            })
+            .collect()
+    }
+
+    fn is_tera_template(&self, template: &str) -> bool { self.tera_names.contains(template) }
+
     pub fn render<T>(&self, template: &str, ctx: &T) -> Result<String>
         where T: Serialize
     {
         let raw = serde_json::to_value(ctx).map_err(Error::RenderContextSerialization)?;
         debug!("Rendering template with context, {}, {}", template, raw);
-        self.0
-            .render(template, &raw)
-            .map_err(|e| Error::TemplateRenderError(format!("{}", e)))
+        if self.is_tera_template(template) {
+            self.render_tera(template, &raw)
+                .map_err(|e| Error::TemplateRenderError(tera_error_message(&e)))
+        } else {
+            self.handlebars
+                .render(template, &raw)
+                .map_err(|e| Error::TemplateRenderError(format!("{}", e)))
+        }
+    }
+
+    fn render_tera(&self, template: &str, raw: &serde_json::Value) -> tera::Result<String> {
+        let ctx = tera::Context::from_serialize(raw)?;
+        self.tera.render(template, &ctx)
+    }
+
+    /// Registers the template file at `path` under `name`, for the engine its extension or the
+    /// renderer's engine selects.
+    pub fn register_template_file<P>(&mut self, name: &str, path: P) -> Result<()>
+        where P: AsRef<Path>
+    {
+        self.register_template_files(vec![(name.to_string(), path.as_ref().to_path_buf())])
+    }
+
+    /// Registers several template files at once. Tera templates may extend, include or import
+    /// each other, so they are only checked once all of them are registered.
+    pub fn register_template_files(&mut self, templates: Vec<(String, PathBuf)>) -> Result<()> {
+        let mut tera_templates = Vec::new();
+        for (name, path) in templates {
+            let template_string =
+                std::fs::read_to_string(&path).map_err(|e| {
+                                                  TemplateFileError::IOError(e, name.clone())
+                                              })?;
+            match TemplateEngine::for_path(&path, self.engine) {
+                TemplateEngine::Handlebars => {
+                    self.register_handlebars_template(&name, &path, template_string)?
+                }
+                TemplateEngine::Tera => tera_templates.push((name, template_string)),
+            }
+        }
+        if !tera_templates.is_empty() {
+            self.tera
+                .add_raw_templates(tera_templates.iter()
+                                                 .map(|(name, template)| {
+                                                     (name.as_str(), template.as_str())
+                                                 })
+                                                 .collect())
+                .map_err(|e| Error::TeraTemplateError(tera_error_message(&e)))?;
+            self.tera_names.extend(tera_templates.into_iter().map(|(name, _)| name));
+        }
+        Ok(())
+    }
+
+    /// Registers a Tera template from a string. Handlebars templates are registered with
+    /// `register_template_string`.
+    pub fn register_tera_template_string(&mut self, name: &str, template: &str) -> Result<()> {
+        self.tera
+            .add_raw_template(name, template)
+            .map_err(|e| Error::TeraTemplateError(tera_error_message(&e)))?;
+        self.tera_names.insert(name.to_string());
+        Ok(())
     }
 
     // This method is only implemented so we can intercept the call to Handlebars and display
     // a deprecation message to users. More information here https://github.com/habitat-sh/habitat/issues/6323.
     // When Handlebars is upgraded and users have had sufficient time to update their templates this
     // can be safely removed.
-    pub fn register_template_file<P>(&mut self,
-                                     name: &str,
-                                     path: P)
-                                     -> result::Result<(), TemplateFileError>
-        where P: AsRef<std::path::Path>
-    {
-        let path = path.as_ref();
-        let template_string =
-            std::fs::read_to_string(path).map_err(|e| {
-                                             TemplateFileError::IOError(e, name.to_owned())
-                                         })?;
-
+    fn register_handlebars_template(&mut self,
+                                    name: &str,
+                                    path: &Path,
+                                    template_string: String)
+                                    -> result::Result<(), TemplateFileError> {
         // If we detect deprecated object access syntax notify the user.
         if RE.is_match(&template_string) {
             // Enumerate over the lines in the template and provide deprecation messages for each
@@ -127,25 +269,38 @@ impl TemplateRenderer {
                 });
         }
 
-        self.0.register_template_string(name, template_string)?;
+        self.handlebars.register_template_string(name, template_string)?;
         Ok(())
     }
 }
 
 impl fmt::Debug for TemplateRenderer {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Handlebars TemplateRenderer")
+        write!(f, "TemplateRenderer ({})", self.engine)
     }
 }
 
 impl Deref for TemplateRenderer {
     type Target = Handlebars;
 
-    fn deref(&self) -> &Handlebars { &self.0 }
+    fn deref(&self) -> &Handlebars { &self.handlebars }
 }
 
 impl DerefMut for TemplateRenderer {
-    fn deref_mut(&mut self) -> &mut Handlebars { &mut self.0 }
+    fn deref_mut(&mut self) -> &mut Handlebars { &mut self.handlebars }
+}
+
+/// Tera reports what went wrong in the sources of its errors, so they are all part of the
+/// message.
+fn tera_error_message(err: &tera::Error) -> String {
+    let mut message = err.to_string();
+    let mut source = err.source();
+    while let Some(err) = source {
+        message.push_str(": ");
+        message.push_str(&err.to_string());
+        source = err.source();
+    }
+    message
 }
 
 /// Disables HTML escaping which is enabled by default in Handlebars.
@@ -344,6 +499,100 @@ test: something"#
                    pkg_root_path(Some(&*FS_ROOT_PATH)).join("core/acl/2.2.52/20161208223311",));
     }
 
+    crate::locked_env_var!(HAB_SECRET_TEMPLATING_TEST_PASSWORD, lock_secret_password);
+
+    #[test]
+    fn secret_helper() {
+        let password = lock_secret_password();
+        password.set("hunter2");
+        let content = "password = \"{{secret \"env:templating/test/password\"}}\"".to_string();
+        let mut renderer = TemplateRenderer::new();
+        renderer.register_template_string("t", content).unwrap();
+
+        let data = service_config_json_from_toml_file("complex_config.toml");
+        let rendered = renderer.render("t", &data).unwrap();
+        assert_eq!(rendered, "password = \"hunter2\"");
+        assert!(!renderer.secrets_expired());
+
+        password.unset();
+        assert!(renderer.render("t", &data).is_err());
+    }
+
+    #[test]
+    fn tera_templates_see_the_same_data() {
+        let mut renderer = TemplateRenderer::new();
+        renderer.register_tera_template_string("t",
+                                               "{% for member in bind.foo.members %}{% if \
+                                                member.sys.ip is starting_with(\"172.\") \
+                                                %}{{ member.sys.ip }}{% endif %}{% endfor %} \
+                                                {{ bind.foo.members | length }}")
+                .unwrap();
+        renderer.register_template_string("h", "{{bind.foo.members[0].sys.ip}}")
+                .unwrap();
+
+        let data = service_config_json_from_toml_file("complex_config.toml");
+        assert_eq!(renderer.render("t", &data).unwrap(), "172.17.0.5 1");
+        assert_eq!(renderer.render("h", &data).unwrap(), "172.17.0.5");
+        assert_eq!(renderer.template_names(), vec!["h".to_string(), "t".to_string()]);
+    }
+
+    #[test]
+    fn tera_templates_are_not_escaped() {
+        let mut renderer = TemplateRenderer::new();
+        renderer.register_tera_template_string("index.html", "{{ html }}")
+                .unwrap();
+
+        let mut m: BTreeMap<String, String> = BTreeMap::new();
+        m.insert("html".into(), "<b>&</b>".into());
+        assert_eq!(renderer.render("index.html", &m).unwrap(), "<b>&</b>");
+    }
+
+    #[test]
+    fn template_files_are_registered_for_their_engine() {
+        let tmp = TempDir::new().expect("create temp dir");
+        let macros = tmp.path().join("macros.tera");
+        let tera = tmp.path().join("tera.conf.tera");
+        let handlebars = tmp.path().join("handlebars.conf");
+        create_with_content(&macros,
+                            "{% macro port(cfg) %}port={{ cfg.port }}{% endmacro port %}");
+        create_with_content(&tera,
+                            "{% import \"macros\" as m %}{{ m::port(cfg=cfg) | upper }}");
+        create_with_content(&handlebars, "port={{cfg.port}}");
+
+        let mut renderer = TemplateRenderer::new();
+        // a template is registered before the one it imports
+        renderer.register_template_files(vec![("tera.conf".to_string(), tera),
+                                              ("macros".to_string(), macros),
+                                              ("handlebars.conf".to_string(),
+                                               handlebars.clone()),])
+                .unwrap();
+
+        let mut cfg = BTreeMap::new();
+        cfg.insert("port".to_string(), 80);
+        let mut data = BTreeMap::new();
+        data.insert("cfg".to_string(), cfg);
+        assert_eq!(renderer.render("tera.conf", &data).unwrap(), "PORT=80");
+        assert_eq!(renderer.render("handlebars.conf", &data).unwrap(), "port=80");
+
+        // Without the extension, files are registered for the renderer's engine
+        let mut renderer = TemplateRenderer::with_engine(TemplateEngine::Tera);
+        create_with_content(&handlebars, "port={{ cfg.port + 1 }}");
+        renderer.register_template_file("handlebars.conf", &handlebars)
+                .unwrap();
+        assert_eq!(renderer.render("handlebars.conf", &data).unwrap(), "port=81");
+    }
+
+    #[test]
+    fn invalid_tera_templates_are_errors() {
+        let mut renderer = TemplateRenderer::new();
+        assert!(renderer.register_tera_template_string("t", "{% if %}").is_err());
+
+        renderer.register_tera_template_string("t", "{{ missing.value }}")
+                .unwrap();
+        let data: BTreeMap<String, String> = BTreeMap::new();
+        assert!(renderer.render("t", &data).is_err());
+    }
+
     #[test]
     fn each_alive_helper_content() {
         let mut renderer = TemplateRenderer::new();
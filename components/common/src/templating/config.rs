@@ -4,9 +4,11 @@ use crate::{error::{Error,
             hcore::{self,
                     crypto,
                     fs::{self,
-                         USER_CONFIG_FILE}},
+                         USER_CONFIG_FILE,
+                         USER_CONFIG_PATCH_FILE}},
             outputln,
             templating::{package::Pkg,
+                         TemplateEngine,
                          TemplateRenderer}};
 use serde::{Serialize,
             Serializer};
@@ -22,14 +24,35 @@ use std::{self,
           result};
 use toml;
 
+mod explain;
+mod merge;
+mod patch;
+
+pub use self::{explain::{CfgExplanation,
+                         CfgSource,
+                         ExplainedValue,
+                         OverriddenValue},
+               merge::{MergeStrategy,
+                       MERGE_DIRECTIVE},
+               patch::{parse_patch,
+                       patch_layer,
+                       PatchOperation}};
+use self::{merge::MergeDirectives,
+           patch::{apply_patch,
+                   patch_operations}};
+
 static LOGKEY: &'static str = "CF";
 static ENV_VAR_PREFIX: &'static str = "HAB";
+static DEFAULT_CONFIG_FILE: &'static str = "default.toml";
 /// The maximum TOML table merge depth allowed before failing the operation. The value here is
 /// somewhat arbitrary (stack size cannot be easily computed beforehand and different libc
 /// implementations will impose different size constraints), however a parallel data structure that
 /// is deeper than this value crosses into overly complex territory when describing configuration
 /// for a single service.
 static TOML_MAX_MERGE_DEPTH: u16 = 30;
+/// The top-level key of a configuration layer holding a JSON Patch, which is applied after the
+/// rest of the layer was merged.
+pub const PATCH_DIRECTIVE: &str = "_patch";
 #[cfg(unix)]
 pub const CONFIG_PERMISSIONS: u32 = 0o740;
 #[cfg(unix)]
@@ -77,7 +100,7 @@ impl PackageConfigPaths for Pkg {
 
     fn default_config_dir(&self) -> PathBuf { self.path.clone() }
 
-    fn recommended_user_config_dir(&self) -> PathBuf { fs::user_config_path(&self.name) }
+    fn recommended_user_config_dir(&self) -> PathBuf { fs::user_config_path(&self.svc_name) }
 
     fn deprecated_user_config_dir(&self) -> PathBuf { self.svc_path.clone() }
 }
@@ -96,6 +119,10 @@ pub struct Cfg {
     pub user_config_path: UserConfigPath,
     /// Last known incarnation number of the census group's service config
     pub gossip_incarnation: u64,
+    /// The `default.toml` the default level configuration is loaded from
+    default_config_path: PathBuf,
+    /// The environment variable the environment level configuration is loaded from
+    environment_var: String,
     /// The path to an optional dev-time configuration directory that
     /// is being used.
     override_config_dir: Option<PathBuf>,
@@ -106,12 +133,12 @@ impl Cfg {
         where P: PackageConfigPaths
     {
         let override_config_dir = config_from.and_then(|c| Some(c.clone()));
-        let default = {
+        let (default, default_config_path) = {
             let pkg_root = match override_config_dir {
                 Some(ref path) => Cow::Borrowed(path),
                 None => Cow::Owned(package.default_config_dir()),
             };
-            Self::load_default(pkg_root.as_ref())?
+            (Self::load_default(pkg_root.as_ref())?, pkg_root.join(DEFAULT_CONFIG_FILE))
         };
         let user_config_path = Self::determine_user_config_path(package);
         let user = Self::load_user(user_config_path.get_path())?;
@@ -122,6 +149,8 @@ impl Cfg {
                   environment,
                   gossip_incarnation: 0,
                   user_config_path,
+                  default_config_path,
+                  environment_var: Self::environment_var(&package.name()),
                   override_config_dir })
     }
 
@@ -132,7 +161,7 @@ impl Cfg {
                     cfg: &toml::value::Table)
                     -> Option<Vec<String>> {
         let mut errors = vec![];
-        for key in cfg.keys() {
+        for key in cfg.keys().filter(|key| !is_directive(key, 0)) {
             if !interface.contains_key(key) {
                 errors.push(format!("Unknown key: {}", key));
             }
@@ -176,6 +205,7 @@ impl Cfg {
                 Some(ref path) => Cow::Borrowed(path),
                 None => Cow::Owned(package.default_config_dir()),
             };
+            self.default_config_path = pkg_root.join(DEFAULT_CONFIG_FILE);
             Self::load_default(pkg_root.as_ref())?
         };
 
@@ -258,7 +288,7 @@ impl Cfg {
     fn load_default<T>(config_from: T) -> Result<Option<toml::value::Table>>
         where T: AsRef<Path>
     {
-        Self::load_toml_file(config_from, "default.toml")
+        Self::load_toml_file(config_from, DEFAULT_CONFIG_FILE)
     }
 
     fn determine_user_config_path<P: PackageConfigPaths>(package: &P) -> UserConfigPath {
@@ -285,10 +315,43 @@ impl Cfg {
         UserConfigPath::Recommended(recommended_dir)
     }
 
+    /// Loads `user.toml` and adds the operations of `user.patch.json`, if there is one, to the
+    /// end of its `_patch`.
     fn load_user<T>(path: T) -> Result<Option<toml::value::Table>>
         where T: AsRef<Path>
     {
-        Self::load_toml_file(path, USER_CONFIG_FILE)
+        let mut user = Self::load_toml_file(&path, USER_CONFIG_FILE)?;
+        if let Some(operations) = Self::load_user_patch(&path)? {
+            let user = user.get_or_insert_with(toml::value::Table::new);
+            let mut patch = match user.remove(PATCH_DIRECTIVE) {
+                Some(patch) => patch_operations(&patch)?,
+                None => Vec::new(),
+            };
+            patch.extend(operations);
+            user.extend(patch_layer(&patch)?);
+        }
+        Ok(user)
+    }
+
+    fn load_user_patch<T>(path: T) -> Result<Option<Vec<PatchOperation>>>
+        where T: AsRef<Path>
+    {
+        let path = path.as_ref().join(USER_CONFIG_PATCH_FILE);
+        let mut file = match File::open(&path) {
+            Ok(file) => file,
+            Err(e) => {
+                debug!("Failed to open '{}', {}", path.display(), e);
+                return Ok(None);
+            }
+        };
+        let mut patch = String::new();
+        match file.read_to_string(&mut patch) {
+            Ok(_) => Ok(Some(parse_patch(&patch)?)),
+            Err(e) => {
+                outputln!("Failed to read '{}', {}", path.display(), e);
+                Ok(None)
+            }
+        }
     }
 
     /// Reloads the user configuration file.
@@ -298,9 +361,13 @@ impl Cfg {
         Ok(())
     }
 
+    fn environment_var(package_name: &str) -> String {
+        format!("{}_{}", ENV_VAR_PREFIX, package_name).to_ascii_uppercase()
+                                                      .replace("-", "_")
+    }
+
     fn load_environment(package_name: &str) -> Result<Option<toml::value::Table>> {
-        let var_name = format!("{}_{}", ENV_VAR_PREFIX, package_name).to_ascii_uppercase()
-                                                                     .replace("-", "_");
+        let var_name = Self::environment_var(package_name);
         match env::var(&var_name) {
             Ok(config) => {
                 // If we've got an environment variable, we'll parsing
@@ -394,19 +461,35 @@ pub struct CfgRenderer(TemplateRenderer);
 
 impl CfgRenderer {
     /// Create a new `CfgRenderer` and load template files from a
-    /// configuration directory, if it exists.
-    pub fn new<T>(templates_path: T) -> Result<Self>
+    /// configuration directory, if it exists. Templates are written for `engine`, unless their
+    /// extension says otherwise.
+    pub fn new<T>(templates_path: T, engine: TemplateEngine) -> Result<Self>
         where T: AsRef<Path>
     {
         if templates_path.as_ref().is_dir() {
             load_templates(templates_path.as_ref(),
                            &PathBuf::new(),
-                           TemplateRenderer::new()).map(CfgRenderer)
+                           TemplateRenderer::with_engine(engine)).map(CfgRenderer)
         } else {
-            Ok(CfgRenderer(TemplateRenderer::new()))
+            Ok(CfgRenderer(TemplateRenderer::with_engine(engine)))
         }
     }
 
+    /// Returns `true` once the lease of a secret used by the configuration templates needs to be
+    /// renewed.
+    pub fn secrets_expired(&self) -> bool { self.0.secrets_expired() }
+
+    /// The paths `compile` writes the configuration files to.
+    pub fn rendered_paths<P>(&self, render_path: P) -> Vec<PathBuf>
+        where P: AsRef<Path>
+    {
+        self.0
+            .rendered_template_names()
+            .iter()
+            .map(|template| render_path.as_ref().join(template))
+            .collect()
+    }
+
     /// Compile and write all configuration files to the configuration directory.
     ///
     /// Returns `true` if the configuration has changed.
@@ -425,7 +508,7 @@ impl CfgRenderer {
         // having issues and be more descriptive about what happened.
 
         let mut changed = false;
-        for template in self.0.get_templates().keys() {
+        for template in self.0.rendered_template_names() {
             let compiled = self.0.render(&template, ctx)?;
             let compiled_hash = crypto::hash::hash_string(&compiled);
             let cfg_dest = render_path.as_ref().join(&template);
@@ -473,9 +556,18 @@ impl CfgRenderer {
     }
 }
 
-// Recursively merges the `other` TOML table into `me`
+// Recursively merges the `other` TOML table into `me`, following its merge directives, then
+// applies its JSON Patch
 fn toml_merge(me: &mut toml::value::Table, other: &toml::value::Table) -> Result<()> {
-    toml_merge_recurse(me, other, 0)
+    toml_merge_recurse(me, other, 0)?;
+    apply_layer_patch(me, other)
+}
+
+fn apply_layer_patch(me: &mut toml::value::Table, layer: &toml::value::Table) -> Result<()> {
+    match layer.get(PATCH_DIRECTIVE) {
+        Some(patch) => apply_patch(me, &patch_operations(patch)?),
+        None => Ok(()),
+    }
 }
 
 fn toml_merge_recurse(me: &mut toml::value::Table,
@@ -488,8 +580,19 @@ fn toml_merge_recurse(me: &mut toml::value::Table,
                                                  TOML_MAX_MERGE_DEPTH)));
     }
 
+    let directives = MergeDirectives::from_table(other)?;
+    for key in directives.removed_keys(me, other) {
+        me.remove(&key);
+    }
     for (key, other_value) in other.iter() {
-        if is_toml_value_a_table(key, me) && is_toml_value_a_table(key, other) {
+        if is_directive(key, depth) {
+            continue;
+        }
+        let strategy = directives.strategy(key);
+        if strategy == MergeStrategy::Merge
+           && is_toml_value_a_table(key, me)
+           && is_toml_value_a_table(key, other)
+        {
             let mut me_at_key = match *(me.get_mut(key).expect("Key should exist in Table")) {
                 toml::Value::Table(ref mut t) => t,
                 _ => {
@@ -503,12 +606,58 @@ fn toml_merge_recurse(me: &mut toml::value::Table,
                                           .expect("TOML Value should be a Table"),
                                depth + 1)?;
         } else {
-            me.insert(key.clone(), other_value.clone());
+            let value = merge_value(strategy, me.remove(key), key, other_value, depth)?;
+            me.insert(key.clone(), value);
         }
     }
     Ok(())
 }
 
+/// Returns the value of `key` once `above` was merged into `below` with `strategy`, for values
+/// which aren't merged key by key.
+fn merge_value(strategy: MergeStrategy,
+               below: Option<toml::Value>,
+               key: &str,
+               above: &toml::Value,
+               depth: u16)
+               -> Result<toml::Value> {
+    match (strategy, below, without_directives(above, depth + 1)?) {
+        (MergeStrategy::Append, Some(toml::Value::Array(mut below)), toml::Value::Array(above)) => {
+            below.extend(above);
+            Ok(toml::Value::Array(below))
+        }
+        (MergeStrategy::Append, Some(_), _) => {
+            Err(Error::TomlMergeError(format!("Cannot append to {}; only arrays can be \
+                                               appended to arrays",
+                                              key)))
+        }
+        (_, _, above) => Ok(above),
+    }
+}
+
+/// Returns a copy of `value` with the merge directives of its tables applied and removed.
+fn without_directives(value: &toml::Value, depth: u16) -> Result<toml::Value> {
+    match *value {
+        toml::Value::Table(ref table) => {
+            let mut merged = toml::value::Table::new();
+            toml_merge_recurse(&mut merged, table, depth)?;
+            Ok(toml::Value::Table(merged))
+        }
+        toml::Value::Array(ref array) => {
+            array.iter()
+                 .map(|value| without_directives(value, depth + 1))
+                 .collect::<Result<_>>()
+                 .map(toml::Value::Array)
+        }
+        _ => Ok(value.clone()),
+    }
+}
+
+/// Returns `true` if `key` holds merge directives or, at the top of a layer, its JSON Patch.
+fn is_directive(key: &str, depth: u16) -> bool {
+    key == MERGE_DIRECTIVE || (depth == 0 && key == PATCH_DIRECTIVE)
+}
+
 fn is_toml_value_a_table(key: &str, table: &toml::value::Table) -> bool {
     match table.get(key) {
         None => false,
@@ -541,31 +690,41 @@ fn set_permissions(path: &Path, _user: &str, _group: &str) -> hcore::error::Resu
 }
 
 /// Recursively walk the configuration directory and subdirectories to
-/// construct the list of template files
+/// construct the list of template files, and register them with `template`
 ///
 /// `dir` should be a directory that exists.
 fn load_templates(dir: &Path,
                   context: &Path,
                   mut template: TemplateRenderer)
                   -> Result<TemplateRenderer> {
+    let mut templates = Vec::new();
+    find_templates(dir, context, &mut templates)?;
+    // JW TODO: This error needs improvement. TemplateFileError is too generic.
+    template.register_template_files(templates)?;
+    Ok(template)
+}
+
+fn find_templates(dir: &Path,
+                  context: &Path,
+                  templates: &mut Vec<(String, PathBuf)>)
+                  -> Result<()> {
     for entry in std::fs::read_dir(dir)?.filter_map(result::Result::ok) {
-        // We're storing the pathname relative to the input config directory
-        // as the identifier for the template
+        // We're storing the pathname relative to the input config directory, without the
+        // extension of its template engine, as the identifier for the template
         let relative_path = context.join(&entry.file_name());
         match entry.file_type() {
             Ok(file_type) if file_type.is_file() => {
-                // JW TODO: This error needs improvement. TemplateFileError is too generic.
-                template.register_template_file(&relative_path.to_string_lossy(), &entry.path())
-                        .map_err(|e| Error::TemplateFileError(Box::new(e)))?;
+                let name = TemplateEngine::rendered_path(&relative_path);
+                templates.push((name.to_string_lossy().into_owned(), entry.path()));
             }
             Ok(file_type) if file_type.is_dir() => {
-                template = load_templates(&entry.path(), &relative_path, template)?
+                find_templates(&entry.path(), &relative_path, templates)?
             }
             Ok(file_type) => trace!("Skipping non file/directory entry: {:?}", file_type),
             Err(e) => debug!("Failed to get file metadata for {:?} : {}", entry, e),
         }
     }
-    Ok(template)
+    Ok(())
 }
 
 /// Create the appropriate directories between a `root` directory
@@ -608,7 +767,8 @@ mod test {
                                   PackageInstall}},
                 templating::{context::RenderContext,
                              test_helpers::*}};
-    use std::{env,
+    use std::{collections::BTreeMap,
+              env,
               fs::{self,
                    OpenOptions}};
     use tempfile::TempDir;
@@ -802,6 +962,95 @@ mod test {
         }
     }
 
+    #[test]
+    fn merge_with_directives() {
+        let mut me = toml_from_str(
+                                   r#"
+            servers = ["a"]
+            legacy_port = 80
+
+            [tls]
+            enabled = false
+            port = 443
+
+            [log]
+            level = "info"
+            "#,
+        );
+        let other = toml_from_str(
+                                  r#"
+            servers = ["b"]
+
+            [_merge]
+            servers = "append"
+            legacy_port = "delete"
+            log = "replace"
+
+            [tls]
+            _merge = "replace"
+            enabled = true
+
+            [log]
+            [log.file]
+            _merge = "replace"
+            path = "/tmp/log"
+            "#,
+        );
+        let expected = toml_from_str(
+                                     r#"
+            servers = ["a", "b"]
+
+            [tls]
+            enabled = true
+
+            [log.file]
+            path = "/tmp/log"
+            "#,
+        );
+        toml_merge(&mut me, &other).unwrap();
+
+        assert_eq!(me, expected);
+    }
+
+    #[test]
+    fn merge_with_replace_at_the_top_of_a_layer() {
+        let mut me = toml_from_str("a = 1
+b = 2");
+        let other = toml_from_str("_merge = \"replace\"\nb = 3");
+        toml_merge(&mut me, &other).unwrap();
+
+        assert_eq!(me, toml_from_str("b = 3"));
+    }
+
+    #[test]
+    fn merge_appending_to_a_value_which_is_not_an_array_fails() {
+        let mut me = toml_from_str("servers = \"a\"");
+        let other = toml_from_str("servers = [\"b\"]\n[_merge]\nservers = \"append\"");
+
+        if let Err(Error::TomlMergeError(_)) = toml_merge(&mut me, &other) {
+            // expected result
+        } else {
+            panic!("Should fail with Error::TomlMergeError");
+        }
+    }
+
+    #[test]
+    fn merge_applies_the_patch_after_the_rest_of_the_layer() {
+        let mut me = toml_from_str("servers = [\"a\", \"b\"]\nport = 80");
+        let other = toml_from_str(
+                                  r#"
+            port = 8080
+            _patch = [
+                { op = "remove", path = "/servers/0" },
+                { op = "test", path = "/port", value = 8080 },
+            ]
+            "#,
+        );
+        toml_merge(&mut me, &other).unwrap();
+
+        assert_eq!(me, toml_from_str("servers = [\"b\"]\nport = 8080"));
+    }
+
     struct TestPkg {
         base_path: PathBuf,
     }
@@ -932,6 +1181,36 @@ mod test {
         assert_eq!(cfg.user, Some(toml_from_str(toml)));
     }
 
+    #[test]
+    fn load_user_patch() {
+        let cfg_data = CfgTestData::new();
+        write_toml(&cfg_data.rucp,
+                   "foo = 42\n_patch = [{ op = \"remove\", path = \"/bar\" }]");
+        write_toml(&cfg_data.pkg
+                            .recommended_user_config_dir()
+                            .join(USER_CONFIG_PATCH_FILE),
+                   r#"[{"op": "add", "path": "/baz", "value": 1}]"#);
+        let cfg = Cfg::new(&cfg_data.pkg, None).expect("create config");
+
+        assert_eq!(cfg.user,
+                   Some(toml_from_str("foo = 42\n_patch = [{ op = \"remove\", path = \
+                                       \"/bar\" }, { op = \"add\", path = \"/baz\", value \
+                                       = 1 }]")));
+    }
+
+    #[test]
+    fn serialize_config_with_directives() {
+        let concrete_path = TempDir::new().expect("create temp dir");
+        let pkg = TestPkg::new(&concrete_path);
+        let mut cfg = Cfg::new(&pkg, None).expect("Could not create config");
+        cfg.default = Some(toml_from_str("port = 80\nservers = [\"a\"]"));
+        cfg.user = Some(toml_from_str("servers = [\"b\"]\n[_merge]\nservers = \"append\""));
+        cfg.gossip = Some(toml_from_str("_patch = [{ op = \"remove\", path = \"/port\" }]"));
+
+        assert_eq!(toml::Value::try_from(&cfg).unwrap(),
+                   toml::Value::Table(toml_from_str("servers = [\"a\", \"b\"]")));
+    }
+
     #[test]
     fn serialize_config() {
         let concrete_path = TempDir::new().expect("create temp dir");
@@ -1105,6 +1384,25 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_load_templates_for_their_engine() {
+        let tmp = TempDir::new().expect("create temp dir");
+        let input_dir = tmp.path().join("input");
+        fs::create_dir_all(&input_dir).expect("create input dir");
+
+        create_with_content(&input_dir.join("a.conf.tera"), "{{ 40 + 2 }}");
+        create_with_content(&input_dir.join("b.conf"), "{{strConcat \"4\" \"2\"}}");
+
+        let renderer = load_templates(&input_dir, &PathBuf::new(), TemplateRenderer::new())
+            .expect("visit config dirs");
+
+        assert_eq!(renderer.template_names(),
+                   vec!["a.conf".to_string(), "b.conf".to_string()]);
+        let data = json!({});
+        assert_eq!(renderer.render("a.conf", &data).unwrap(), "42");
+        assert_eq!(renderer.render("b.conf", &data).unwrap(), "42");
+    }
+
     #[test]
     #[cfg(target_os = "linux")]
     #[should_panic(expected = "Not a directory")]
@@ -1152,12 +1450,38 @@ mod test {
 
         // Load templates from pkg config dir, and compile then into
         // the output directory
-        let renderer = CfgRenderer::new(&config_dir).expect("create cfg renderer");
+        let renderer = CfgRenderer::new(&config_dir, TemplateEngine::default())
+            .expect("create cfg renderer");
         renderer.compile("test", &pkg, &output_dir, &ctx)
                 .expect("compile");
         let deep_output_dir = output_dir.join("dir_a").join("dir_b");
 
         assert_eq!(file_content(deep_output_dir.join("config.txt")),
                    "config message is Hello");
+        assert_eq!(renderer.rendered_paths(&output_dir),
+                   vec![deep_output_dir.join("config.txt")]);
+    }
+
+    #[test]
+    fn tera_partials_are_not_rendered_to_files() {
+        let tmp = TempDir::new().expect("create temp dir");
+        let config_dir = tmp.path().join("config");
+        fs::create_dir_all(config_dir.join("macros")).expect("create config/macros");
+        create_with_content(config_dir.join("macros").join("ports.tera"),
+                            "{% macro port(p) %}port={{ p }}{% endmacro port %}");
+        create_with_content(config_dir.join("_header.tera"), "# managed by Habitat");
+        create_with_content(config_dir.join("_notes.txt"), "not a Tera template");
+        create_with_content(config_dir.join("app.conf.tera"),
+                            "{% import \"macros/ports\" as m %}{% include \"_header\" %}\n\
+                             {{ m::port(p=80) }}");
+
+        let renderer = CfgRenderer::new(&config_dir, TemplateEngine::default())
+            .expect("create cfg renderer");
+        let output_dir = tmp.path().join("output");
+        assert_eq!(renderer.rendered_paths(&output_dir),
+                   vec![output_dir.join("_notes.txt"), output_dir.join("app.conf")]);
+        let data: BTreeMap<String, String> = BTreeMap::new();
+        assert_eq!(renderer.0.render("app.conf", &data).unwrap(),
+                   "# managed by Habitat\nport=80");
     }
 }
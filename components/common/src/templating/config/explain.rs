@@ -0,0 +1,431 @@
+//! Explains where each value of a service's effective configuration came from.
+//!
+//! The effective configuration is built by merging the default, environment, user and gossip
+//! layers of a `Cfg` on top of each other. Explaining it repeats that merge while recording the
+//! layer which set each value, along with the values of lower layers it replaced. Values changed
+//! by the JSON Patch of a layer are recorded as set by that layer.
+
+use std::{collections::{btree_map,
+                        BTreeMap},
+          fmt,
+          path::PathBuf};
+
+use toml;
+
+use super::{apply_layer_patch,
+            is_directive,
+            is_toml_value_a_table,
+            merge::{MergeDirectives,
+                    MergeStrategy},
+            merge_value,
+            Cfg,
+            TOML_MAX_MERGE_DEPTH};
+use crate::{error::{Error,
+                    Result},
+            hcore::fs::USER_CONFIG_FILE};
+
+/// The layer of configuration which set a value.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "layer", rename_all = "snake_case")]
+pub enum CfgSource {
+    /// The package's `default.toml`.
+    Default { path: PathBuf },
+    /// The `HAB_<PKG>` environment variable of the Supervisor.
+    Environment { var: String },
+    /// The service's `user.toml`.
+    User { path: PathBuf },
+    /// Configuration applied to the service group with `hab config apply`.
+    Gossip { incarnation: u64 },
+}
+
+impl fmt::Display for CfgSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            CfgSource::Default { ref path } => write!(f, "default.toml ({})", path.display()),
+            CfgSource::Environment { ref var } => write!(f, "environment variable {}", var),
+            CfgSource::User { ref path } => write!(f, "user.toml ({})", path.display()),
+            CfgSource::Gossip { incarnation } => {
+                write!(f, "gossiped configuration (incarnation {})", incarnation)
+            }
+        }
+    }
+}
+
+/// A value which was replaced by a higher layer.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OverriddenValue {
+    /// The key the value was set at, if it differs from the key of the value replacing it. This
+    /// happens when a table is replaced by a value which isn't a table, or the other way around.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key:    Option<String>,
+    pub value:  toml::Value,
+    pub source: CfgSource,
+}
+
+/// A value of the effective configuration and the layer which set it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExplainedValue {
+    pub value:      toml::Value,
+    pub source:     CfgSource,
+    /// The values this one replaced, most recent first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overridden: Vec<OverriddenValue>,
+}
+
+/// The effective configuration of a service, keyed by the dotted path to each value.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CfgExplanation(BTreeMap<String, ExplainedValue>);
+
+impl CfgExplanation {
+    pub fn get(&self, key: &str) -> Option<&ExplainedValue> { self.0.get(key) }
+
+    pub fn iter(&self) -> btree_map::Iter<'_, String, ExplainedValue> { self.0.iter() }
+
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+
+    /// Merge the `layer` into `me` the way `toml_merge` does, recording the source of every value
+    /// taken from it.
+    fn merge_layer(&mut self,
+                   me: &mut toml::value::Table,
+                   layer: &toml::value::Table,
+                   source: &CfgSource)
+                   -> Result<()> {
+        self.merge(me, layer, "", source, 0)?;
+        let before = flatten(me);
+        apply_layer_patch(me, layer)?;
+        let after = flatten(me);
+        for key in before.keys().filter(|key| !after.contains_key(*key)) {
+            self.0.remove(key);
+        }
+        for (key, value) in after.iter() {
+            if before.get(key) != Some(value) {
+                let overridden = self.remove(key);
+                self.insert(key.clone(), value, source, &overridden);
+            }
+        }
+        Ok(())
+    }
+
+    /// Merge `other` into `me` the way `toml_merge_recurse` does, recording the source of every
+    /// value taken from `other`.
+    fn merge(&mut self,
+             me: &mut toml::value::Table,
+             other: &toml::value::Table,
+             prefix: &str,
+             source: &CfgSource,
+             depth: u16)
+             -> Result<()> {
+        if depth > TOML_MAX_MERGE_DEPTH {
+            return Err(Error::TomlMergeError(format!("Max recursive merge depth of {} \
+                                                      exceeded.",
+                                                     TOML_MAX_MERGE_DEPTH)));
+        }
+
+        let directives = MergeDirectives::from_table(other)?;
+        for key in directives.removed_keys(me, other) {
+            me.remove(&key);
+            self.remove(&join(prefix, &key));
+        }
+        for (key, other_value) in other.iter() {
+            if is_directive(key, depth) {
+                continue;
+            }
+            let path = join(prefix, key);
+            let strategy = directives.strategy(key);
+            if strategy == MergeStrategy::Merge
+               && is_toml_value_a_table(key, me)
+               && is_toml_value_a_table(key, other)
+            {
+                let me_at_key = me.get_mut(key)
+                                  .and_then(toml::Value::as_table_mut)
+                                  .expect("TOML Value should be a Table");
+                let other_at_key = other_value.as_table()
+                                              .expect("TOML Value should be a Table");
+                self.merge(me_at_key, other_at_key, &path, source, depth + 1)?;
+            } else {
+                let value = merge_value(strategy, me.remove(key), key, other_value, depth)?;
+                let overridden = self.remove(&path);
+                self.insert(path, &value, source, &overridden);
+                me.insert(key.clone(), value);
+            }
+        }
+        Ok(())
+    }
+
+    /// Record `value` and, if it is a table, everything in it as set by `source`.
+    fn insert(&mut self,
+              path: String,
+              value: &toml::Value,
+              source: &CfgSource,
+              overridden: &[OverriddenValue]) {
+        match *value {
+            toml::Value::Table(ref table) if !table.is_empty() => {
+                for (key, value) in table.iter() {
+                    self.insert(format!("{}.{}", path, key), value, source, overridden);
+                }
+            }
+            _ => {
+                let overridden = overridden.iter()
+                                           .cloned()
+                                           .map(|mut o| {
+                                               if o.key.as_ref() == Some(&path) {
+                                                   o.key = None;
+                                               }
+                                               o
+                                           })
+                                           .collect();
+                self.0.insert(path,
+                              ExplainedValue { value: value.clone(),
+                                               source: source.clone(),
+                                               overridden });
+            }
+        }
+    }
+
+    /// Remove the values at and below `path`, returning them, and the values they overrode, as
+    /// overridden values.
+    fn remove(&mut self, path: &str) -> Vec<OverriddenValue> {
+        let below = format!("{}.", path);
+        let keys = self.0
+                       .keys()
+                       .filter(|k| *k == path || k.starts_with(&below))
+                       .cloned()
+                       .collect::<Vec<_>>();
+        let mut removed = Vec::new();
+        for key in keys {
+            let previous = self.0.remove(&key).expect("Explained key should exist");
+            removed.push(OverriddenValue { key:    Some(key.clone()),
+                                           value:  previous.value,
+                                           source: previous.source, });
+            removed.extend(previous.overridden.into_iter().map(|mut o| {
+                                                              if o.key.is_none() {
+                                                                  o.key = Some(key.clone());
+                                                              }
+                                                              o
+                                                          }));
+        }
+        removed
+    }
+}
+
+fn join(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", prefix, key)
+    }
+}
+
+/// The values of `table` keyed by their dotted path, the way `CfgExplanation::insert` records
+/// them.
+fn flatten(table: &toml::value::Table) -> BTreeMap<String, toml::Value> {
+    fn flatten_into(path: String, value: &toml::Value, flat: &mut BTreeMap<String, toml::Value>) {
+        match *value {
+            toml::Value::Table(ref table) if !table.is_empty() => {
+                for (key, value) in table.iter() {
+                    flatten_into(join(&path, key), value, flat);
+                }
+            }
+            _ => {
+                flat.insert(path, value.clone());
+            }
+        }
+    }
+
+    let mut flat = BTreeMap::new();
+    for (key, value) in table.iter() {
+        flatten_into(key.clone(), value, &mut flat);
+    }
+    flat
+}
+
+impl fmt::Display for CfgExplanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (key, explained) in self.0.iter() {
+            writeln!(f, "{} = {}", key, explained.value)?;
+            writeln!(f, "    set by {}", explained.source)?;
+            for overridden in explained.overridden.iter() {
+                match overridden.key {
+                    Some(ref key) => {
+                        writeln!(f,
+                                 "    overrides {} = {} from {}",
+                                 key, overridden.value, overridden.source)?
+                    }
+                    None => {
+                        writeln!(f,
+                                 "    overrides {} from {}",
+                                 overridden.value, overridden.source)?
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Cfg {
+    /// Returns the effective configuration, with the layer which set each value and the values it
+    /// replaced.
+    pub fn explain(&self) -> Result<CfgExplanation> {
+        let layers = [(&self.default,
+                       CfgSource::Default { path: self.default_config_path.clone(), }),
+                      (&self.environment,
+                       CfgSource::Environment { var: self.environment_var.clone(), }),
+                      (&self.user,
+                       CfgSource::User { path: self.user_config_path
+                                                   .get_path()
+                                                   .join(USER_CONFIG_FILE), }),
+                      (&self.gossip,
+                       CfgSource::Gossip { incarnation: self.gossip_incarnation, })];
+        let mut explanation = CfgExplanation::default();
+        let mut table = toml::value::Table::new();
+        for &(layer, ref source) in layers.iter() {
+            if let Some(layer) = layer {
+                explanation.merge_layer(&mut table, layer, source)?;
+            }
+        }
+        Ok(explanation)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::templating::config::UserConfigPath;
+
+    fn toml_from_str(content: &str) -> Option<toml::value::Table> {
+        Some(toml::from_str(content).expect("Content should parse as TOML"))
+    }
+
+    fn cfg() -> Cfg {
+        Cfg { default:             toml_from_str("port = 6379\n\
+                                                  [tls]\n\
+                                                  enabled = false\n\
+                                                  port = 6380\n"),
+              environment:         toml_from_str("port = 7000"),
+              user:                toml_from_str("port = 7001\n\
+                                                  [tls]\n\
+                                                  enabled = true\n"),
+              gossip:              toml_from_str("tls = \"off\"\nmaxmemory = \"1gb\""),
+              user_config_path:    UserConfigPath::Recommended(PathBuf::from("/hab/user/redis/\
+                                                                          config")),
+              gossip_incarnation:  3,
+              default_config_path: PathBuf::from("/hab/pkgs/core/redis/default.toml"),
+              environment_var:     "HAB_REDIS".to_string(),
+              override_config_dir: None, }
+    }
+
+    fn user() -> CfgSource {
+        CfgSource::User { path: PathBuf::from("/hab/user/redis/config/user.toml"), }
+    }
+
+    fn default() -> CfgSource {
+        CfgSource::Default { path: PathBuf::from("/hab/pkgs/core/redis/default.toml"), }
+    }
+
+    #[test]
+    fn explain_records_the_layer_of_each_value() {
+        let explanation = cfg().explain().unwrap();
+        let port = explanation.get("port").unwrap();
+        assert_eq!(port.value, toml::Value::Integer(7001));
+        assert_eq!(port.source, user());
+        assert_eq!(port.overridden,
+                   vec![OverriddenValue { key:    None,
+                                          value:  toml::Value::Integer(7000),
+                                          source: CfgSource::Environment { var: "HAB_REDIS"
+                                                                                .to_string(), }, },
+                        OverriddenValue { key:    None,
+                                          value:  toml::Value::Integer(6379),
+                                          source: default(), }]);
+
+        let maxmemory = explanation.get("maxmemory").unwrap();
+        assert_eq!(maxmemory.source, CfgSource::Gossip { incarnation: 3 });
+        assert!(maxmemory.overridden.is_empty());
+    }
+
+    #[test]
+    fn explain_records_replaced_tables() {
+        let explanation = cfg().explain().unwrap();
+        assert!(explanation.get("tls.enabled").is_none());
+        let tls = explanation.get("tls").unwrap();
+        assert_eq!(tls.value, toml::Value::String("off".to_string()));
+        assert_eq!(tls.overridden,
+                   vec![OverriddenValue { key:    Some("tls.enabled".to_string()),
+                                          value:  toml::Value::Boolean(true),
+                                          source: user(), },
+                        OverriddenValue { key:    Some("tls.enabled".to_string()),
+                                          value:  toml::Value::Boolean(false),
+                                          source: default(), },
+                        OverriddenValue { key:    Some("tls.port".to_string()),
+                                          value:  toml::Value::Integer(6380),
+                                          source: default(), }]);
+    }
+
+    #[test]
+    fn explain_matches_the_effective_configuration() {
+        let cfg = cfg();
+        let effective = toml::Value::try_from(&cfg).unwrap();
+        let explanation = cfg.explain().unwrap();
+        for (key, explained) in explanation.iter() {
+            let value = key.split('.')
+                           .fold(Some(&effective), |value, k| value.and_then(|v| v.get(k)));
+            assert_eq!(value, Some(&explained.value), "value of {}", key);
+        }
+        assert_eq!(explanation.iter().count(), 3);
+    }
+
+    #[test]
+    fn explain_follows_merge_directives_and_patches() {
+        let mut cfg = cfg();
+        cfg.environment = None;
+        cfg.user = toml_from_str("_patch = [{ op = \"remove\", path = \"/port\" }]\n\
+                                  [tls]\n\
+                                  _merge = \"replace\"\n\
+                                  enabled = true\n");
+        cfg.gossip = toml_from_str("_patch = [{ op = \"add\", path = \"/maxmemory\", value = \
+                                    \"1gb\" }]");
+        let explanation = cfg.explain().unwrap();
+        assert!(explanation.get("port").is_none());
+        assert!(explanation.get("tls.port").is_none());
+        let enabled = explanation.get("tls.enabled").unwrap();
+        assert_eq!(enabled.source, user());
+        assert_eq!(enabled.overridden,
+                   vec![OverriddenValue { key:    None,
+                                          value:  toml::Value::Boolean(false),
+                                          source: default(), }]);
+        let maxmemory = explanation.get("maxmemory").unwrap();
+        assert_eq!(maxmemory.source, CfgSource::Gossip { incarnation: 3 });
+
+        let effective = toml::Value::try_from(&cfg).unwrap();
+        for (key, explained) in explanation.iter() {
+            let value = key.split('.')
+                           .fold(Some(&effective), |value, k| value.and_then(|v| v.get(k)));
+            assert_eq!(value, Some(&explained.value), "value of {}", key);
+        }
+        assert_eq!(explanation.iter().count(), 2);
+    }
+
+    #[test]
+    fn explanations_round_trip_through_json() {
+        let explanation = cfg().explain().unwrap();
+        let json = serde_json::to_string(&explanation).unwrap();
+        assert_eq!(serde_json::from_str::<CfgExplanation>(&json).unwrap(),
+                   explanation);
+    }
+
+    #[test]
+    fn display_explains_each_value() {
+        let mut cfg = cfg();
+        cfg.environment = None;
+        cfg.gossip = None;
+        let expected = "port = 7001\n    \
+                        set by user.toml (/hab/user/redis/config/user.toml)\n    \
+                        overrides 6379 from default.toml (/hab/pkgs/core/redis/default.toml)\n\
+                        tls.enabled = true\n    \
+                        set by user.toml (/hab/user/redis/config/user.toml)\n    \
+                        overrides false from default.toml (/hab/pkgs/core/redis/default.toml)\n\
+                        tls.port = 6380\n    \
+                        set by default.toml (/hab/pkgs/core/redis/default.toml)\n";
+        assert_eq!(cfg.explain().unwrap().to_string(), expected);
+    }
+}
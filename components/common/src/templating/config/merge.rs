@@ -0,0 +1,188 @@
+//! Merge directives, which control how a configuration layer is merged into the layers below it.
+//!
+//! Without directives, tables are merged key by key and any other value replaces the value below
+//! it. A table can change that with a `_merge` key. Set to a string, it is the strategy for the
+//! table itself:
+//!
+//! ```toml
+//! [upstreams]
+//! _merge = "replace"
+//! ```
+//!
+//! Set to a table, it holds the strategies for the keys of the table:
+//!
+//! ```toml
+//! [_merge]
+//! servers = "append"
+//! legacy_port = "delete"
+//! ```
+
+use std::{collections::BTreeMap,
+          result,
+          str::FromStr};
+
+use toml;
+
+use crate::error::{Error,
+                   Result};
+
+/// The key holding the merge directives of a table.
+pub const MERGE_DIRECTIVE: &str = "_merge";
+
+/// How a value is merged into the value below it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MergeStrategy {
+    /// Tables are merged key by key; any other value replaces the value below it.
+    Merge,
+    /// The value replaces the value below it, even if both are tables.
+    Replace,
+    /// The array is appended to the array below it.
+    Append,
+    /// The key is removed from the layers below. If the same layer sets the key, its value is
+    /// kept, which makes this the same as `Replace`.
+    Delete,
+}
+
+impl FromStr for MergeStrategy {
+    type Err = Error;
+
+    fn from_str(value: &str) -> result::Result<Self, Self::Err> {
+        match value {
+            "merge" => Ok(MergeStrategy::Merge),
+            "replace" => Ok(MergeStrategy::Replace),
+            "append" => Ok(MergeStrategy::Append),
+            "delete" => Ok(MergeStrategy::Delete),
+            _ => {
+                Err(Error::TomlMergeError(format!("Unknown merge strategy '{}'; expected \
+                                                   'merge', 'replace', 'append' or 'delete'",
+                                                  value)))
+            }
+        }
+    }
+}
+
+/// The merge directives of a table.
+#[derive(Debug, Default)]
+pub struct MergeDirectives {
+    replace_table: bool,
+    keys:          BTreeMap<String, MergeStrategy>,
+}
+
+impl MergeDirectives {
+    /// Reads the `_merge` key of `table`.
+    pub fn from_table(table: &toml::value::Table) -> Result<Self> {
+        match table.get(MERGE_DIRECTIVE) {
+            None => Ok(Self::default()),
+            Some(toml::Value::String(strategy)) => {
+                match strategy.parse()? {
+                    MergeStrategy::Merge => Ok(Self::default()),
+                    MergeStrategy::Replace => {
+                        Ok(Self { replace_table: true,
+                                  ..Self::default() })
+                    }
+                    _ => {
+                        Err(Error::TomlMergeError(format!("A table can only be merged or \
+                                                           replaced, not '{}'",
+                                                          strategy)))
+                    }
+                }
+            }
+            Some(toml::Value::Table(strategies)) => {
+                let keys = strategies.iter()
+                                     .map(|(key, strategy)| {
+                                         match strategy.as_str() {
+                                             Some(s) => Ok((key.clone(), s.parse()?)),
+                                             None => Err(not_a_strategy(key, strategy)),
+                                         }
+                                     })
+                                     .collect::<Result<_>>()?;
+                Ok(Self { replace_table: false,
+                          keys })
+            }
+            Some(other) => Err(not_a_strategy(MERGE_DIRECTIVE, other)),
+        }
+    }
+
+    /// The strategy for merging the value of `key`.
+    pub fn strategy(&self, key: &str) -> MergeStrategy {
+        if self.replace_table {
+            return MergeStrategy::Replace;
+        }
+        match self.keys.get(key) {
+            Some(MergeStrategy::Delete) => MergeStrategy::Replace,
+            Some(strategy) => *strategy,
+            None => MergeStrategy::Merge,
+        }
+    }
+
+    /// The keys of `below` which have to be removed before `above` is merged into it.
+    pub fn removed_keys(&self,
+                        below: &toml::value::Table,
+                        above: &toml::value::Table)
+                        -> Vec<String> {
+        below.keys()
+             .filter(|key| !above.contains_key(*key))
+             .filter(|key| {
+                 self.replace_table || self.keys.get(*key) == Some(&MergeStrategy::Delete)
+             })
+             .cloned()
+             .collect()
+    }
+}
+
+fn not_a_strategy(key: &str, value: &toml::Value) -> Error {
+    Error::TomlMergeError(format!("The merge strategy for '{}' must be a string, not {}",
+                                  key,
+                                  value.type_str()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn directives(content: &str) -> Result<MergeDirectives> {
+        MergeDirectives::from_table(&toml::from_str(content).expect("Content should parse as \
+                                                                     TOML"))
+    }
+
+    fn table(content: &str) -> toml::value::Table {
+        toml::from_str(content).expect("Content should parse as TOML")
+    }
+
+    #[test]
+    fn tables_without_directives_are_merged() {
+        let directives = directives("a = 1").unwrap();
+        assert_eq!(directives.strategy("a"), MergeStrategy::Merge);
+        assert!(directives.removed_keys(&table("b = 1"), &table("a = 1"))
+                          .is_empty());
+    }
+
+    #[test]
+    fn replaced_tables_replace_every_key() {
+        let directives = directives("_merge = \"replace\"\na = 1").unwrap();
+        assert_eq!(directives.strategy("a"), MergeStrategy::Replace);
+        assert_eq!(directives.removed_keys(&table("a = 2\nb = 1"), &table("a = 1")),
+                   vec!["b".to_string()]);
+    }
+
+    #[test]
+    fn strategies_per_key() {
+        let directives =
+            directives("[_merge]\nservers = \"append\"\nport = \"delete\"\nhost = \"delete\"\n\
+                        [host]\nname = \"x\"").unwrap();
+        assert_eq!(directives.strategy("servers"), MergeStrategy::Append);
+        assert_eq!(directives.strategy("host"), MergeStrategy::Replace);
+        assert_eq!(directives.strategy("other"), MergeStrategy::Merge);
+        assert_eq!(directives.removed_keys(&table("port = 1\nhost = 2\nservers = []"),
+                                           &table("[host]\nname = \"x\"")),
+                   vec!["port".to_string()]);
+    }
+
+    #[test]
+    fn invalid_directives_are_errors() {
+        assert!(directives("_merge = \"append\"").is_err());
+        assert!(directives("_merge = \"overwrite\"").is_err());
+        assert!(directives("_merge = 1").is_err());
+        assert!(directives("[_merge]\nport = true").is_err());
+    }
+}
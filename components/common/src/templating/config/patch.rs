@@ -0,0 +1,292 @@
+//! JSON Patch ([RFC 6902][1]) support for configuration layers.
+//!
+//! A layer may carry a list of patch operations under its top-level `_patch` key. They are
+//! applied to the configuration merged so far, after the rest of the layer was merged in. Paths
+//! are JSON Pointers ([RFC 6901][2]) into the configuration. Values can be anything TOML can
+//! represent, so `null` is not allowed.
+//!
+//! [1]: https://tools.ietf.org/html/rfc6902
+//! [2]: https://tools.ietf.org/html/rfc6901
+
+use serde_json;
+use toml;
+
+use super::PATCH_DIRECTIVE;
+use crate::error::{Error,
+                   Result};
+
+/// A single JSON Patch operation.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum PatchOperation {
+    Add { path: String, value: toml::Value },
+    Remove { path: String },
+    Replace { path: String, value: toml::Value },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: toml::Value },
+}
+
+/// Parses a JSON Patch document.
+pub fn parse_patch(json: &str) -> Result<Vec<PatchOperation>> {
+    serde_json::from_str(json).map_err(|e| Error::ConfigPatchError(e.to_string()))
+}
+
+/// Returns a configuration layer consisting of nothing but `operations`, as sent by
+/// `hab config apply --patch`.
+pub fn patch_layer(operations: &[PatchOperation]) -> Result<toml::value::Table> {
+    let operations =
+        toml::Value::try_from(operations).map_err(|e| Error::ConfigPatchError(e.to_string()))?;
+    let mut layer = toml::value::Table::new();
+    layer.insert(PATCH_DIRECTIVE.to_string(), operations);
+    Ok(layer)
+}
+
+/// Reads the operations from the `_patch` value of a layer.
+pub fn patch_operations(value: &toml::Value) -> Result<Vec<PatchOperation>> {
+    value.clone().try_into().map_err(|e| {
+                                Error::ConfigPatchError(format!("Invalid {}: {}",
+                                                                PATCH_DIRECTIVE, e))
+                            })
+}
+
+/// Applies `operations` to `table` in order. If one of them fails, `table` is left unchanged.
+pub fn apply_patch(table: &mut toml::value::Table, operations: &[PatchOperation]) -> Result<()> {
+    let mut doc = toml::Value::Table(table.clone());
+    for operation in operations {
+        apply_operation(&mut doc, operation)?;
+    }
+    match doc {
+        toml::Value::Table(patched) => {
+            *table = patched;
+            Ok(())
+        }
+        _ => Err(Error::ConfigPatchError("The configuration must remain a table".to_string())),
+    }
+}
+
+fn apply_operation(doc: &mut toml::Value, operation: &PatchOperation) -> Result<()> {
+    match *operation {
+        PatchOperation::Add { ref path, ref value } => add(doc, path, value.clone()),
+        PatchOperation::Remove { ref path } => remove(doc, path).map(|_| ()),
+        PatchOperation::Replace { ref path, ref value } => {
+            if path.is_empty() {
+                *doc = value.clone();
+                return Ok(());
+            }
+            remove(doc, path)?;
+            add(doc, path, value.clone())
+        }
+        PatchOperation::Move { ref from, ref path } => {
+            if path.starts_with(&format!("{}/", from)) {
+                return Err(Error::ConfigPatchError(format!("Cannot move '{}' into itself",
+                                                           from)));
+            }
+            let value = remove(doc, from)?;
+            add(doc, path, value)
+        }
+        PatchOperation::Copy { ref from, ref path } => {
+            let value = get(doc, from)?.clone();
+            add(doc, path, value)
+        }
+        PatchOperation::Test { ref path, ref value } => {
+            if get(doc, path)? == value {
+                Ok(())
+            } else {
+                Err(Error::ConfigPatchError(format!("The value at '{}' is not {}", path, value)))
+            }
+        }
+    }
+}
+
+fn add(doc: &mut toml::Value, pointer: &str, value: toml::Value) -> Result<()> {
+    if pointer.is_empty() {
+        *doc = value;
+        return Ok(());
+    }
+    let (parent, last) = parent_mut(doc, pointer)?;
+    match parent {
+        toml::Value::Table(table) => {
+            table.insert(last, value);
+            Ok(())
+        }
+        toml::Value::Array(array) => {
+            if last == "-" {
+                array.push(value);
+                return Ok(());
+            }
+            let index = array_index(&last)?;
+            if index > array.len() {
+                return Err(not_found(pointer));
+            }
+            array.insert(index, value);
+            Ok(())
+        }
+        _ => Err(not_found(pointer)),
+    }
+}
+
+fn remove(doc: &mut toml::Value, pointer: &str) -> Result<toml::Value> {
+    let (parent, last) = parent_mut(doc, pointer)?;
+    match parent {
+        toml::Value::Table(table) => table.remove(&last).ok_or_else(|| not_found(pointer)),
+        toml::Value::Array(array) => {
+            let index = array_index(&last)?;
+            if index < array.len() {
+                Ok(array.remove(index))
+            } else {
+                Err(not_found(pointer))
+            }
+        }
+        _ => Err(not_found(pointer)),
+    }
+}
+
+fn get<'a>(doc: &'a toml::Value, pointer: &str) -> Result<&'a toml::Value> {
+    let mut value = doc;
+    for token in tokens(pointer)? {
+        value = child(value, &token)?.ok_or_else(|| not_found(pointer))?;
+    }
+    Ok(value)
+}
+
+/// Resolves all but the last token of `pointer`, returning the value they point to and the last
+/// token.
+fn parent_mut<'a>(doc: &'a mut toml::Value,
+                  pointer: &str)
+                  -> Result<(&'a mut toml::Value, String)> {
+    let mut tokens = tokens(pointer)?;
+    let last = tokens.pop().ok_or_else(|| {
+                                Error::ConfigPatchError("The whole configuration cannot be \
+                                                         removed or moved"
+                                                                          .to_string())
+                            })?;
+    let mut value = doc;
+    for token in tokens {
+        value = match value {
+                    toml::Value::Table(table) => table.get_mut(&token),
+                    toml::Value::Array(array) => array.get_mut(array_index(&token)?),
+                    _ => None,
+                }.ok_or_else(|| not_found(pointer))?;
+    }
+    Ok((value, last))
+}
+
+fn child<'a>(value: &'a toml::Value, token: &str) -> Result<Option<&'a toml::Value>> {
+    Ok(match *value {
+        toml::Value::Table(ref table) => table.get(token),
+        toml::Value::Array(ref array) => array.get(array_index(token)?),
+        _ => None,
+    })
+}
+
+fn tokens(pointer: &str) -> Result<Vec<String>> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    if !pointer.starts_with('/') {
+        return Err(Error::ConfigPatchError(format!("Invalid JSON Pointer '{}'", pointer)));
+    }
+    Ok(pointer[1..].split('/')
+                   .map(|token| token.replace("~1", "/").replace("~0", "~"))
+                   .collect())
+}
+
+fn array_index(token: &str) -> Result<usize> {
+    let valid = !token.is_empty()
+                && token.chars().all(|c| c.is_ascii_digit())
+                && (token == "0" || !token.starts_with('0'));
+    if valid {
+        token.parse()
+             .map_err(|_| Error::ConfigPatchError(format!("Invalid array index '{}'", token)))
+    } else {
+        Err(Error::ConfigPatchError(format!("Invalid array index '{}'", token)))
+    }
+}
+
+fn not_found(pointer: &str) -> Error {
+    Error::ConfigPatchError(format!("No value at '{}'", pointer))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn table(content: &str) -> toml::value::Table {
+        toml::from_str(content).expect("Content should parse as TOML")
+    }
+
+    fn patched(content: &str, patch: &str) -> Result<toml::value::Table> {
+        let mut table = table(content);
+        apply_patch(&mut table, &parse_patch(patch)?)?;
+        Ok(table)
+    }
+
+    #[test]
+    fn add_inserts_into_tables_and_arrays() {
+        let result = patched("servers = [\"a\", \"c\"]\n[tls]\nport = 1",
+                             r#"[{"op": "add", "path": "/servers/1", "value": "b"},
+                                 {"op": "add", "path": "/servers/-", "value": "d"},
+                                 {"op": "add", "path": "/tls/enabled", "value": true}]"#).unwrap();
+        assert_eq!(result,
+                   table("servers = [\"a\", \"b\", \"c\", \"d\"]\n[tls]\nport = 1\nenabled = \
+                          true"));
+    }
+
+    #[test]
+    fn remove_and_replace() {
+        let result = patched("a = 1\nb = [1, 2, 3]\n[c]\nd = 1",
+                             r#"[{"op": "remove", "path": "/a"},
+                                 {"op": "remove", "path": "/b/0"},
+                                 {"op": "replace", "path": "/c", "value": {"e": 2}}]"#).unwrap();
+        assert_eq!(result, table("b = [2, 3]\n[c]\ne = 2"));
+    }
+
+    #[test]
+    fn move_copy_and_test() {
+        let result = patched("a = 1\n[c]\nd = \"x\"",
+                             r#"[{"op": "test", "path": "/c/d", "value": "x"},
+                                 {"op": "move", "from": "/a", "path": "/c/a"},
+                                 {"op": "copy", "from": "/c/d", "path": "/e"}]"#).unwrap();
+        assert_eq!(result, table("e = \"x\"\n[c]\nd = \"x\"\na = 1"));
+    }
+
+    #[test]
+    fn pointers_are_unescaped() {
+        let result = patched("\"a/b\" = 1\n\"c~d\" = 2",
+                             r#"[{"op": "remove", "path": "/a~1b"},
+                                 {"op": "remove", "path": "/c~0d"}]"#).unwrap();
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn failed_patches_leave_the_table_unchanged() {
+        let mut t = table("a = 1");
+        let patch = parse_patch(r#"[{"op": "remove", "path": "/a"},
+                                    {"op": "test", "path": "/b", "value": 1}]"#).unwrap();
+        assert!(apply_patch(&mut t, &patch).is_err());
+        assert_eq!(t, table("a = 1"));
+    }
+
+    #[test]
+    fn invalid_patches_are_errors() {
+        assert!(patched("a = [1]", r#"[{"op": "remove", "path": "/a/01"}]"#).is_err());
+        assert!(patched("a = [1]", r#"[{"op": "add", "path": "/a/5", "value": 1}]"#).is_err());
+        assert!(patched("a = 1", r#"[{"op": "remove", "path": "a"}]"#).is_err());
+        assert!(patched("a = 1", r#"[{"op": "remove", "path": ""}]"#).is_err());
+        assert!(patched("[a]", r#"[{"op": "move", "from": "/a", "path": "/a/b"}]"#).is_err());
+        assert!(patched("a = 1", r#"[{"op": "replace", "path": "", "value": 1}]"#).is_err());
+        assert!(patched("a = 1", r#"[{"op": "add", "path": "/b", "value": null}]"#).is_err());
+        assert!(patched("a = 1", r#"[{"op": "frobnicate", "path": "/a"}]"#).is_err());
+    }
+
+    #[test]
+    fn patch_layers_round_trip_through_toml() {
+        let patch = parse_patch(r#"[{"op": "add", "path": "/servers/-", "value": {"a": 1}},
+                                    {"op": "remove", "path": "/port"}]"#).unwrap();
+        let layer = patch_layer(&patch).unwrap();
+        let layer: toml::value::Table =
+            toml::from_str(&toml::to_string(&layer).unwrap()).unwrap();
+        assert_eq!(patch_operations(&layer[PATCH_DIRECTIVE]).unwrap(), patch);
+    }
+}
@@ -0,0 +1,135 @@
+//! The template engines configuration templates and hooks can be written for.
+//!
+//! Templates are Handlebars templates unless the package sets `pkg_template_engine` in its plan.
+//! Regardless of that setting, a template whose file name ends in `.tera` is a Tera template,
+//! and is rendered to a file of the same name without that extension. Tera templates which are
+//! only there to be imported or included by others, those whose file name starts with `_` or which
+//! are in a `macros` directory, aren't rendered to a file of their own.
+
+use std::{fmt,
+          path::{Path,
+                 PathBuf},
+          result,
+          str::FromStr};
+
+use crate::{error::{Error,
+                    Result},
+            hcore::package::PackageInstall};
+
+/// The file extension which marks a template as a Tera template.
+pub const TERA_EXTENSION: &str = "tera";
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TemplateEngine {
+    Handlebars,
+    Tera,
+}
+
+impl TemplateEngine {
+    /// The engine the templates of `package` are written for, as set by `pkg_template_engine` in
+    /// its plan.
+    pub fn for_package(package: &PackageInstall) -> Result<TemplateEngine> {
+        match package.template_engine()? {
+            Some(engine) => engine.parse(),
+            None => Ok(TemplateEngine::default()),
+        }
+    }
+
+    /// The engine the template at `path` is written for, where `default` is the engine of the
+    /// package it belongs to.
+    pub fn for_path(path: &Path, default: TemplateEngine) -> TemplateEngine {
+        if path.extension().map_or(false, |ext| ext == TERA_EXTENSION) {
+            TemplateEngine::Tera
+        } else {
+            default
+        }
+    }
+
+    /// Whether the Tera template at `path` is only imported or included by other templates, and
+    /// so isn't rendered to a file of its own.
+    pub fn is_partial(path: &Path) -> bool {
+        path.file_name()
+            .map_or(false, |name| name.to_string_lossy().starts_with('_'))
+        || path.parent()
+               .map_or(false, |dir| dir.components().any(|c| c.as_os_str() == "macros"))
+    }
+
+    /// The path the template at `path` is rendered to, i.e. `path` without a `.tera` extension.
+    pub fn rendered_path(path: &Path) -> PathBuf {
+        if path.extension().map_or(false, |ext| ext == TERA_EXTENSION) {
+            path.with_extension("")
+        } else {
+            path.to_path_buf()
+        }
+    }
+}
+
+impl Default for TemplateEngine {
+    fn default() -> Self { TemplateEngine::Handlebars }
+}
+
+impl FromStr for TemplateEngine {
+    type Err = Error;
+
+    fn from_str(value: &str) -> result::Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_ref() {
+            "handlebars" => Ok(TemplateEngine::Handlebars),
+            "tera" => Ok(TemplateEngine::Tera),
+            _ => Err(Error::BadTemplateEngine(value.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for TemplateEngine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let engine = match *self {
+            TemplateEngine::Handlebars => "handlebars",
+            TemplateEngine::Tera => "tera",
+        };
+        write!(f, "{}", engine)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_template_engine() {
+        assert_eq!("handlebars".parse::<TemplateEngine>().unwrap(),
+                   TemplateEngine::Handlebars);
+        assert_eq!("Tera\n".parse::<TemplateEngine>().unwrap(),
+                   TemplateEngine::Tera);
+        assert!("mustache".parse::<TemplateEngine>().is_err());
+    }
+
+    #[test]
+    fn tera_extension_selects_tera() {
+        assert_eq!(TemplateEngine::for_path(Path::new("nginx.conf.tera"),
+                                            TemplateEngine::Handlebars),
+                   TemplateEngine::Tera);
+        assert_eq!(TemplateEngine::for_path(Path::new("nginx.conf"), TemplateEngine::Handlebars),
+                   TemplateEngine::Handlebars);
+        assert_eq!(TemplateEngine::for_path(Path::new("nginx.conf"), TemplateEngine::Tera),
+                   TemplateEngine::Tera);
+    }
+
+    #[test]
+    fn partials_are_recognized_by_their_name() {
+        assert!(TemplateEngine::is_partial(Path::new("_ports.tera")));
+        assert!(TemplateEngine::is_partial(Path::new("conf/_ports")));
+        assert!(TemplateEngine::is_partial(Path::new("macros/ports")));
+        assert!(TemplateEngine::is_partial(Path::new("conf/macros/ports")));
+        assert!(!TemplateEngine::is_partial(Path::new("conf/nginx.conf")));
+        assert!(!TemplateEngine::is_partial(Path::new("macros")));
+    }
+
+    #[test]
+    fn rendered_path_drops_the_tera_extension() {
+        assert_eq!(TemplateEngine::rendered_path(Path::new("conf/nginx.conf.tera")),
+                   PathBuf::from("conf/nginx.conf"));
+        assert_eq!(TemplateEngine::rendered_path(Path::new("conf/nginx.conf")),
+                   PathBuf::from("conf/nginx.conf"));
+    }
+}
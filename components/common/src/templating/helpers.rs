@@ -1,23 +1,86 @@
+//! Helpers available to every template rendered by a Supervisor.
+//!
+//! Helper names are lowerCamelCase and start with the kind of value they work on, so related
+//! helpers sort together and new ones have an obvious name:
+//!
+//! * `str*` helpers take and return strings (`strJoin`, `strSplit`, `strTrim`, ...)
+//! * `int*` helpers do integer math (`intAdd`, `intDiv`, ...)
+//! * `base64*` and `sha256` encode and hash strings
+//! * `ip*` and `cidr*` helpers work on IP addresses and networks
+//! * `each*` helpers are blocks which iterate over a list (`eachAlive`, `eachSorted`, ...)
+//! * `to*` helpers serialize a value (`toJson`, `toIni`, ...)
+//!
+//! Helpers which answer a question (`strContains`, `ipInCidr`) are blocks rendering their body if
+//! the answer is yes and their `else` block otherwise, as the result of a subexpression is always
+//! a string, and thus always true for `if`. Once a helper is released its name and parameters
+//! don't change; behaviour changes get a new helper.
+
+mod default;
 mod each_alive;
+mod each_healthy;
+mod each_sorted;
+mod each_where;
+mod encoding;
+mod file_contents;
+mod int_math;
+mod ip;
 mod pkg_path_for;
+mod secret;
 mod str_concat;
+mod str_contains;
 mod str_join;
 mod str_replace;
+mod str_split;
+mod str_trim;
+pub mod tera_filters;
+mod to_ini;
 mod to_json;
 mod to_lowercase;
 mod to_toml;
 mod to_uppercase;
 mod to_yaml;
 
+use std::collections::BTreeMap;
+
+use handlebars::{Handlebars,
+                 Helper,
+                 RenderContext,
+                 Renderable};
 use serde::Serialize;
 use serde_json::{self,
                  Value as Json};
 
-pub use self::{each_alive::EACH_ALIVE,
+use super::RenderResult;
+
+pub use self::{default::{COALESCE,
+                         DEFAULT},
+               each_alive::EACH_ALIVE,
+               each_healthy::EACH_HEALTHY,
+               each_sorted::EACH_SORTED,
+               each_where::EACH_WHERE,
+               encoding::{BASE64_DECODE,
+                          BASE64_ENCODE,
+                          SHA256},
+               file_contents::FILE_CONTENTS,
+               int_math::{INT_ADD,
+                          INT_DIV,
+                          INT_MOD,
+                          INT_MUL,
+                          INT_SUB},
+               ip::{CIDR_HOST,
+                    CIDR_NETMASK,
+                    CIDR_NETWORK,
+                    IP_IN_CIDR},
                pkg_path_for::PKG_PATH_FOR,
+               secret::{SecretFunction,
+                        SecretHelper},
                str_concat::STR_CONCAT,
+               str_contains::STR_CONTAINS,
                str_join::STR_JOIN,
                str_replace::STR_REPLACE,
+               str_split::STR_SPLIT,
+               str_trim::STR_TRIM,
+               to_ini::TO_INI,
                to_json::TO_JSON,
                to_lowercase::TO_LOWERCASE,
                to_toml::TO_TOML,
@@ -49,3 +112,98 @@ fn to_json<T>(src: &T) -> Json
 {
     serde_json::to_value(src).unwrap_or(Json::Null)
 }
+
+/// The text a value is rendered as: strings without their quotes, everything else as JSON.
+fn to_text(value: &Json) -> String {
+    match *value {
+        Json::String(ref s) => s.clone(),
+        Json::Null => String::new(),
+        ref other => other.to_string(),
+    }
+}
+
+/// Look up a dotted path such as `cfg.role` or `sys.ip` in `value`.
+fn lookup<'a>(value: &'a Json, path: &str) -> Option<&'a Json> {
+    if path.is_empty() {
+        return Some(value);
+    }
+    value.pointer(&format!("/{}", path.replace('.', "/")))
+}
+
+/// Render the block of a conditional helper if `condition` holds and its `else` block otherwise.
+/// Used outside of a block, the helper renders `true` or `false`.
+fn render_condition(condition: bool,
+                    h: &Helper<'_>,
+                    r: &Handlebars,
+                    rc: &mut RenderContext<'_>)
+                    -> RenderResult<()> {
+    if h.template().is_none() && h.inverse().is_none() {
+        rc.writer.write_all(condition.to_string().as_bytes())?;
+        return Ok(());
+    }
+    let template = if condition { h.template() } else { h.inverse() };
+    match template {
+        Some(template) => template.render(r, rc),
+        None => Ok(()),
+    }
+}
+
+/// Render the block of an iterating helper once for each item, or its `else` block if there are
+/// none. Each item is paired with its index in the list given as the first parameter, if it came
+/// from there, so that the item is also the context of the block; otherwise the item can only be
+/// reached through a block parameter, as in `{{#strSplit cfg.hosts "," as |host|}}`.
+fn render_items(items: &[(Option<usize>, Json)],
+                h: &Helper<'_>,
+                r: &Handlebars,
+                rc: &mut RenderContext<'_>)
+                -> RenderResult<()> {
+    let template = match h.template() {
+        Some(template) => template,
+        None => return Ok(()),
+    };
+    if items.is_empty() {
+        if let Some(else_template) = h.inverse() {
+            else_template.render(r, rc)?;
+        }
+        return Ok(());
+    }
+
+    let value = h.param(0);
+    let local_path_root = value.and_then(|v| v.path_root())
+                               .map(|p| format!("{}/{}", rc.get_path(), p));
+    let inner_path = value.and_then(|v| v.path()).map(|p| p.to_string());
+    rc.promote_local_vars();
+    let len = items.len();
+    for (i, &(index, ref item)) in items.iter().enumerate() {
+        let mut local_rc = rc.derive();
+        if let Some(ref p) = local_path_root {
+            local_rc.push_local_path_root(p.clone());
+        }
+        local_rc.set_local_var("@first".to_string(), to_json(&(i == 0usize)));
+        local_rc.set_local_var("@last".to_string(), to_json(&(i == len - 1)));
+        local_rc.set_local_var("@index".to_string(), to_json(&i));
+
+        if let (Some(index), Some(inner_path)) = (index, inner_path.as_ref()) {
+            let new_path = format!("{}/{}.[{}]", local_rc.get_path(), inner_path, index);
+            local_rc.set_path(new_path);
+        }
+
+        if let Some(block_param) = h.block_param() {
+            let mut map = BTreeMap::new();
+            map.insert(block_param.to_string(), item.clone());
+            local_rc.push_block_context(&map)?;
+        }
+
+        template.render(r, &mut local_rc)?;
+
+        if h.block_param().is_some() {
+            local_rc.pop_block_context();
+        }
+
+        if local_path_root.is_some() {
+            local_rc.pop_local_path_root();
+        }
+    }
+    rc.demote_local_vars();
+    Ok(())
+}
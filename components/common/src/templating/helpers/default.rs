@@ -0,0 +1,90 @@
+use handlebars::{Handlebars,
+                 Helper,
+                 HelperDef,
+                 RenderContext,
+                 RenderError};
+use serde_json::Value as Json;
+
+use super::{super::RenderResult,
+            to_text};
+
+/// Whether a value was left out: missing values, `null` and empty strings are. Unlike the
+/// truthiness used by `if`, `0` and `false` are proper values.
+fn is_missing(value: &Json) -> bool {
+    match *value {
+        Json::Null => true,
+        Json::String(ref s) => s.is_empty(),
+        _ => false,
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct DefaultHelper;
+
+impl HelperDef for DefaultHelper {
+    fn call(&self, h: &Helper<'_>, _: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let value = h.param(0)
+                     .ok_or_else(|| RenderError::new("Expected 2 parameters for \"default\""))?
+                     .value();
+        let fallback = h.param(1)
+                        .ok_or_else(|| RenderError::new("Expected 2 parameters for \"default\""))?
+                        .value();
+        let rendered = if is_missing(value) { fallback } else { value };
+        rc.writer.write_all(to_text(rendered).into_bytes().as_ref())?;
+        Ok(())
+    }
+}
+
+pub static DEFAULT: DefaultHelper = DefaultHelper;
+
+#[derive(Clone, Copy)]
+pub struct CoalesceHelper;
+
+impl HelperDef for CoalesceHelper {
+    fn call(&self, h: &Helper<'_>, _: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let value = h.params()
+                     .iter()
+                     .map(handlebars::ContextJson::value)
+                     .find(|v| !is_missing(v))
+                     .map(to_text)
+                     .unwrap_or_default();
+        rc.writer.write_all(value.into_bytes().as_ref())?;
+        Ok(())
+    }
+}
+
+pub static COALESCE: CoalesceHelper = CoalesceHelper;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn render(template: &str) -> String {
+        let json = json!({
+            "cfg": { "port": 0, "host": "", "name": "redis", "tls": false }
+        });
+        let mut handlebars = Handlebars::new();
+        handlebars.register_helper("default", Box::new(DEFAULT));
+        handlebars.register_helper("coalesce", Box::new(COALESCE));
+        handlebars.template_render(template, &json).unwrap()
+    }
+
+    #[test]
+    fn default_replaces_missing_values() {
+        assert_eq!(render("{{default cfg.missing 6379}}"), "6379");
+        assert_eq!(render("{{default cfg.host \"localhost\"}}"), "localhost");
+        assert_eq!(render("{{default cfg.name \"other\"}}"), "redis");
+    }
+
+    #[test]
+    fn default_keeps_zero_and_false() {
+        assert_eq!(render("{{default cfg.port 6379}}"), "0");
+        assert_eq!(render("{{default cfg.tls true}}"), "false");
+    }
+
+    #[test]
+    fn coalesce_renders_the_first_present_value() {
+        assert_eq!(render("{{coalesce cfg.missing cfg.host cfg.name \"x\"}}"), "redis");
+        assert_eq!(render("{{coalesce cfg.missing cfg.host}}"), "");
+    }
+}
@@ -0,0 +1,80 @@
+use handlebars::{Handlebars,
+                 Helper,
+                 HelperDef,
+                 RenderContext,
+                 RenderError};
+use serde_json::Value as Json;
+
+use super::{super::RenderResult,
+            render_items};
+
+/// Iterates over the members of a list which are alive and whose latest health check didn't
+/// fail, e.g. `{{#eachHealthy bind.backend.members as |member|}}`. Members gossiped by
+/// Supervisors which don't gossip their health, whose health is `UNKNOWN` or missing, are kept.
+#[derive(Clone, Copy)]
+pub struct EachHealthyHelper;
+
+impl HelperDef for EachHealthyHelper {
+    fn call(&self, h: &Helper<'_>, r: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let value = h.param(0)
+                     .ok_or_else(|| RenderError::new("Param not found for helper \"eachHealthy\""))?
+                     .value();
+        let items = match *value {
+            Json::Null => Vec::new(),
+            Json::Array(ref list) => {
+                list.iter()
+                    .enumerate()
+                    .filter(|&(_, member)| healthy(member))
+                    .map(|(i, member)| (Some(i), member.clone()))
+                    .collect()
+            }
+            _ => return Err(RenderError::new("Param type is not a list for \"eachHealthy\"")),
+        };
+        render_items(&items, h, r, rc)
+    }
+}
+
+pub static EACH_HEALTHY: EachHealthyHelper = EachHealthyHelper;
+
+/// Whether `member` is alive and not known to be unhealthy. This is the same as
+/// `CensusMember::healthy` in the Supervisor, and is shared with the `eachHealthy` Tera filter.
+pub(super) fn healthy(member: &Json) -> bool {
+    let health = member["health"].as_str().unwrap_or("UNKNOWN");
+    member["alive"].as_bool().unwrap_or(false) && (health == "OK" || health == "UNKNOWN")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn render(template: &str) -> String {
+        let json = json!({
+            "members": [
+                { "member_id": "a", "alive": true, "health": "OK" },
+                { "member_id": "b", "alive": true, "health": "CRITICAL" },
+                { "member_id": "c", "alive": false, "health": "OK" },
+                { "member_id": "d", "alive": true },
+                { "member_id": "e", "alive": true, "health": "OK" },
+                { "member_id": "f", "alive": true, "health": "UNKNOWN" },
+                { "member_id": "g", "alive": true, "health": "WARNING" }
+            ]
+        });
+        let mut handlebars = Handlebars::new();
+        handlebars.register_helper("eachHealthy", Box::new(EACH_HEALTHY));
+        handlebars.template_render(template, &json).unwrap()
+    }
+
+    #[test]
+    fn renders_only_alive_members_not_known_to_be_unhealthy() {
+        assert_eq!(render("{{#eachHealthy members as |m|}}{{m.member_id}}{{/eachHealthy}}"),
+                   "adef");
+        assert_eq!(render("{{#eachHealthy members}}{{member_id}}{{/eachHealthy}}"),
+                   "adef");
+    }
+
+    #[test]
+    fn renders_else_without_healthy_members() {
+        assert_eq!(render("{{#eachHealthy missing}}x{{else}}none{{/eachHealthy}}"),
+                   "none");
+    }
+}
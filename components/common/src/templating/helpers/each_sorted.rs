@@ -0,0 +1,110 @@
+use std::cmp::Ordering;
+
+use handlebars::{Handlebars,
+                 Helper,
+                 HelperDef,
+                 RenderContext,
+                 RenderError};
+use serde_json::Value as Json;
+
+use super::{super::RenderResult,
+            lookup,
+            render_items,
+            to_text};
+
+/// Iterates over a list in sorted order. Without a key the items themselves are compared,
+/// otherwise the values found at the given path in each item, e.g.
+/// `{{#eachSorted bind.db.members "sys.hostname"}}`.
+#[derive(Clone, Copy)]
+pub struct EachSortedHelper;
+
+impl HelperDef for EachSortedHelper {
+    fn call(&self, h: &Helper<'_>, r: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let value = h.param(0)
+                     .ok_or_else(|| RenderError::new("Param not found for helper \"eachSorted\""))?
+                     .value();
+        let key = match h.param(1) {
+            Some(key) => {
+                key.value().as_str().ok_or_else(|| {
+                                        RenderError::new("Expected a string key for \
+                                                          \"eachSorted\"")
+                                    })?
+            }
+            None => "",
+        };
+        let mut items = match *value {
+            Json::Null => Vec::new(),
+            Json::Array(ref list) => {
+                list.iter()
+                    .enumerate()
+                    .map(|(i, item)| (Some(i), item.clone()))
+                    .collect()
+            }
+            _ => return Err(RenderError::new("Param type is not a list for \"eachSorted\"")),
+        };
+        items.sort_by(|a, b| compare(lookup(&a.1, key), lookup(&b.1, key)));
+        render_items(&items, h, r, rc)
+    }
+}
+
+pub static EACH_SORTED: EachSortedHelper = EachSortedHelper;
+
+/// Numbers are compared by value and everything else by its text. Items without a value for the
+/// key come first.
+fn compare(a: Option<&Json>, b: Option<&Json>) -> Ordering {
+    match (a, b) {
+        (Some(&Json::Number(ref a)), Some(&Json::Number(ref b))) => {
+            a.as_f64()
+             .partial_cmp(&b.as_f64())
+             .unwrap_or(Ordering::Equal)
+        }
+        (Some(a), Some(b)) => to_text(a).cmp(&to_text(b)),
+        (None, Some(_)) => Ordering::Less,
+        (Some(_), None) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn render(template: &str) -> String {
+        let json = json!({
+            "ports": [8080, 443, 80],
+            "members": [
+                { "sys": { "hostname": "c" }, "cfg": { "weight": 10 } },
+                { "sys": { "hostname": "a" }, "cfg": { "weight": 2 } },
+                { "sys": { "hostname": "b" }, "cfg": { "weight": 1 } }
+            ],
+            "empty": []
+        });
+        let mut handlebars = Handlebars::new();
+        handlebars.register_helper("eachSorted", Box::new(EACH_SORTED));
+        handlebars.template_render(template, &json).unwrap()
+    }
+
+    #[test]
+    fn sorts_values() {
+        assert_eq!(render("{{#eachSorted ports as |port|}}{{port}} {{/eachSorted}}"),
+                   "80 443 8080 ");
+    }
+
+    #[test]
+    fn sorts_by_key() {
+        assert_eq!(render("{{#eachSorted members \"sys.hostname\"}}\
+                           {{sys.hostname}}\
+                           {{/eachSorted}}"),
+                   "abc");
+        assert_eq!(render("{{#eachSorted members \"cfg.weight\" as |m|}}\
+                           {{@index}}{{m.sys.hostname}}\
+                           {{/eachSorted}}"),
+                   "0b1a2c");
+    }
+
+    #[test]
+    fn renders_else_for_empty_lists() {
+        assert_eq!(render("{{#eachSorted empty}}x{{else}}none{{/eachSorted}}"), "none");
+        assert_eq!(render("{{#eachSorted missing}}x{{else}}none{{/eachSorted}}"), "none");
+    }
+}
@@ -0,0 +1,99 @@
+use handlebars::{Handlebars,
+                 Helper,
+                 HelperDef,
+                 RenderContext,
+                 RenderError};
+use serde_json::Value as Json;
+
+use super::{super::RenderResult,
+            lookup,
+            render_items,
+            to_text,
+            JsonTruthy};
+
+/// Iterates over the items of a list with a matching value at the given path. With a third
+/// parameter the value must be equal to it, e.g. `{{#eachWhere bind.db.members "cfg.role"
+/// "primary"}}`, otherwise it must be truthy, e.g. `{{#eachWhere bind.db.members "suspect"}}`.
+#[derive(Clone, Copy)]
+pub struct EachWhereHelper;
+
+impl HelperDef for EachWhereHelper {
+    fn call(&self, h: &Helper<'_>, r: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let value = h.param(0)
+                     .ok_or_else(|| RenderError::new("Expected 2 parameters for \"eachWhere\""))?
+                     .value();
+        let key = h.param(1)
+                   .and_then(|v| v.value().as_str())
+                   .ok_or_else(|| RenderError::new("Expected 2 parameters for \"eachWhere\""))?;
+        let expected = h.param(2).map(handlebars::ContextJson::value);
+        let items = match *value {
+            Json::Null => Vec::new(),
+            Json::Array(ref list) => {
+                list.iter()
+                    .enumerate()
+                    .filter(|&(_, item)| matches(lookup(item, key), expected))
+                    .map(|(i, item)| (Some(i), item.clone()))
+                    .collect()
+            }
+            _ => return Err(RenderError::new("Param type is not a list for \"eachWhere\"")),
+        };
+        render_items(&items, h, r, rc)
+    }
+}
+
+pub static EACH_WHERE: EachWhereHelper = EachWhereHelper;
+
+/// Values are compared by their text, so that `"8080"` matches `8080`.
+fn matches(found: Option<&Json>, expected: Option<&Json>) -> bool {
+    match (found, expected) {
+        (Some(found), Some(expected)) => found == expected || to_text(found) == to_text(expected),
+        (Some(found), None) => found.is_truthy(),
+        (None, _) => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn render(template: &str) -> String {
+        let json = json!({
+            "members": [
+                { "member_id": "a", "alive": true, "cfg": { "role": "primary", "port": 5432 } },
+                { "member_id": "b", "alive": false, "cfg": { "role": "replica" } },
+                { "member_id": "c", "alive": true, "cfg": { "role": "replica" } }
+            ]
+        });
+        let mut handlebars = Handlebars::new();
+        handlebars.register_helper("eachWhere", Box::new(EACH_WHERE));
+        handlebars.template_render(template, &json).unwrap()
+    }
+
+    #[test]
+    fn filters_on_truthy_values() {
+        assert_eq!(render("{{#eachWhere members \"alive\"}}{{member_id}}{{/eachWhere}}"),
+                   "ac");
+    }
+
+    #[test]
+    fn filters_on_equal_values() {
+        assert_eq!(render("{{#eachWhere members \"cfg.role\" \"replica\" as |m|}}\
+                           {{m.member_id}}\
+                           {{/eachWhere}}"),
+                   "bc");
+        assert_eq!(render("{{#eachWhere members \"cfg.port\" \"5432\"}}\
+                           {{member_id}}\
+                           {{/eachWhere}}"),
+                   "a");
+    }
+
+    #[test]
+    fn renders_else_without_matches() {
+        assert_eq!(render("{{#eachWhere members \"cfg.role\" \"arbiter\"}}\
+                           x\
+                           {{else}}\
+                           none\
+                           {{/eachWhere}}"),
+                   "none");
+    }
+}
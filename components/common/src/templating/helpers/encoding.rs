@@ -0,0 +1,98 @@
+use handlebars::{Handlebars,
+                 Helper,
+                 HelperDef,
+                 RenderContext,
+                 RenderError};
+use sodiumoxide::crypto::hash::sha256;
+
+use super::super::RenderResult;
+
+fn string_param<'a>(h: &'a Helper<'_>, name: &str) -> RenderResult<&'a str> {
+    h.param(0)
+     .and_then(|v| v.value().as_str())
+     .ok_or_else(|| RenderError::new(format!("Expected 1 string parameter for \"{}\"", name)))
+}
+
+#[derive(Clone, Copy)]
+pub struct Base64EncodeHelper;
+
+impl HelperDef for Base64EncodeHelper {
+    fn call(&self, h: &Helper<'_>, _: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let param = string_param(h, "base64Encode")?;
+        rc.writer
+          .write_all(base64::encode(param).into_bytes().as_ref())?;
+        Ok(())
+    }
+}
+
+pub static BASE64_ENCODE: Base64EncodeHelper = Base64EncodeHelper;
+
+#[derive(Clone, Copy)]
+pub struct Base64DecodeHelper;
+
+impl HelperDef for Base64DecodeHelper {
+    fn call(&self, h: &Helper<'_>, _: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let param = string_param(h, "base64Decode")?;
+        let bytes = base64::decode(param.trim()).map_err(|e| {
+                        RenderError::new(format!("Can't decode base64 parameter: {}", e))
+                    })?;
+        let decoded = String::from_utf8(bytes).map_err(|_| {
+                          RenderError::new("Decoded base64 parameter is not valid UTF-8")
+                      })?;
+        rc.writer.write_all(decoded.into_bytes().as_ref())?;
+        Ok(())
+    }
+}
+
+pub static BASE64_DECODE: Base64DecodeHelper = Base64DecodeHelper;
+
+/// Renders the SHA-256 digest of a string as lowercase hex.
+#[derive(Clone, Copy)]
+pub struct Sha256Helper;
+
+impl HelperDef for Sha256Helper {
+    fn call(&self, h: &Helper<'_>, _: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let param = string_param(h, "sha256")?;
+        let digest = sha256::hash(param.as_bytes());
+        rc.writer
+          .write_all(hex::encode(&digest.0[..]).into_bytes().as_ref())?;
+        Ok(())
+    }
+}
+
+pub static SHA256: Sha256Helper = Sha256Helper;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn render(template: &str) -> Result<String, handlebars::TemplateRenderError> {
+        let json = json!({ "cfg": { "password": "hunter2", "encoded": "aHVudGVyMg==" } });
+        let mut handlebars = Handlebars::new();
+        handlebars.register_helper("base64Encode", Box::new(BASE64_ENCODE));
+        handlebars.register_helper("base64Decode", Box::new(BASE64_DECODE));
+        handlebars.register_helper("sha256", Box::new(SHA256));
+        handlebars.template_render(template, &json)
+    }
+
+    #[test]
+    fn base64_round_trips() {
+        assert_eq!(render("{{base64Encode cfg.password}}").unwrap(), "aHVudGVyMg==");
+        assert_eq!(render("{{base64Decode cfg.encoded}}").unwrap(), "hunter2");
+        assert_eq!(render("{{base64Decode (base64Encode cfg.password)}}").unwrap(),
+                   "hunter2");
+    }
+
+    #[test]
+    fn base64_decode_rejects_invalid_input() {
+        assert!(render("{{base64Decode \"not base64!\"}}").is_err());
+    }
+
+    #[test]
+    fn sha256_renders_hex_digests() {
+        assert_eq!(render("{{sha256 \"\"}}").unwrap(),
+                   "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+        assert_eq!(render("{{sha256 cfg.password}}").unwrap(),
+                   "f52fbd32b2b3b86ff88ef6c490628285f482af15ddcb29541f94bcf526a3f6c7");
+    }
+}
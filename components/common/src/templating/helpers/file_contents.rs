@@ -0,0 +1,80 @@
+use std::{fs,
+          path::{Component,
+                 Path,
+                 PathBuf}};
+
+use handlebars::{Handlebars,
+                 Helper,
+                 HelperDef,
+                 RenderContext,
+                 RenderError};
+
+use super::super::RenderResult;
+
+/// Renders the contents of a file uploaded to the service group with `hab file upload`, e.g.
+/// `{{fileContents "ca.pem"}}`. Only files in the service's `files` directory can be read.
+#[derive(Clone, Copy)]
+pub struct FileContentsHelper;
+
+impl HelperDef for FileContentsHelper {
+    fn call(&self, h: &Helper<'_>, _: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let name = h.param(0)
+                    .and_then(|v| v.value().as_str())
+                    .ok_or_else(|| {
+                        RenderError::new("Expected 1 string parameter for \"fileContents\"")
+                    })?;
+        if name.is_empty()
+           || Path::new(name).components().any(|c| {
+                                              match c {
+                                                  Component::Normal(_) => false,
+                                                  _ => true,
+                                              }
+                                          })
+        {
+            return Err(RenderError::new(format!("Invalid file name for \"fileContents\": {}",
+                                                name)));
+        }
+        let files_path = rc.context().data()["pkg"]["svc_files_path"]
+                           .as_str()
+                           .map(PathBuf::from)
+                           .ok_or_else(|| {
+                               RenderError::new("No service files path for \"fileContents\"")
+                           })?;
+        let contents = fs::read_to_string(files_path.join(name)).map_err(|e| {
+                           RenderError::new(format!("Can't read service file {}: {}", name, e))
+                       })?;
+        rc.writer.write_all(contents.into_bytes().as_ref())?;
+        Ok(())
+    }
+}
+
+pub static FILE_CONTENTS: FileContentsHelper = FileContentsHelper;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn render(files: &TempDir, template: &str) -> Option<String> {
+        let json = json!({ "pkg": { "svc_files_path": files.path() } });
+        let mut handlebars = Handlebars::new();
+        handlebars.register_helper("fileContents", Box::new(FILE_CONTENTS));
+        handlebars.template_render(template, &json).ok()
+    }
+
+    #[test]
+    fn renders_service_files() {
+        let files = TempDir::new().unwrap();
+        fs::write(files.path().join("ca.pem"), "-----BEGIN CERTIFICATE-----").unwrap();
+        assert_eq!(render(&files, "{{fileContents \"ca.pem\"}}").unwrap(),
+                   "-----BEGIN CERTIFICATE-----");
+        assert!(render(&files, "{{fileContents \"missing.pem\"}}").is_none());
+    }
+
+    #[test]
+    fn rejects_files_outside_of_the_files_directory() {
+        let files = TempDir::new().unwrap();
+        assert!(render(&files, "{{fileContents \"../config/app.conf\"}}").is_none());
+        assert!(render(&files, "{{fileContents \"/etc/passwd\"}}").is_none());
+    }
+}
@@ -0,0 +1,94 @@
+use handlebars::{Handlebars,
+                 Helper,
+                 HelperDef,
+                 RenderContext,
+                 RenderError};
+use serde_json::Value as Json;
+
+use super::super::RenderResult;
+
+/// Integer arithmetic on two parameters, which may be numbers or strings holding an integer,
+/// e.g. `{{intAdd cfg.port 1}}`. Overflows and division by zero are errors.
+#[derive(Clone, Copy)]
+pub struct IntMathHelper {
+    name: &'static str,
+    op:   fn(i64, i64) -> Option<i64>,
+}
+
+impl IntMathHelper {
+    fn param(&self, h: &Helper<'_>, idx: usize) -> RenderResult<i64> {
+        let value = h.param(idx)
+                     .ok_or_else(|| {
+                         RenderError::new(format!("Expected 2 parameters for \"{}\"", self.name))
+                     })?
+                     .value();
+        let int = match *value {
+            Json::Number(ref n) => n.as_i64(),
+            Json::String(ref s) => s.trim().parse().ok(),
+            _ => None,
+        };
+        int.ok_or_else(|| {
+               RenderError::new(format!("Expected an integer parameter for \"{}\", got {}",
+                                        self.name, value))
+           })
+    }
+}
+
+impl HelperDef for IntMathHelper {
+    fn call(&self, h: &Helper<'_>, _: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let a = self.param(h, 0)?;
+        let b = self.param(h, 1)?;
+        let result = (self.op)(a, b).ok_or_else(|| {
+                                         RenderError::new(format!("Can't compute \"{}\" of {} \
+                                                                   and {}",
+                                                                  self.name, a, b))
+                                     })?;
+        rc.writer.write_all(result.to_string().into_bytes().as_ref())?;
+        Ok(())
+    }
+}
+
+pub static INT_ADD: IntMathHelper = IntMathHelper { name: "intAdd",
+                                                    op:   i64::checked_add, };
+pub static INT_SUB: IntMathHelper = IntMathHelper { name: "intSub",
+                                                    op:   i64::checked_sub, };
+pub static INT_MUL: IntMathHelper = IntMathHelper { name: "intMul",
+                                                    op:   i64::checked_mul, };
+pub static INT_DIV: IntMathHelper = IntMathHelper { name: "intDiv",
+                                                    op:   i64::checked_div, };
+pub static INT_MOD: IntMathHelper = IntMathHelper { name: "intMod",
+                                                    op:   i64::checked_rem, };
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn render(template: &str) -> Option<String> {
+        let json = json!({ "cfg": { "port": 8080, "workers": "6" } });
+        let mut handlebars = Handlebars::new();
+        handlebars.register_helper("intAdd", Box::new(INT_ADD));
+        handlebars.register_helper("intSub", Box::new(INT_SUB));
+        handlebars.register_helper("intMul", Box::new(INT_MUL));
+        handlebars.register_helper("intDiv", Box::new(INT_DIV));
+        handlebars.register_helper("intMod", Box::new(INT_MOD));
+        handlebars.template_render(template, &json).ok()
+    }
+
+    #[test]
+    fn computes_integer_math() {
+        assert_eq!(render("{{intAdd cfg.port 1}}").unwrap(), "8081");
+        assert_eq!(render("{{intSub cfg.port 80}}").unwrap(), "8000");
+        assert_eq!(render("{{intMul cfg.workers 2}}").unwrap(), "12");
+        assert_eq!(render("{{intDiv cfg.workers 4}}").unwrap(), "1");
+        assert_eq!(render("{{intMod cfg.port 7}}").unwrap(), "2");
+        assert_eq!(render("{{intAdd (intMul cfg.workers 2) -1}}").unwrap(), "11");
+    }
+
+    #[test]
+    fn rejects_invalid_operations() {
+        assert!(render("{{intDiv cfg.port 0}}").is_none());
+        assert!(render("{{intAdd cfg.port \"one\"}}").is_none());
+        assert!(render("{{intAdd cfg.port 1.5}}").is_none());
+        assert!(render("{{intMul 9223372036854775807 2}}").is_none());
+    }
+}
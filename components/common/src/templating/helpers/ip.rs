@@ -0,0 +1,234 @@
+use std::net::{IpAddr,
+               Ipv4Addr,
+               Ipv6Addr};
+
+use handlebars::{Handlebars,
+                 Helper,
+                 HelperDef,
+                 RenderContext,
+                 RenderError};
+use serde_json::Value as Json;
+
+use super::{super::RenderResult,
+            render_condition};
+
+/// A network in CIDR notation, e.g. `10.0.0.0/16` or `fd00::/8`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Cidr {
+    addr:   IpAddr,
+    prefix: u32,
+}
+
+impl Cidr {
+    fn parse(value: &str) -> Option<Self> {
+        let mut parts = value.trim().splitn(2, '/');
+        let addr: IpAddr = parts.next()?.parse().ok()?;
+        let prefix = match parts.next() {
+            Some(prefix) => prefix.parse().ok()?,
+            None => width(addr),
+        };
+        if prefix > width(addr) {
+            return None;
+        }
+        Some(Cidr { addr, prefix })
+    }
+
+    fn mask(self) -> u128 {
+        let width = width(self.addr);
+        let all = if width == 128 { !0 } else { (1u128 << width) - 1 };
+        all.checked_shl(width - self.prefix).unwrap_or(0) & all
+    }
+
+    fn contains(self, addr: IpAddr) -> bool {
+        addr.is_ipv4() == self.addr.is_ipv4()
+        && bits(addr) & self.mask() == bits(self.addr) & self.mask()
+    }
+
+    fn network(self) -> IpAddr { from_bits(self.addr, bits(self.addr) & self.mask()) }
+
+    fn netmask(self) -> IpAddr { from_bits(self.addr, self.mask()) }
+
+    /// The `n`th address of the network; negative numbers count back from the last address.
+    fn host(self, n: i64) -> Option<IpAddr> {
+        let host_bits = width(self.addr) - self.prefix;
+        let last = if host_bits == 128 { !0 } else { (1u128 << host_bits) - 1 };
+        let offset = if n < 0 {
+            last.checked_sub(u128::from((n + 1).abs() as u64))?
+        } else {
+            u128::from(n as u64)
+        };
+        if offset > last {
+            return None;
+        }
+        Some(from_bits(self.addr, (bits(self.addr) & self.mask()) | offset))
+    }
+}
+
+fn width(addr: IpAddr) -> u32 {
+    match addr {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    }
+}
+
+fn bits(addr: IpAddr) -> u128 {
+    match addr {
+        IpAddr::V4(addr) => u128::from(u32::from(addr)),
+        IpAddr::V6(addr) => u128::from(addr),
+    }
+}
+
+fn from_bits(family: IpAddr, bits: u128) -> IpAddr {
+    match family {
+        IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::from(bits as u32)),
+        IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::from(bits)),
+    }
+}
+
+fn cidr_param(h: &Helper<'_>, idx: usize, name: &str) -> RenderResult<Cidr> {
+    let value = h.param(idx)
+                 .and_then(|v| v.value().as_str())
+                 .ok_or_else(|| {
+                     RenderError::new(format!("Expected a CIDR parameter for \"{}\"", name))
+                 })?;
+    Cidr::parse(value).ok_or_else(|| {
+                          RenderError::new(format!("Invalid CIDR for \"{}\": {}", name, value))
+                      })
+}
+
+/// Renders its block if an address is part of a network, e.g. `{{#ipInCidr sys.ip
+/// "10.0.0.0/8"}}`, and its `else` block otherwise.
+#[derive(Clone, Copy)]
+pub struct IpInCidrHelper;
+
+impl HelperDef for IpInCidrHelper {
+    fn call(&self, h: &Helper<'_>, r: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let addr = h.param(0)
+                    .and_then(|v| v.value().as_str())
+                    .and_then(|v| v.trim().parse::<IpAddr>().ok())
+                    .ok_or_else(|| {
+                        RenderError::new("Expected an IP address parameter for \"ipInCidr\"")
+                    })?;
+        let cidr = cidr_param(h, 1, "ipInCidr")?;
+        render_condition(cidr.contains(addr), h, r, rc)
+    }
+}
+
+pub static IP_IN_CIDR: IpInCidrHelper = IpInCidrHelper;
+
+/// Renders the network address of a CIDR, e.g. `10.0.0.0` for `10.0.1.17/16`.
+#[derive(Clone, Copy)]
+pub struct CidrNetworkHelper;
+
+impl HelperDef for CidrNetworkHelper {
+    fn call(&self, h: &Helper<'_>, _: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let cidr = cidr_param(h, 0, "cidrNetwork")?;
+        rc.writer
+          .write_all(cidr.network().to_string().into_bytes().as_ref())?;
+        Ok(())
+    }
+}
+
+pub static CIDR_NETWORK: CidrNetworkHelper = CidrNetworkHelper;
+
+/// Renders the netmask of a CIDR, e.g. `255.255.0.0` for `10.0.1.17/16`.
+#[derive(Clone, Copy)]
+pub struct CidrNetmaskHelper;
+
+impl HelperDef for CidrNetmaskHelper {
+    fn call(&self, h: &Helper<'_>, _: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let cidr = cidr_param(h, 0, "cidrNetmask")?;
+        rc.writer
+          .write_all(cidr.netmask().to_string().into_bytes().as_ref())?;
+        Ok(())
+    }
+}
+
+pub static CIDR_NETMASK: CidrNetmaskHelper = CidrNetmaskHelper;
+
+/// Renders the address with the given number in a network, e.g. `10.0.0.5` for
+/// `{{cidrHost "10.0.0.0/16" 5}}`. Negative numbers count back from the last address.
+#[derive(Clone, Copy)]
+pub struct CidrHostHelper;
+
+impl HelperDef for CidrHostHelper {
+    fn call(&self, h: &Helper<'_>, _: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let cidr = cidr_param(h, 0, "cidrHost")?;
+        let n = h.param(1)
+                 .and_then(|v| {
+                     match *v.value() {
+                         Json::Number(ref n) => n.as_i64(),
+                         Json::String(ref s) => s.trim().parse().ok(),
+                         _ => None,
+                     }
+                 })
+                 .ok_or_else(|| RenderError::new("Expected a host number for \"cidrHost\""))?;
+        let host = cidr.host(n).ok_or_else(|| {
+                                   RenderError::new(format!("Host number {} is outside of the \
+                                                             network for \"cidrHost\"",
+                                                            n))
+                               })?;
+        rc.writer.write_all(host.to_string().into_bytes().as_ref())?;
+        Ok(())
+    }
+}
+
+pub static CIDR_HOST: CidrHostHelper = CidrHostHelper;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn render(template: &str) -> Option<String> {
+        let json = json!({ "sys": { "ip": "10.0.1.17" }, "cfg": { "net": "10.0.0.0/16" } });
+        let mut handlebars = Handlebars::new();
+        handlebars.register_helper("ipInCidr", Box::new(IP_IN_CIDR));
+        handlebars.register_helper("cidrNetwork", Box::new(CIDR_NETWORK));
+        handlebars.register_helper("cidrNetmask", Box::new(CIDR_NETMASK));
+        handlebars.register_helper("cidrHost", Box::new(CIDR_HOST));
+        handlebars.template_render(template, &json).ok()
+    }
+
+    #[test]
+    fn parses_cidrs() {
+        assert_eq!(Cidr::parse("10.0.0.0/8"),
+                   Some(Cidr { addr:   "10.0.0.0".parse().unwrap(),
+                               prefix: 8, }));
+        assert_eq!(Cidr::parse("fd00::1").map(|c| c.prefix), Some(128));
+        assert_eq!(Cidr::parse("10.0.0.0/33"), None);
+        assert_eq!(Cidr::parse("10.0.0/8"), None);
+    }
+
+    #[test]
+    fn ip_in_cidr_renders_matching_block() {
+        assert_eq!(render("{{#ipInCidr sys.ip cfg.net}}in{{else}}out{{/ipInCidr}}").unwrap(),
+                   "in");
+        assert_eq!(render("{{#ipInCidr sys.ip \"10.1.0.0/16\"}}in{{else}}out{{/ipInCidr}}")
+                       .unwrap(),
+                   "out");
+        assert_eq!(render("{{#ipInCidr \"fd00::1\" \"fd00::/8\"}}in{{/ipInCidr}}").unwrap(),
+                   "in");
+        assert_eq!(render("{{#ipInCidr sys.ip \"::/0\"}}in{{else}}out{{/ipInCidr}}").unwrap(),
+                   "out");
+        assert_eq!(render("{{#ipInCidr sys.ip \"0.0.0.0/0\"}}in{{/ipInCidr}}").unwrap(),
+                   "in");
+    }
+
+    #[test]
+    fn renders_networks_and_netmasks() {
+        assert_eq!(render("{{cidrNetwork \"10.0.1.17/16\"}}").unwrap(), "10.0.0.0");
+        assert_eq!(render("{{cidrNetmask \"10.0.1.17/20\"}}").unwrap(), "255.255.240.0");
+        assert_eq!(render("{{cidrNetmask \"10.0.1.17/0\"}}").unwrap(), "0.0.0.0");
+        assert_eq!(render("{{cidrNetwork \"fd00:1:2::3/48\"}}").unwrap(), "fd00:1:2::");
+        assert!(render("{{cidrNetwork \"nonsense\"}}").is_none());
+    }
+
+    #[test]
+    fn renders_hosts() {
+        assert_eq!(render("{{cidrHost cfg.net 5}}").unwrap(), "10.0.0.5");
+        assert_eq!(render("{{cidrHost cfg.net 258}}").unwrap(), "10.0.1.2");
+        assert_eq!(render("{{cidrHost cfg.net -1}}").unwrap(), "10.0.255.255");
+        assert_eq!(render("{{cidrHost \"fd00::/64\" 10}}").unwrap(), "fd00::a");
+        assert!(render("{{cidrHost \"10.0.0.0/30\" 4}}").is_none());
+    }
+}
@@ -0,0 +1,67 @@
+use std::{collections::HashMap,
+          sync::Arc};
+
+use handlebars::{Handlebars,
+                 Helper,
+                 HelperDef,
+                 RenderContext,
+                 RenderError};
+use tera;
+
+use super::super::{secrets::{self,
+                             SecretLeases},
+                   RenderResult};
+
+/// Renders a secret looked up through the secret providers, e.g. `{{secret "vault:db/password"}}`.
+///
+/// Leases of the secrets are tracked in the renderer's `SecretLeases` so the templates can be
+/// rendered again once a lease needs to be renewed.
+#[derive(Clone)]
+pub struct SecretHelper(Arc<SecretLeases>);
+
+impl SecretHelper {
+    pub fn new(leases: Arc<SecretLeases>) -> Self { SecretHelper(leases) }
+}
+
+impl HelperDef for SecretHelper {
+    fn call(&self, h: &Helper<'_>, _: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let reference = h.param(0)
+                         .and_then(|v| v.value().as_str())
+                         .ok_or_else(|| {
+                             RenderError::new("Expected a secret reference for \"secret\"")
+                         })?;
+        let secret = lookup(&self.0, reference).map_err(RenderError::new)?;
+        rc.writer.write_all(secret.into_bytes().as_ref())?;
+        Ok(())
+    }
+}
+
+/// The `secret` function of Tera templates, e.g. `{{ secret(ref="vault:db/password") }}`.
+#[derive(Clone)]
+pub struct SecretFunction(Arc<SecretLeases>);
+
+impl SecretFunction {
+    pub fn new(leases: Arc<SecretLeases>) -> Self { SecretFunction(leases) }
+}
+
+impl tera::Function for SecretFunction {
+    fn call(&self, args: &HashMap<String, tera::Value>) -> tera::Result<tera::Value> {
+        let reference = args.get("ref")
+                            .and_then(tera::Value::as_str)
+                            .ok_or_else(|| {
+                                tera::Error::msg("Expected a secret reference for \"secret\"")
+                            })?;
+        lookup(&self.0, reference).map(tera::Value::String)
+                                  .map_err(tera::Error::msg)
+    }
+}
+
+fn lookup(leases: &SecretLeases, reference: &str) -> Result<String, String> {
+    let secret = secrets::get(reference).map_err(|e| {
+                                            format!("Failed to read secret {}: {}", reference, e)
+                                        })?;
+    if let Some(renew_at) = secret.renew_at {
+        leases.track(renew_at);
+    }
+    Ok(secret.value)
+}
@@ -0,0 +1,76 @@
+use handlebars::{Handlebars,
+                 Helper,
+                 HelperDef,
+                 RenderContext,
+                 RenderError};
+use serde_json::Value as Json;
+
+use super::{super::RenderResult,
+            render_condition,
+            to_text};
+
+/// Renders its block if a string contains a substring, or a list contains a value, e.g.
+/// `{{#strContains cfg.features "tls"}}`, and its `else` block otherwise.
+#[derive(Clone, Copy)]
+pub struct StrContainsHelper;
+
+impl HelperDef for StrContainsHelper {
+    fn call(&self, h: &Helper<'_>, r: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let haystack = h.param(0)
+                        .ok_or_else(|| {
+                            RenderError::new("Expected 2 parameters for \"strContains\"")
+                        })?
+                        .value();
+        let needle = h.param(1)
+                      .map(|v| to_text(v.value()))
+                      .ok_or_else(|| {
+                          RenderError::new("Expected 2 parameters for \"strContains\"")
+                      })?;
+        let contains = match *haystack {
+            Json::String(ref s) => s.contains(&needle),
+            Json::Array(ref list) => list.iter().any(|v| to_text(v) == needle),
+            Json::Null => false,
+            _ => {
+                return Err(RenderError::new("Expected a string or a list for \
+                                             \"strContains\""));
+            }
+        };
+        render_condition(contains, h, r, rc)
+    }
+}
+
+pub static STR_CONTAINS: StrContainsHelper = StrContainsHelper;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn render(template: &str) -> String {
+        let json = json!({ "cfg": { "features": ["tls", "auth"], "mode": "cluster-tls" } });
+        let mut handlebars = Handlebars::new();
+        handlebars.register_helper("strContains", Box::new(STR_CONTAINS));
+        handlebars.template_render(template, &json).unwrap()
+    }
+
+    #[test]
+    fn checks_strings() {
+        assert_eq!(render("{{#strContains cfg.mode \"tls\"}}yes{{else}}no{{/strContains}}"),
+                   "yes");
+        assert_eq!(render("{{#strContains cfg.mode \"raft\"}}yes{{else}}no{{/strContains}}"),
+                   "no");
+    }
+
+    #[test]
+    fn checks_lists() {
+        assert_eq!(render("{{#strContains cfg.features \"auth\"}}yes{{/strContains}}"),
+                   "yes");
+        assert_eq!(render("{{#strContains cfg.features \"au\"}}yes{{else}}no{{/strContains}}"),
+                   "no");
+    }
+
+    #[test]
+    fn renders_the_result_outside_of_blocks() {
+        assert_eq!(render("{{strContains cfg.mode \"tls\"}}"), "true");
+        assert_eq!(render("{{strContains cfg.missing \"tls\"}}"), "false");
+    }
+}
@@ -0,0 +1,67 @@
+use handlebars::{Handlebars,
+                 Helper,
+                 HelperDef,
+                 RenderContext,
+                 RenderError};
+use serde_json::Value as Json;
+
+use super::{super::RenderResult,
+            render_items};
+
+/// Iterates over the parts of a string split by a separator. The parts are strings rather than
+/// values in the context, so they are reached through a block parameter, e.g.
+/// `{{#strSplit cfg.hosts "," as |host|}}`.
+#[derive(Clone, Copy)]
+pub struct StrSplitHelper;
+
+impl HelperDef for StrSplitHelper {
+    fn call(&self, h: &Helper<'_>, r: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let param = h.param(0)
+                     .and_then(|v| v.value().as_str())
+                     .ok_or_else(|| RenderError::new("Expected 2 parameters for \"strSplit\""))?;
+        let separator =
+            h.param(1)
+             .and_then(|v| v.value().as_str())
+             .ok_or_else(|| RenderError::new("Expected 2 parameters for \"strSplit\""))?;
+        let items: Vec<(Option<usize>, Json)> = if param.is_empty() {
+            Vec::new()
+        } else {
+            param.split(separator)
+                 .map(|part| (None, Json::String(part.to_string())))
+                 .collect()
+        };
+        render_items(&items, h, r, rc)
+    }
+}
+
+pub static STR_SPLIT: StrSplitHelper = StrSplitHelper;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn render(template: &str) -> String {
+        let json = json!({ "cfg": { "hosts": "a.example.com,b.example.com", "empty": "" } });
+        let mut handlebars = Handlebars::new();
+        handlebars.register_helper("strSplit", Box::new(STR_SPLIT));
+        handlebars.template_render(template, &json).unwrap()
+    }
+
+    #[test]
+    fn iterates_over_parts() {
+        assert_eq!(render("{{#strSplit cfg.hosts \",\" as |host|}}\
+                           {{@index}}={{host}}{{#unless @last}};{{/unless}}\
+                           {{/strSplit}}"),
+                   "0=a.example.com;1=b.example.com");
+    }
+
+    #[test]
+    fn renders_else_for_empty_strings() {
+        assert_eq!(render("{{#strSplit cfg.empty \",\" as |host|}}\
+                           {{host}}\
+                           {{else}}\
+                           none\
+                           {{/strSplit}}"),
+                   "none");
+    }
+}
@@ -0,0 +1,46 @@
+use handlebars::{Handlebars,
+                 Helper,
+                 HelperDef,
+                 RenderContext,
+                 RenderError};
+
+use super::super::RenderResult;
+
+/// Removes whitespace, or the characters given as a second parameter, from both ends of a
+/// string.
+#[derive(Clone, Copy)]
+pub struct StrTrimHelper;
+
+impl HelperDef for StrTrimHelper {
+    fn call(&self, h: &Helper<'_>, _: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let param =
+            h.param(0)
+             .and_then(|v| v.value().as_str())
+             .ok_or_else(|| RenderError::new("Expected a string parameter for \"strTrim\""))?;
+        let trimmed = match h.param(1).and_then(|v| v.value().as_str()) {
+            Some(chars) => param.trim_matches(|c: char| chars.contains(c)),
+            None => param.trim(),
+        };
+        rc.writer.write_all(trimmed.as_bytes())?;
+        Ok(())
+    }
+}
+
+pub static STR_TRIM: StrTrimHelper = StrTrimHelper;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_trim_helper() {
+        let json = json!({ "path": "  /hab/svc/redis/  \n" });
+        let mut handlebars = Handlebars::new();
+        handlebars.register_helper("strTrim", Box::new(STR_TRIM));
+        assert_eq!(handlebars.template_render("[{{strTrim path}}]", &json).unwrap(),
+                   "[/hab/svc/redis/]");
+        assert_eq!(handlebars.template_render("[{{strTrim (strTrim path) \"/\"}}]", &json)
+                             .unwrap(),
+                   "[hab/svc/redis]");
+    }
+}
@@ -0,0 +1,130 @@
+//! The Habitat helpers of Tera templates. Tera has its own, snake_case names for filters and
+//! functions, and most helpers have a built-in equivalent there, so only those which don't are
+//! registered here, under the name of the Handlebars helper:
+//!
+//! * `{{ cfg | toToml }}`, `toJson`, `toYaml` and `toIni` serialize a value
+//! * `{% for m in bind.db.members | eachAlive %}` and `eachHealthy` keep the members of a list
+//!   which are alive, or alive and not known to be unhealthy
+//! * `{{ secret(ref="vault:db/password") }}` reads a secret, see `SecretFunction`
+//!
+//! The string, integer and encoding helpers are Tera built-ins: `join(sep=",")` for `strJoin`,
+//! `split`, `trim`, `replace`, `upper`, `lower`, `base64_encode` and `base64_decode`, and the
+//! `+`, `-`, `*`, `/` and `%` operators for the `int*` helpers. `eachSorted` and `eachWhere` are
+//! the `sort` and `filter` filters, and `default` is the `default` filter. `pkgPathFor` and
+//! `fileContents` have no Tera equivalent yet, as Tera filters can't see the rendering context.
+
+use std::collections::HashMap;
+
+use serde_json::Value as Json;
+use tera::{self,
+           Tera};
+
+use super::{each_healthy::healthy,
+            to_ini};
+
+/// Registers the filters of this module with `tera`.
+pub fn register(tera: &mut Tera) {
+    tera.register_filter("toToml", to_toml);
+    tera.register_filter("toJson", to_json);
+    tera.register_filter("toYaml", to_yaml);
+    tera.register_filter("toIni", to_ini);
+    tera.register_filter("eachAlive", each_alive);
+    tera.register_filter("eachHealthy", each_healthy);
+}
+
+fn to_toml(value: &Json, _: &HashMap<String, Json>) -> tera::Result<Json> {
+    toml::to_string(value).map(Json::String)
+                          .map_err(|e| {
+                              tera::Error::msg(format!("Can't serialize value to TOML: {}", e))
+                          })
+}
+
+fn to_json(value: &Json, _: &HashMap<String, Json>) -> tera::Result<Json> {
+    serde_json::to_string_pretty(value).map(Json::String)
+                                       .map_err(|e| {
+                                           tera::Error::msg(format!("Can't serialize value to \
+                                                                     JSON: {}",
+                                                                    e))
+                                       })
+}
+
+fn to_yaml(value: &Json, _: &HashMap<String, Json>) -> tera::Result<Json> {
+    serde_yaml::to_string(value).map(Json::String)
+                                .map_err(|e| {
+                                    tera::Error::msg(format!("Can't serialize value to YAML: {}",
+                                                             e))
+                                })
+}
+
+fn to_ini(value: &Json, _: &HashMap<String, Json>) -> tera::Result<Json> {
+    let table = value.as_object()
+                     .ok_or_else(|| tera::Error::msg("Expected a table for \"toIni\""))?;
+    Ok(Json::String(to_ini::to_ini(table)))
+}
+
+fn each_alive(value: &Json, _: &HashMap<String, Json>) -> tera::Result<Json> {
+    members(value, "eachAlive", |member| member["alive"].as_bool().unwrap_or(false))
+}
+
+fn each_healthy(value: &Json, _: &HashMap<String, Json>) -> tera::Result<Json> {
+    members(value, "eachHealthy", healthy)
+}
+
+/// The members of the list `value` for which `keep` holds. A missing list has no members.
+fn members<F>(value: &Json, filter: &str, keep: F) -> tera::Result<Json>
+    where F: Fn(&Json) -> bool
+{
+    match *value {
+        Json::Null => Ok(Json::Array(Vec::new())),
+        Json::Array(ref list) => {
+            Ok(Json::Array(list.iter().filter(|m| keep(m)).cloned().collect()))
+        }
+        _ => Err(tera::Error::msg(format!("Expected a list for \"{}\"", filter))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn render(template: &str) -> String {
+        let json = json!({
+            "cfg": { "port": 80, "tls": { "enabled": true } },
+            "members": [
+                { "member_id": "a", "alive": true, "health": "OK" },
+                { "member_id": "b", "alive": true, "health": "CRITICAL" },
+                { "member_id": "c", "alive": false, "health": "OK" },
+                { "member_id": "d", "alive": true, "health": "UNKNOWN" }
+            ]
+        });
+        let mut tera = Tera::default();
+        register(&mut tera);
+        tera.add_raw_template("t", template).unwrap();
+        tera.render("t", &tera::Context::from_serialize(&json).unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn values_are_serialized() {
+        assert_eq!(render("{{ cfg | toToml }}"),
+                   "port = 80\n\n[tls]\nenabled = true\n");
+        assert_eq!(render("{{ cfg.tls | toJson }}"), "{\n  \"enabled\": true\n}");
+        assert_eq!(render("{{ cfg | toIni }}"), "port = 80\n\n[tls]\nenabled = true\n");
+        assert!(render("{{ cfg.tls | toYaml }}").contains("enabled: true"));
+    }
+
+    #[test]
+    fn members_are_filtered() {
+        assert_eq!(render("{% for m in members | eachAlive %}{{ m.member_id }}{% endfor %}"),
+                   "abd");
+        assert_eq!(render("{% for m in members | eachHealthy %}{{ m.member_id }}{% endfor %}"),
+                   "ad");
+    }
+
+    #[test]
+    fn built_in_filters_stand_in_for_the_string_helpers() {
+        assert_eq!(render("{{ members | map(attribute=\"member_id\") | join(sep=\",\") | upper \
+                           }}"),
+                   "A,B,C,D");
+    }
+}
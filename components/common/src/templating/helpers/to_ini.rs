@@ -0,0 +1,97 @@
+use handlebars::{Handlebars,
+                 Helper,
+                 HelperDef,
+                 RenderContext,
+                 RenderError};
+use serde_json::{Map,
+                 Value as Json};
+
+use super::{super::RenderResult,
+            to_text};
+
+/// Serializes a table to INI. Values at the top of the table come first, followed by a section
+/// for each nested table, named with the path to it, e.g. `[database.replica]`. INI has no lists,
+/// so their items are joined with commas.
+#[derive(Clone, Copy)]
+pub struct ToIniHelper;
+
+impl HelperDef for ToIniHelper {
+    fn call(&self, h: &Helper<'_>, _: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let table = h.param(0)
+                     .and_then(|v| v.value().as_object())
+                     .ok_or_else(|| RenderError::new("Expected a table parameter for \"toIni\""))?;
+        rc.writer.write_all(to_ini(table).as_bytes())?;
+        Ok(())
+    }
+}
+
+pub static TO_INI: ToIniHelper = ToIniHelper;
+
+/// Serializes `table` to INI, as described for `ToIniHelper`.
+pub(super) fn to_ini(table: &Map<String, Json>) -> String {
+    let mut ini = String::new();
+    write_section(&mut ini, None, table);
+    ini.trim_start().to_string()
+}
+
+fn write_section(ini: &mut String, name: Option<&str>, table: &Map<String, Json>) {
+    if let Some(name) = name {
+        ini.push_str(&format!("\n[{}]\n", name));
+    }
+    for (key, value) in table.iter().filter(|&(_, v)| !v.is_object()) {
+        let value = match *value {
+            Json::Array(ref list) => list.iter().map(to_text).collect::<Vec<_>>().join(","),
+            ref other => to_text(other),
+        };
+        ini.push_str(&format!("{} = {}\n", key, value));
+    }
+    for (key, value) in table.iter() {
+        if let Json::Object(ref nested) = *value {
+            let nested_name = match name {
+                Some(name) => format!("{}.{}", name, key),
+                None => key.to_string(),
+            };
+            write_section(ini, Some(&nested_name), nested);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_ini_helper() {
+        let json = json!({
+            "cfg": {
+                "log_level": "info",
+                "database": {
+                    "port": 5432,
+                    "hosts": ["a", "b"],
+                    "replica": { "enabled": true }
+                },
+                "workers": 4
+            }
+        });
+        let mut handlebars = Handlebars::new();
+        handlebars.register_helper("toIni", Box::new(TO_INI));
+        let expected = "log_level = info\n\
+                        workers = 4\n\
+                        \n\
+                        [database]\n\
+                        hosts = a,b\n\
+                        port = 5432\n\
+                        \n\
+                        [database.replica]\n\
+                        enabled = true\n";
+        assert_eq!(handlebars.template_render("{{toIni cfg}}", &json).unwrap(),
+                   expected);
+    }
+
+    #[test]
+    fn test_ini_helper_requires_a_table() {
+        let mut handlebars = Handlebars::new();
+        handlebars.register_helper("toIni", Box::new(TO_INI));
+        assert!(handlebars.template_render("{{toIni \"nope\"}}", &json!({})).is_err());
+    }
+}
@@ -1,5 +1,9 @@
+use self::history::{HookLog,
+                    HookRun};
 use super::{package::Pkg,
-            TemplateRenderer};
+            TemplateEngine,
+            TemplateRenderer,
+            TERA_EXTENSION};
 use crate::{error::{Error,
                     Result},
             outputln};
@@ -25,10 +29,23 @@ use std::{ffi::OsStr,
                BufReader},
           path::{Path,
                  PathBuf},
-          result};
+          result,
+          sync::mpsc::{self,
+                       RecvTimeoutError},
+          thread,
+          time::{Duration,
+                 Instant}};
+
+pub mod history;
 
 #[cfg(not(windows))]
 pub const HOOK_PERMISSIONS: u32 = 0o755;
+/// The number of seconds a hook may run for unless its package or service spec sets a timeout.
+pub const DEFAULT_HOOK_TIMEOUT_SECS: u32 = 600;
+/// How long a timed out hook is given to exit after being asked to terminate, before it is
+/// killed.
+#[cfg(unix)]
+const HOOK_TERMINATION_GRACE_PERIOD: Duration = Duration::from_secs(5);
 static LOGKEY: &'static str = "HK";
 
 pub fn stdout_log_path<T>(package_name: &str) -> PathBuf
@@ -63,8 +80,13 @@ pub trait Hook: fmt::Debug + Sized + Send {
 
     /// Tries to load a hook if a (deprecated) hook file exists.
     ///
-    /// Returns the hook if template file (deprecated or not) is found
-    fn load<C, T>(package_name: &str, concrete_path: C, template_path: T) -> Option<Self>
+    /// Returns the hook if template file (deprecated or not) is found. Hook files with a `.tera`
+    /// extension are Tera templates; all others are written for `engine`.
+    fn load<C, T>(package_name: &str,
+                  concrete_path: C,
+                  template_path: T,
+                  engine: TemplateEngine)
+                  -> Option<Self>
         where C: AsRef<Path>,
               T: AsRef<Path>
     {
@@ -76,10 +98,13 @@ pub trait Hook: fmt::Debug + Sized + Send {
         };
         let concrete = concrete_path.as_ref().join(&file_name);
         let template = template_path.as_ref().join(&file_name);
+        let tera_template = template_path.as_ref()
+                                         .join(format!("{}.{}", file_name, TERA_EXTENSION));
         let deprecated_template = deprecated_file_name.as_ref()
                                                       .map(|n| template_path.as_ref().join(n));
 
         let has_template = template.exists();
+        let has_tera_template = tera_template.exists();
         let has_deprecated_template = deprecated_template.as_ref().map_or(false, |t| t.exists());
 
         if has_template && file_name == "reload" {
@@ -96,7 +121,18 @@ pub trait Hook: fmt::Debug + Sized + Send {
                     &file_name
                 );
             }
+            if has_tera_template {
+                outputln!(preamble package_name,
+                    "Hook file {} detected along with {}. You should keep only one of them; \
+                     using {}.",
+                    tera_template.display(),
+                    &file_name,
+                    &file_name
+                );
+            }
             template
+        } else if has_tera_template {
+            tera_template
         } else if has_deprecated_template {
             outputln!(preamble package_name,
                 "Deprecated hook file detected: {}. You should use {} instead.",
@@ -110,7 +146,7 @@ pub trait Hook: fmt::Debug + Sized + Send {
                    template.display());
             return None;
         };
-        match RenderPair::new(concrete, &template_to_use, Self::file_name()) {
+        match RenderPair::new(concrete, &template_to_use, Self::file_name(), engine) {
             Ok(pair) => Some(Self::new(package_name, pair)),
             Err(err) => {
                 outputln!(preamble package_name, "Failed to load hook: {}", err);
@@ -183,7 +219,27 @@ pub trait Hook: fmt::Debug + Sized + Send {
                 Habitat core developers");
     }
 
-    /// Run a compiled hook.
+    /// The number of seconds this hook may run for when neither its package nor its service spec
+    /// sets a timeout.
+    fn default_timeout() -> u32 { DEFAULT_HOOK_TIMEOUT_SECS }
+
+    /// How long this hook may run for before it is terminated, or `None` if it may run forever,
+    /// which is requested with a timeout of 0 seconds.
+    fn timeout(pkg: &Pkg) -> Option<Duration> {
+        match pkg.hook_timeouts
+                 .get(Self::file_name())
+                 .cloned()
+                 .unwrap_or_else(Self::default_timeout)
+        {
+            0 => None,
+            secs => Some(Duration::from_secs(secs.into())),
+        }
+    }
+
+    /// The exit value of a hook which was terminated because it ran for longer than its timeout.
+    fn timed_out_value() -> Self::ExitValue { Self::ExitValue::default() }
+
+    /// Run a compiled hook, terminating it if it runs for longer than its timeout.
     fn run<T>(&self,
               service_group: &str,
               pkg: &Pkg,
@@ -199,9 +255,23 @@ pub trait Hook: fmt::Debug + Sized + Send {
                 return Self::ExitValue::default();
             }
         };
+        let watchdog = Self::timeout(pkg).and_then(|timeout| {
+                                             Watchdog::start::<Self>(service_group,
+                                                                     child.id(),
+                                                                     timeout)
+                                         });
+        let mut run = HookRun::start();
+        let started = Instant::now();
         let mut hook_output = HookOutput::new(self.stdout_log_path(), self.stderr_log_path());
-        hook_output.stream_output::<Self>(service_group, &mut child);
-        match child.wait() {
+        hook_output.stream_output::<Self>(service_group, &mut child, &run.run_id);
+        let status = child.wait();
+        let timed_out = watchdog.map_or(false, Watchdog::stop);
+        run.finish(started.elapsed(),
+                   status.as_ref().ok().and_then(ExitStatus::code),
+                   timed_out);
+        hook_output.record_run::<Self>(service_group, run);
+        match status {
+            Ok(_) if timed_out => Self::timed_out_value(),
             Ok(status) => self.handle_exit(pkg, &hook_output, status),
             Err(err) => {
                 outputln!(preamble service_group,
@@ -408,11 +478,15 @@ pub struct RenderPair {
 }
 
 impl RenderPair {
-    pub fn new<C, T>(concrete_path: C, template_path: T, name: &'static str) -> Result<Self>
+    pub fn new<C, T>(concrete_path: C,
+                     template_path: T,
+                     name: &'static str,
+                     engine: TemplateEngine)
+                     -> Result<Self>
         where C: Into<PathBuf>,
               T: AsRef<Path>
     {
-        let mut renderer = TemplateRenderer::new();
+        let mut renderer = TemplateRenderer::with_engine(engine);
         renderer.register_template_file(&name, template_path.as_ref())?;
         Ok(RenderPair { path: concrete_path.into(),
                         renderer })
@@ -433,6 +507,83 @@ impl Serialize for RenderPair {
     }
 }
 
+/// Watches a running hook from a separate thread, terminating its process if it runs for longer
+/// than its timeout.
+struct Watchdog {
+    cancel: mpsc::Sender<()>,
+    handle: thread::JoinHandle<bool>,
+}
+
+impl Watchdog {
+    fn start<H: Hook>(service_group: &str, pid: u32, timeout: Duration) -> Option<Self> {
+        let hook_name = H::file_name();
+        let (cancel, canceled) = mpsc::channel();
+        let service_group_copy = service_group.to_string();
+        let spawned = thread::Builder::new().name(format!("{}-watchdog", hook_name))
+                                            .spawn(move || {
+                                                match canceled.recv_timeout(timeout) {
+                                                    Err(RecvTimeoutError::Timeout) => {
+                                                        outputln!(preamble service_group_copy,
+                                                                  "{} timed out after {} \
+                                                                   seconds; terminating it",
+                                                                  hook_name,
+                                                                  timeout.as_secs());
+                                                        terminate(&service_group_copy,
+                                                                  hook_name,
+                                                                  pid,
+                                                                  &canceled);
+                                                        true
+                                                    }
+                                                    _ => false,
+                                                }
+                                            });
+        match spawned {
+            Ok(handle) => Some(Watchdog { cancel, handle }),
+            Err(err) => {
+                outputln!(preamble service_group,
+                          "Unable to enforce the timeout of {}, {}", hook_name, err);
+                None
+            }
+        }
+    }
+
+    /// Stop watching the hook once it has exited. Returns `true` if the hook was terminated
+    /// because it timed out.
+    fn stop(self) -> bool {
+        drop(self.cancel);
+        self.handle.join().unwrap_or(false)
+    }
+}
+
+/// Hooks run in their own process group, so signal the whole group to also take care of anything
+/// the hook started. Politely ask the hook to exit first, and kill it if it's still running after
+/// the grace period.
+#[cfg(unix)]
+fn terminate(service_group: &str, hook_name: &str, pid: u32, canceled: &mpsc::Receiver<()>) {
+    use habitat_core::os::process::{self,
+                                    Pid,
+                                    Signal};
+
+    let pgid = -(pid as Pid);
+    if let Err(err) = process::signal(pgid, Signal::TERM) {
+        debug!("Unable to send SIGTERM to {} hook: {}", hook_name, err);
+    }
+    if let Err(RecvTimeoutError::Timeout) = canceled.recv_timeout(HOOK_TERMINATION_GRACE_PERIOD) {
+        outputln!(preamble service_group,
+                  "{} did not exit after SIGTERM; sending SIGKILL", hook_name);
+        if let Err(err) = process::signal(pgid, Signal::KILL) {
+            debug!("Unable to send SIGKILL to {} hook: {}", hook_name, err);
+        }
+    }
+}
+
+#[cfg(windows)]
+fn terminate(service_group: &str, hook_name: &str, pid: u32, _: &mpsc::Receiver<()>) {
+    if let Err(err) = habitat_core::os::process::terminate(pid) {
+        outputln!(preamble service_group, "Unable to terminate {}, {}", hook_name, err);
+    }
+}
+
 pub struct HookOutput<'a> {
     stdout_log_file: &'a Path,
     stderr_log_file: &'a Path,
@@ -458,11 +609,19 @@ impl<'a> HookOutput<'a> {
         }
     }
 
-    fn stream_output<H: Hook>(&mut self, service_group: &str, process: &mut Child) {
+    /// Streams the output of `process` to the output of the Supervisor and to the logs of the
+    /// hook. The logs holding the output of the latest run are overwritten, while the log of all
+    /// runs is appended to.
+    fn stream_output<H: Hook>(&mut self,
+                              service_group: &str,
+                              process: &mut Child,
+                              run_id: &str) {
         let mut stdout_log =
             File::create(&self.stdout_log_file).expect("couldn't create log output file");
         let mut stderr_log =
             File::create(&self.stderr_log_file).expect("couldn't create log output file");
+        let mut hook_log =
+            HookLog::configured(history::log_path(self.logs_dir(), H::file_name()), run_id);
 
         let preamble_str = self.stream_preamble::<H>(service_group);
         if let Some(ref mut stdout) = process.stdout {
@@ -471,6 +630,9 @@ impl<'a> HookOutput<'a> {
                     outputln!(preamble preamble_str, l);
                     stdout_log.write_fmt(format_args!("{}\n", l))
                               .expect("couldn't write line");
+                    if let Err(err) = hook_log.write_line("stdout", l) {
+                        debug!("Unable to write to {} log: {}", H::file_name(), err);
+                    }
                 }
             }
         }
@@ -480,11 +642,30 @@ impl<'a> HookOutput<'a> {
                     outputln!(preamble preamble_str, l);
                     stderr_log.write_fmt(format_args!("{}\n", l))
                               .expect("couldn't write line");
+                    if let Err(err) = hook_log.write_line("stderr", l) {
+                        debug!("Unable to write to {} log: {}", H::file_name(), err);
+                    }
                 }
             }
         }
     }
 
+    /// Adds a finished run to the history of the hook.
+    fn record_run<H: Hook>(&self, service_group: &str, run: HookRun) {
+        let path = history::history_path(self.logs_dir(), H::file_name());
+        if let Err(err) = history::record_configured_run(&path, run) {
+            outputln!(preamble service_group,
+                      "Unable to record the run of {}, {}", H::file_name(), err);
+        }
+    }
+
+    /// The logs of a hook all live next to the log holding the stdout of its latest run.
+    fn logs_dir(&self) -> &Path {
+        self.stdout_log_file
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+    }
+
     fn stream_preamble<H: Hook>(&self, service_group: &str) -> String {
         format!("{} hook[{}]:", service_group, H::file_name())
     }
@@ -547,7 +728,10 @@ mod tests {
         let concrete_path = rendered_hooks_path();
         let template_path = hook_templates_path();
 
-        let hook = InstallHook::load(&service_group, &concrete_path, &template_path)
+        let hook = InstallHook::load(&service_group,
+                                     &concrete_path,
+                                     &template_path,
+                                     TemplateEngine::Handlebars)
             .expect("Could not create testing install hook");
 
         let content = r#"
@@ -566,7 +750,10 @@ echo "The message is Hello World"
         let service_group = service_group();
         let concrete_path = rendered_hooks_path();
         let template_path = hook_templates_path();
-        let hook = InstallHook::load(&service_group, &concrete_path, &template_path)
+        let hook = InstallHook::load(&service_group,
+                                     &concrete_path,
+                                     &template_path,
+                                     TemplateEngine::Handlebars)
             .expect("Could not create testing install hook");
 
         assert_eq!(hash_content(hook.path()).unwrap(), "");
@@ -578,7 +765,10 @@ echo "The message is Hello World"
         let concrete_path = rendered_hooks_path();
         let template_path = hook_templates_path();
 
-        let hook = InstallHook::load(&service_group, &concrete_path, &template_path)
+        let hook = InstallHook::load(&service_group,
+                                     &concrete_path,
+                                     &template_path,
+                                     TemplateEngine::Handlebars)
             .expect("Could not create testing install hook");
 
         // Since we're trying to update a file that should already
@@ -608,7 +798,10 @@ echo "The message is Hello World"
         let concrete_path = rendered_hooks_path();
         let template_path = hook_templates_path();
 
-        let hook = InstallHook::load(&service_group, &concrete_path, &template_path)
+        let hook = InstallHook::load(&service_group,
+                                     &concrete_path,
+                                     &template_path,
+                                     TemplateEngine::Handlebars)
             .expect("Could not create testing install hook");
 
         // In this test, we'll start with *no* rendered content.
@@ -635,7 +828,10 @@ echo "The message is Hello World"
         let concrete_path = rendered_hooks_path();
         let template_path = hook_templates_path();
 
-        let hook = InstallHook::load(&service_group, &concrete_path, &template_path)
+        let hook = InstallHook::load(&service_group,
+                                     &concrete_path,
+                                     &template_path,
+                                     TemplateEngine::Handlebars)
             .expect("Could not create testing install hook");
 
         let initial_content = r#"
@@ -660,6 +856,26 @@ echo "The message is Hola Mundo"
         assert_eq!(post_change_content, updated_content);
     }
 
+    #[test]
+    fn load_a_tera_hook() {
+        let service_group = service_group();
+        let concrete_path = rendered_hooks_path();
+        let template_path = TempDir::new().expect("create temp dir");
+        create_with_content(template_path.path().join("install.tera"),
+                            "echo \"{{ cfg.message | lower }}\"");
+
+        let hook = InstallHook::load(&service_group,
+                                     &concrete_path,
+                                     &template_path,
+                                     TemplateEngine::Handlebars)
+            .expect("Could not create testing install hook");
+
+        assert_eq!(hook.path(), concrete_path.path().join("install"));
+        let data = json!({ "cfg": { "message": "Hello World" } });
+        assert_eq!(hook.renderer().render("install", &data).unwrap(),
+                   "echo \"hello world\"");
+    }
+
     /// Avert your eyes, children; avert your eyes!
     ///
     /// All I wanted was a simple RenderContext so I could compile a
@@ -692,7 +908,10 @@ echo "The message is Hola Mundo"
         let concrete_path = rendered_hooks_path();
         let template_path = hook_templates_path();
 
-        let hook = InstallHook::load(&service_group, &concrete_path, &template_path)
+        let hook = InstallHook::load(&service_group,
+                                     &concrete_path,
+                                     &template_path,
+                                     TemplateEngine::Handlebars)
             .expect("Could not create testing install hook");
 
         ////////////////////////////////////////////////////////////////////////
@@ -769,7 +988,7 @@ echo "The message is Hello"
             ServiceGroup::new(None, "dummy", "service", None).expect("couldn't create \
                                                                       ServiceGroup");
 
-        hook_output.stream_output::<InstallHook>(&service_group, &mut child);
+        hook_output.stream_output::<InstallHook>(&service_group, &mut child, "test-run");
 
         let mut stdout = String::new();
         hook_output.stdout()
@@ -785,6 +1004,80 @@ echo "The message is Hello"
                    .expect("couldn't read stderr");
         assert_eq!(stderr, "This is stderr\n");
 
+        let log = stdfs::read_to_string(tmp_dir.path()
+                                               .join("logs")
+                                               .join(format!("{}.log", InstallHook::file_name())))
+                  .expect("couldn't read hook log");
+        let lines: Vec<&str> = log.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with(" test-run stdout This is stdout"));
+        assert!(lines[1].ends_with(" test-run stderr This is stderr"));
+
         stdfs::remove_dir_all(tmp_dir).expect("remove temp dir");
     }
+
+    fn test_pkg() -> Pkg {
+        let pg_id = PackageIdent::new("testing",
+                                      "test_service",
+                                      Some("1.0.0"),
+                                      Some("20170712000000"));
+        let pkg_install = PackageInstall::new_from_parts(pg_id,
+                                                         PathBuf::from("/tmp"),
+                                                         PathBuf::from("/tmp"),
+                                                         PathBuf::from("/tmp"));
+        Pkg::from_install(&pkg_install).expect("Could not create package!")
+    }
+
+    #[test]
+    fn hook_timeouts_fall_back_to_the_default() {
+        let mut pkg = test_pkg();
+        assert_eq!(InstallHook::timeout(&pkg),
+                   Some(Duration::from_secs(DEFAULT_HOOK_TIMEOUT_SECS.into())));
+
+        pkg.hook_timeouts.insert("install".to_string(), 10);
+        assert_eq!(InstallHook::timeout(&pkg), Some(Duration::from_secs(10)));
+
+        pkg.hook_timeouts.insert("install".to_string(), 0);
+        assert_eq!(InstallHook::timeout(&pkg), None);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn watchdog_terminates_hooks_which_time_out() {
+        use std::process::Command;
+
+        let mut cmd = Command::new("sleep");
+        cmd.arg("30");
+        // Hooks lead their own process group, which is what gets signaled
+        unsafe {
+            cmd.pre_exec(|| {
+                   libc::setpgid(0, 0);
+                   Ok(())
+               });
+        }
+        let mut child = cmd.spawn().expect("couldn't run sleep");
+        let watchdog = Watchdog::start::<InstallHook>("dummy.service",
+                                                      child.id(),
+                                                      Duration::from_millis(100))
+                           .expect("couldn't start watchdog");
+        let status = child.wait().expect("couldn't wait for sleep");
+
+        assert!(watchdog.stop());
+        assert_eq!(status.signal(), Some(libc::SIGTERM));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn watchdog_leaves_hooks_which_finish_in_time_alone() {
+        use std::process::Command;
+
+        let mut child = Command::new("true").spawn().expect("couldn't run true");
+        let watchdog =
+            Watchdog::start::<InstallHook>("dummy.service", child.id(), Duration::from_secs(30))
+                .expect("couldn't start watchdog");
+        let status = child.wait().expect("couldn't wait for true");
+
+        assert!(!watchdog.stop());
+        assert!(status.success());
+    }
 }
@@ -0,0 +1,310 @@
+//! Retention of hook output and of the outcome of recent hook runs.
+//!
+//! The output of the latest run of a hook is kept in `<hook>.stdout.log` and `<hook>.stderr.log`
+//! in the logs directory of its service. In addition, the output of every run is appended to
+//! `<hook>.log`, one line per line of output, prefixed with the time it was read, the id of the
+//! run and the stream it was read from. Once that log would grow past `HAB_HOOK_LOG_MAX_BYTES`,
+//! it is rotated to `<hook>.log.1`, which is rotated to `<hook>.log.2`, and so on, keeping at
+//! most `HAB_HOOK_LOG_KEEP` rotated logs.
+//!
+//! The run id, start time, duration and exit code of the last `HAB_HOOK_HISTORY_SIZE` runs of a
+//! hook are kept in `<hook>.history.json`.
+
+use crate::error::{Error,
+                   Result};
+use serde_json;
+use std::{collections::BTreeMap,
+          ffi::OsStr,
+          fs::{self,
+               File,
+               OpenOptions},
+          io::{self,
+               Write},
+          path::{Path,
+                 PathBuf},
+          time::Duration};
+use time;
+use uuid::Uuid;
+
+const HISTORY_SUFFIX: &str = ".history.json";
+
+habitat_core::env_config_int!(
+    /// The size in bytes past which the log of a hook is rotated.
+    HookLogMaxBytes,
+    u64,
+    HAB_HOOK_LOG_MAX_BYTES,
+    10 * 1024 * 1024);
+
+habitat_core::env_config_int!(
+    /// The number of rotated logs kept for each hook.
+    HookLogKeep,
+    usize,
+    HAB_HOOK_LOG_KEEP,
+    5);
+
+habitat_core::env_config_int!(
+    /// The number of runs kept in the history of each hook.
+    HookHistorySize,
+    usize,
+    HAB_HOOK_HISTORY_SIZE,
+    10);
+
+/// The outcome of a single run of a hook.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct HookRun {
+    /// Identifies the lines of this run in the log of the hook.
+    pub run_id:      String,
+    /// When the run started, in RFC 3339 format.
+    pub started_at:  String,
+    pub duration_ms: u64,
+    /// The exit code of the hook, which is missing if the hook was terminated by a signal.
+    pub exit_code:   Option<i32>,
+    /// Whether the hook was terminated because it ran for longer than its timeout.
+    pub timed_out:   bool,
+}
+
+impl HookRun {
+    /// Starts a new run with a fresh run id.
+    pub fn start() -> Self {
+        HookRun { run_id:      Uuid::new_v4().to_string(),
+                  started_at:  time::now_utc().rfc3339().to_string(),
+                  duration_ms: 0,
+                  exit_code:   None,
+                  timed_out:   false, }
+    }
+
+    /// Records how the run ended.
+    pub fn finish(&mut self, duration: Duration, exit_code: Option<i32>, timed_out: bool) {
+        self.duration_ms = duration.as_secs() * 1_000 + u64::from(duration.subsec_millis());
+        self.exit_code = exit_code;
+        self.timed_out = timed_out;
+    }
+}
+
+/// The log every run of a hook appends its output to.
+pub struct HookLog {
+    path:      PathBuf,
+    run_id:    String,
+    max_bytes: u64,
+    keep:      usize,
+    file:      Option<File>,
+    size:      u64,
+}
+
+impl HookLog {
+    pub fn new(path: PathBuf, run_id: &str, max_bytes: u64, keep: usize) -> Self {
+        let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        HookLog { path,
+                  run_id: run_id.to_string(),
+                  max_bytes,
+                  keep,
+                  file: None,
+                  size }
+    }
+
+    /// Opens the log at `path` using the size limit and number of rotated logs from the
+    /// environment.
+    pub fn configured(path: PathBuf, run_id: &str) -> Self {
+        Self::new(path,
+                  run_id,
+                  HookLogMaxBytes::configured_value().into(),
+                  HookLogKeep::configured_value().into())
+    }
+
+    /// Appends a line of output read from `stream`, rotating the log first if the line would
+    /// take it past its size limit.
+    pub fn write_line(&mut self, stream: &str, line: &str) -> io::Result<()> {
+        let entry = format!("{} {} {} {}\n",
+                            time::now_utc().rfc3339(),
+                            self.run_id,
+                            stream,
+                            line);
+        if self.size > 0 && self.size + entry.len() as u64 > self.max_bytes {
+            self.file = None;
+            rotate(&self.path, self.keep)?;
+            self.size = 0;
+        }
+        if self.file.is_none() {
+            self.file = Some(OpenOptions::new().create(true)
+                                               .append(true)
+                                               .open(&self.path)?);
+        }
+        if let Some(ref mut file) = self.file {
+            file.write_all(entry.as_bytes())?;
+        }
+        self.size += entry.len() as u64;
+        Ok(())
+    }
+}
+
+/// The path of the log every run of `hook_name` appends its output to.
+pub fn log_path(logs_dir: &Path, hook_name: &str) -> PathBuf {
+    logs_dir.join(format!("{}.log", hook_name))
+}
+
+/// The path of the history of `hook_name`.
+pub fn history_path(logs_dir: &Path, hook_name: &str) -> PathBuf {
+    logs_dir.join(format!("{}{}", hook_name, HISTORY_SUFFIX))
+}
+
+/// Reads the history at `path`, oldest run first. A missing history is empty.
+pub fn read_history(path: &Path) -> Result<Vec<HookRun>> {
+    match fs::read(path) {
+        Ok(content) => {
+            serde_json::from_slice(&content).map_err(|e| {
+                                                Error::HookHistoryCorrupt(path.to_path_buf(), e)
+                                            })
+        }
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(Error::IO(e)),
+    }
+}
+
+/// Adds `run` to the history at `path`, dropping the oldest runs so at most `size` are kept.
+/// A history which can't be decoded is started over.
+pub fn record_run(path: &Path, run: HookRun, size: usize) -> Result<()> {
+    let mut runs = match read_history(path) {
+        Err(Error::HookHistoryCorrupt(..)) => Vec::new(),
+        result => result?,
+    };
+    runs.push(run);
+    let excess = runs.len().saturating_sub(size);
+    runs.drain(..excess);
+
+    let content = serde_json::to_vec_pretty(&runs).expect("Hook runs should serialize as JSON");
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, content)?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+/// Adds `run` to the history at `path`, keeping as many runs as configured in the environment.
+pub fn record_configured_run(path: &Path, run: HookRun) -> Result<()> {
+    record_run(path, run, HookHistorySize::configured_value().into())
+}
+
+/// Reads the histories of all hooks which have one in `logs_dir`, keyed by hook name.
+pub fn read_histories(logs_dir: &Path) -> Result<BTreeMap<String, Vec<HookRun>>> {
+    let entries = match fs::read_dir(logs_dir) {
+        Ok(entries) => entries,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(e) => return Err(Error::IO(e)),
+    };
+    let mut histories = BTreeMap::new();
+    for entry in entries {
+        let path = entry?.path();
+        let hook_name = match path.file_name().and_then(OsStr::to_str) {
+            Some(name) if name.ends_with(HISTORY_SUFFIX) => {
+                name[..name.len() - HISTORY_SUFFIX.len()].to_string()
+            }
+            _ => continue,
+        };
+        histories.insert(hook_name, read_history(&path)?);
+    }
+    Ok(histories)
+}
+
+fn rotated_path(path: &Path, generation: usize) -> PathBuf {
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(format!(".{}", generation));
+    PathBuf::from(rotated)
+}
+
+/// Moves `path` to `path.1`, `path.1` to `path.2` and so on, dropping `path.<keep>`.
+fn rotate(path: &Path, keep: usize) -> io::Result<()> {
+    if keep == 0 {
+        return fs::remove_file(path);
+    }
+    for generation in (1..keep).rev() {
+        let older = rotated_path(path, generation);
+        if older.exists() {
+            fs::rename(&older, rotated_path(path, generation + 1))?;
+        }
+    }
+    fs::rename(path, rotated_path(path, 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn run(exit_code: i32) -> HookRun {
+        let mut run = HookRun::start();
+        run.finish(Duration::from_millis(1_500), Some(exit_code), false);
+        run
+    }
+
+    #[test]
+    fn log_lines_carry_the_run_id_and_stream() {
+        let tmp = TempDir::new().expect("create temp dir");
+        let path = log_path(tmp.path(), "reconfigure");
+        let mut log = HookLog::new(path.clone(), "abc", 1024, 2);
+        log.write_line("stdout", "hello").unwrap();
+        log.write_line("stderr", "oops").unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with(" abc stdout hello"));
+        assert!(lines[1].ends_with(" abc stderr oops"));
+    }
+
+    #[test]
+    fn logs_are_rotated_by_size_and_count() {
+        let tmp = TempDir::new().expect("create temp dir");
+        let path = log_path(tmp.path(), "run");
+        for run_id in &["one", "two", "three", "four"] {
+            let mut log = HookLog::new(path.clone(), run_id, 60, 2);
+            log.write_line("stdout", "a line long enough to fill the log").unwrap();
+        }
+
+        assert!(fs::read_to_string(&path).unwrap().contains(" four "));
+        assert!(fs::read_to_string(rotated_path(&path, 1)).unwrap()
+                                                            .contains(" three "));
+        assert!(fs::read_to_string(rotated_path(&path, 2)).unwrap()
+                                                            .contains(" two "));
+        assert!(!rotated_path(&path, 3).exists());
+    }
+
+    #[test]
+    fn history_keeps_the_last_runs() {
+        let tmp = TempDir::new().expect("create temp dir");
+        let path = history_path(tmp.path(), "reconfigure");
+        assert!(read_history(&path).unwrap().is_empty());
+
+        for code in 0..4 {
+            record_run(&path, run(code), 3).unwrap();
+        }
+        let history = read_history(&path).unwrap();
+        let codes: Vec<Option<i32>> = history.iter().map(|r| r.exit_code).collect();
+        assert_eq!(codes, vec![Some(1), Some(2), Some(3)]);
+        assert_eq!(history[0].duration_ms, 1_500);
+    }
+
+    #[test]
+    fn corrupt_histories_are_started_over() {
+        let tmp = TempDir::new().expect("create temp dir");
+        let path = history_path(tmp.path(), "init");
+        fs::write(&path, "not json").unwrap();
+        assert!(read_history(&path).is_err());
+
+        record_run(&path, run(1), 3).unwrap();
+        assert_eq!(read_history(&path).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn histories_are_keyed_by_hook() {
+        let tmp = TempDir::new().expect("create temp dir");
+        record_run(&history_path(tmp.path(), "init"), run(0), 3).unwrap();
+        record_run(&history_path(tmp.path(), "health-check"), run(2), 3).unwrap();
+        fs::write(log_path(tmp.path(), "init"), "").unwrap();
+
+        let histories = read_histories(tmp.path()).unwrap();
+        assert_eq!(histories.keys().collect::<Vec<_>>(),
+                   vec!["health-check", "init"]);
+        assert_eq!(histories["health-check"][0].exit_code, Some(2));
+        assert!(read_histories(&tmp.path().join("missing")).unwrap()
+                                                             .is_empty());
+    }
+}
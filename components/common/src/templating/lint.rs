@@ -0,0 +1,317 @@
+//! Static checks for templates, used to catch mistakes before a template is rendered by a
+//! Supervisor.
+//!
+//! The checks work on the template source and a rendering context. Only variables which are
+//! resolved against the root of the context are checked: inside blocks which change the context
+//! (`each`, `with` and the `each*` helpers) variables are only checked if they are reached through
+//! `@root` or enough `../` segments. The conditions of `if` and `unless` are not checked, as they
+//! are commonly used to test for optional values, and neither is anything a block is guarded by:
+//! inside `{{#if cfg.tls}}` (or the `else` of `{{#unless cfg.tls}}`, or a `with` or `each` block
+//! over `cfg.tls`) neither `cfg.tls` nor anything below it is reported.
+
+use regex::Regex;
+use serde_json::Value as Json;
+
+lazy_static! {
+    static ref EXPRESSION: Regex =
+        Regex::new(r"(?s)\{\{\{?~?\s*(.*?)\s*~?\}?\}\}").expect("Failed to compile template \
+                                                               expression regex");
+    static ref SEGMENT: Regex =
+        Regex::new(r"\[([^\]]*)\]|([^.\[\]]+)").expect("Failed to compile path segment regex");
+}
+
+/// Block helpers which change the context their body is rendered in.
+const CONTEXT_CHANGING_HELPERS: &[&str] =
+    &["each", "eachAlive", "eachHealthy", "eachSorted", "eachWhere", "strSplit", "with"];
+/// Block helpers whose parameters are not checked.
+const CONDITIONAL_HELPERS: &[&str] = &["if", "unless"];
+
+/// A block opened by a block helper.
+struct Block {
+    /// Whether the block changes the context its body is rendered in.
+    changes_context: bool,
+    /// The root path of the value which the block's body is only rendered if it is defined.
+    guard:           Option<String>,
+    /// Whether the part of the block the template is in is guarded, which for `unless` is only
+    /// its `else` part.
+    guarded:         bool,
+}
+
+/// A variable which could not be found in the rendering context.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UndefinedVariable {
+    /// The line of the template the variable is used on, starting at 1.
+    pub line: usize,
+    pub path: String,
+}
+
+/// Find all variables used in `template` which are not defined in `ctx`.
+pub fn undefined_variables(template: &str, ctx: &Json) -> Vec<UndefinedVariable> {
+    let mut undefined = Vec::new();
+    let mut blocks: Vec<Block> = Vec::new();
+
+    for captures in EXPRESSION.captures_iter(template) {
+        let expression = captures.get(1).expect("Expression capture group");
+        let body = expression.as_str();
+        let line = template[..expression.start()].matches('\n').count() + 1;
+        // Parameters are resolved in the context the expression appears in, which for block
+        // helpers is the context outside of the block they open.
+        let depth = blocks.iter().filter(|b| b.changes_context).count();
+
+        let (check_params, params) = if body.starts_with('!') || body.starts_with('>') {
+            continue;
+        } else if body.starts_with('/') {
+            blocks.pop();
+            continue;
+        } else if body.starts_with('#') {
+            let tokens = tokenize(&body[1..]);
+            let helper = tokens.first().map(String::as_str).unwrap_or_default();
+            let check = !CONDITIONAL_HELPERS.contains(&helper);
+            let params = tokens.iter().skip(1).cloned().collect::<Vec<_>>();
+            let guard = params.first()
+                              .and_then(|param| root_path(param, depth))
+                              .map(str::to_string);
+            blocks.push(Block { changes_context: CONTEXT_CHANGING_HELPERS.contains(&helper),
+                                guard,
+                                guarded: helper != "unless" });
+            (check, params)
+        } else if body == "else" || body.starts_with("else ") || body.starts_with('^') {
+            if let Some(block) = blocks.last_mut() {
+                let tokens = tokenize(body);
+                if tokens.get(1).map(String::as_str) == Some("if") {
+                    // `{{else if cond}}` is guarded by its own condition
+                    let outer_depth = depth - if block.changes_context { 1 } else { 0 };
+                    block.guard = tokens.get(2)
+                                        .and_then(|param| root_path(param, outer_depth))
+                                        .map(str::to_string);
+                    block.guarded = true;
+                } else {
+                    block.guarded = !block.guarded;
+                }
+            }
+            continue;
+        } else {
+            let tokens = tokenize(body);
+            // A single token is a variable; otherwise the first token names a helper.
+            if tokens.len() == 1 {
+                (true, tokens)
+            } else {
+                (true, tokens.into_iter().skip(1).collect())
+            }
+        };
+        if !check_params {
+            continue;
+        }
+
+        for param in params {
+            let param = match param.find('=') {
+                Some(idx) => param[idx + 1..].to_string(),
+                None => param,
+            };
+            if let Some(path) = root_path(&param, depth) {
+                if !is_guarded(&blocks, path) && !is_defined(ctx, path) {
+                    undefined.push(UndefinedVariable { line,
+                                                       path: param.clone() });
+                }
+            }
+        }
+    }
+    undefined
+}
+
+/// Split an expression into its tokens, keeping quoted strings together and dropping the
+/// parentheses of subexpressions along with the helper they name.
+fn tokenize(expression: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+    let mut skip_next = false;
+
+    let mut push = |current: &mut String, skip_next: &mut bool| {
+        if !current.is_empty() {
+            if *skip_next {
+                *skip_next = false;
+            } else {
+                tokens.push(current.clone());
+            }
+            current.clear();
+        }
+    };
+
+    for c in expression.chars() {
+        match quote {
+            Some(q) => {
+                current.push(c);
+                if c == q {
+                    quote = None;
+                }
+            }
+            None => {
+                match c {
+                    '"' | '\'' => {
+                        quote = Some(c);
+                        current.push(c);
+                    }
+                    '(' => {
+                        push(&mut current, &mut skip_next);
+                        skip_next = true;
+                    }
+                    ')' => push(&mut current, &mut skip_next),
+                    c if c.is_whitespace() => push(&mut current, &mut skip_next),
+                    c => current.push(c),
+                }
+            }
+        }
+    }
+    push(&mut current, &mut skip_next);
+    tokens
+}
+
+/// Returns the part of `param` which is looked up from the root of the context, or `None` if
+/// `param` is a literal or is looked up relative to the context of an enclosing block.
+fn root_path(param: &str, depth: usize) -> Option<&str> {
+    if param.is_empty()
+       || param.starts_with('"')
+       || param.starts_with('\'')
+       || param.starts_with('|')
+       || param.parse::<f64>().is_ok()
+       || ["true", "false", "null", "undefined", "as", "this", "."].contains(&param)
+    {
+        return None;
+    }
+    if param.starts_with("@root.") {
+        return Some(&param["@root.".len()..]);
+    }
+    if param.starts_with('@') || param.starts_with("this.") || param.starts_with("./") {
+        return None;
+    }
+
+    let mut path = param;
+    let mut up = 0;
+    while path.starts_with("../") {
+        path = &path[3..];
+        up += 1;
+    }
+    if up == depth {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+/// Whether `path` is, or is below, the guard of a block the template is in.
+fn is_guarded(blocks: &[Block], path: &str) -> bool {
+    blocks.iter()
+          .filter(|block| block.guarded)
+          .filter_map(|block| block.guard.as_ref())
+          .any(|guard| {
+              path == guard
+              || (path.starts_with(guard.as_str())
+                  && (path[guard.len()..].starts_with('.') || path[guard.len()..].starts_with('[')))
+          })
+}
+
+fn is_defined(ctx: &Json, path: &str) -> bool {
+    let mut value = ctx;
+    for captures in SEGMENT.captures_iter(path) {
+        let segment = captures.get(1)
+                              .or_else(|| captures.get(2))
+                              .map(|m| m.as_str())
+                              .unwrap_or_default();
+        let next = match *value {
+            Json::Object(ref map) => map.get(segment),
+            Json::Array(ref list) => segment.parse::<usize>().ok().and_then(|i| list.get(i)),
+            _ => None,
+        };
+        match next {
+            Some(next) => value = next,
+            None => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ctx() -> Json {
+        json!({
+            "cfg": { "port": 6379, "tls": { "enabled": false } },
+            "sys": { "ip": "10.0.0.1" },
+            "bind": { "db": { "members": [ { "sys": { "ip": "10.0.0.2" } } ] } }
+        })
+    }
+
+    fn paths(template: &str) -> Vec<String> {
+        undefined_variables(template, &ctx()).into_iter()
+                                             .map(|u| u.path)
+                                             .collect()
+    }
+
+    #[test]
+    fn defined_variables_are_not_reported() {
+        assert!(paths("port {{cfg.port}}\nbind {{sys.ip}}").is_empty());
+        assert!(paths("{{bind.db.members[0].sys.ip}}").is_empty());
+        assert!(paths("{{bind.db.members.[0].sys.ip}}").is_empty());
+        assert!(paths("{{toJson cfg.tls}}").is_empty());
+    }
+
+    #[test]
+    fn undefined_variables_are_reported_with_their_line() {
+        let undefined = undefined_variables("port {{cfg.port}}\n\nhost {{cfg.host}}", &ctx());
+        assert_eq!(undefined,
+                   vec![UndefinedVariable { line: 3,
+                                            path: "cfg.host".to_string(), }]);
+    }
+
+    #[test]
+    fn helper_parameters_are_checked() {
+        assert_eq!(paths("{{strReplace cfg.name \"a\" \"b\"}}"), vec!["cfg.name"]);
+        assert_eq!(paths("{{strJoin (toLowercase cfg.names) \",\"}}"),
+                   vec!["cfg.names"]);
+        assert!(paths("{{pkgPathFor \"core/openssl\"}}").is_empty());
+    }
+
+    #[test]
+    fn conditions_are_not_checked() {
+        assert!(paths("{{#if cfg.optional}}x{{/if}}").is_empty());
+        assert!(paths("{{#unless cfg.optional}}none{{else if cfg.other}}x{{/unless}}").is_empty());
+    }
+
+    #[test]
+    fn guarded_variables_are_not_reported() {
+        assert!(paths("{{#if cfg.optional}}{{cfg.optional}}{{/if}}").is_empty());
+        assert!(paths("{{#if cfg.optional}}{{cfg.optional.port}}{{/if}}").is_empty());
+        assert!(paths("{{#unless cfg.optional}}x{{else}}{{cfg.optional}}{{/unless}}").is_empty());
+        assert!(paths("{{#if cfg.a}}{{else if cfg.b}}{{cfg.b.port}}{{/if}}").is_empty());
+        assert!(paths("{{#with cfg.optional}}{{../cfg.optional.port}}{{/with}}").is_empty());
+
+        assert_eq!(paths("{{#if cfg.optional}}x{{else}}{{cfg.optional}}{{/if}}"),
+                   vec!["cfg.optional"]);
+        assert_eq!(paths("{{#unless cfg.optional}}{{cfg.optional}}{{/unless}}"),
+                   vec!["cfg.optional"]);
+        assert_eq!(paths("{{#if cfg.optional}}{{cfg.optionally}}{{/if}}"),
+                   vec!["cfg.optionally"]);
+    }
+
+    #[test]
+    fn context_changing_blocks_are_respected() {
+        let template = "{{#eachAlive bind.db.members as |member|}}\n\
+                        {{member.sys.ip}} {{sys.ip}} {{../cfg.port}} {{../cfg.nope}}\n\
+                        {{@root.cfg.missing}}\n\
+                        {{/eachAlive}}\n\
+                        {{#each bind.nope.members}}{{/each}}\n\
+                        {{cfg.after}}";
+        assert_eq!(paths(template),
+                   vec!["../cfg.nope",
+                        "@root.cfg.missing",
+                        "bind.nope.members",
+                        "cfg.after"]);
+    }
+
+    #[test]
+    fn comments_and_partials_are_ignored() {
+        assert!(paths("{{! cfg.nope }}{{> partial}}").is_empty());
+    }
+}
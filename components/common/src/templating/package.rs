@@ -5,8 +5,10 @@ use std::{collections::HashMap,
           result};
 
 use crate::hcore::{fs,
-                   os::{process::{ShutdownSignal,
+                   os::{process::{ResourceLimits,
+                                  ShutdownSignal,
                                   ShutdownTimeout},
+                        sandbox::Sandbox,
                         users},
                    package::{PackageIdent,
                              PackageInstall},
@@ -17,6 +19,7 @@ use serde::{ser::SerializeStruct,
 
 use crate::{error::{Error,
                     Result},
+            templating::TemplateEngine,
             util::path};
 
 const DEFAULT_USER: &str = "hab";
@@ -67,6 +70,11 @@ pub struct Pkg {
     pub ident: PackageIdent,
     pub origin: String,
     pub name: String,
+    /// The name of the service running the package, which differs from the package name when
+    /// several instances of the package run on the same Supervisor, e.g. `redis@cache`. The
+    /// `svc_*` paths are named after it.
+    #[serde(default)]
+    pub svc_name: String,
     pub version: String,
     pub release: String,
     pub deps: Vec<PackageIdent>,
@@ -87,21 +95,39 @@ pub struct Pkg {
     pub svc_group: String,
     pub shutdown_signal: ShutdownSignal,
     pub shutdown_timeout: ShutdownTimeout,
+    /// The number of seconds each hook may run for, keyed by hook file name. Hooks which are not
+    /// listed fall back to their default timeout.
+    #[serde(default)]
+    pub hook_timeouts: HashMap<String, u32>,
+    /// The resources the service may use.
+    #[serde(default)]
+    pub resource_limits: ResourceLimits,
+    /// The hardening applied to the service's process.
+    #[serde(default)]
+    pub sandbox: Sandbox,
+    /// The template engine of configuration templates and hooks without a `.tera` extension.
+    #[serde(default)]
+    pub template_engine: TemplateEngine,
 }
 
 impl Pkg {
     pub fn from_install(package: &PackageInstall) -> Result<Self> {
+        Self::from_install_for_service(package, &package.ident.name)
+    }
+
+    /// Like `from_install`, but names the `svc_*` paths after the service `svc_name` rather than
+    /// after the package.
+    pub fn from_install_for_service(package: &PackageInstall, svc_name: &str) -> Result<Self> {
         let (svc_user, svc_group) = get_user_and_group(&package)?;
-        let pkg = Pkg { svc_path: fs::svc_path(&package.ident.name),
-                        svc_config_path: fs::svc_config_path(&package.ident.name),
-                        svc_config_install_path: fs::svc_config_install_path(&package.ident
-                                                                                     .name),
-                        svc_data_path: fs::svc_data_path(&package.ident.name),
-                        svc_files_path: fs::svc_files_path(&package.ident.name),
-                        svc_run: fs::svc_path(&package.ident.name).join("run"),
-                        svc_static_path: fs::svc_static_path(&package.ident.name),
-                        svc_var_path: fs::svc_var_path(&package.ident.name),
-                        svc_pid_file: fs::svc_pid_file(&package.ident.name),
+        let pkg = Pkg { svc_path: fs::svc_path(svc_name),
+                        svc_config_path: fs::svc_config_path(svc_name),
+                        svc_config_install_path: fs::svc_config_install_path(svc_name),
+                        svc_data_path: fs::svc_data_path(svc_name),
+                        svc_files_path: fs::svc_files_path(svc_name),
+                        svc_run: fs::svc_path(svc_name).join("run"),
+                        svc_static_path: fs::svc_static_path(svc_name),
+                        svc_var_path: fs::svc_var_path(svc_name),
+                        svc_pid_file: fs::svc_pid_file(svc_name),
                         svc_user,
                         svc_group,
                         env: Env::new(&package)?,
@@ -112,6 +138,7 @@ impl Pkg {
                         ident: package.ident.clone(),
                         origin: package.ident.origin.clone(),
                         name: package.ident.name.clone(),
+                        svc_name: svc_name.to_string(),
                         version: package.ident
                                         .version
                                         .clone()
@@ -121,7 +148,11 @@ impl Pkg {
                                         .clone()
                                         .expect("No package release in PackageInstall"),
                         shutdown_signal: package.shutdown_signal()?.unwrap_or_default(),
-                        shutdown_timeout: package.shutdown_timeout()?.unwrap_or_default() };
+                        shutdown_timeout: package.shutdown_timeout()?.unwrap_or_default(),
+                        hook_timeouts: package.hook_timeouts()?,
+                        resource_limits: package.resource_limits()?,
+                        sandbox: package.sandbox()?,
+                        template_engine: TemplateEngine::for_package(package)? };
         Ok(pkg)
     }
 }
@@ -145,7 +176,7 @@ impl<'a> Serialize for PkgProxy<'a> {
         where S: Serializer
     {
         let p = &self.pkg;
-        let mut strukt = serializer.serialize_struct("pkg", 21)?;
+        let mut strukt = serializer.serialize_struct("pkg", 23)?;
         strukt.serialize_field("ident", &p.ident.to_string())?;
         strukt.serialize_field("origin", &p.origin)?;
         strukt.serialize_field("name", &p.name)?;
@@ -170,6 +201,8 @@ impl<'a> Serialize for PkgProxy<'a> {
         strukt.serialize_field("svc_group", &p.svc_group)?;
         strukt.serialize_field("shutdown_signal", &p.shutdown_signal)?;
         strukt.serialize_field("shutdown_timeout", &p.shutdown_timeout)?;
+        strukt.serialize_field("resource_limits", &p.resource_limits)?;
+        strukt.serialize_field("sandbox", &p.sandbox)?;
         strukt.end()
     }
 }
@@ -0,0 +1,300 @@
+//! Secret providers which can be read from templates through the `secret` helper.
+//!
+//! A secret is referenced as `[provider:]path/key`, for example `vault:secret/data/db/password`
+//! or `env:db/password`. When no provider is given the `vault` provider is used.
+//!
+//! Secrets which come with a lease are cached until two thirds of their lease duration have
+//! passed. Renderers which used such a secret report themselves as expired at that point, so the
+//! Supervisor can render its templates again, which either renews the lease or fetches a fresh
+//! secret.
+
+mod env;
+mod file;
+mod vault;
+
+use std::{collections::HashMap,
+          sync::Mutex,
+          time::{Duration,
+                 Instant}};
+
+use crate::error::{Error,
+                   Result};
+
+pub use self::{env::EnvProvider,
+               file::FileProvider,
+               vault::VaultProvider};
+
+/// The provider which is used when a secret reference does not name one.
+pub const DEFAULT_PROVIDER: &str = "vault";
+
+lazy_static! {
+    static ref STORE: SecretStore = SecretStore::from_env();
+}
+
+/// Look up a secret by its reference using the providers configured from the environment.
+pub fn get(reference: &str) -> Result<SecretValue> { STORE.get(reference) }
+
+/// A secret as returned by a `SecretProvider`.
+#[derive(Clone, Debug)]
+pub struct Secret {
+    pub value: String,
+    pub lease: Option<Lease>,
+}
+
+impl Secret {
+    pub fn new<T>(value: T) -> Self
+        where T: Into<String>
+    {
+        Secret { value: value.into(),
+                 lease: None, }
+    }
+}
+
+/// The lease a provider handed out along with a secret.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lease {
+    pub id:        Option<String>,
+    pub duration:  Duration,
+    pub renewable: bool,
+}
+
+impl Lease {
+    /// The point in time at which a lease obtained at `obtained` should be renewed.
+    fn renew_at(&self, obtained: Instant) -> Instant { obtained + self.duration * 2 / 3 }
+}
+
+/// A secret value along with the point in time at which it needs to be refreshed, if any.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SecretValue {
+    pub value:    String,
+    pub renew_at: Option<Instant>,
+}
+
+pub trait SecretProvider: Send + Sync {
+    /// Fetch the secret stored under `key` at `path`.
+    fn fetch(&self, path: &str, key: &str) -> Result<Secret>;
+
+    /// Extend the given lease, returning the renewed lease.
+    fn renew(&self, _lease: &Lease) -> Result<Lease> {
+        Err(Error::SecretProviderError("Leases of this provider cannot be renewed".to_string()))
+    }
+}
+
+struct CachedSecret {
+    secret:   Secret,
+    renew_at: Instant,
+}
+
+pub struct SecretStore {
+    providers: HashMap<&'static str, Box<dyn SecretProvider>>,
+    cache:     Mutex<HashMap<String, CachedSecret>>,
+}
+
+impl SecretStore {
+    pub fn new() -> Self {
+        SecretStore { providers: HashMap::new(),
+                      cache:     Mutex::new(HashMap::new()), }
+    }
+
+    /// Create a store with the `env` and `file` providers, and the `vault` provider if
+    /// `HAB_VAULT_ADDR` is set.
+    pub fn from_env() -> Self {
+        let mut store = Self::new();
+        store.register("env", Box::new(EnvProvider));
+        store.register("file", Box::new(FileProvider::from_env()));
+        match VaultProvider::from_env() {
+            Ok(Some(vault)) => store.register("vault", Box::new(vault)),
+            Ok(None) => debug!("HAB_VAULT_ADDR is not set; the vault secret provider is disabled"),
+            Err(e) => error!("Failed to configure the vault secret provider: {}", e),
+        }
+        store
+    }
+
+    pub fn register(&mut self, name: &'static str, provider: Box<dyn SecretProvider>) {
+        self.providers.insert(name, provider);
+    }
+
+    pub fn get(&self, reference: &str) -> Result<SecretValue> {
+        let (name, path, key) = parse_reference(reference)?;
+        let provider =
+            self.providers
+                .get(name)
+                .ok_or_else(|| Error::UnknownSecretProvider(name.to_string()))?;
+        let mut cache = self.cache.lock().expect("Secret cache lock poisoned");
+        let now = Instant::now();
+
+        if let Some(cached) = cache.get_mut(reference) {
+            if now < cached.renew_at {
+                return Ok(SecretValue { value:    cached.secret.value.clone(),
+                                        renew_at: Some(cached.renew_at), });
+            }
+            let renewed = match cached.secret.lease {
+                Some(ref lease) if lease.renewable => {
+                    provider.renew(lease)
+                            .map_err(|e| debug!("Failed to renew lease of {}: {}", reference, e))
+                            .ok()
+                }
+                _ => None,
+            };
+            if let Some(lease) = renewed {
+                debug!("Renewed lease of secret {}", reference);
+                cached.renew_at = lease.renew_at(now);
+                cached.secret.lease = Some(lease);
+                return Ok(SecretValue { value:    cached.secret.value.clone(),
+                                        renew_at: Some(cached.renew_at), });
+            }
+        }
+
+        let secret = provider.fetch(path, key)?;
+        let value = SecretValue { value:    secret.value.clone(),
+                                  renew_at: secret.lease.as_ref().map(|l| l.renew_at(now)), };
+        match value.renew_at {
+            Some(renew_at) => {
+                cache.insert(reference.to_string(), CachedSecret { secret, renew_at });
+            }
+            None => {
+                cache.remove(reference);
+            }
+        }
+        Ok(value)
+    }
+}
+
+impl Default for SecretStore {
+    fn default() -> Self { Self::new() }
+}
+
+/// Tracks the earliest point in time at which a secret used by a renderer needs to be refreshed.
+#[derive(Debug, Default)]
+pub struct SecretLeases(Mutex<Option<Instant>>);
+
+impl SecretLeases {
+    pub fn track(&self, renew_at: Instant) {
+        let mut next = self.0.lock().expect("Secret leases lock poisoned");
+        *next = Some(next.map_or(renew_at, |n| n.min(renew_at)));
+    }
+
+    /// Returns `true` once a tracked lease needs to be refreshed. The lease is forgotten at that
+    /// point; rendering the templates again will track the refreshed one.
+    pub fn expired(&self) -> bool {
+        let mut next = self.0.lock().expect("Secret leases lock poisoned");
+        match *next {
+            Some(renew_at) if renew_at <= Instant::now() => {
+                *next = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Split a secret reference into its provider, path and key.
+fn parse_reference(reference: &str) -> Result<(&str, &str, &str)> {
+    let (provider, rest) = match reference.find(':') {
+        Some(idx) => (&reference[..idx], &reference[idx + 1..]),
+        None => (DEFAULT_PROVIDER, reference),
+    };
+    match rest.rfind('/') {
+        Some(idx) if idx > 0 && idx < rest.len() - 1 => {
+            Ok((provider, &rest[..idx], &rest[idx + 1..]))
+        }
+        _ => Err(Error::InvalidSecretReference(reference.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::{atomic::{AtomicUsize,
+                             Ordering},
+                    Arc};
+
+    struct CountingProvider {
+        fetches: Arc<AtomicUsize>,
+        lease:   Option<Lease>,
+    }
+
+    impl SecretProvider for CountingProvider {
+        fn fetch(&self, path: &str, key: &str) -> Result<Secret> {
+            let count = self.fetches.fetch_add(1, Ordering::SeqCst);
+            Ok(Secret { value: format!("{}/{}-{}", path, key, count),
+                        lease: self.lease.clone(), })
+        }
+    }
+
+    fn store_with(lease: Option<Lease>) -> (SecretStore, Arc<AtomicUsize>) {
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let mut store = SecretStore::new();
+        store.register("test",
+                       Box::new(CountingProvider { fetches: Arc::clone(&fetches),
+                                                   lease }));
+        (store, fetches)
+    }
+
+    #[test]
+    fn parse_reference_works() {
+        assert_eq!(parse_reference("secret/data/db/password").unwrap(),
+                   ("vault", "secret/data/db", "password"));
+        assert_eq!(parse_reference("env:db/password").unwrap(),
+                   ("env", "db", "password"));
+        assert!(parse_reference("password").is_err());
+        assert!(parse_reference("env:db/").is_err());
+        assert!(parse_reference("env:/password").is_err());
+    }
+
+    #[test]
+    fn unknown_providers_are_an_error() {
+        let (store, _) = store_with(None);
+        match store.get("nope:db/password") {
+            Err(Error::UnknownSecretProvider(ref name)) => assert_eq!(name, "nope"),
+            other => panic!("Expected an unknown provider error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn secrets_without_a_lease_are_fetched_every_time() {
+        let (store, fetches) = store_with(None);
+        let first = store.get("test:db/password").unwrap();
+        assert_eq!(first.value, "db/password-0");
+        assert_eq!(first.renew_at, None);
+        assert_eq!(store.get("test:db/password").unwrap().value,
+                   "db/password-1");
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn leased_secrets_are_cached_until_they_need_renewal() {
+        let lease = Lease { id:        Some("lease".to_string()),
+                            duration:  Duration::from_secs(3600),
+                            renewable: false, };
+        let (store, fetches) = store_with(Some(lease));
+        let first = store.get("test:db/password").unwrap();
+        assert!(first.renew_at.is_some());
+        assert_eq!(store.get("test:db/password").unwrap(), first);
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn expired_leases_are_fetched_again() {
+        let lease = Lease { id:        None,
+                            duration:  Duration::from_secs(0),
+                            renewable: false, };
+        let (store, fetches) = store_with(Some(lease));
+        assert_eq!(store.get("test:db/password").unwrap().value,
+                   "db/password-0");
+        assert_eq!(store.get("test:db/password").unwrap().value,
+                   "db/password-1");
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn secret_leases_expire_once() {
+        let leases = SecretLeases::default();
+        assert!(!leases.expired());
+        leases.track(Instant::now() + Duration::from_secs(3600));
+        assert!(!leases.expired());
+        leases.track(Instant::now());
+        assert!(leases.expired());
+        assert!(!leases.expired());
+    }
+}
@@ -0,0 +1,64 @@
+use crate::{error::{Error,
+                    Result},
+            hcore::env};
+
+use super::{Secret,
+            SecretProvider};
+
+/// Prefix of the environment variables the `env` provider reads secrets from.
+pub const ENV_VAR_PREFIX: &str = "HAB_SECRET_";
+
+/// Reads secrets from the Supervisor's environment.
+///
+/// Only variables starting with `HAB_SECRET_` are exposed, so templates cannot read arbitrary
+/// parts of the environment. The reference `env:db/password` reads `HAB_SECRET_DB_PASSWORD`.
+pub struct EnvProvider;
+
+impl EnvProvider {
+    pub fn var_name(path: &str, key: &str) -> String {
+        let name = format!("{}_{}", path, key).chars()
+                                             .map(|c| {
+                                                 if c.is_ascii_alphanumeric() {
+                                                     c.to_ascii_uppercase()
+                                                 } else {
+                                                     '_'
+                                                 }
+                                             })
+                                             .collect::<String>();
+        format!("{}{}", ENV_VAR_PREFIX, name)
+    }
+}
+
+impl SecretProvider for EnvProvider {
+    fn fetch(&self, path: &str, key: &str) -> Result<Secret> {
+        let name = Self::var_name(path, key);
+        env::var(&name).map(Secret::new)
+                       .map_err(|_| Error::SecretNotFound(format!("{}/{}", path, key)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    crate::locked_env_var!(HAB_SECRET_DB_ADMIN_PASSWORD, lock_password);
+
+    #[test]
+    fn var_names_are_derived_from_the_reference() {
+        assert_eq!(EnvProvider::var_name("db/admin", "password"),
+                   "HAB_SECRET_DB_ADMIN_PASSWORD");
+        assert_eq!(EnvProvider::var_name("my-app", "api.key"),
+                   "HAB_SECRET_MY_APP_API_KEY");
+    }
+
+    #[test]
+    fn fetch_reads_the_environment() {
+        let password = lock_password();
+        password.set("hunter2");
+        assert_eq!(EnvProvider.fetch("db/admin", "password").unwrap().value,
+                   "hunter2");
+
+        password.unset();
+        assert!(EnvProvider.fetch("db/admin", "password").is_err());
+    }
+}
@@ -0,0 +1,112 @@
+use std::{fs,
+          io,
+          path::{Component,
+                 Path,
+                 PathBuf}};
+
+use crate::{error::{Error,
+                    Result},
+            hcore::{crypto::{keys::box_key_pair::WrappedSealedBox,
+                             BoxKeyPair},
+                    env,
+                    fs::{cache_key_path,
+                         FS_ROOT_PATH}}};
+
+use super::{Secret,
+            SecretProvider};
+
+/// Environment variable which overrides the directory the `file` provider reads secrets from.
+pub const SECRETS_PATH_ENVVAR: &str = "HAB_SECRETS_PATH";
+
+/// Reads secrets from encrypted files on the local filesystem.
+///
+/// The reference `file:db/password` reads `/hab/secrets/db/password`, which must contain a
+/// payload encrypted with a `BoxKeyPair` (for example from a user to a service group key), just
+/// like the files uploaded with `hab file upload`. The keys needed to decrypt it are looked up in
+/// the key cache.
+pub struct FileProvider {
+    root:           PathBuf,
+    cache_key_path: PathBuf,
+}
+
+impl FileProvider {
+    pub fn new<P, Q>(root: P, cache_key_path: Q) -> Self
+        where P: Into<PathBuf>,
+              Q: Into<PathBuf>
+    {
+        FileProvider { root:           root.into(),
+                       cache_key_path: cache_key_path.into(), }
+    }
+
+    pub fn from_env() -> Self {
+        let root = match env::var(SECRETS_PATH_ENVVAR) {
+            Ok(path) => PathBuf::from(path),
+            Err(_) => FS_ROOT_PATH.join("hab").join("secrets"),
+        };
+        Self::new(root, cache_key_path(Some(&*FS_ROOT_PATH)))
+    }
+}
+
+impl SecretProvider for FileProvider {
+    fn fetch(&self, path: &str, key: &str) -> Result<Secret> {
+        let relative = Path::new(path).join(key);
+        if relative.components().any(|c| {
+                                    match c {
+                                        Component::Normal(_) => false,
+                                        _ => true,
+                                    }
+                                })
+        {
+            return Err(Error::InvalidSecretReference(format!("file:{}/{}", path, key)));
+        }
+
+        let bytes = fs::read(self.root.join(&relative)).map_err(|e| {
+                        match e.kind() {
+                            io::ErrorKind::NotFound => {
+                                Error::SecretNotFound(format!("{}/{}", path, key))
+                            }
+                            _ => Error::IO(e),
+                        }
+                    })?;
+        let payload = WrappedSealedBox::from_bytes(&bytes)?;
+        let value = BoxKeyPair::decrypt_with_path(&payload, &self.cache_key_path)?;
+        Ok(Secret::new(String::from_utf8(value)?))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn fetch_decrypts_secret_files() {
+        let keys = TempDir::new().unwrap();
+        let secrets = TempDir::new().unwrap();
+        let user = BoxKeyPair::generate_pair_for_user("ruby").unwrap();
+        let service = BoxKeyPair::generate_pair_for_service("acme", "db.default").unwrap();
+        user.to_pair_files(keys.path()).unwrap();
+        service.to_pair_files(keys.path()).unwrap();
+
+        let payload = user.encrypt(b"hunter2", Some(&service)).unwrap();
+        fs::create_dir_all(secrets.path().join("db")).unwrap();
+        fs::write(secrets.path().join("db").join("password"),
+                  payload.into_bytes()).unwrap();
+
+        let provider = FileProvider::new(secrets.path(), keys.path());
+        assert_eq!(provider.fetch("db", "password").unwrap().value, "hunter2");
+        match provider.fetch("db", "missing") {
+            Err(Error::SecretNotFound(_)) => (),
+            other => panic!("Expected a missing secret, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn fetch_rejects_paths_outside_of_the_secrets_directory() {
+        let provider = FileProvider::new("/hab/secrets", "/hab/cache/keys");
+        match provider.fetch("../etc", "shadow") {
+            Err(Error::InvalidSecretReference(_)) => (),
+            other => panic!("Expected an invalid reference, got {:?}", other),
+        }
+    }
+}
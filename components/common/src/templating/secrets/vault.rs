@@ -0,0 +1,211 @@
+use std::{io::Read,
+          time::Duration};
+
+use habitat_http_client::ApiClient;
+use hyper::{client::{RequestBuilder,
+                     Response},
+            header::{Authorization,
+                     Bearer},
+            status::StatusCode};
+use serde_json::{self,
+                 Value as Json};
+
+use crate::{error::{Error,
+                    Result},
+            hcore::env,
+            PROGRAM_NAME};
+
+use super::{Lease,
+            Secret,
+            SecretProvider};
+
+const VERSION: &str = include_str!(concat!(env!("OUT_DIR"), "/VERSION"));
+
+/// Environment variable holding the address of the Vault server, e.g. `http://127.0.0.1:8200`.
+pub const VAULT_ADDR_ENVVAR: &str = "HAB_VAULT_ADDR";
+/// Environment variable holding the token used to authenticate with the Vault server.
+pub const VAULT_TOKEN_ENVVAR: &str = "HAB_VAULT_TOKEN";
+
+/// Reads secrets from a server implementing the HashiCorp Vault HTTP API.
+///
+/// The reference `vault:secret/data/db/password` reads the `password` field of the secret at
+/// `secret/data/db`. Both version 1 and version 2 of the key/value secrets engine are supported,
+/// as are dynamic secrets, whose leases are renewed through `sys/leases/renew`.
+pub struct VaultProvider {
+    client: ApiClient,
+    token:  String,
+}
+
+impl VaultProvider {
+    pub fn new(addr: &str, token: String) -> Result<Self> {
+        let client = ApiClient::new(addr, &PROGRAM_NAME, VERSION, None).map_err(|e| {
+                         Error::SecretProviderError(format!("Invalid Vault address {}: {}",
+                                                            addr, e))
+                     })?;
+        Ok(VaultProvider { client, token })
+    }
+
+    /// Create a provider from `HAB_VAULT_ADDR` and `HAB_VAULT_TOKEN`, if the former is set.
+    pub fn from_env() -> Result<Option<Self>> {
+        match env::var(VAULT_ADDR_ENVVAR) {
+            Ok(addr) => {
+                let token = env::var(VAULT_TOKEN_ENVVAR).unwrap_or_default();
+                Self::new(&addr, token).map(Some)
+            }
+            Err(_) => Ok(None),
+        }
+    }
+
+    fn authorize<'a>(&self, rb: RequestBuilder<'a>) -> RequestBuilder<'a> {
+        rb.header(Authorization(Bearer { token: self.token.clone(), }))
+    }
+
+    fn read_response(mut res: Response, path: &str) -> Result<Json> {
+        let mut body = String::new();
+        res.read_to_string(&mut body)?;
+        match res.status {
+            StatusCode::Ok => {
+                serde_json::from_str(&body).map_err(|e| {
+                                               Error::SecretProviderError(format!(
+                        "Invalid response from Vault for {}: {}",
+                        path, e
+                    ))
+                                           })
+            }
+            StatusCode::NotFound => Err(Error::SecretNotFound(path.to_string())),
+            status => {
+                Err(Error::SecretProviderError(format!("Vault responded with {} for {}: \
+                                                        {}",
+                                                       status,
+                                                       path,
+                                                       body.trim())))
+            }
+        }
+    }
+}
+
+impl SecretProvider for VaultProvider {
+    fn fetch(&self, path: &str, key: &str) -> Result<Secret> {
+        let res = self.authorize(self.client.get(&format!("v1/{}", path)))
+                      .send()
+                      .map_err(|e| Error::SecretProviderError(format!("{}", e)))?;
+        let body = Self::read_response(res, path)?;
+        let value = secret_data(&body).and_then(|data| data.get(key))
+                                      .ok_or_else(|| {
+                                          Error::SecretNotFound(format!("{}/{}", path, key))
+                                      })?;
+        let value = match *value {
+            Json::String(ref s) => s.clone(),
+            ref other => other.to_string(),
+        };
+        Ok(Secret { value,
+                    lease: lease(&body) })
+    }
+
+    fn renew(&self, lease: &Lease) -> Result<Lease> {
+        let id = lease.id.as_ref().ok_or_else(|| {
+                                      Error::SecretProviderError("Lease has no id".to_string())
+                                  })?;
+        let request = serde_json::json!({ "lease_id": id }).to_string();
+        let res = self.authorize(self.client.put("v1/sys/leases/renew"))
+                      .body(request.as_str())
+                      .send()
+                      .map_err(|e| Error::SecretProviderError(format!("{}", e)))?;
+        let body = Self::read_response(res, id)?;
+        lease(&body).ok_or_else(|| {
+                        Error::SecretProviderError(format!("Lease {} was not renewed", id))
+                    })
+    }
+}
+
+/// The fields of a secret. Version 2 of the key/value engine nests them in a second `data`
+/// object, next to the secret's `metadata`.
+fn secret_data(body: &Json) -> Option<&serde_json::Map<String, Json>> {
+    let data = body.get("data")?;
+    match (data.get("data"), data.get("metadata")) {
+        (Some(nested), Some(_)) => nested.as_object(),
+        _ => data.as_object(),
+    }
+}
+
+fn lease(body: &Json) -> Option<Lease> {
+    let duration = body.get("lease_duration").and_then(Json::as_u64)?;
+    if duration == 0 {
+        return None;
+    }
+    let id = body.get("lease_id")
+                 .and_then(Json::as_str)
+                 .filter(|id| !id.is_empty())
+                 .map(str::to_string);
+    let renewable = body.get("renewable").and_then(Json::as_bool).unwrap_or(false);
+    Some(Lease { renewable: renewable && id.is_some(),
+                 id,
+                 duration: Duration::from_secs(duration) })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn secret_data_reads_kv_version_1_responses() {
+        let body = json!({
+            "lease_id": "",
+            "lease_duration": 2_764_800,
+            "renewable": false,
+            "data": { "password": "hunter2" }
+        });
+        assert_eq!(secret_data(&body).unwrap()["password"], "hunter2");
+        assert_eq!(lease(&body),
+                   Some(Lease { id:        None,
+                                duration:  Duration::from_secs(2_764_800),
+                                renewable: false, }));
+    }
+
+    #[test]
+    fn secret_data_reads_kv_version_2_responses() {
+        let body = json!({
+            "lease_id": "",
+            "lease_duration": 0,
+            "renewable": false,
+            "data": {
+                "data": { "password": "hunter2" },
+                "metadata": { "version": 3 }
+            }
+        });
+        assert_eq!(secret_data(&body).unwrap()["password"], "hunter2");
+        assert_eq!(lease(&body), None);
+    }
+
+    #[test]
+    fn lease_reads_dynamic_secret_leases() {
+        let body = json!({
+            "lease_id": "database/creds/readonly/2f6a614c",
+            "lease_duration": 3600,
+            "renewable": true,
+            "data": { "username": "v-token-readonly", "password": "A1a-xyz" }
+        });
+        assert_eq!(lease(&body),
+                   Some(Lease { id:        Some("database/creds/readonly/2f6a614c".to_string()),
+                                duration:  Duration::from_secs(3600),
+                                renewable: true, }));
+    }
+
+    /// Requires a Vault dev server, e.g. `vault server -dev -dev-root-token-id=root`, with
+    /// `vault kv put secret/habitat password=hunter2` applied to it.
+    #[test]
+    #[cfg(feature = "functional")]
+    fn fetch_reads_from_a_vault_dev_server() {
+        let addr = env::var(VAULT_ADDR_ENVVAR).unwrap_or_else(|_| {
+                                                  "http://127.0.0.1:8200".to_string()
+                                              });
+        let token = env::var(VAULT_TOKEN_ENVVAR).unwrap_or_else(|_| "root".to_string());
+        let vault = VaultProvider::new(&addr, token).unwrap();
+        assert_eq!(vault.fetch("secret/data/habitat", "password").unwrap().value,
+                   "hunter2");
+        match vault.fetch("secret/data/habitat", "missing") {
+            Err(Error::SecretNotFound(_)) => (),
+            other => panic!("Expected a missing secret, got {:?}", other),
+        }
+    }
+}
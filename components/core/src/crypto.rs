@@ -118,10 +118,14 @@
 //! 1. The last line is left empty, meaning that 2 newline characters (`\n`) separate the header
 //!    from the payload
 //!
-//! The remainder of the file is a compressed tarball of the contents to be extracted on disk. At
-//! present, the tarball is compressed using `xz` but is considered an implementation detail. Also
-//! note unlike the format of keys, the compressed tarball is **not** Base64 encoded--it is the
-//! compressed tarball itself.
+//! The remainder of the file is a compressed tarball of the contents to be extracted on disk. A
+//! `HART-1` tarball is always compressed using `xz`. Also note unlike the format of keys, the
+//! compressed tarball is **not** Base64 encoded--it is the compressed tarball itself.
+//!
+//! A `HART-2` artifact has one more plaintext line between the signature and the empty line,
+//! naming the compression applied to the tarball: `xz`, `zstd`, or `none`. Since the payload
+//! hash is computed over the tarball alone, this line is not covered by the signature; a tampered
+//! value only results in a payload that fails to unpack.
 //!
 //! Note that the BLAKE2b hash functions use a digest length of 32 bytes (256 bits!). More details
 //! about the hashing strategy can be found in the [libsodium hashing
@@ -162,7 +166,7 @@
 //! ```
 //!
 //! The above command starts streaming the file to standard out at line 6, skipping the first 5
-//! plaintext lines.
+//! plaintext lines. For a `HART-2` artifact the payload starts at line 7 instead.
 //!
 //! If the Habitat artifact needs to be extracted on disk without verifying its integrity or
 //! authenticity, this can be accomplished with:
@@ -237,6 +241,8 @@ pub static SIG_HASH_TYPE: &'static str = "BLAKE2b";
 /// at runtime. This is useful for testing.
 pub static CACHE_KEY_PATH_ENV_VAR: &'static str = "HAB_CACHE_KEY_PATH";
 pub static HART_FORMAT_VERSION: &'static str = "HART-1";
+/// Artifact format version which declares the compression of its payload in the header
+pub static HART_V2_FORMAT_VERSION: &'static str = "HART-2";
 pub static BOX_FORMAT_VERSION: &'static str = "BOX-1";
 pub static ANONYMOUS_BOX_FORMAT_VERSION: &'static str = "ANONYMOUS-BOX-1";
 /// Create secret key files with these permissions
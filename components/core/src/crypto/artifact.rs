@@ -1,9 +1,11 @@
-use std::{fs::File,
+use std::{fmt,
+          fs::File,
           io::{self,
                prelude::*,
                BufReader,
                BufWriter},
-          path::Path};
+          path::Path,
+          str::FromStr};
 
 use base64;
 use sodiumoxide::crypto::sign;
@@ -12,14 +14,81 @@ use super::{hash,
             keys::parse_name_with_rev,
             SigKeyPair,
             HART_FORMAT_VERSION,
+            HART_V2_FORMAT_VERSION,
             SIG_HASH_TYPE};
 use crate::error::{Error,
                    Result};
 
+/// The compression applied to the tarball payload of a Habitat artifact.
+///
+/// `HART-1` artifacts are always compressed with `xz`, while `HART-2` artifacts declare their
+/// compression on the header line following the signature.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Compression {
+    Xz,
+    Zstd,
+    None,
+}
+
+impl Compression {
+    /// The artifact format version that must be written in the header of an artifact whose
+    /// payload uses this compression. `xz` payloads keep using `HART-1` so that older clients
+    /// can still read them.
+    pub fn format_version(self) -> &'static str {
+        match self {
+            Compression::Xz => HART_FORMAT_VERSION,
+            Compression::Zstd | Compression::None => HART_V2_FORMAT_VERSION,
+        }
+    }
+}
+
+impl Default for Compression {
+    fn default() -> Self { Compression::Xz }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match *self {
+            Compression::Xz => "xz",
+            Compression::Zstd => "zstd",
+            Compression::None => "none",
+        };
+        write!(f, "{}", value)
+    }
+}
+
+impl FromStr for Compression {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "xz" => Ok(Compression::Xz),
+            "zstd" => Ok(Compression::Zstd),
+            "none" => Ok(Compression::None),
+            _ => Err(Error::InvalidArtifactCompression(value.to_string())),
+        }
+    }
+}
+
 /// Generate and sign a package
 pub fn sign<P1: ?Sized, P2: ?Sized>(src: &P1, dst: &P2, pair: &SigKeyPair) -> Result<()>
     where P1: AsRef<Path>,
           P2: AsRef<Path>
+{
+    sign_with_compression(src, dst, pair, Compression::Xz)
+}
+
+/// Generate and sign a package whose payload was compressed with `compression`.
+///
+/// Anything other than `xz` produces a `HART-2` artifact which records the compression in its
+/// header.
+pub fn sign_with_compression<P1: ?Sized, P2: ?Sized>(src: &P1,
+                                                     dst: &P2,
+                                                     pair: &SigKeyPair,
+                                                     compression: Compression)
+                                                     -> Result<()>
+    where P1: AsRef<Path>,
+          P2: AsRef<Path>
 {
     let hash = hash::hash_file(&src)?;
     debug!("File hash for {} = {}", src.as_ref().display(), &hash);
@@ -27,12 +96,17 @@ pub fn sign<P1: ?Sized, P2: ?Sized>(src: &P1, dst: &P2, pair: &SigKeyPair) -> Re
     let signature = sign::sign(&hash.as_bytes(), pair.secret()?);
     let output_file = File::create(dst)?;
     let mut writer = BufWriter::new(&output_file);
+    let format_version = compression.format_version();
     write!(writer,
-           "{}\n{}\n{}\n{}\n\n",
-           HART_FORMAT_VERSION,
+           "{}\n{}\n{}\n{}\n",
+           format_version,
            pair.name_with_rev(),
            SIG_HASH_TYPE,
            base64::encode(&signature))?;
+    if format_version == HART_V2_FORMAT_VERSION {
+        writeln!(writer, "{}", compression)?;
+    }
+    writeln!(writer)?;
     let mut file = File::open(src)?;
     io::copy(&mut file, &mut writer)?;
     Ok(())
@@ -40,30 +114,19 @@ pub fn sign<P1: ?Sized, P2: ?Sized>(src: &P1, dst: &P2, pair: &SigKeyPair) -> Re
 
 /// return a BufReader to the .tar bytestream, skipping the signed header
 pub fn get_archive_reader<P: AsRef<Path>>(src: &P) -> Result<BufReader<File>> {
-    let f = File::open(src)?;
-    let mut your_format_version = String::new();
-    let mut your_key_name = String::new();
-    let mut your_hash_type = String::new();
-    let mut your_signature_raw = String::new();
-    let mut empty_line = String::new();
+    let (_, reader) = get_archive_reader_with_compression(src)?;
+    Ok(reader)
+}
 
+/// return the payload compression and a BufReader to the .tar bytestream, skipping the signed
+/// header
+pub fn get_archive_reader_with_compression<P: AsRef<Path>>(
+    src: &P)
+    -> Result<(Compression, BufReader<File>)> {
+    let f = File::open(src)?;
     let mut reader = BufReader::new(f);
-    if reader.read_line(&mut your_format_version)? == 0 {
-        return Err(Error::CryptoError("Can't read format version".to_string()));
-    }
-    if reader.read_line(&mut your_key_name)? == 0 {
-        return Err(Error::CryptoError("Can't read keyname".to_string()));
-    }
-    if reader.read_line(&mut your_hash_type)? == 0 {
-        return Err(Error::CryptoError("Can't read hash type".to_string()));
-    }
-    if reader.read_line(&mut your_signature_raw)? == 0 {
-        return Err(Error::CryptoError("Can't read signature".to_string()));
-    }
-    if reader.read_line(&mut empty_line)? == 0 {
-        return Err(Error::CryptoError("Can't end of header".to_string()));
-    }
-    Ok(reader)
+    let header = read_artifact_header(&mut reader)?;
+    Ok((header.compression, reader))
 }
 
 pub struct ArtifactHeader {
@@ -71,18 +134,21 @@ pub struct ArtifactHeader {
     pub key_name:       String,
     pub hash_type:      String,
     pub signature_raw:  String,
+    pub compression:    Compression,
 }
 
 impl ArtifactHeader {
     pub fn new(format_version: String,
                key_name: String,
                hash_type: String,
-               signature_raw: String)
+               signature_raw: String,
+               compression: Compression)
                -> ArtifactHeader {
         ArtifactHeader { format_version,
                          key_name,
                          hash_type,
-                         signature_raw }
+                         signature_raw,
+                         compression }
     }
 }
 
@@ -93,13 +159,19 @@ pub fn get_artifact_header<P: ?Sized>(src: &P) -> Result<ArtifactHeader>
     where P: AsRef<Path>
 {
     let f = File::open(src)?;
+    let mut reader = BufReader::new(f);
+    read_artifact_header(&mut reader)
+}
+
+/// Consume the header lines of an artifact from `reader`, leaving it positioned at the start of
+/// the payload.
+fn read_artifact_header<R: BufRead>(reader: &mut R) -> Result<ArtifactHeader> {
     let mut your_format_version = String::new();
     let mut your_key_name = String::new();
     let mut your_hash_type = String::new();
     let mut your_signature_raw = String::new();
     let mut empty_line = String::new();
 
-    let mut reader = BufReader::new(f);
     if reader.read_line(&mut your_format_version)? == 0 {
         return Err(Error::CryptoError("Can't read format version".to_string()));
     }
@@ -112,10 +184,11 @@ pub fn get_artifact_header<P: ?Sized>(src: &P) -> Result<ArtifactHeader>
     if reader.read_line(&mut your_signature_raw)? == 0 {
         return Err(Error::CryptoError("Can't read signature".to_string()));
     }
+    let your_format_version = your_format_version.trim().to_string();
+    let compression = read_compression(reader, &your_format_version)?;
     if reader.read_line(&mut empty_line)? == 0 {
         return Err(Error::CryptoError("Can't end of header".to_string()));
     }
-    let your_format_version = your_format_version.trim().to_string();
     let your_key_name = your_key_name.trim().to_string();
     let your_hash_type = your_hash_type.trim().to_string();
     let your_signature_raw = your_signature_raw.trim().to_string();
@@ -123,7 +196,25 @@ pub fn get_artifact_header<P: ?Sized>(src: &P) -> Result<ArtifactHeader>
     Ok(ArtifactHeader::new(your_format_version,
                            your_key_name,
                            your_hash_type,
-                           your_signature_raw))
+                           your_signature_raw,
+                           compression))
+}
+
+/// Read the compression line of a `HART-2` header. Older formats carry no such line and are
+/// always `xz` compressed.
+fn read_compression<R: BufRead>(reader: &mut R, format_version: &str) -> Result<Compression> {
+    if format_version != HART_V2_FORMAT_VERSION {
+        return Ok(Compression::Xz);
+    }
+    let mut buffer = String::new();
+    if reader.read_line(&mut buffer)? == 0 {
+        return Err(Error::CryptoError("Corrupt payload, can't read compression".to_string()));
+    }
+    buffer.trim().parse()
+}
+
+fn is_supported_format_version(format_version: &str) -> bool {
+    format_version == HART_FORMAT_VERSION || format_version == HART_V2_FORMAT_VERSION
 }
 
 /// verify the crypto signature of a .hart file
@@ -134,7 +225,7 @@ pub fn verify<P1: ?Sized, P2: ?Sized>(src: &P1, cache_key_path: &P2) -> Result<(
     let f = File::open(src)?;
     let mut reader = BufReader::new(f);
 
-    let _ = {
+    let format_version = {
         let mut buffer = String::new();
         match reader.read_line(&mut buffer) {
             Ok(0) => {
@@ -143,7 +234,7 @@ pub fn verify<P1: ?Sized, P2: ?Sized>(src: &P1, cache_key_path: &P2) -> Result<(
                                                        .to_string()));
             }
             Ok(_) => {
-                if buffer.trim() != HART_FORMAT_VERSION {
+                if !is_supported_format_version(buffer.trim()) {
                     let msg = format!("Unsupported format version: {}", &buffer.trim());
                     return Err(Error::CryptoError(msg));
                 }
@@ -196,6 +287,7 @@ pub fn verify<P1: ?Sized, P2: ?Sized>(src: &P1, cache_key_path: &P2) -> Result<(
             Err(e) => return Err(Error::from(e)),
         }
     };
+    read_compression(&mut reader, &format_version)?;
     {
         let mut buffer = String::new();
         if reader.read_line(&mut buffer)? == 0 {
@@ -234,7 +326,7 @@ pub fn artifact_signer<P: AsRef<Path>>(src: &P) -> Result<String> {
                                                        .to_string()));
             }
             Ok(_) => {
-                if buffer.trim() != HART_FORMAT_VERSION {
+                if !is_supported_format_version(buffer.trim()) {
                     let msg = format!("Unsupported format version: {}", &buffer.trim());
                     return Err(Error::CryptoError(msg));
                 }
@@ -271,6 +363,7 @@ mod test {
                         test_support::*,
                         SigKeyPair,
                         HART_FORMAT_VERSION,
+                        HART_V2_FORMAT_VERSION,
                         SIG_HASH_TYPE},
                 *};
 
@@ -508,4 +601,86 @@ mod test {
         assert_eq!(SIG_HASH_TYPE, hart_header.hash_type);
         assert!(!hart_header.signature_raw.is_empty());
     }
+
+    #[test]
+    fn sign_and_verify_with_compression() {
+        let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let pair = SigKeyPair::generate_pair_for_origin("unicorn").unwrap();
+        pair.to_pair_files(cache.path()).unwrap();
+
+        for compression in &[Compression::Xz, Compression::Zstd, Compression::None] {
+            let dst = cache.path().join(format!("signed-{}.dat", compression));
+            sign_with_compression(&fixture("signme.dat"), &dst, &pair, *compression).unwrap();
+            verify(&dst, cache.path()).unwrap();
+            assert_eq!(pair.name_with_rev(), artifact_signer(&dst).unwrap());
+        }
+    }
+
+    #[test]
+    fn get_artifact_header_with_compression() {
+        let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let pair = SigKeyPair::generate_pair_for_origin("unicorn").unwrap();
+        pair.to_pair_files(cache.path()).unwrap();
+        let dst = cache.path().join("src.signed");
+        sign_with_compression(&fixture("signme.dat"), &dst, &pair, Compression::Zstd).unwrap();
+
+        let hart_header = get_artifact_header(&dst).unwrap();
+        assert_eq!(HART_V2_FORMAT_VERSION, hart_header.format_version);
+        assert_eq!(Compression::Zstd, hart_header.compression);
+        assert_eq!(SIG_HASH_TYPE, hart_header.hash_type);
+    }
+
+    #[test]
+    fn get_archive_reader_with_compression_working() {
+        let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let pair = SigKeyPair::generate_pair_for_origin("unicorn").unwrap();
+        pair.to_pair_files(cache.path()).unwrap();
+        let src = cache.path().join("src.in");
+        let dst = cache.path().join("src.signed");
+        let mut f = File::create(&src).unwrap();
+        f.write_all(b"hearty goodness").unwrap();
+        sign_with_compression(&src, &dst, &pair, Compression::None).unwrap();
+
+        let mut buffer = String::new();
+        let (compression, mut reader) = get_archive_reader_with_compression(&dst).unwrap();
+        reader.read_to_string(&mut buffer).unwrap();
+        assert_eq!(Compression::None, compression);
+        assert_eq!(buffer.as_bytes(), b"hearty goodness");
+    }
+
+    #[test]
+    fn hart_1_is_always_xz() {
+        let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let pair = SigKeyPair::generate_pair_for_origin("unicorn").unwrap();
+        pair.to_pair_files(cache.path()).unwrap();
+        let dst = cache.path().join("src.signed");
+        sign(&fixture("signme.dat"), &dst, &pair).unwrap();
+
+        let hart_header = get_artifact_header(&dst).unwrap();
+        assert_eq!(HART_FORMAT_VERSION, hart_header.format_version);
+        assert_eq!(Compression::Xz, hart_header.compression);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid artifact compression: bzip2")]
+    fn verify_invalid_compression() {
+        let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let pair = SigKeyPair::generate_pair_for_origin("unicorn").unwrap();
+        pair.to_pair_files(cache.path()).unwrap();
+        let dst = cache.path().join("signed.dat");
+        let mut f = File::create(&dst).unwrap();
+        f.write_all(format!("HART-2\n{}\nBLAKE2b\nU3VycHJpc2Uh\nbzip2\n\n",
+                            pair.name_with_rev()).as_bytes())
+         .unwrap();
+
+        verify(&dst, cache.path()).unwrap();
+    }
+
+    #[test]
+    fn compression_from_str() {
+        assert_eq!(Compression::Xz, "xz".parse::<Compression>().unwrap());
+        assert_eq!(Compression::Zstd, "zstd".parse::<Compression>().unwrap());
+        assert_eq!(Compression::None, "none".parse::<Compression>().unwrap());
+        assert!("gzip".parse::<Compression>().is_err());
+    }
 }
@@ -85,16 +85,28 @@ pub enum Error {
     FullyQualifiedPackageIdentRequired(String),
     /// Occurs when an application environment string cannot be successfully parsed.
     InvalidApplicationEnvironment(String),
+    /// Occurs when an artifact declares a payload compression which is not recognized.
+    InvalidArtifactCompression(String),
     /// Occurs when a service binding cannot be successfully parsed.
     InvalidBinding(String),
+    /// Occurs when a number or percentage of canaries cannot be successfully parsed.
+    InvalidCanaryCount(String),
     /// Occurs when a package identifier string cannot be successfully parsed.
     InvalidPackageIdent(String),
     /// Occurs when a package target string cannot be successfully parsed.
     InvalidPackageTarget(String),
     /// Occurs when a package type is not recognized.
     InvalidPackageType(String),
+    /// Occurs when a resource limit has an unknown name or an out of range value.
+    InvalidResourceLimit(String),
+    /// Occurs when a sandbox option has an unknown name or value.
+    InvalidSandboxOption(String),
     /// Occurs when a service group string cannot be successfully parsed.
     InvalidServiceGroup(String),
+    /// Occurs when an update window string cannot be successfully parsed.
+    InvalidUpdateWindow(String),
+    /// Occurs when a package version constraint cannot be successfully parsed.
+    InvalidVersionConstraint(String),
     /// Occurs when an origin is in an invalid format
     InvalidOrigin(String),
     /// Occurs when an OsString path cannot be converted to a String
@@ -266,11 +278,22 @@ impl fmt::Display for Error {
                          string is in the form application.environment (example: twitter.prod)",
                         e)
             }
+            Error::InvalidArtifactCompression(ref e) => {
+                format!("Invalid artifact compression: {}. Supported compressions are xz, zstd, \
+                         and none",
+                        e)
+            }
             Error::InvalidBinding(ref binding) => {
                 format!("Invalid binding '{}', must be of the form <NAME>:<SERVICE_GROUP> where \
                          <NAME> is a service name, and <SERVICE_GROUP> is a valid service group",
                         binding)
             }
+            Error::InvalidCanaryCount(ref e) => {
+                format!("Invalid number of canaries: {}. A valid number of canaries is a \
+                         positive number of members (example: 2) or a percentage of the service \
+                         group (example: 10%)",
+                        e)
+            }
             Error::InvalidPackageIdent(ref e) => {
                 format!("Invalid package identifier: {:?}. A valid identifier is in the form \
                          origin/name (example: acme/redis)",
@@ -282,11 +305,35 @@ impl fmt::Display for Error {
                         e)
             }
             Error::InvalidPackageType(ref e) => format!("Invalid package type: {}.", e),
+            Error::InvalidResourceLimit(ref e) => {
+                format!("Invalid resource limit: {}. Valid limits are memory_max and pids_max \
+                         (any number), cpu_quota (a percentage of one CPU above 0), and \
+                         cpu_weight and io_weight (1 to 10000)",
+                        e)
+            }
+            Error::InvalidSandboxOption(ref e) => {
+                format!("Invalid sandbox option: {}. Valid options are private_tmp, \
+                         read_only_pkgs, no_new_privs and private_network (true or false), \
+                         capabilities (a list of capability names), and seccomp_profile \
+                         (default)",
+                        e)
+            }
             Error::InvalidServiceGroup(ref e) => {
                 format!("Invalid service group: {}. A valid service group string is in the form \
                          service.group (example: redis.production)",
                         e)
             }
+            Error::InvalidUpdateWindow(ref e) => {
+                format!("Invalid update window: {}. A valid update window is a crontab schedule \
+                         followed by an optional time zone (example: * 2-4 * * sat,sun \
+                         Europe/Berlin)",
+                        e)
+            }
+            Error::InvalidVersionConstraint(ref e) => {
+                format!("Invalid version constraint: {}. A valid constraint is a comma-separated \
+                         list of comparators (example: >=1.0.2,<1.1)",
+                        e)
+            }
             Error::InvalidOrigin(ref origin) => {
                 format!("Invalid origin: {}. Origins must begin with a lowercase letter or \
                          number. Allowed characters include lowercase letters, numbers, -, and _. \
@@ -439,10 +486,17 @@ impl error::Error for Error {
                 "Application environment strings must be in application.environment format \
                  (example: twitter.prod)"
             }
+            Error::InvalidArtifactCompression(_) => {
+                "Artifact compression must be one of xz, zstd, or none"
+            }
             Error::InvalidBinding(_) => {
                 "Service Bind strings must be in name:service_group format (example \
                  cache:redis.cache@organization)."
             }
+            Error::InvalidCanaryCount(_) => {
+                "Canaries must be a positive number of members or a percentage of the service \
+                 group (example: 2 or 10%)"
+            }
             Error::InvalidPackageIdent(_) => {
                 "Package identifiers must be in origin/name format (example: acme/redis)"
             }
@@ -450,10 +504,19 @@ impl error::Error for Error {
                 "Package targets must be in architecture-platform format (example: x86_64-linux)"
             }
             Error::InvalidPackageType(_) => "Unsupported package type supplied.",
+            Error::InvalidResourceLimit(_) => "Resource limit has an invalid name or value",
+            Error::InvalidSandboxOption(_) => "Sandbox option has an invalid name or value",
             Error::InvalidServiceGroup(_) => {
                 "Service group strings must be in service.group[@organization] format (example: \
                  redis.production or foo.default@bazcorp)"
             }
+            Error::InvalidUpdateWindow(_) => {
+                "Update windows must be a crontab schedule followed by an optional time zone \
+                 (example: * 2-4 * * sat,sun Europe/Berlin)"
+            }
+            Error::InvalidVersionConstraint(_) => {
+                "Version constraints must be comma-separated comparators such as >=1.0.2 or ~1.2"
+            }
             Error::InvalidOrigin(_) => {
                 "Origins must begin with a lowercase letter or number.  Allowed characters include \
                  a - z, 0 - 9, _, and -. No more than 255 characters."
@@ -45,6 +45,9 @@ pub const FS_ROOT_ENVVAR: &str = "FS_ROOT";
 pub const SYSTEMDRIVE_ENVVAR: &str = "SYSTEMDRIVE";
 /// The file where user-defined configuration for each service is found.
 pub const USER_CONFIG_FILE: &str = "user.toml";
+/// The file where a JSON Patch applied on top of the user-defined configuration of each service
+/// is found.
+pub const USER_CONFIG_PATCH_FILE: &str = "user.patch.json";
 /// Permissions that service-owned service directories should
 /// have. The user and group will be `SVC_USER` / `SVC_GROUP`.
 #[cfg(not(windows))]
@@ -1,7 +1,9 @@
+pub mod cgroups;
 pub mod ffi;
 pub mod filesystem;
 pub mod net;
 pub mod process;
+pub mod sandbox;
 pub mod signals;
 pub mod system;
 pub mod users;
@@ -0,0 +1,325 @@
+//! Control groups (cgroups v2) in which the Launcher runs services.
+//!
+//! The Launcher is expected to run in a cgroup delegated to it, e.g. by systemd's
+//! `Delegate=yes`, and leaves cgroups alone unless it is, see `is_delegated`. It moves itself
+//! (and so the Supervisor) into the `hab-launcher` child of that cgroup, as the kernel only lets
+//! cgroups without processes hand controllers to their children, and runs each service in its
+//! own child of `hab-services`:
+//!
+//! ```text
+//! <delegated cgroup>/
+//!     hab-launcher/
+//!     hab-services/
+//!         redis.default/
+//!         nginx.default/
+//! ```
+//!
+//! None of this applies on hosts which don't mount the unified cgroup hierarchy, where services
+//! simply run in the Launcher's cgroup.
+
+use crate::os::process::{Pid,
+                         ResourceLimits};
+use serde_derive::{Deserialize,
+                   Serialize};
+use std::{fs::{self,
+               File,
+               OpenOptions},
+          io,
+          path::{Path,
+                 PathBuf}};
+
+/// Where the unified cgroup hierarchy is mounted.
+pub const CGROUP_MOUNT: &str = "/sys/fs/cgroup";
+/// The cgroup the Launcher and the Supervisor run in.
+pub const LAUNCHER_CGROUP: &str = "hab-launcher";
+/// The cgroup holding a cgroup for each service.
+pub const SERVICES_CGROUP: &str = "hab-services";
+
+/// The controllers enabled for service cgroups, as far as the delegated cgroup has them.
+const CONTROLLERS: &[&str] = &["cpu", "io", "memory", "pids"];
+/// The period over which `cpu.max` quotas are measured, in microseconds.
+const CPU_PERIOD_USECS: u64 = 100_000;
+
+/// What a service's processes are currently using, as accounted by its cgroup.
+#[derive(Deserialize, Serialize, Eq, PartialEq, Debug, Clone, Default)]
+pub struct ResourceUsage {
+    /// The number of bytes of memory in use, see `memory.current`.
+    pub memory_current: Option<u64>,
+    /// The CPU time used so far, in microseconds, see `usage_usec` in `cpu.stat`.
+    pub cpu_usage_usec: Option<u64>,
+    /// The number of processes and threads running, see `pids.current`.
+    pub pids_current:   Option<u64>,
+    /// The number of bytes read from disk so far, see `rbytes` in `io.stat`.
+    pub io_read_bytes:  Option<u64>,
+    /// The number of bytes written to disk so far, see `wbytes` in `io.stat`.
+    pub io_write_bytes: Option<u64>,
+}
+
+/// Moves the current process into the `hab-launcher` child of its cgroup and enables the
+/// controllers services are limited with. Returns the cgroup holding the service cgroups.
+///
+/// This must be called before the process starts any children, since they would stay behind in
+/// the delegated cgroup and keep it from enabling controllers. Fails if the cgroup was not
+/// delegated to the current process, unless `force` is set.
+pub fn delegate(force: bool) -> io::Result<PathBuf> {
+    let own = cgroup_of("self")?;
+    if own.file_name().map_or(false, |name| name == LAUNCHER_CGROUP) {
+        // We were restarted and already did this
+        return services_cgroup();
+    }
+    if !force && !is_delegated(&own) {
+        return Err(io::Error::new(io::ErrorKind::PermissionDenied,
+                                  format!("{} was not delegated to the Launcher",
+                                          own.display())));
+    }
+    let launcher = own.join(LAUNCHER_CGROUP);
+    fs::create_dir_all(&launcher)?;
+    fs::write(launcher.join("cgroup.procs"), "0")?;
+    enable_controllers(&own)?;
+    let services = own.join(SERVICES_CGROUP);
+    fs::create_dir_all(&services)?;
+    enable_controllers(&services)?;
+    Ok(services)
+}
+
+/// Returns the cgroup holding the service cgroups, if the current process has been delegated.
+pub fn services_cgroup() -> io::Result<PathBuf> {
+    let own = cgroup_of("self")?;
+    match own.parent() {
+        Some(parent) if own.file_name().map_or(false, |name| name == LAUNCHER_CGROUP) => {
+            Ok(parent.join(SERVICES_CGROUP))
+        }
+        _ => {
+            Err(io::Error::new(io::ErrorKind::NotFound,
+                               format!("{} is not a {} cgroup",
+                                       own.display(),
+                                       LAUNCHER_CGROUP)))
+        }
+    }
+}
+
+/// Creates the cgroup of the service with the given id, or resets the limits of the one left
+/// behind by an earlier run of the service, and applies the limits to it.
+pub fn create_service_cgroup(services: &Path,
+                             id: &str,
+                             limits: &ResourceLimits)
+                             -> io::Result<PathBuf> {
+    let cgroup = services.join(id);
+    match fs::create_dir(&cgroup) {
+        Err(ref err) if err.kind() == io::ErrorKind::AlreadyExists => {}
+        result => result?,
+    }
+    let cpu_max = match limits.cpu_quota {
+        Some(percent) => {
+            format!("{} {}",
+                    u64::from(percent) * CPU_PERIOD_USECS / 100,
+                    CPU_PERIOD_USECS)
+        }
+        None => format!("max {}", CPU_PERIOD_USECS),
+    };
+    // Unset limits are written as well, to reset those of an earlier run of the service
+    let settings = [("memory.max", limits.memory_max.is_some(), limit_or_max(limits.memory_max)),
+                    ("cpu.weight",
+                     limits.cpu_weight.is_some(),
+                     limits.cpu_weight.unwrap_or(100).to_string()),
+                    ("cpu.max", limits.cpu_quota.is_some(), cpu_max),
+                    ("pids.max", limits.pids_max.is_some(), limit_or_max(limits.pids_max)),
+                    ("io.weight",
+                     limits.io_weight.is_some(),
+                     format!("default {}", limits.io_weight.unwrap_or(100)))];
+    for (file, is_set, value) in settings.iter() {
+        let path = cgroup.join(file);
+        // Controllers which aren't available have no interface files
+        if path.exists() {
+            fs::write(&path, value)?;
+        } else if *is_set {
+            return Err(io::Error::new(io::ErrorKind::NotFound,
+                                      format!("{} is not available to limit the service with",
+                                              file)));
+        }
+    }
+    Ok(cgroup)
+}
+
+/// Opens the `cgroup.procs` file of the cgroup, to which a process writes `0` to move itself
+/// into the cgroup. It is opened before forking a service, so that the child can join the cgroup
+/// before it execs the service, with the permissions of the Launcher rather than of the
+/// service's user. The file is closed on exec.
+pub fn open_procs(cgroup: &Path) -> io::Result<File> {
+    OpenOptions::new().write(true)
+                      .open(cgroup.join("cgroup.procs"))
+}
+
+/// Whether the cgroup was delegated to the current process, i.e. it may create cgroups under it
+/// and move processes between them without interfering with the cgroups of a service manager.
+/// systemd marks the cgroups of units with `Delegate=yes` with the `trusted.delegate` or, for
+/// user units, `user.delegate` extended attribute, and cgroups delegated to an unprivileged user
+/// are owned by that user.
+#[cfg(target_os = "linux")]
+pub fn is_delegated(cgroup: &Path) -> bool {
+    use std::{ffi::CString,
+              os::unix::{ffi::OsStrExt,
+                         fs::MetadataExt}};
+
+    let euid = unsafe { libc::geteuid() };
+    if euid != 0
+       && fs::metadata(cgroup).map_or(false, |metadata| metadata.uid() == euid)
+    {
+        return true;
+    }
+    let path = match CString::new(cgroup.as_os_str().as_bytes()) {
+        Ok(path) => path,
+        Err(_) => return false,
+    };
+    ["trusted.delegate\0", "user.delegate\0"].iter().any(|name| {
+        let mut value = [0u8; 1];
+        let len = unsafe {
+            libc::getxattr(path.as_ptr(),
+                           name.as_ptr() as *const libc::c_char,
+                           value.as_mut_ptr() as *mut libc::c_void,
+                           value.len())
+        };
+        len == 1 && value[0] == b'1'
+    })
+}
+
+/// Cgroups are only managed on Linux.
+#[cfg(not(target_os = "linux"))]
+pub fn is_delegated(_cgroup: &Path) -> bool { false }
+
+/// Returns the service cgroup the process with the given id runs in, if any.
+pub fn service_cgroup_of(pid: Pid) -> Option<PathBuf> {
+    let cgroup = cgroup_of(&pid.to_string()).ok()?;
+    let parent = cgroup.parent()?;
+    if parent.file_name().map_or(false, |name| name == SERVICES_CGROUP) {
+        Some(cgroup)
+    } else {
+        None
+    }
+}
+
+/// Reads the current resource usage of a cgroup. Usage of controllers which aren't enabled for
+/// the cgroup is left unset.
+pub fn resource_usage(cgroup: &Path) -> ResourceUsage {
+    let read = |file: &str| fs::read_to_string(cgroup.join(file)).ok();
+    let io_stat = read("io.stat");
+    ResourceUsage { memory_current: read("memory.current").and_then(|s| s.trim().parse().ok()),
+                    cpu_usage_usec: read("cpu.stat").and_then(|s| stat(&s, "usage_usec")),
+                    pids_current:   read("pids.current").and_then(|s| s.trim().parse().ok()),
+                    io_read_bytes:  io_stat.as_ref().map(|s| io_stat_total(s, "rbytes")),
+                    io_write_bytes: io_stat.as_ref().map(|s| io_stat_total(s, "wbytes")), }
+}
+
+/// Returns the cgroup of the process with the given id (or `self`), as found in its
+/// `/proc/<pid>/cgroup` file.
+fn cgroup_of(pid: &str) -> io::Result<PathBuf> {
+    let content = fs::read_to_string(Path::new("/proc").join(pid).join("cgroup"))?;
+    parse_proc_cgroup(&content).ok_or_else(|| {
+                                   io::Error::new(io::ErrorKind::NotFound,
+                                                  "not running in a cgroup v2 hierarchy")
+                               })
+}
+
+/// Finds the unified hierarchy's entry of a `/proc/<pid>/cgroup` file, e.g. `0::/system.slice`.
+fn parse_proc_cgroup(content: &str) -> Option<PathBuf> {
+    content.lines()
+           .find(|line| line.starts_with("0::"))
+           .map(|line| Path::new(CGROUP_MOUNT).join(line["0::".len()..].trim_start_matches('/')))
+}
+
+/// Enables the controllers in `CONTROLLERS` the cgroup has for its children.
+fn enable_controllers(cgroup: &Path) -> io::Result<()> {
+    let available = fs::read_to_string(cgroup.join("cgroup.controllers"))?;
+    for controller in CONTROLLERS.iter()
+                                 .filter(|c| available.split_whitespace().any(|a| a == **c))
+    {
+        fs::write(cgroup.join("cgroup.subtree_control"),
+                  format!("+{}", controller))?;
+    }
+    Ok(())
+}
+
+fn limit_or_max(limit: Option<u64>) -> String {
+    limit.map_or_else(|| "max".to_string(), |limit| limit.to_string())
+}
+
+/// Reads a value of a flat keyed file like `cpu.stat`.
+fn stat(content: &str, key: &str) -> Option<u64> {
+    content.lines()
+           .filter_map(|line| {
+               let mut fields = line.split_whitespace();
+               match (fields.next(), fields.next()) {
+                   (Some(k), Some(v)) if k == key => v.parse().ok(),
+                   _ => None,
+               }
+           })
+           .next()
+}
+
+/// Sums a value of `io.stat` across all devices, whose lines look like
+/// `8:0 rbytes=1024 wbytes=0 rios=1 wios=0 dbytes=0 dios=0`.
+fn io_stat_total(content: &str, key: &str) -> u64 {
+    content.split_whitespace()
+           .filter_map(|field| {
+               let mut parts = field.splitn(2, '=');
+               match (parts.next(), parts.next()) {
+                   (Some(k), Some(v)) if k == key => v.parse::<u64>().ok(),
+                   _ => None,
+               }
+           })
+           .sum()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempfile::Builder;
+
+    #[test]
+    fn finds_the_unified_hierarchy_cgroup() {
+        let content = "12:pids:/user.slice\n0::/system.slice/hab-sup.service\n";
+        assert_eq!(parse_proc_cgroup(content),
+                   Some(PathBuf::from("/sys/fs/cgroup/system.slice/hab-sup.service")));
+        assert_eq!(parse_proc_cgroup("0::/\n"), Some(PathBuf::from("/sys/fs/cgroup")));
+        assert_eq!(parse_proc_cgroup("1:name=systemd:/\n"), None);
+    }
+
+    #[test]
+    fn applies_limits_to_the_available_controllers() {
+        let services = Builder::new().prefix("hab-services").tempdir().unwrap();
+        let cgroup = services.path().join("redis.default");
+        fs::create_dir_all(&cgroup).unwrap();
+        for file in &["memory.max", "cpu.max", "cpu.weight", "pids.max"] {
+            fs::write(cgroup.join(file), "").unwrap();
+        }
+        let limits = ResourceLimits { memory_max: Some(1024),
+                                      cpu_quota: Some(150),
+                                      ..Default::default() };
+
+        create_service_cgroup(services.path(), "redis.default", &limits).unwrap();
+        let read = |file: &str| fs::read_to_string(cgroup.join(file)).unwrap();
+        assert_eq!(read("memory.max"), "1024");
+        assert_eq!(read("cpu.max"), "150000 100000");
+        assert_eq!(read("cpu.weight"), "100");
+        assert_eq!(read("pids.max"), "max");
+        assert!(!cgroup.join("io.weight").exists());
+    }
+
+    #[test]
+    fn reads_resource_usage() {
+        let cgroup = Builder::new().prefix("redis.default").tempdir().unwrap();
+        let write = |file: &str, content: &str| fs::write(cgroup.path().join(file), content);
+        write("memory.current", "4096\n").unwrap();
+        write("cpu.stat", "usage_usec 1500\nuser_usec 1000\nsystem_usec 500\n").unwrap();
+        write("io.stat",
+              "8:0 rbytes=100 wbytes=10 rios=1 wios=1\n8:16 rbytes=20 wbytes=0 rios=1 wios=0\n")
+            .unwrap();
+
+        assert_eq!(resource_usage(cgroup.path()),
+                   ResourceUsage { memory_current: Some(4096),
+                                   cpu_usage_usec: Some(1500),
+                                   pids_current:   None,
+                                   io_read_bytes:  Some(120),
+                                   io_write_bytes: Some(10), });
+    }
+}
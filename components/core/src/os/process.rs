@@ -21,6 +21,7 @@ pub use self::windows::{become_command,
                         current_pid,
                         handle_from_pid,
                         is_alive,
+                        terminate,
                         Pid};
 use crate::{error::Error,
             util::serde_string};
@@ -168,6 +169,70 @@ impl From<ShutdownSignal> for Signal {
     fn from(shutdown_signal: ShutdownSignal) -> Self { shutdown_signal.0 }
 }
 
+/// The resources a service's processes may use, all of them unlimited unless set. On Linux the
+/// Launcher enforces these with the service's cgroup; they are ignored elsewhere.
+#[derive(Deserialize, Serialize, Eq, PartialEq, Debug, Clone, Default, Hash)]
+pub struct ResourceLimits {
+    /// The number of bytes of memory the service may use, see `memory.max`.
+    pub memory_max: Option<u64>,
+    /// The service's share of CPU time relative to other services, from 1 to 10000 (the
+    /// default for processes is 100), see `cpu.weight`.
+    pub cpu_weight: Option<u32>,
+    /// The CPU time the service may use as a percentage of one CPU, e.g. 150 for one and a half
+    /// CPUs, see `cpu.max`.
+    pub cpu_quota:  Option<u32>,
+    /// The number of processes and threads the service may run, see `pids.max`.
+    pub pids_max:   Option<u64>,
+    /// The service's share of disk IO relative to other services, from 1 to 10000 (the default
+    /// for processes is 100), see `io.weight`.
+    pub io_weight:  Option<u32>,
+}
+
+impl ResourceLimits {
+    pub fn is_empty(&self) -> bool { *self == ResourceLimits::default() }
+
+    /// Sets the limit with the given name, as used in the `RESOURCE_LIMITS` metafile.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), Error> {
+        let invalid = || Error::InvalidResourceLimit(format!("{}={}", name, value));
+        match name {
+            "memory_max" => self.memory_max = Some(value.parse().map_err(|_| invalid())?),
+            "cpu_weight" => self.cpu_weight = Some(value.parse().map_err(|_| invalid())?),
+            "cpu_quota" => self.cpu_quota = Some(value.parse().map_err(|_| invalid())?),
+            "pids_max" => self.pids_max = Some(value.parse().map_err(|_| invalid())?),
+            "io_weight" => self.io_weight = Some(value.parse().map_err(|_| invalid())?),
+            _ => return Err(invalid()),
+        }
+        self.validate()
+    }
+
+    /// Returns an error if a limit is outside of the range the kernel accepts for it.
+    pub fn validate(&self) -> Result<(), Error> {
+        let weight = |name: &str, value: Option<u32>| {
+            match value {
+                Some(w) if w < 1 || w > 10000 => {
+                    Err(Error::InvalidResourceLimit(format!("{}={}", name, w)))
+                }
+                _ => Ok(()),
+            }
+        };
+        weight("cpu_weight", self.cpu_weight)?;
+        weight("io_weight", self.io_weight)?;
+        if self.cpu_quota == Some(0) {
+            return Err(Error::InvalidResourceLimit("cpu_quota=0".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Overrides these limits with those which are set in `other`.
+    pub fn merge(&mut self, other: &ResourceLimits) {
+        self.memory_max = other.memory_max.or(self.memory_max);
+        self.cpu_weight = other.cpu_weight.or(self.cpu_weight);
+        self.cpu_quota = other.cpu_quota.or(self.cpu_quota);
+        self.pids_max = other.pids_max.or(self.pids_max);
+        self.io_weight = other.io_weight.or(self.io_weight);
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -204,4 +269,36 @@ mod test {
                              .expect("Couldn't parse back into a Signal!"));
         }
     }
+
+    #[test]
+    fn resource_limits_are_set_by_name() {
+        let mut limits = ResourceLimits::default();
+        assert!(limits.is_empty());
+        limits.set("memory_max", "536870912").unwrap();
+        limits.set("cpu_quota", "150").unwrap();
+        assert_eq!(limits.memory_max, Some(536_870_912));
+        assert_eq!(limits.cpu_quota, Some(150));
+        assert!(!limits.is_empty());
+
+        assert!(limits.set("memory", "1").is_err());
+        assert!(limits.set("pids_max", "lots").is_err());
+        assert!(limits.set("cpu_weight", "0").is_err());
+        assert!(limits.set("io_weight", "10001").is_err());
+        assert!(limits.set("cpu_quota", "0").is_err());
+    }
+
+    #[test]
+    fn resource_limits_are_overridden_by_those_set() {
+        let mut limits = ResourceLimits { memory_max: Some(1024),
+                                          pids_max: Some(100),
+                                          ..Default::default() };
+        limits.merge(&ResourceLimits { pids_max: Some(50),
+                                       io_weight: Some(200),
+                                       ..Default::default() });
+        assert_eq!(limits,
+                   ResourceLimits { memory_max: Some(1024),
+                                    pids_max: Some(50),
+                                    io_weight: Some(200),
+                                    ..Default::default() });
+    }
 }
@@ -56,6 +56,29 @@ pub fn is_alive(pid: Pid) -> bool {
     }
 }
 
+/// Forcefully terminates the process with the given process identifier. Windows has no
+/// equivalent of SIGTERM, so this is the only way to stop a process we didn't create a console for.
+pub fn terminate(pid: Pid) -> Result<()> {
+    match handle_from_pid(pid) {
+        Some(handle) => {
+            let ret = unsafe { processthreadsapi::TerminateProcess(handle, 1) };
+            unsafe {
+                let _ = handleapi::CloseHandle(handle);
+            }
+            if ret == 0 {
+                return Err(Error::TerminateProcessFailed(format!(
+                    "Failed to call TerminateProcess on pid {}: {}",
+                    pid,
+                    io::Error::last_os_error()
+                )));
+            }
+            Ok(())
+        }
+        // The process has already exited
+        None => Ok(()),
+    }
+}
+
 /// Executes a command as a child process and exits with the child's exit code.
 ///
 /// Note that if successful, this function will not return.
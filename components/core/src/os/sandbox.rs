@@ -0,0 +1,194 @@
+//! Hardening the Launcher applies to a service's process before it execs the service, similar
+//! to systemd's sandboxing directives (`PrivateTmp=`, `ReadOnlyPaths=`, `NoNewPrivileges=`,
+//! `CapabilityBoundingSet=`, `SystemCallFilter=` and `PrivateNetwork=`).
+//!
+//! Sandboxing is only supported on Linux and is ignored elsewhere.
+
+use crate::error::Error;
+use serde_derive::{Deserialize,
+                   Serialize};
+
+/// The names of the Linux capabilities, indexed by their number.
+pub const CAPABILITIES: &[&str] = &["CAP_CHOWN",
+                                    "CAP_DAC_OVERRIDE",
+                                    "CAP_DAC_READ_SEARCH",
+                                    "CAP_FOWNER",
+                                    "CAP_FSETID",
+                                    "CAP_KILL",
+                                    "CAP_SETGID",
+                                    "CAP_SETUID",
+                                    "CAP_SETPCAP",
+                                    "CAP_LINUX_IMMUTABLE",
+                                    "CAP_NET_BIND_SERVICE",
+                                    "CAP_NET_BROADCAST",
+                                    "CAP_NET_ADMIN",
+                                    "CAP_NET_RAW",
+                                    "CAP_IPC_LOCK",
+                                    "CAP_IPC_OWNER",
+                                    "CAP_SYS_MODULE",
+                                    "CAP_SYS_RAWIO",
+                                    "CAP_SYS_CHROOT",
+                                    "CAP_SYS_PTRACE",
+                                    "CAP_SYS_PACCT",
+                                    "CAP_SYS_ADMIN",
+                                    "CAP_SYS_BOOT",
+                                    "CAP_SYS_NICE",
+                                    "CAP_SYS_RESOURCE",
+                                    "CAP_SYS_TIME",
+                                    "CAP_SYS_TTY_CONFIG",
+                                    "CAP_MKNOD",
+                                    "CAP_LEASE",
+                                    "CAP_AUDIT_WRITE",
+                                    "CAP_AUDIT_CONTROL",
+                                    "CAP_SETFCAP",
+                                    "CAP_MAC_OVERRIDE",
+                                    "CAP_MAC_ADMIN",
+                                    "CAP_SYSLOG",
+                                    "CAP_WAKE_ALARM",
+                                    "CAP_BLOCK_SUSPEND",
+                                    "CAP_AUDIT_READ",
+                                    "CAP_PERFMON",
+                                    "CAP_BPF",
+                                    "CAP_CHECKPOINT_RESTORE"];
+
+/// The seccomp profiles the Launcher can apply. `default` denies the system calls used to
+/// administer the host (loading kernel modules, mounting, rebooting, setting the clock, tracing
+/// other processes and the like), much like container runtimes' default profiles.
+pub const SECCOMP_PROFILES: &[&str] = &["default"];
+
+/// The sandboxing options of a service, all of them off unless set.
+#[derive(Deserialize, Serialize, Eq, PartialEq, Debug, Clone, Default, Hash)]
+pub struct Sandbox {
+    /// Mount a private, empty tmpfs on `/tmp`, see `PrivateTmp=`.
+    pub private_tmp:     Option<bool>,
+    /// Make the package directories under `/hab/pkgs` read-only, see `ReadOnlyPaths=`.
+    pub read_only_pkgs:  Option<bool>,
+    /// Keep the service from gaining privileges through setuid binaries or file capabilities,
+    /// see `NoNewPrivileges=`.
+    pub no_new_privs:    Option<bool>,
+    /// The only capabilities the service holds, all others being dropped, see
+    /// `CapabilityBoundingSet=`. Services running as a user other than root are granted these as
+    /// ambient capabilities, see `AmbientCapabilities=`.
+    pub capabilities:    Option<Vec<String>>,
+    /// One of `SECCOMP_PROFILES`, which limits the system calls the service may make. This
+    /// implies `no_new_privs`, see `SystemCallFilter=`.
+    pub seccomp_profile: Option<String>,
+    /// Run the service in its own network namespace with nothing but a loopback interface, see
+    /// `PrivateNetwork=`.
+    pub private_network: Option<bool>,
+}
+
+impl Sandbox {
+    pub fn is_empty(&self) -> bool { *self == Sandbox::default() }
+
+    /// Sets the option with the given name, as used in the `SANDBOX` metafile. `capabilities`
+    /// are separated by whitespace.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), Error> {
+        let invalid = || Error::InvalidSandboxOption(format!("{}={}", name, value));
+        let flag = || value.parse::<bool>().map_err(|_| invalid());
+        match name {
+            "private_tmp" => self.private_tmp = Some(flag()?),
+            "read_only_pkgs" => self.read_only_pkgs = Some(flag()?),
+            "no_new_privs" => self.no_new_privs = Some(flag()?),
+            "capabilities" => {
+                self.capabilities = Some(value.split_whitespace().map(str::to_string).collect())
+            }
+            "seccomp_profile" => self.seccomp_profile = Some(value.to_string()),
+            "private_network" => self.private_network = Some(flag()?),
+            _ => return Err(invalid()),
+        }
+        self.validate()
+    }
+
+    /// Returns an error if a capability or the seccomp profile isn't known.
+    pub fn validate(&self) -> Result<(), Error> {
+        for name in self.capabilities.iter().flatten() {
+            if capability(name).is_none() {
+                return Err(Error::InvalidSandboxOption(format!("capabilities={}", name)));
+            }
+        }
+        match self.seccomp_profile {
+            Some(ref profile) if !SECCOMP_PROFILES.contains(&profile.as_str()) => {
+                Err(Error::InvalidSandboxOption(format!("seccomp_profile={}", profile)))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Overrides these options with those which are set in `other`.
+    pub fn merge(&mut self, other: &Sandbox) {
+        self.private_tmp = other.private_tmp.or(self.private_tmp);
+        self.read_only_pkgs = other.read_only_pkgs.or(self.read_only_pkgs);
+        self.no_new_privs = other.no_new_privs.or(self.no_new_privs);
+        if other.capabilities.is_some() {
+            self.capabilities = other.capabilities.clone();
+        }
+        if other.seccomp_profile.is_some() {
+            self.seccomp_profile = other.seccomp_profile.clone();
+        }
+        self.private_network = other.private_network.or(self.private_network);
+    }
+}
+
+/// Returns the number of the capability with the given name, which may be given in any case and
+/// with or without its `CAP_` prefix, e.g. `CAP_NET_BIND_SERVICE` or `net_bind_service`.
+pub fn capability(name: &str) -> Option<u32> {
+    let name = name.to_uppercase();
+    let name = if name.starts_with("CAP_") {
+        name
+    } else {
+        format!("CAP_{}", name)
+    };
+    CAPABILITIES.iter()
+                .position(|c| *c == name)
+                .map(|number| number as u32)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sandbox_options_are_set_by_name() {
+        let mut sandbox = Sandbox::default();
+        assert!(sandbox.is_empty());
+        sandbox.set("private_tmp", "true").unwrap();
+        sandbox.set("capabilities", "CAP_NET_BIND_SERVICE  chown").unwrap();
+        sandbox.set("seccomp_profile", "default").unwrap();
+        assert_eq!(sandbox.private_tmp, Some(true));
+        assert_eq!(sandbox.capabilities,
+                   Some(vec!["CAP_NET_BIND_SERVICE".to_string(), "chown".to_string()]));
+        assert_eq!(sandbox.seccomp_profile, Some("default".to_string()));
+        assert!(!sandbox.is_empty());
+
+        assert!(sandbox.set("private_var", "true").is_err());
+        assert!(sandbox.set("no_new_privs", "yes").is_err());
+        assert!(sandbox.set("capabilities", "CAP_EVERYTHING").is_err());
+        assert!(sandbox.set("seccomp_profile", "strict").is_err());
+    }
+
+    #[test]
+    fn sandbox_options_are_overridden_by_those_set() {
+        let mut sandbox = Sandbox { private_tmp: Some(true),
+                                    capabilities: Some(vec!["CAP_CHOWN".to_string()]),
+                                    ..Default::default() };
+        sandbox.merge(&Sandbox { private_tmp: Some(false),
+                                 capabilities: Some(Vec::new()),
+                                 private_network: Some(true),
+                                 ..Default::default() });
+        assert_eq!(sandbox,
+                   Sandbox { private_tmp: Some(false),
+                             capabilities: Some(Vec::new()),
+                             private_network: Some(true),
+                             ..Default::default() });
+    }
+
+    #[test]
+    fn capabilities_are_looked_up_by_name() {
+        assert_eq!(capability("CAP_CHOWN"), Some(0));
+        assert_eq!(capability("net_bind_service"), Some(10));
+        assert_eq!(capability("Sys_Admin"), Some(21));
+        assert_eq!(capability("CAP_CHECKPOINT_RESTORE"), Some(40));
+        assert_eq!(capability("CAP_EVERYTHING"), None);
+    }
+}
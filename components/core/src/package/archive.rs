@@ -3,7 +3,8 @@ use super::{metadata::{MetaFile,
             Identifiable,
             PackageIdent,
             PackageTarget};
-use crate::{crypto::{artifact,
+use crate::{crypto::{artifact::{self,
+                               Compression},
                      hash},
             error::{Error,
                     Result}};
@@ -371,11 +372,7 @@ impl PackageArchive {
     /// * If the package cannot be unpacked
     pub fn unpack(&self, fs_root_path: Option<&Path>) -> Result<()> {
         let root = fs_root_path.unwrap_or_else(|| Path::new("/"));
-        let tar_reader = artifact::get_archive_reader(&self.path)?;
-        let mut builder = reader::Builder::new();
-        builder.support_format(ReadFormat::Gnutar)?;
-        builder.support_filter(ReadFilter::Xz)?;
-        let mut reader = builder.open_stream(tar_reader)?;
+        let mut reader = self.payload_reader()?;
         let writer = writer::Disk::new();
         let mut extract_options = ExtractOptions::new();
         extract_options.add(ExtractOption::Time);
@@ -387,6 +384,31 @@ impl PackageArchive {
         Ok(())
     }
 
+    /// Open a reader over the tarball payload of this archive, decompressing it according to the
+    /// compression declared in the artifact header. Zstandard is decoded here rather than by
+    /// libarchive, as the version we link against predates zstd support.
+    fn payload_reader(&self) -> Result<reader::StreamReader> {
+        let (compression, tar_reader) =
+            artifact::get_archive_reader_with_compression(&self.path)?;
+        let mut builder = reader::Builder::new();
+        builder.support_format(ReadFormat::Gnutar)?;
+        let reader = match compression {
+            Compression::Xz => {
+                builder.support_filter(ReadFilter::Xz)?;
+                builder.open_stream(tar_reader)?
+            }
+            Compression::Zstd => {
+                builder.support_filter(ReadFilter::None)?;
+                builder.open_stream(zstd::stream::read::Decoder::with_buffer(tar_reader)?)?
+            }
+            Compression::None => {
+                builder.support_filter(ReadFilter::None)?;
+                builder.open_stream(tar_reader)?
+            }
+        };
+        Ok(reader)
+    }
+
     fn read_deps(&mut self, file: MetaFile) -> Result<Vec<PackageIdent>> {
         let mut deps: Vec<PackageIdent> = vec![];
 
@@ -418,11 +440,7 @@ impl PackageArchive {
         }
         let mut metadata = Metadata::new();
         let mut matched_count = 0u8;
-        let tar_reader = artifact::get_archive_reader(&self.path)?;
-        let mut builder = reader::Builder::new();
-        builder.support_format(ReadFormat::Gnutar)?;
-        builder.support_filter(ReadFilter::Xz)?;
-        let mut reader = builder.open_stream(tar_reader)?;
+        let mut reader = self.payload_reader()?;
         loop {
             let mut matched_type: Option<MetaFile> = None;
             if let Some(entry) = reader.next_header() {
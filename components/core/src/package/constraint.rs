@@ -0,0 +1,273 @@
+//! Version constraints for package identifiers.
+//!
+//! A version constraint narrows the set of releases a fuzzy package identifier can resolve to,
+//! without pinning it to one exact version. It is written after the package name, separated by an
+//! `@`, as a comma-separated list of comparators which must all hold:
+//!
+//! ```text
+//! core/openssl@>=1.0.2,<1.1
+//! core/redis@~4.0
+//! acme/myapp@^2
+//! ```
+//!
+//! The supported comparators are:
+//!
+//! * `=1.2.3` (or `==1.2.3`): exactly that version
+//! * `>1.2.3`, `>=1.2.3`, `<1.2.3`, `<=1.2.3`: ordered comparisons
+//! * `~1.2`: at least `1.2` but below the next minor version (`>=1.2,<1.3`)
+//! * `^1.2`: at least `1.2` but below the next major version (`>=1.2,<2`)
+//!
+//! Versions are compared with [`version_sort`], so a pre-release extension sorts below the plain
+//! version number (`1.1.0-rc1` satisfies `<1.1`). A version which `version_sort` cannot compare,
+//! such as `master`, never satisfies a constraint.
+//!
+//! [`version_sort`]: ../ident/fn.version_sort.html
+
+use super::ident::version_sort;
+use crate::{error::{Error,
+                    Result},
+            util::serde_string};
+use serde;
+use std::{cmp::Ordering,
+          fmt,
+          result,
+          str::FromStr};
+
+/// The separator between a package name and its version constraint.
+pub const CONSTRAINT_SEPARATOR: char = '@';
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Op {
+    Exact,
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+    Tilde,
+    Caret,
+}
+
+impl Op {
+    fn as_str(self) -> &'static str {
+        match self {
+            Op::Exact => "=",
+            Op::Greater => ">",
+            Op::GreaterEq => ">=",
+            Op::Less => "<",
+            Op::LessEq => "<=",
+            Op::Tilde => "~",
+            Op::Caret => "^",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct Comparator {
+    op:      Op,
+    version: String,
+}
+
+impl Comparator {
+    fn matches(&self, version: &str) -> bool {
+        let cmp = |bound: &str| version_sort(version, bound).ok();
+        let at_least = |bound: &str| cmp(bound).map_or(false, |o| o != Ordering::Less);
+        let at_most = |bound: &str| cmp(bound).map_or(false, |o| o != Ordering::Greater);
+        match self.op {
+            Op::Exact => cmp(&self.version) == Some(Ordering::Equal),
+            Op::Greater => cmp(&self.version) == Some(Ordering::Greater),
+            Op::GreaterEq => at_least(&self.version),
+            Op::Less => cmp(&self.version) == Some(Ordering::Less),
+            Op::LessEq => at_most(&self.version),
+            Op::Tilde | Op::Caret => {
+                at_least(&self.version) && cmp(&self.upper_bound()) == Some(Ordering::Less)
+            }
+        }
+    }
+
+    /// The exclusive upper bound of a `~` or `^` comparator: the next minor version for `~`, and
+    /// the next major version for `^`. A `~` on a bare major version behaves like `^`.
+    fn upper_bound(&self) -> String {
+        let parts: Vec<u64> = self.version
+                                  .split(|c: char| !c.is_ascii_digit())
+                                  .take_while(|p| !p.is_empty())
+                                  .map(|p| p.parse().unwrap_or(0))
+                                  .collect();
+        let major = parts.get(0).cloned().unwrap_or(0);
+        match (self.op, parts.get(1)) {
+            (Op::Tilde, Some(minor)) => format!("{}.{}", major, minor + 1),
+            _ => format!("{}", major + 1),
+        }
+    }
+}
+
+impl fmt::Display for Comparator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.op.as_str(), self.version)
+    }
+}
+
+impl FromStr for Comparator {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let value = value.trim();
+        let (op, version) = if value.starts_with(">=") {
+            (Op::GreaterEq, &value[2..])
+        } else if value.starts_with("<=") {
+            (Op::LessEq, &value[2..])
+        } else if value.starts_with("==") {
+            (Op::Exact, &value[2..])
+        } else if value.starts_with('>') {
+            (Op::Greater, &value[1..])
+        } else if value.starts_with('<') {
+            (Op::Less, &value[1..])
+        } else if value.starts_with('=') {
+            (Op::Exact, &value[1..])
+        } else if value.starts_with('~') {
+            (Op::Tilde, &value[1..])
+        } else if value.starts_with('^') {
+            (Op::Caret, &value[1..])
+        } else {
+            return Err(Error::InvalidVersionConstraint(value.to_string()));
+        };
+        let version = version.trim();
+        // Only versions `version_sort` understands can be compared against
+        if version.is_empty() || version_sort(version, version).is_err() {
+            return Err(Error::InvalidVersionConstraint(value.to_string()));
+        }
+        Ok(Comparator { op,
+                        version: version.to_string() })
+    }
+}
+
+/// A set of version comparators which must all be satisfied by a package version.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct VersionConstraint(Vec<Comparator>);
+
+impl VersionConstraint {
+    /// Returns `true` if the given package version satisfies every comparator of this constraint.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use habitat_core::package::constraint::VersionConstraint;
+    /// use std::str::FromStr;
+    ///
+    /// let constraint = VersionConstraint::from_str(">=1.0.2,<1.1").unwrap();
+    ///
+    /// assert!(constraint.matches("1.0.2"));
+    /// assert!(constraint.matches("1.0.20"));
+    /// assert!(!constraint.matches("1.1.0"));
+    /// assert!(!constraint.matches("1.0.1"));
+    /// ```
+    pub fn matches(&self, version: &str) -> bool { self.0.iter().all(|c| c.matches(version)) }
+}
+
+impl fmt::Display for VersionConstraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let comparators: Vec<String> = self.0.iter().map(ToString::to_string).collect();
+        write!(f, "{}", comparators.join(","))
+    }
+}
+
+impl FromStr for VersionConstraint {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let comparators = value.split(',')
+                               .map(Comparator::from_str)
+                               .collect::<Result<Vec<_>>>()?;
+        Ok(VersionConstraint(comparators))
+    }
+}
+
+impl serde::Serialize for VersionConstraint {
+    fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
+        where S: serde::Serializer
+    {
+        serde_string::serialize(self, serializer)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for VersionConstraint {
+    fn deserialize<D>(deserializer: D) -> result::Result<Self, D::Error>
+        where D: serde::Deserializer<'de>
+    {
+        serde_string::deserialize(deserializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn constraint(s: &str) -> VersionConstraint { VersionConstraint::from_str(s).unwrap() }
+
+    #[test]
+    fn ordered_comparators() {
+        assert!(constraint(">1.0").matches("1.0.1"));
+        assert!(!constraint(">1.0").matches("1.0.0"));
+        assert!(constraint(">=1.0").matches("1.0.0"));
+        assert!(constraint("<2").matches("1.9.9"));
+        assert!(!constraint("<2").matches("2.0"));
+        assert!(constraint("<=2").matches("2.0.0"));
+        assert!(!constraint("<=2").matches("2.0.1"));
+    }
+
+    #[test]
+    fn exact_comparator() {
+        assert!(constraint("=1.2").matches("1.2.0"));
+        assert!(constraint("==1.2.3").matches("1.2.3"));
+        assert!(!constraint("=1.2.3").matches("1.2.4"));
+    }
+
+    #[test]
+    fn tilde_comparator() {
+        let c = constraint("~1.2");
+        assert!(c.matches("1.2.0"));
+        assert!(c.matches("1.2.99"));
+        assert!(!c.matches("1.3.0"));
+        assert!(!c.matches("1.1.9"));
+        // A tilde on a major version only allows the whole major series
+        assert!(constraint("~1").matches("1.9"));
+        assert!(!constraint("~1").matches("2.0"));
+    }
+
+    #[test]
+    fn caret_comparator() {
+        let c = constraint("^2.1");
+        assert!(c.matches("2.1.0"));
+        assert!(c.matches("2.99.1"));
+        assert!(!c.matches("3.0.0"));
+        assert!(!c.matches("2.0.9"));
+    }
+
+    #[test]
+    fn all_comparators_must_match() {
+        let c = constraint(">=1.0.2, <1.1");
+        assert!(c.matches("1.0.2"));
+        assert!(!c.matches("1.0.2-rc1"));
+        assert!(!c.matches("1.1"));
+    }
+
+    #[test]
+    fn non_numeric_versions_never_match() {
+        assert!(!constraint(">=0").matches("master"));
+    }
+
+    #[test]
+    fn invalid_constraints() {
+        assert!(VersionConstraint::from_str("").is_err());
+        assert!(VersionConstraint::from_str("1.0").is_err());
+        assert!(VersionConstraint::from_str(">=").is_err());
+        assert!(VersionConstraint::from_str(">=abc").is_err());
+        assert!(VersionConstraint::from_str(">=1.0,").is_err());
+    }
+
+    #[test]
+    fn display_round_trips() {
+        let c = constraint(">= 1.0.2,<1.1");
+        assert_eq!(">=1.0.2,<1.1", c.to_string());
+        assert_eq!(c, constraint(&c.to_string()));
+    }
+}
@@ -1,6 +1,8 @@
 use crate::{error::{Error,
                     Result},
-            package::PackageTarget};
+            package::{constraint::{VersionConstraint,
+                                   CONSTRAINT_SEPARATOR},
+                      PackageTarget}};
 use regex::Regex;
 use serde_derive::{Deserialize,
                    Serialize};
@@ -22,6 +24,10 @@ pub struct PackageIdent {
     pub name:    String,
     pub version: Option<String>,
     pub release: Option<String>,
+    /// Restricts the versions a fuzzy identifier may resolve to. An identifier with a version
+    /// constraint never carries an explicit version or release.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version_constraint: Option<VersionConstraint>,
 }
 
 pub trait Identifiable: fmt::Display + Into<PackageIdent> {
@@ -30,6 +36,8 @@ pub trait Identifiable: fmt::Display + Into<PackageIdent> {
     fn version(&self) -> Option<&str>;
     fn release(&self) -> Option<&str>;
 
+    fn version_constraint(&self) -> Option<&VersionConstraint> { None }
+
     fn fully_qualified(&self) -> bool { self.version().is_some() && self.release().is_some() }
 
     fn valid(&self) -> bool {
@@ -41,6 +49,12 @@ pub trait Identifiable: fmt::Display + Into<PackageIdent> {
         if self.origin() != other.origin() || self.name() != other.name() {
             return false;
         }
+        if let Some(constraint) = other.version_constraint() {
+            match self.version() {
+                Some(version) if constraint.matches(version) => {}
+                _ => return false,
+            }
+        }
         if self.version().is_some() {
             if other.version().is_none() {
                 return true;
@@ -68,10 +82,21 @@ impl PackageIdent {
                                 version: Option<T>,
                                 release: Option<T>)
                                 -> Self {
-        PackageIdent { origin:  origin.into(),
-                       name:    name.into(),
-                       version: version.map(Into::into),
-                       release: release.map(Into::into), }
+        PackageIdent { origin:             origin.into(),
+                       name:               name.into(),
+                       version:            version.map(Into::into),
+                       release:            release.map(Into::into),
+                       version_constraint: None, }
+    }
+
+    /// Creates a new package identifier which resolves to the latest release satisfying the
+    /// given version constraint.
+    pub fn with_constraint<T: Into<String>>(origin: T,
+                                            name: T,
+                                            constraint: VersionConstraint)
+                                            -> Self {
+        PackageIdent { version_constraint: Some(constraint),
+                       ..PackageIdent::new(origin, name, None, None) }
     }
 
     pub fn archive_name(&self) -> Result<String> {
@@ -164,6 +189,10 @@ impl Identifiable for PackageIdent {
     fn version(&self) -> Option<&str> { self.version.as_ref().map(String::as_str) }
 
     fn release(&self) -> Option<&str> { self.release.as_ref().map(String::as_str) }
+
+    fn version_constraint(&self) -> Option<&VersionConstraint> {
+        self.version_constraint.as_ref()
+    }
 }
 
 impl Default for PackageIdent {
@@ -185,6 +214,10 @@ impl fmt::Display for PackageIdent {
                    self.origin,
                    self.name,
                    self.version.as_ref().unwrap())
+        } else if let Some(ref constraint) = self.version_constraint {
+            write!(f,
+                   "{}/{}{}{}",
+                   self.origin, self.name, CONSTRAINT_SEPARATOR, constraint)
         } else {
             write!(f, "{}/{}", self.origin, self.name)
         }
@@ -199,6 +232,15 @@ impl FromStr for PackageIdent {
     type Err = Error;
 
     fn from_str(value: &str) -> result::Result<Self, Self::Err> {
+        if let Some(idx) = value.find(CONSTRAINT_SEPARATOR) {
+            let (ident, constraint) = value.split_at(idx);
+            let items: Vec<&str> = ident.split('/').collect();
+            if items.len() != 2 {
+                return Err(Error::InvalidPackageIdent(value.to_string()));
+            }
+            let constraint = VersionConstraint::from_str(&constraint[1..])?;
+            return Ok(PackageIdent::with_constraint(items[0], items[1], constraint));
+        }
         let items: Vec<&str> = value.split('/').collect();
         let (origin, name, ver, rel) = match items.len() {
             2 => (items[0], items[1], None, None),
@@ -745,4 +787,34 @@ mod tests {
         assert_eq!(Some("rise-up"), iter.next());
         assert_eq!(None, iter.next());
     }
+
+    #[test]
+    fn from_str_with_version_constraint() {
+        let ident = PackageIdent::from_str("core/openssl@>=1.0.2,<1.1").unwrap();
+
+        assert_eq!("core", ident.origin);
+        assert_eq!("openssl", ident.name);
+        assert_eq!(None, ident.version);
+        assert_eq!(None, ident.release);
+        assert!(!ident.fully_qualified());
+        assert_eq!("core/openssl@>=1.0.2,<1.1", ident.to_string());
+    }
+
+    #[test]
+    fn from_str_with_version_and_constraint() {
+        assert!(PackageIdent::from_str("core/openssl/1.0.2@>=1.0.2").is_err());
+        assert!(PackageIdent::from_str("core/openssl@latest").is_err());
+    }
+
+    #[test]
+    fn satisfies_version_constraint() {
+        let wanted = PackageIdent::from_str("core/openssl@>=1.0.2,<1.1").unwrap();
+        let in_range = PackageIdent::from_str("core/openssl/1.0.2/20180608102213").unwrap();
+        let too_new = PackageIdent::from_str("core/openssl/1.1.0/20180608102213").unwrap();
+        let other = PackageIdent::from_str("core/libressl/1.0.2/20180608102213").unwrap();
+
+        assert!(in_range.satisfies(&wanted));
+        assert!(!too_new.satisfies(&wanted));
+        assert!(!other.satisfies(&wanted));
+    }
 }
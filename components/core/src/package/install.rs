@@ -10,8 +10,10 @@ use super::{list::package_list_for_ident,
 use crate::{error::{Error,
                     Result},
             fs,
-            os::process::{ShutdownSignal,
-                          ShutdownTimeout}};
+            os::{process::{ResourceLimits,
+                           ShutdownSignal,
+                           ShutdownTimeout},
+                 sandbox::Sandbox}};
 use serde_derive::{Deserialize,
                    Serialize};
 use std::{cmp::{Ordering,
@@ -380,6 +382,9 @@ impl PackageInstall {
 
     pub fn ident(&self) -> &PackageIdent { &self.ident }
 
+    /// Returns the contents of the package's `MANIFEST` metafile.
+    pub fn manifest(&self) -> Result<String> { self.read_metafile(MetaFile::Manifest) }
+
     /// Returns the path elements of the package's `PATH` metafile if it exists, or an empty `Vec`
     /// if not found.
     ///
@@ -595,6 +600,79 @@ impl PackageInstall {
         }
     }
 
+    /// Returns the number of seconds each hook may run for, keyed by hook file name (e.g.
+    /// `health-check`), as set by `pkg_hook_timeouts` in the plan.
+    pub fn hook_timeouts(&self) -> Result<HashMap<String, u32>> {
+        match self.read_metafile(MetaFile::HookTimeouts) {
+            Ok(body) => {
+                let parsed_value =
+                    parse_key_value(&body).map_err(|_| {
+                                              Error::MetaFileMalformed(MetaFile::HookTimeouts)
+                                          })?;
+                parsed_value.into_iter()
+                            .map(|(hook, secs)| {
+                                secs.parse()
+                                    .map(|secs| (hook, secs))
+                                    .map_err(|_| Error::MetaFileMalformed(MetaFile::HookTimeouts))
+                            })
+                            .collect()
+            }
+            Err(Error::MetaFileNotFound(MetaFile::HookTimeouts)) => Ok(HashMap::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Returns the resources the package's service may use, as set by `pkg_resource_limits` in the
+    /// plan.
+    pub fn resource_limits(&self) -> Result<ResourceLimits> {
+        match self.read_metafile(MetaFile::ResourceLimits) {
+            Ok(body) => {
+                let parsed_value =
+                    parse_key_value(&body).map_err(|_| {
+                                              Error::MetaFileMalformed(MetaFile::ResourceLimits)
+                                          })?;
+                let mut limits = ResourceLimits::default();
+                for (name, value) in parsed_value {
+                    limits.set(&name, &value)?;
+                }
+                Ok(limits)
+            }
+            Err(Error::MetaFileNotFound(MetaFile::ResourceLimits)) => Ok(ResourceLimits::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Returns the sandboxing options of the package's service, as set by `pkg_sandbox` in the
+    /// plan.
+    pub fn sandbox(&self) -> Result<Sandbox> {
+        match self.read_metafile(MetaFile::Sandbox) {
+            Ok(body) => {
+                let parsed_value =
+                    parse_key_value(&body).map_err(|_| {
+                                              Error::MetaFileMalformed(MetaFile::Sandbox)
+                                          })?;
+                let mut sandbox = Sandbox::default();
+                for (name, value) in parsed_value {
+                    sandbox.set(&name, &value)?;
+                }
+                Ok(sandbox)
+            }
+            Err(Error::MetaFileNotFound(MetaFile::Sandbox)) => Ok(Sandbox::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Returns the template engine the package's configuration templates and hooks are written
+    /// for, as set by `pkg_template_engine` in the plan, or None if the package doesn't contain a
+    /// TEMPLATE_ENGINE Metafile
+    pub fn template_engine(&self) -> Result<Option<String>> {
+        match self.read_metafile(MetaFile::TemplateEngine) {
+            Ok(body) => Ok(Some(body)),
+            Err(Error::MetaFileNotFound(MetaFile::TemplateEngine)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
     /// Read the contents of a given metafile.
     ///
     /// # Failures
@@ -799,6 +877,61 @@ core/bar=pub:core/publish sub:core/subscribe
         assert!(bind_map.is_err());
     }
 
+    #[test]
+    fn reading_hook_timeouts_works() {
+        let fs_root = Builder::new().prefix("fs-root").tempdir().unwrap();
+        let package_install = testing_package_install("core/redis", fs_root.path());
+        assert!(package_install.hook_timeouts().unwrap().is_empty());
+
+        write_metafile(&package_install,
+                       MetaFile::HookTimeouts,
+                       "health-check=10\nreconfigure=120");
+        let timeouts = package_install.hook_timeouts().unwrap();
+        assert_eq!(timeouts.len(), 2);
+        assert_eq!(timeouts["health-check"], 10);
+        assert_eq!(timeouts["reconfigure"], 120);
+
+        write_metafile(&package_install, MetaFile::HookTimeouts, "init=forever");
+        assert!(package_install.hook_timeouts().is_err());
+    }
+
+    #[test]
+    fn reading_resource_limits_works() {
+        let fs_root = Builder::new().prefix("fs-root").tempdir().unwrap();
+        let package_install = testing_package_install("core/redis", fs_root.path());
+        assert!(package_install.resource_limits().unwrap().is_empty());
+
+        write_metafile(&package_install,
+                       MetaFile::ResourceLimits,
+                       "memory_max=536870912\ncpu_weight=50");
+        let limits = package_install.resource_limits().unwrap();
+        assert_eq!(limits.memory_max, Some(536_870_912));
+        assert_eq!(limits.cpu_weight, Some(50));
+        assert_eq!(limits.pids_max, None);
+
+        write_metafile(&package_install, MetaFile::ResourceLimits, "swap_max=0");
+        assert!(package_install.resource_limits().is_err());
+    }
+
+    #[test]
+    fn reading_sandbox_works() {
+        let fs_root = Builder::new().prefix("fs-root").tempdir().unwrap();
+        let package_install = testing_package_install("core/redis", fs_root.path());
+        assert!(package_install.sandbox().unwrap().is_empty());
+
+        write_metafile(&package_install,
+                       MetaFile::Sandbox,
+                       "private_tmp=true\ncapabilities=CAP_NET_BIND_SERVICE CAP_SETUID");
+        let sandbox = package_install.sandbox().unwrap();
+        assert_eq!(sandbox.private_tmp, Some(true));
+        assert_eq!(sandbox.capabilities,
+                   Some(vec!["CAP_NET_BIND_SERVICE".to_string(), "CAP_SETUID".to_string()]));
+        assert_eq!(sandbox.private_network, None);
+
+        write_metafile(&package_install, MetaFile::Sandbox, "private_tmp=sure");
+        assert!(package_install.sandbox().is_err());
+    }
+
     /// Composite packages don't need to have a BIND_MAP file, and
     /// standalone packages will never have them. This is OK.
     #[test]
@@ -148,14 +148,17 @@ pub enum MetaFile {
     EnvironmentSep,
     Exports,
     Exposes,
+    HookTimeouts,
     Ident,
     LdFlags,
     LdRunPath,
     Manifest,
     Path,
     ResolvedServices, // Composite-only
+    ResourceLimits,
     RuntimeEnvironment,
     RuntimePath,
+    Sandbox,
     ShutdownSignal,
     ShutdownTimeout,
     Services, // Composite-only
@@ -163,6 +166,7 @@ pub enum MetaFile {
     SvcUser,
     Target,
     TDeps,
+    TemplateEngine,
     Type,
 }
 
@@ -181,14 +185,17 @@ impl fmt::Display for MetaFile {
             MetaFile::EnvironmentSep => "ENVIRONMENT_SEP",
             MetaFile::Exports => "EXPORTS",
             MetaFile::Exposes => "EXPOSES",
+            MetaFile::HookTimeouts => "HOOK_TIMEOUTS",
             MetaFile::Ident => "IDENT",
             MetaFile::LdFlags => "LDFLAGS",
             MetaFile::LdRunPath => "LD_RUN_PATH",
             MetaFile::Manifest => "MANIFEST",
             MetaFile::Path => "PATH",
             MetaFile::ResolvedServices => "RESOLVED_SERVICES",
+            MetaFile::ResourceLimits => "RESOURCE_LIMITS",
             MetaFile::RuntimeEnvironment => "RUNTIME_ENVIRONMENT",
             MetaFile::RuntimePath => "RUNTIME_PATH",
+            MetaFile::Sandbox => "SANDBOX",
             MetaFile::Services => "SERVICES",
             MetaFile::ShutdownSignal => "SHUTDOWN_SIGNAL",
             MetaFile::ShutdownTimeout => "SHUTDOWN_TIMEOUT",
@@ -196,6 +203,7 @@ impl fmt::Display for MetaFile {
             MetaFile::SvcUser => "SVC_USER",
             MetaFile::Target => "TARGET",
             MetaFile::TDeps => "TDEPS",
+            MetaFile::TemplateEngine => "TEMPLATE_ENGINE",
             MetaFile::Type => "TYPE",
         };
         write!(f, "{}", id)
@@ -1,3 +1,7 @@
+mod update_window;
+
+pub use self::update_window::UpdateWindow;
+
 use crate::error::{Error,
                    Result};
 use regex::Regex;
@@ -408,6 +412,56 @@ impl From<HealthCheckInterval> for Duration {
     fn from(h: HealthCheckInterval) -> Self { h.0 }
 }
 
+/// How many members of a service group a canary update updates first, either as a number of
+/// members or as a percentage of the group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CanaryCount {
+    Members(u32),
+    Percent(u32),
+}
+
+impl CanaryCount {
+    /// The number of canaries in a group of `group_size` members. Percentages are rounded up, so
+    /// a group with members always has at least one canary.
+    pub fn of(self, group_size: usize) -> usize {
+        let count = match self {
+            CanaryCount::Members(n) => n as usize,
+            CanaryCount::Percent(p) => (group_size * p as usize + 99) / 100,
+        };
+        count.min(group_size)
+    }
+}
+
+impl Default for CanaryCount {
+    fn default() -> Self { CanaryCount::Members(1) }
+}
+
+impl fmt::Display for CanaryCount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            CanaryCount::Members(n) => write!(f, "{}", n),
+            CanaryCount::Percent(p) => write!(f, "{}%", p),
+        }
+    }
+}
+
+impl FromStr for CanaryCount {
+    type Err = Error;
+
+    fn from_str(value: &str) -> result::Result<Self, Self::Err> {
+        let trimmed = value.trim();
+        let count = if trimmed.ends_with('%') {
+            trimmed[..trimmed.len() - 1].parse()
+                                        .ok()
+                                        .filter(|p| *p > 0 && *p <= 100)
+                                        .map(CanaryCount::Percent)
+        } else {
+            trimmed.parse().ok().filter(|n| *n > 0).map(CanaryCount::Members)
+        };
+        count.ok_or_else(|| Error::InvalidCanaryCount(value.to_string()))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::str::FromStr;
@@ -735,6 +789,26 @@ mod test {
         HealthCheckInterval::from_str("oh-noes").unwrap();
     }
 
+    #[test]
+    fn canary_count_from_str() {
+        assert_eq!(CanaryCount::from_str("3").unwrap(), CanaryCount::Members(3));
+        assert_eq!(CanaryCount::from_str("25%").unwrap(), CanaryCount::Percent(25));
+        assert_eq!(CanaryCount::Percent(25).to_string(), "25%");
+        assert!(CanaryCount::from_str("0").is_err());
+        assert!(CanaryCount::from_str("0%").is_err());
+        assert!(CanaryCount::from_str("150%").is_err());
+        assert!(CanaryCount::from_str("some").is_err());
+    }
+
+    #[test]
+    fn canary_count_of_a_group() {
+        assert_eq!(CanaryCount::Members(2).of(5), 2);
+        assert_eq!(CanaryCount::Members(8).of(5), 5);
+        assert_eq!(CanaryCount::Percent(10).of(5), 1);
+        assert_eq!(CanaryCount::Percent(50).of(5), 3);
+        assert_eq!(CanaryCount::Percent(50).of(0), 0);
+    }
+
     #[test]
     fn health_check_interval_display() {
         assert_eq!("(5s)".to_owned(),
@@ -0,0 +1,229 @@
+use crate::error::{Error,
+                   Result};
+use chrono::{DateTime,
+             Datelike,
+             Timelike,
+             Utc};
+use chrono_tz::Tz;
+use std::{fmt,
+          hash::{Hash,
+                 Hasher},
+          result,
+          str::FromStr};
+
+const MONTHS: &[&str] = &["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct",
+                          "nov", "dec"];
+const DAYS_OF_WEEK: &[&str] = &["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// The times at which the Supervisor may update a service.
+///
+/// An update window is written like the schedule of a crontab entry, optionally followed by a
+/// time zone, e.g. `* 2-4 * * sat,sun Europe/Berlin`. The window is open during every minute that
+/// matches all five fields: minute, hour, day of month, month and day of week. As in cron, a day
+/// matches if either its day of month or its day of week matches when both fields are
+/// restricted. Times are in UTC unless a time zone is given.
+#[derive(Clone, Debug)]
+pub struct UpdateWindow {
+    minutes:       Field,
+    hours:         Field,
+    days_of_month: Field,
+    months:        Field,
+    days_of_week:  Field,
+    time_zone:     Tz,
+    expression:    String,
+}
+
+impl UpdateWindow {
+    /// Whether the window is open now.
+    pub fn is_open(&self) -> bool { self.is_open_at(Utc::now()) }
+
+    /// Whether the window is open at the given time.
+    pub fn is_open_at(&self, time: DateTime<Utc>) -> bool {
+        let time = time.with_timezone(&self.time_zone);
+        let day_of_month = self.days_of_month.contains(time.day());
+        let day_of_week = self.days_of_week.contains(time.weekday().num_days_from_sunday());
+        let day = if self.days_of_month.any || self.days_of_week.any {
+            day_of_month && day_of_week
+        } else {
+            day_of_month || day_of_week
+        };
+        let time_of_day = self.minutes.contains(time.minute()) && self.hours.contains(time.hour());
+        day && time_of_day && self.months.contains(time.month())
+    }
+}
+
+impl PartialEq for UpdateWindow {
+    fn eq(&self, other: &UpdateWindow) -> bool { self.expression == other.expression }
+}
+
+impl Eq for UpdateWindow {}
+
+impl Hash for UpdateWindow {
+    fn hash<H: Hasher>(&self, state: &mut H) { self.expression.hash(state); }
+}
+
+impl fmt::Display for UpdateWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "{}", self.expression) }
+}
+
+impl FromStr for UpdateWindow {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let invalid = || Error::InvalidUpdateWindow(value.to_string());
+        let parts: Vec<&str> = value.split_whitespace().collect();
+        let time_zone = match parts.len() {
+            5 => Tz::UTC,
+            6 => parts[5].parse().map_err(|_| invalid())?,
+            _ => return Err(invalid()),
+        };
+        let minutes = Field::parse(parts[0], 0, 59, &[]).ok_or_else(invalid)?;
+        let hours = Field::parse(parts[1], 0, 23, &[]).ok_or_else(invalid)?;
+        let days_of_month = Field::parse(parts[2], 1, 31, &[]).ok_or_else(invalid)?;
+        let months = Field::parse(parts[3], 1, 12, MONTHS).ok_or_else(invalid)?;
+        let mut days_of_week = Field::parse(parts[4], 0, 7, DAYS_OF_WEEK).ok_or_else(invalid)?;
+        // Both 0 and 7 are Sunday
+        if days_of_week.contains(7) {
+            days_of_week.values |= 1;
+        }
+        Ok(UpdateWindow { minutes,
+                          hours,
+                          days_of_month,
+                          months,
+                          days_of_week,
+                          time_zone,
+                          expression: parts.join(" ") })
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for UpdateWindow {
+    fn deserialize<D>(deserializer: D) -> result::Result<Self, D::Error>
+        where D: serde::Deserializer<'de>
+    {
+        let s = <String as serde::Deserialize>::deserialize(deserializer)?;
+        UpdateWindow::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+impl serde::Serialize for UpdateWindow {
+    fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
+        where S: serde::Serializer
+    {
+        serializer.serialize_str(&self.expression)
+    }
+}
+
+/// The values matched by one field of an update window.
+#[derive(Clone, Copy, Debug)]
+struct Field {
+    /// A bit set of the matching values
+    values: u64,
+    /// Whether the field is unrestricted, i.e. starts with `*`
+    any:    bool,
+}
+
+impl Field {
+    /// Parses a comma-separated list of values (`5`), ranges (`1-5`) and wildcards (`*`), each
+    /// optionally followed by a step (`*/15`). `names` are accepted in place of the values
+    /// starting at `min`.
+    fn parse(field: &str, min: u32, max: u32, names: &[&str]) -> Option<Field> {
+        let value = |s: &str| {
+            let lower = s.to_lowercase();
+            names.iter()
+                 .position(|name| *name == lower)
+                 .map(|i| i as u32 + min)
+                 .or_else(|| s.parse().ok())
+                 .filter(|v| *v >= min && *v <= max)
+        };
+        let mut values = 0;
+        for item in field.split(',') {
+            let mut parts = item.splitn(2, '/');
+            let range = parts.next()?;
+            let step = match parts.next() {
+                Some(step) => step.parse().ok().filter(|s| *s > 0)?,
+                None => 1,
+            };
+            let (start, end) = if range == "*" {
+                (min, max)
+            } else {
+                let mut bounds = range.splitn(2, '-');
+                let start = value(bounds.next()?)?;
+                let end = match bounds.next() {
+                    Some(end) => value(end)?,
+                    None => start,
+                };
+                (start, end)
+            };
+            if start > end {
+                return None;
+            }
+            for v in (start..=end).step_by(step) {
+                values |= 1 << v;
+            }
+        }
+        Some(Field { values,
+                     any: field.starts_with('*') })
+    }
+
+    fn contains(self, value: u32) -> bool { self.values & (1 << value) != 0 }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn update_window_from_str() {
+        let window = UpdateWindow::from_str("*/15  2-4 * * sat,SUN Europe/Berlin").unwrap();
+        assert_eq!(window.to_string(), "*/15 2-4 * * sat,SUN Europe/Berlin");
+        assert!(UpdateWindow::from_str("* * * *").is_err());
+        assert!(UpdateWindow::from_str("60 * * * *").is_err());
+        assert!(UpdateWindow::from_str("* 4-2 * * *").is_err());
+        assert!(UpdateWindow::from_str("* * 0 * *").is_err());
+        assert!(UpdateWindow::from_str("*/0 * * * *").is_err());
+        assert!(UpdateWindow::from_str("* * * * * Nowhere/Special").is_err());
+    }
+
+    #[test]
+    fn update_window_is_open() {
+        let window = UpdateWindow::from_str("* 2-3 * * sat,sun").unwrap();
+        // 2019-06-01 was a Saturday
+        assert!(window.is_open_at(Utc.ymd(2019, 6, 1).and_hms(2, 0, 0)));
+        assert!(window.is_open_at(Utc.ymd(2019, 6, 2).and_hms(3, 59, 59)));
+        assert!(!window.is_open_at(Utc.ymd(2019, 6, 2).and_hms(4, 0, 0)));
+        assert!(!window.is_open_at(Utc.ymd(2019, 6, 3).and_hms(2, 0, 0)));
+
+        let window = UpdateWindow::from_str("0-29 22 * jun 7").unwrap();
+        assert!(window.is_open_at(Utc.ymd(2019, 6, 2).and_hms(22, 29, 0)));
+        assert!(!window.is_open_at(Utc.ymd(2019, 6, 2).and_hms(22, 30, 0)));
+        assert!(!window.is_open_at(Utc.ymd(2019, 7, 7).and_hms(22, 0, 0)));
+    }
+
+    #[test]
+    fn update_window_in_a_time_zone() {
+        let window = UpdateWindow::from_str("* 2 * * * Europe/Berlin").unwrap();
+        // Berlin is two hours ahead of UTC in summer
+        assert!(window.is_open_at(Utc.ymd(2019, 6, 1).and_hms(0, 30, 0)));
+        assert!(!window.is_open_at(Utc.ymd(2019, 6, 1).and_hms(2, 30, 0)));
+    }
+
+    #[test]
+    fn update_window_matches_either_day_field() {
+        let window = UpdateWindow::from_str("* * 1 * mon").unwrap();
+        // 2019-06-01 was a Saturday, 2019-06-03 a Monday
+        assert!(window.is_open_at(Utc.ymd(2019, 6, 1).and_hms(12, 0, 0)));
+        assert!(window.is_open_at(Utc.ymd(2019, 6, 3).and_hms(12, 0, 0)));
+        assert!(!window.is_open_at(Utc.ymd(2019, 6, 4).and_hms(12, 0, 0)));
+
+        // A day field starting with `*` restricts the other one
+        let window = UpdateWindow::from_str("* * */2 * mon").unwrap();
+        assert!(window.is_open_at(Utc.ymd(2019, 6, 3).and_hms(12, 0, 0)));
+        assert!(!window.is_open_at(Utc.ymd(2019, 6, 10).and_hms(12, 0, 0)));
+        assert!(!window.is_open_at(Utc.ymd(2019, 6, 1).and_hms(12, 0, 0)));
+
+        let window = UpdateWindow::from_str("* * * * mon").unwrap();
+        assert!(window.is_open_at(Utc.ymd(2019, 6, 3).and_hms(12, 0, 0)));
+        assert!(!window.is_open_at(Utc.ymd(2019, 6, 4).and_hms(12, 0, 0)));
+    }
+}
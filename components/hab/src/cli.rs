@@ -23,8 +23,10 @@ use habitat_core::{crypto::{keys::PairType,
                              Identifiable,
                              PackageIdent,
                              PackageTarget},
-                   service::{HealthCheckInterval,
-                             ServiceGroup},
+                   service::{CanaryCount,
+                             HealthCheckInterval,
+                             ServiceGroup,
+                             UpdateWindow},
                    ChannelIdent};
 use habitat_sup_protocol;
 use std::{net::SocketAddr,
@@ -78,8 +80,12 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
             (@subcommand show =>
                 (about: "Displays the default configuration options for a service")
                 (aliases: &["sh", "sho"])
-                (@arg PKG_IDENT: +required +takes_value {valid_ident}
-                    "A package identifier (ex: core/redis, core/busybox-static/1.42.2)")
+                (@arg PKG_IDENT: +required +takes_value {valid_service_ident}
+                    "A package identifier, optionally followed by an instance name (ex: \
+                    core/redis, core/redis@cache)")
+                (@arg EXPLAIN: -e --explain
+                    "Display the effective configuration instead, showing where each value came \
+                    from and which values it overrode")
                 (@arg REMOTE_SUP: --("remote-sup") -r +takes_value
                     "Address to a remote Supervisor's Control Gateway [default: 127.0.0.1:9632]")
             )
@@ -477,6 +483,15 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
                     (ex: core/busybox-static/1.24.2/20160708162350)")
                 (@arg FULL_PATHS: -p "Show full path to file")
             )
+            (@subcommand sbom =>
+                (about: "Generates a software bill of materials for a package and its runtime \
+                    dependencies")
+                (@arg PKG_IDENT_OR_ARTIFACT: +required +takes_value
+                    "An installed package identifier (ex: core/redis) or filepath to a Habitat \
+                    Artifact (ex: /home/core-redis-3.0.7-21120102031201-x86_64-linux.hart)")
+                (@arg FORMAT: -f --format +takes_value possible_value[cyclonedx spdx]
+                    default_value[cyclonedx] "The SBOM document format")
+            )
             (@subcommand search =>
                 (about: "Search for a package in Builder")
                 (@arg SEARCH_TERM: +required +takes_value "Search term")
@@ -491,6 +506,9 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
                 (about: "Signs an archive with an origin key, generating a Habitat Artifact")
                 (aliases: &["s", "si", "sig"])
                 (@arg ORIGIN: --origin +takes_value {valid_origin} "Origin key used to create signature")
+                (@arg COMPRESSION: --compression +takes_value possible_value[xz zstd none] default_value[xz]
+                    "The compression already applied to the source archive. Anything other than \
+                    xz produces a HART-2 artifact, which older clients cannot read")
                 (@arg SOURCE: +required {file_exists}
                     "A path to a source archive file \
                     (ex: /home/acme-redis-3.0.7-21120102031201.tar.xz)")
@@ -501,6 +519,16 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
                     Default value is hab/cache/keys if root and .hab/cache/keys under the home \
                     directory otherwise."))
             )
+            (@subcommand gc =>
+                (about: "Remove installed packages which are no longer needed")
+                (@arg KEEP: -k --keep +takes_value default_value("2") {valid_numeric::<usize>}
+                    "The number of releases of each package to keep, newest first. \
+                    At least one release is always kept.")
+                (@arg DRYRUN: -d --dryrun "Just show what would be removed, don't actually do it")
+                (@arg EXCLUDE: --exclude +takes_value +multiple {valid_ident}
+                    "Identifier of one or more packages that should not be removed. \
+                    (ex: core/redis, core/busybox-static/1.42.2/21120102031201)")
+            )
             (@subcommand uninstall =>
                 (about: "Safely uninstall a package and dependencies from the local filesystem")
                 (aliases: &["un", "unin"])
@@ -602,6 +630,14 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
                 (@arg SOURCE: +required {file_exists} "A path to a Habitat Artifact \
                     (ex: /home/acme-redis-3.0.7-21120102031201-x86_64-linux.hart)")
             )
+            (@subcommand diff =>
+                (about: "Compares the metadata and files of two packages")
+                (@arg TO_JSON: -j --json "Output will be rendered in json")
+                (@arg A: +required +takes_value "An installed package identifier or filepath to \
+                    a Habitat Artifact (ex: /home/acme-redis-3.0.7-21120102031201-x86_64-linux.hart)")
+                (@arg B: +required +takes_value "An installed package identifier or filepath to \
+                    a Habitat Artifact to compare against the first")
+            )
             (@subcommand dependencies =>
                 (about: "Returns the Habitat Artifact dependencies. By default it will return \
                     the direct dependencies of the package")
@@ -632,13 +668,17 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
             (@subcommand render =>
                 (about: "Renders plan config files")
                 (aliases: &["r", "re", "ren", "rend", "rende"])
-                (@arg TEMPLATE_PATH: +required {file_exists} "Path to config to render")
+                (@arg TEMPLATE_PATH: +required {file_or_dir_exists}
+                    "Path to config to render, or to a plan directory to render all of its config and hook templates")
                 (@arg DEFAULT_TOML: -d --("default-toml") +takes_value default_value("./default.toml") "Path to default.toml")
                 (@arg USER_TOML: -u --("user-toml") +takes_value "Path to user.toml, defaults to none")
                 (@arg MOCK_DATA: -m --("mock-data") +takes_value "Path to json file with mock data for template, defaults to none")
+                (@arg CONTEXT: -c --context +takes_value {file_exists}
+                    "Path to json file with rendering context data (sys, svc, bind, pkg) to simulate the census, defaults to none")
                 (@arg PRINT: -p --("print") "Prints config to STDOUT")
                 (@arg RENDER_DIR: -r --("render-dir") +takes_value default_value("./results") "Path to render templates")
                 (@arg NO_RENDER: -n --("no-render") "Don't write anything to disk, ignores --render-dir")
+                (@arg LINT: -l --lint "Report undefined variables and rendering errors of every template")
                 (@arg QUIET: -q --("no-verbose") --quiet
                     "Don't print any helper messages.  When used with `--print` will only print config file")
             )
@@ -697,11 +737,49 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
                         directory otherwise."))
                 )
             )
+            (@subcommand hooks =>
+                (about: "Displays the latest runs of the hooks of a loaded service")
+                (aliases: &["ho", "hoo", "hook"])
+                (@arg PKG_IDENT: +required +takes_value {valid_service_ident}
+                    "A package identifier, optionally followed by an instance name (ex: \
+                    core/redis, core/redis@cache)")
+                (@arg REMOTE_SUP: --("remote-sup") -r +takes_value
+                    "Address to a remote Supervisor's Control Gateway [default: 127.0.0.1:9632]")
+            )
             (subcommand: sub_svc_load().aliases(&["l", "lo", "loa"]))
             (subcommand: sub_svc_start().aliases(&["star"]))
             (subcommand: sub_svc_status().aliases(&["stat", "statu"]))
             (subcommand: sub_svc_stop().aliases(&["sto"]))
             (subcommand: sub_svc_unload().aliases(&["u", "un", "unl", "unlo", "unloa"]))
+            (@subcommand update =>
+                (about: "Applies the pending update of a loaded service")
+                (aliases: &["up", "upd", "upda", "updat"])
+                (@arg PKG_IDENT: +required +takes_value {valid_service_ident}
+                    "A package identifier, optionally followed by an instance name (ex: \
+                    core/redis, core/redis@cache)")
+                (@arg NOW: --now +required
+                    "Apply the update now, even if the service's update window is closed")
+                (@arg REMOTE_SUP: --("remote-sup") -r +takes_value
+                    "Address to a remote Supervisor's Control Gateway [default: 127.0.0.1:9632]")
+            )
+            (@subcommand hold =>
+                (about: "Holds a loaded service at its current release, stopping its updates")
+                (aliases: &["hol"])
+                (@arg PKG_IDENT: +required +takes_value {valid_service_ident}
+                    "A package identifier, optionally followed by an instance name (ex: \
+                    core/redis, core/redis@cache)")
+                (@arg REMOTE_SUP: --("remote-sup") -r +takes_value
+                    "Address to a remote Supervisor's Control Gateway [default: 127.0.0.1:9632]")
+            )
+            (@subcommand release =>
+                (about: "Releases a held service, letting it be updated again")
+                (aliases: &["rel", "rele", "relea", "releas"])
+                (@arg PKG_IDENT: +required +takes_value {valid_service_ident}
+                    "A package identifier, optionally followed by an instance name (ex: \
+                    core/redis, core/redis@cache)")
+                (@arg REMOTE_SUP: --("remote-sup") -r +takes_value
+                    "Address to a remote Supervisor's Control Gateway [default: 127.0.0.1:9632]")
+            )
         )
         (@subcommand studio =>
             (about: "Commands relating to Habitat Studios")
@@ -914,6 +992,8 @@ fn sub_config_apply() -> App<'static, 'static> {
         "A version number (positive integer) for this configuration (ex: 42)")
     (@arg FILE: {file_exists_or_stdin}
         "Path to local file on disk (ex: /tmp/config.toml, default: <stdin>)")
+    (@arg PATCH: --patch
+        "Read FILE as a JSON Patch (RFC 6902) which is applied on top of the other configuration layers")
     (@arg USER: -u --user +takes_value "Name of a user key to use for encryption")
     (@arg REMOTE_SUP: --("remote-sup") -r +takes_value
         "Address to a remote Supervisor's Control Gateway [default: 127.0.0.1:9632]")
@@ -1006,6 +1086,12 @@ pub fn sub_sup_run(feature_flags: FeatureFlag) -> App<'static, 'static> {
                              "Use package config from this path, rather than the package itself")
                             (@arg AUTO_UPDATE: --("auto-update") -A "Enable automatic updates for the Supervisor \
                                                                      itself")
+                            (@arg PKG_GC_INTERVAL: --("pkg-gc-interval") +takes_value {valid_pkg_gc_interval}
+                             "Remove installed packages which are no longer needed every PKG_GC_INTERVAL \
+                              seconds, keeping those of loaded services [default: disabled]")
+                            (@arg PKG_GC_KEEP: --("pkg-gc-keep") +takes_value requires[PKG_GC_INTERVAL] {valid_numeric::<usize>}
+                             "The number of releases of each package to keep when removing unused \
+                              packages [default: 2]")
                             (@arg KEY_FILE: --key +takes_value {file_exists} requires[CERT_FILE]
                              "Used for enabling TLS for the HTTP gateway. Read private key from KEY_FILE. \
                               This should be a RSA private key or PKCS8-encoded private key, in PEM format.")
@@ -1030,12 +1116,33 @@ pub fn sub_sup_run(feature_flags: FeatureFlag) -> App<'static, 'static> {
                             (@arg TOPOLOGY: --topology -t +takes_value possible_value[standalone leader]
                              "Service topology; [default: none]")
                             (@arg STRATEGY: --strategy -s +takes_value {valid_update_strategy}
-                             "The update strategy; [default: none] [values: none, at-once, rolling]")
+                             "The update strategy; [default: none] [values: none, at-once, rolling, canary]")
+                            (@arg UPDATE_CANARIES: --("update-canaries") +takes_value {valid_canary_count}
+                             "The number (ex: 2) or percentage (ex: 10%) of members the canary update \
+                              strategy updates first [default: 1]")
+                            (@arg UPDATE_BAKE_PERIOD: --("update-bake-period") +takes_value {valid_numeric::<u32>}
+                             "The number of seconds the canaries must stay healthy before the canary update \
+                              strategy updates the rest of the service group [default: 300]")
+                            (@arg UPDATE_ROLLBACK_WINDOW: --("update-rollback-window") +takes_value {valid_numeric::<u32>}
+                             "The number of seconds an updated release has to become healthy before it is \
+                              rolled back to the release that ran before; 0 disables rollbacks [default: 0]")
+                            (@arg UPDATE_WINDOW: --("update-window") +takes_value {valid_update_window}
+                             "When updates may be applied, as a crontab schedule followed by an optional time \
+                              zone (ex: \"* 2-4 * * sat,sun Europe/Berlin\"). Updates found outside of the window \
+                              are applied when it opens [default: always]")
                             (@arg BIND: --bind +takes_value +multiple
                              "One or more service groups to bind to a configuration")
                             (@arg BINDING_MODE: --("binding-mode") +takes_value {valid_binding_mode}
                              "Governs how the presence or absence of binds affects service startup. `strict` blocks \
                               startup until all binds are present. [default: strict] [values: relaxed, strict]")
+                            (@arg BIND_HEALTHY_ONLY: --("bind-healthy-only")
+                             "Only include members of bound service groups whose latest health check was OK")
+                            (@arg START_AFTER: --("start-after") +takes_value +multiple {valid_ident}
+                             "One or more services (ex: core/postgresql) on this Supervisor which must be running \
+                              before this service starts")
+                            (@arg REQUIRES_HEALTHY: --("requires-healthy")
+                             "Wait for the services this service starts after or binds to to be healthy, \
+                              rather than just running, before starting it")
                             (@arg VERBOSE: -v "Verbose output; shows file and line/column numbers")
                             (@arg NO_COLOR: --("no-color") "Turn ANSI color off")
                             (@arg JSON: --("json-logging") "Use structured JSON logging for the Supervisor. \
@@ -1075,8 +1182,9 @@ pub fn sub_sup_term() -> App<'static, 'static> {
 fn sub_svc_start() -> App<'static, 'static> {
     clap_app!(@subcommand start =>
         (about: "Start a loaded, but stopped, Habitat service.")
-        (@arg PKG_IDENT: +required +takes_value {valid_ident}
-            "A Habitat package identifier (ex: core/redis)")
+        (@arg PKG_IDENT: +required +takes_value {valid_service_ident}
+            "A Habitat package identifier, optionally followed by an instance name (ex: \
+            core/redis, core/redis@cache)")
         (@arg REMOTE_SUP: --("remote-sup") -r +takes_value
             "Address to a remote Supervisor's Control Gateway [default: 127.0.0.1:9632]")
     )
@@ -1087,7 +1195,11 @@ fn sub_svc_start() -> App<'static, 'static> {
 pub fn sub_svc_status() -> App<'static, 'static> {
     clap_app!(@subcommand status =>
         (about: "Query the status of Habitat services.")
-        (@arg PKG_IDENT: +takes_value {valid_ident} "A Habitat package identifier (ex: core/redis)")
+        (@arg PKG_IDENT: +takes_value {valid_service_ident}
+            "A Habitat package identifier, optionally followed by an instance name (ex: \
+            core/redis, core/redis@cache)")
+        (@arg UPDATES: --updates
+            "Also show the update each service is waiting for and whether it is held")
         (@arg REMOTE_SUP: --("remote-sup") -r +takes_value
         "Address to a remote Supervisor's Control Gateway [default: 127.0.0.1:9632]")
     )
@@ -1102,8 +1214,9 @@ pub fn parse_optional_arg<T: FromStr>(name: &str, m: &ArgMatches) -> Option<T>
 fn sub_svc_stop() -> App<'static, 'static> {
     let sub = clap_app!(@subcommand stop =>
         (about: "Stop a running Habitat service.")
-        (@arg PKG_IDENT: +required +takes_value {valid_ident}
-            "A Habitat package identifier (ex: core/redis)")
+        (@arg PKG_IDENT: +required +takes_value {valid_service_ident}
+            "A Habitat package identifier, optionally followed by an instance name (ex: \
+            core/redis, core/redis@cache)")
         (@arg REMOTE_SUP: --("remote-sup") -r +takes_value
             "Address to a remote Supervisor's Control Gateway [default: 127.0.0.1:9632]")
     );
@@ -1115,8 +1228,9 @@ fn sub_svc_load() -> App<'static, 'static> {
         (about: "Load a service to be started and supervised by Habitat from a package \
             identifier. If an installed package doesn't satisfy the given package \
             identifier, a suitable package will be installed from Builder.")
-        (@arg PKG_IDENT: +required +takes_value {valid_ident}
-            "A Habitat package identifier (ex: core/redis)")
+        (@arg PKG_IDENT: +required +takes_value {valid_service_ident}
+            "A Habitat package identifier, optionally followed by an instance name (ex: \
+            core/redis, core/redis@cache)")
         (@arg APPLICATION: --application -a +takes_value requires[ENVIRONMENT]
             "Application name; [default: not set].")
         (@arg ENVIRONMENT: --environment -e +takes_value requires[APPLICATION]
@@ -1132,12 +1246,33 @@ fn sub_svc_load() -> App<'static, 'static> {
         (@arg TOPOLOGY: --topology -t +takes_value possible_value[standalone leader]
             "Service topology; [default: none]")
         (@arg STRATEGY: --strategy -s +takes_value {valid_update_strategy}
-            "The update strategy; [default: none] [values: none, at-once, rolling]")
+            "The update strategy; [default: none] [values: none, at-once, rolling, canary]")
+        (@arg UPDATE_CANARIES: --("update-canaries") +takes_value {valid_canary_count}
+            "The number (ex: 2) or percentage (ex: 10%) of members the canary update strategy \
+             updates first [default: 1]")
+        (@arg UPDATE_BAKE_PERIOD: --("update-bake-period") +takes_value {valid_numeric::<u32>}
+            "The number of seconds the canaries must stay healthy before the canary update \
+             strategy updates the rest of the service group [default: 300]")
+        (@arg UPDATE_ROLLBACK_WINDOW: --("update-rollback-window") +takes_value {valid_numeric::<u32>}
+            "The number of seconds an updated release has to become healthy before it is rolled \
+             back to the release that ran before; 0 disables rollbacks [default: 0]")
+        (@arg UPDATE_WINDOW: --("update-window") +takes_value {valid_update_window}
+            "When updates may be applied, as a crontab schedule followed by an optional time \
+             zone (ex: \"* 2-4 * * sat,sun Europe/Berlin\"). Updates found outside of the window \
+             are applied when it opens [default: always]")
         (@arg BIND: --bind +takes_value +multiple
             "One or more service groups to bind to a configuration")
         (@arg BINDING_MODE: --("binding-mode") +takes_value {valid_binding_mode}
              "Governs how the presence or absence of binds affects service startup. `strict` blocks \
               startup until all binds are present. [default: strict] [values: relaxed, strict]")
+        (@arg BIND_HEALTHY_ONLY: --("bind-healthy-only")
+            "Only include members of bound service groups whose latest health check was OK")
+        (@arg START_AFTER: --("start-after") +takes_value +multiple {valid_ident}
+            "One or more services (ex: core/postgresql) on this Supervisor which must be running \
+             before this service starts")
+        (@arg REQUIRES_HEALTHY: --("requires-healthy")
+            "Wait for the services this service starts after or binds to to be healthy, rather \
+             than just running, before starting it")
         (@arg FORCE: --force -f "Load or reload an already loaded service. If the service \
             was previously loaded and running this operation will also restart the service")
         (@arg REMOTE_SUP: --("remote-sup") -r +takes_value
@@ -1159,8 +1294,9 @@ fn sub_svc_unload() -> App<'static, 'static> {
     let sub = clap_app!(@subcommand unload =>
         (about: "Unload a service loaded by the Habitat Supervisor. If the service is \
             running it will additionally be stopped.")
-        (@arg PKG_IDENT: +required +takes_value {valid_ident}
-            "A Habitat package identifier (ex: core/redis)")
+        (@arg PKG_IDENT: +required +takes_value {valid_service_ident}
+            "A Habitat package identifier, optionally followed by an instance name (ex: \
+            core/redis, core/redis@cache)")
         (@arg REMOTE_SUP: --("remote-sup") -r +takes_value
             "Address to a remote Supervisor's Control Gateway [default: 127.0.0.1:9632]")
     );
@@ -1270,6 +1406,15 @@ fn file_exists(val: String) -> result::Result<(), String> {
     }
 }
 
+#[allow(clippy::needless_pass_by_value)] // Signature required by CLAP
+fn file_or_dir_exists(val: String) -> result::Result<(), String> {
+    if Path::new(&val).exists() {
+        Ok(())
+    } else {
+        Err(format!("File or directory: '{}' cannot be found", &val))
+    }
+}
+
 fn file_exists_or_stdin(val: String) -> result::Result<(), String> {
     if val == "-" {
         Ok(())
@@ -1304,6 +1449,15 @@ fn valid_numeric<T: FromStr>(val: String) -> result::Result<(), String> {
     }
 }
 
+#[allow(clippy::needless_pass_by_value)] // Signature required by CLAP
+fn valid_pkg_gc_interval(val: String) -> result::Result<(), String> {
+    match val.parse::<u64>() {
+        Ok(secs) if secs > 0 => Ok(()),
+        Ok(_) => Err("The package garbage collection interval must be at least 1 second".into()),
+        Err(_) => Err(format!("'{}' is not a valid number", &val)),
+    }
+}
+
 #[allow(clippy::needless_pass_by_value)] // Signature required by CLAP
 fn valid_health_check_interval(val: String) -> result::Result<(), String> {
     match HealthCheckInterval::from_str(&val) {
@@ -1324,6 +1478,22 @@ fn valid_update_strategy(val: String) -> result::Result<(), String> {
     }
 }
 
+#[allow(clippy::needless_pass_by_value)] // Signature required by CLAP
+fn valid_canary_count(val: String) -> result::Result<(), String> {
+    match CanaryCount::from_str(&val) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)] // Signature required by CLAP
+fn valid_update_window(val: String) -> result::Result<(), String> {
+    match UpdateWindow::from_str(&val) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
 #[allow(clippy::needless_pass_by_value)] // Signature required by CLAP
 fn valid_ident(val: String) -> result::Result<(), String> {
     match PackageIdent::from_str(&val) {
@@ -1336,6 +1506,21 @@ fn valid_ident(val: String) -> result::Result<(), String> {
     }
 }
 
+#[allow(clippy::needless_pass_by_value)] // Signature required by CLAP
+fn valid_service_ident(val: String) -> result::Result<(), String> {
+    let (ident, _) = habitat_sup_protocol::ctl::split_instance_name(&val);
+    match PackageIdent::from_str(ident) {
+        Ok(_) => Ok(()),
+        Err(_) => {
+            Err(format!("'{}' is not valid. Services are given as a package identifier of the \
+                         form origin/name[/version[/release]] or origin/name@constraint, \
+                         optionally followed by @instance, where instance may only contain \
+                         letters, digits, '-' and '_'",
+                        &val))
+        }
+    }
+}
+
 #[allow(clippy::needless_pass_by_value)] // Signature required by CLAP
 fn valid_target(val: String) -> result::Result<(), String> {
     match PackageTarget::from_str(&val) {
@@ -1804,4 +1989,39 @@ mod tests {
         }
 
     }
+
+    mod svc_commands {
+        use super::*;
+
+        #[test]
+        fn svc_commands_take_an_instance() {
+            let r = get(no_feature_flags()).get_matches_from_safe(vec!["hab",
+                                                                       "svc",
+                                                                       "stop",
+                                                                       "core/redis@cache"]);
+            assert!(r.is_ok());
+        }
+
+        #[test]
+        fn svc_load_takes_a_version_constraint_with_or_without_an_instance() {
+            for service in &["core/openssl@>=1.0.2,<1.1", "core/openssl@>=1.0.2,<1.1@legacy"] {
+                let r = get(no_feature_flags()).get_matches_from_safe(vec!["hab",
+                                                                           "svc",
+                                                                           "load",
+                                                                           *service]);
+                assert!(r.is_ok(), "{} was rejected", service);
+            }
+        }
+
+        #[test]
+        fn svc_commands_reject_invalid_instances() {
+            for service in &["core/redis@", "core/redis@../cache", "core@cache"] {
+                let r = get(no_feature_flags()).get_matches_from_safe(vec!["hab",
+                                                                           "svc",
+                                                                           "start",
+                                                                           *service]);
+                assert_eq!(r.unwrap_err().kind, clap::ErrorKind::ValueValidation);
+            }
+        }
+    }
 }
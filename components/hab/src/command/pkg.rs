@@ -3,6 +3,7 @@ pub mod build;
 pub mod channels;
 pub mod delete;
 pub mod demote;
+pub mod diff;
 pub mod dependencies;
 pub mod env;
 pub mod exec;
@@ -14,6 +15,7 @@ pub mod list;
 pub mod path;
 pub mod promote;
 pub mod provides;
+pub mod sbom;
 pub mod search;
 pub mod sign;
 pub mod uninstall;
@@ -0,0 +1,459 @@
+//! Compares two packages, to explain why a rebuild produced a different artifact or to review what
+//! changed between two releases.
+//!
+//! Each side is either a `.hart` file, which is unpacked into a temporary directory, or the
+//! identifier of an installed package. The metadata files of the two packages are compared line by
+//! line, and every other file by type, permissions, size and BLAKE2b hash.
+
+use crate::{common::ui::{UIWriter,
+                         UI},
+            error::Result,
+            hcore::{crypto::hash,
+                    fs::pkg_install_path,
+                    package::{metadata::MetaFile,
+                              PackageArchive,
+                              PackageIdent,
+                              PackageInstall}}};
+use std::{collections::{BTreeMap,
+                        BTreeSet},
+          fs,
+          path::{Path,
+                 PathBuf},
+          str::FromStr};
+use tempfile::TempDir;
+use walkdir::WalkDir;
+
+/// The metadata files which are compared as text rather than as plain files.
+const METAFILES: &[MetaFile] = &[MetaFile::Binds,
+                                 MetaFile::BindsOptional,
+                                 MetaFile::BuildDeps,
+                                 MetaFile::BuildTDeps,
+                                 MetaFile::CFlags,
+                                 MetaFile::Config,
+                                 MetaFile::Deps,
+                                 MetaFile::Environment,
+                                 MetaFile::EnvironmentSep,
+                                 MetaFile::Exports,
+                                 MetaFile::Exposes,
+                                 MetaFile::HookTimeouts,
+                                 MetaFile::Ident,
+                                 MetaFile::LdFlags,
+                                 MetaFile::LdRunPath,
+                                 MetaFile::Manifest,
+                                 MetaFile::Path,
+                                 MetaFile::ResourceLimits,
+                                 MetaFile::RuntimeEnvironment,
+                                 MetaFile::RuntimePath,
+                                 MetaFile::Sandbox,
+                                 MetaFile::ShutdownSignal,
+                                 MetaFile::ShutdownTimeout,
+                                 MetaFile::SvcGroup,
+                                 MetaFile::SvcUser,
+                                 MetaFile::Target,
+                                 MetaFile::TDeps,
+                                 MetaFile::TemplateEngine,
+                                 MetaFile::Type];
+
+pub fn start(ui: &mut UI, a: &str, b: &str, fs_root_path: &Path, to_json: bool) -> Result<()> {
+    let a = Snapshot::load(a, fs_root_path)?;
+    let b = Snapshot::load(b, fs_root_path)?;
+    let report = DiffReport::new(&a, &b);
+
+    if to_json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        ui.begin(format!("Comparing {} with {}", &report.a, &report.b))?;
+        ui.para("")?;
+        report.print();
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum FileKind {
+    File,
+    Directory,
+    Symlink,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+struct FileInfo {
+    kind:   FileKind,
+    /// Permission bits, shown in octal
+    mode:   String,
+    size:   u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hash:   Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target: Option<String>,
+}
+
+/// The contents of one package, keyed by path relative to the package's install prefix.
+struct Snapshot {
+    ident:    PackageIdent,
+    metadata: BTreeMap<String, String>,
+    files:    BTreeMap<String, FileInfo>,
+    // Keeps an unpacked artifact around until we're done with it
+    _tmp:     Option<TempDir>,
+}
+
+impl Snapshot {
+    fn load(src: &str, fs_root_path: &Path) -> Result<Self> {
+        if Path::new(src).is_file() {
+            let mut archive = PackageArchive::new(src);
+            let ident = archive.ident()?;
+            let tmp = TempDir::new()?;
+            archive.unpack(Some(tmp.path()))?;
+            let prefix = pkg_install_path(&ident, Some(tmp.path()));
+            Self::from_prefix(ident, &prefix, Some(tmp))
+        } else {
+            let install = PackageInstall::load(&PackageIdent::from_str(src)?, Some(fs_root_path))?;
+            Self::from_prefix(install.ident().clone(), install.installed_path(), None)
+        }
+    }
+
+    fn from_prefix(ident: PackageIdent, prefix: &Path, tmp: Option<TempDir>) -> Result<Self> {
+        let metafiles: BTreeSet<String> = METAFILES.iter().map(ToString::to_string).collect();
+        let mut metadata = BTreeMap::new();
+        let mut files = BTreeMap::new();
+        for entry in WalkDir::new(prefix).min_depth(1).follow_links(false) {
+            let entry = entry.map_err(|e| {
+                                e.into_io_error()
+                                 .expect("walkdir doesn't follow links, so can't find a loop")
+                            })?;
+            let path = entry.path()
+                            .strip_prefix(prefix)
+                            .expect("walkdir entries are below the root")
+                            .to_string_lossy()
+                            .into_owned();
+            let file_type = entry.file_type();
+            if file_type.is_file() && entry.depth() == 1 && metafiles.contains(&path) {
+                metadata.insert(path, fs::read_to_string(entry.path())?);
+                continue;
+            }
+            let md = fs::symlink_metadata(entry.path())?;
+            let info = if file_type.is_symlink() {
+                FileInfo { kind:   FileKind::Symlink,
+                           mode:   mode(&md),
+                           size:   0,
+                           hash:   None,
+                           target: Some(fs::read_link(entry.path())?.to_string_lossy()
+                                                                   .into_owned()), }
+            } else if file_type.is_dir() {
+                FileInfo { kind:   FileKind::Directory,
+                           mode:   mode(&md),
+                           size:   0,
+                           hash:   None,
+                           target: None, }
+            } else {
+                FileInfo { kind:   FileKind::File,
+                           mode:   mode(&md),
+                           size:   md.len(),
+                           hash:   Some(hash::hash_file(entry.path())?),
+                           target: None, }
+            };
+            files.insert(path, info);
+        }
+        Ok(Snapshot { ident,
+                      metadata,
+                      files,
+                      _tmp: tmp })
+    }
+}
+
+#[cfg(unix)]
+fn mode(md: &fs::Metadata) -> String {
+    use std::os::unix::fs::PermissionsExt;
+    format!("{:04o}", md.permissions().mode() & 0o7777)
+}
+
+#[cfg(windows)]
+fn mode(md: &fs::Metadata) -> String {
+    if md.permissions().readonly() {
+        "readonly".to_string()
+    } else {
+        "writable".to_string()
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Change {
+    Added,
+    Removed,
+    Changed,
+}
+
+impl Change {
+    fn symbol(&self) -> &'static str {
+        match *self {
+            Change::Added => "+",
+            Change::Removed => "-",
+            Change::Changed => "~",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct MetadataDiff {
+    file:          String,
+    change:        Change,
+    removed_lines: Vec<String>,
+    added_lines:   Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct FileDiff {
+    path:   String,
+    change: Change,
+    /// What differs for a changed file: `kind`, `mode`, `content` and/or `target`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    fields: Vec<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    old:    Option<FileInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    new:    Option<FileInfo>,
+}
+
+#[derive(Debug, Serialize)]
+struct DiffReport {
+    a:        String,
+    b:        String,
+    metadata: Vec<MetadataDiff>,
+    files:    Vec<FileDiff>,
+}
+
+impl DiffReport {
+    fn new(a: &Snapshot, b: &Snapshot) -> Self {
+        let mut metadata = vec![];
+        let names: BTreeSet<&String> = a.metadata.keys().chain(b.metadata.keys()).collect();
+        for name in names {
+            let old = a.metadata.get(name).map(String::as_str).unwrap_or_default();
+            let new = b.metadata.get(name).map(String::as_str).unwrap_or_default();
+            let change = match (a.metadata.contains_key(name), b.metadata.contains_key(name)) {
+                (false, _) => Change::Added,
+                (_, false) => Change::Removed,
+                _ if old == new => continue,
+                _ => Change::Changed,
+            };
+            let (removed_lines, added_lines) = line_diff(old, new);
+            metadata.push(MetadataDiff { file: name.clone(),
+                                         change,
+                                         removed_lines,
+                                         added_lines });
+        }
+
+        let mut files = vec![];
+        let paths: BTreeSet<&String> = a.files.keys().chain(b.files.keys()).collect();
+        for path in paths {
+            let (old, new) = (a.files.get(path), b.files.get(path));
+            let (change, fields) = match (old, new) {
+                (None, _) => (Change::Added, vec![]),
+                (_, None) => (Change::Removed, vec![]),
+                (Some(old), Some(new)) => {
+                    let fields = changed_fields(old, new);
+                    if fields.is_empty() {
+                        continue;
+                    }
+                    (Change::Changed, fields)
+                }
+            };
+            files.push(FileDiff { path: path.clone(),
+                                  change,
+                                  fields,
+                                  old: old.cloned(),
+                                  new: new.cloned() });
+        }
+
+        DiffReport { a: a.ident.to_string(),
+                     b: b.ident.to_string(),
+                     metadata,
+                     files }
+    }
+
+    fn print(&self) {
+        if self.metadata.is_empty() && self.files.is_empty() {
+            println!("The packages are identical");
+            return;
+        }
+        if !self.metadata.is_empty() {
+            println!("Metadata:");
+            for diff in &self.metadata {
+                println!("  {} {}", diff.change.symbol(), diff.file);
+                for line in &diff.removed_lines {
+                    println!("      - {}", line);
+                }
+                for line in &diff.added_lines {
+                    println!("      + {}", line);
+                }
+            }
+            println!();
+        }
+        if !self.files.is_empty() {
+            println!("Files:");
+            for diff in &self.files {
+                match (&diff.old, &diff.new) {
+                    (Some(old), Some(new)) => {
+                        let details: Vec<String> =
+                            diff.fields
+                                .iter()
+                                .map(|field| {
+                                    match *field {
+                                        "mode" => format!("mode {} -> {}", old.mode, new.mode),
+                                        "kind" => {
+                                            format!("type {:?} -> {:?}", old.kind, new.kind)
+                                        }
+                                        "content" => format!("content {} -> {} bytes",
+                                                             old.size, new.size),
+                                        field => field.to_string(),
+                                    }
+                                })
+                                .collect();
+                        println!("  {} {} ({})",
+                                 diff.change.symbol(),
+                                 diff.path,
+                                 details.join(", "));
+                    }
+                    _ => println!("  {} {}", diff.change.symbol(), diff.path),
+                }
+            }
+            println!();
+        }
+        println!("{} metadata files and {} files differ",
+                 self.metadata.len(),
+                 self.files.len());
+    }
+}
+
+fn changed_fields(old: &FileInfo, new: &FileInfo) -> Vec<&'static str> {
+    let mut fields = vec![];
+    if old.kind != new.kind {
+        fields.push("kind");
+    }
+    if old.mode != new.mode {
+        fields.push("mode");
+    }
+    if old.hash != new.hash {
+        fields.push("content");
+    }
+    if old.target != new.target {
+        fields.push("target");
+    }
+    fields
+}
+
+/// The lines only found in `old`, and the lines only found in `new`, each in their original order.
+/// Metadata files are short lists where order rarely matters, so this is all the diff needed.
+fn line_diff(old: &str, new: &str) -> (Vec<String>, Vec<String>) {
+    let old_lines: BTreeSet<&str> = old.lines().collect();
+    let new_lines: BTreeSet<&str> = new.lines().collect();
+    let removed = old.lines()
+                     .filter(|l| !new_lines.contains(l))
+                     .map(str::to_string)
+                     .collect();
+    let added = new.lines()
+                   .filter(|l| !old_lines.contains(l))
+                   .map(str::to_string)
+                   .collect();
+    (removed, added)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Write;
+
+    fn write(root: &Path, path: &str, contents: &str) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::File::create(path).unwrap()
+                              .write_all(contents.as_bytes())
+                              .unwrap();
+    }
+
+    fn snapshot(root: &Path) -> Snapshot {
+        Snapshot::from_prefix(PackageIdent::from_str("core/redis/4.0.14/20190319155852").unwrap(),
+                              root,
+                              None).unwrap()
+    }
+
+    #[test]
+    fn identical_packages_have_no_differences() {
+        let a = TempDir::new().unwrap();
+        write(a.path(), "DEPS", "core/glibc/2.27/20190115002733\n");
+        write(a.path(), "bin/redis-server", "server");
+
+        let report = DiffReport::new(&snapshot(a.path()), &snapshot(a.path()));
+        assert!(report.metadata.is_empty());
+        assert!(report.files.is_empty());
+    }
+
+    #[test]
+    fn metadata_files_are_compared_by_line() {
+        let a = TempDir::new().unwrap();
+        let b = TempDir::new().unwrap();
+        write(a.path(),
+              "DEPS",
+              "core/glibc/2.27/20190115002733\ncore/openssl/1.0.2r/20190305210149\n");
+        write(b.path(),
+              "DEPS",
+              "core/glibc/2.29/20190601000000\ncore/openssl/1.0.2r/20190305210149\n");
+        write(b.path(), "EXPOSES", "6379");
+
+        let report = DiffReport::new(&snapshot(a.path()), &snapshot(b.path()));
+        assert!(report.files.is_empty());
+        assert_eq!(report.metadata.len(), 2);
+        assert_eq!(report.metadata[0].file, "DEPS");
+        assert_eq!(report.metadata[0].change, Change::Changed);
+        assert_eq!(report.metadata[0].removed_lines,
+                   vec!["core/glibc/2.27/20190115002733"]);
+        assert_eq!(report.metadata[0].added_lines,
+                   vec!["core/glibc/2.29/20190601000000"]);
+        assert_eq!(report.metadata[1].file, "EXPOSES");
+        assert_eq!(report.metadata[1].change, Change::Added);
+    }
+
+    #[test]
+    fn files_are_compared_by_content() {
+        let a = TempDir::new().unwrap();
+        let b = TempDir::new().unwrap();
+        write(a.path(), "bin/redis-server", "server");
+        write(a.path(), "bin/redis-cli", "cli");
+        write(b.path(), "bin/redis-server", "rebuilt server");
+        write(b.path(), "bin/redis-benchmark", "benchmark");
+
+        let report = DiffReport::new(&snapshot(a.path()), &snapshot(b.path()));
+        let changes: Vec<(&str, &Change)> =
+            report.files
+                  .iter()
+                  .map(|f| (f.path.as_str(), &f.change))
+                  .collect();
+        assert_eq!(changes,
+                   vec![("bin/redis-benchmark", &Change::Added),
+                        ("bin/redis-cli", &Change::Removed),
+                        ("bin/redis-server", &Change::Changed)]);
+        assert_eq!(report.files[2].fields, vec!["content"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn files_are_compared_by_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let a = TempDir::new().unwrap();
+        let b = TempDir::new().unwrap();
+        write(a.path(), "bin/redis-server", "server");
+        write(b.path(), "bin/redis-server", "server");
+        fs::set_permissions(a.path().join("bin/redis-server"),
+                            fs::Permissions::from_mode(0o755)).unwrap();
+        fs::set_permissions(b.path().join("bin/redis-server"),
+                            fs::Permissions::from_mode(0o775)).unwrap();
+
+        let report = DiffReport::new(&snapshot(a.path()), &snapshot(b.path()));
+        assert_eq!(report.files.len(), 1);
+        assert_eq!(report.files[0].fields, vec!["mode"]);
+        assert_eq!(report.files[0].old.as_ref().unwrap().mode, "0755");
+        assert_eq!(report.files[0].new.as_ref().unwrap().mode, "0775");
+    }
+}
@@ -15,6 +15,7 @@ pub fn start(ui: &mut UI, src: &Path) -> Result<()> {
         println!("Key Name       : {}", header.key_name);
         println!("Hash Type      : {}", header.hash_type);
         println!("Raw Signature  : {}", header.signature_raw);
+        println!("Compression    : {}", header.compression);
     } else {
         ui.warn("Failed to read package header.")?;
     }
@@ -0,0 +1,581 @@
+//! Generates a software bill of materials (SBOM) for a package and its transitive runtime
+//! dependencies, as CycloneDX or SPDX JSON.
+//!
+//! Licenses, source URLs and upstream URLs come from the `MANIFEST` the plan build wrote into each
+//! package. Checksums are the BLAKE2b digests of the `.hart` files: the given artifact itself, or
+//! the copies of installed packages still in the artifact cache.
+//!
+//! Licenses which aren't SPDX license identifiers or expressions are listed by name in CycloneDX
+//! documents, and as `LicenseRef-` licenses in SPDX documents.
+
+use crate::{error::Result,
+            hcore::{crypto::hash,
+                    fs::cache_artifact_path,
+                    package::{Identifiable,
+                              PackageArchive,
+                              PackageIdent,
+                              PackageInstall}},
+            VERSION};
+use chrono::{SecondsFormat,
+             Utc};
+use license_exprs;
+use serde_json::{self,
+                 json,
+                 Map,
+                 Value as Json};
+use std::{collections::BTreeMap,
+          iter,
+          path::Path,
+          str::FromStr};
+use uuid::Uuid;
+
+/// The checksum algorithm of Habitat artifacts, as named by both CycloneDX and SPDX.
+const CHECKSUM_ALGORITHM: &str = "BLAKE2b-256";
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SbomFormat {
+    CycloneDx,
+    Spdx,
+}
+
+/// Print an SBOM for `src`, which is either the path to a `.hart` file or the identifier of an
+/// installed package. Dependencies which are not installed are listed by identifier only.
+pub fn start(src: &str, format: SbomFormat, fs_root_path: &Path) -> Result<()> {
+    let (root, tdeps) = if Path::new(src).is_file() {
+        let mut archive = PackageArchive::new(src);
+        let manifest = archive.manifest().ok();
+        let root = Component::new(archive.ident()?,
+                                  archive.deps()?,
+                                  manifest.as_ref().map(String::as_str),
+                                  Some(archive.checksum()?));
+        (root, archive.tdeps()?)
+    } else {
+        let install = PackageInstall::load(&PackageIdent::from_str(src)?, Some(fs_root_path))?;
+        (installed_component(&install, fs_root_path)?, install.tdeps()?)
+    };
+
+    let mut deps = Vec::with_capacity(tdeps.len());
+    for ident in tdeps {
+        deps.push(match PackageInstall::load(&ident, Some(fs_root_path)) {
+                      Ok(install) => installed_component(&install, fs_root_path)?,
+                      Err(_) => Component::new(ident, vec![], None, None),
+                  });
+    }
+
+    let timestamp = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+    let document = match format {
+        SbomFormat::CycloneDx => cyclonedx(&root, &deps, &timestamp),
+        SbomFormat::Spdx => spdx(&root, &deps, &timestamp),
+    };
+    println!("{}", serde_json::to_string_pretty(&document)?);
+    Ok(())
+}
+
+fn installed_component(install: &PackageInstall, fs_root_path: &Path) -> Result<Component> {
+    let ident = install.ident().clone();
+    let manifest = install.manifest().ok();
+    let checksum = ident.archive_name()
+                        .ok()
+                        .map(|name| cache_artifact_path(Some(fs_root_path)).join(name))
+                        .and_then(|path| hash::hash_file(path).ok());
+    Ok(Component::new(ident,
+                      install.deps()?,
+                      manifest.as_ref().map(String::as_str),
+                      checksum))
+}
+
+/// A package in the bill of materials.
+#[derive(Debug)]
+struct Component {
+    ident:         PackageIdent,
+    /// Direct runtime dependencies, when known
+    deps:          Vec<PackageIdent>,
+    description:   Option<String>,
+    licenses:      Vec<License>,
+    source_url:    Option<String>,
+    source_sha256: Option<String>,
+    upstream_url:  Option<String>,
+    /// BLAKE2b digest of the package's `.hart` file
+    checksum:      Option<String>,
+}
+
+impl Component {
+    fn new(ident: PackageIdent,
+           deps: Vec<PackageIdent>,
+           manifest: Option<&str>,
+           checksum: Option<String>)
+           -> Self {
+        let mut component = Component { ident,
+                                        deps,
+                                        description: None,
+                                        licenses: vec![],
+                                        source_url: None,
+                                        source_sha256: None,
+                                        upstream_url: None,
+                                        checksum };
+        if let Some(manifest) = manifest {
+            component.read_manifest(manifest);
+        }
+        component
+    }
+
+    /// Fill in the plan metadata from the header of a `MANIFEST`, which looks like:
+    ///
+    /// ```text
+    /// # core / redis
+    /// Persistent key-value database, with built-in net interface
+    ///
+    /// * __Upstream URL__: [http://redis.io/](http://redis.io/)
+    /// * __License__: BSD-3-Clause
+    /// * __Source__: [http://download.redis.io/...](http://download.redis.io/...)
+    /// * __SHA__: `2e1e...`
+    /// ```
+    ///
+    /// Fields the plan didn't set are written as prose placeholders, which are skipped.
+    fn read_manifest(&mut self, manifest: &str) {
+        let mut description = vec![];
+        let mut in_fields = false;
+        for line in manifest.lines().skip(1) {
+            // Everything past the header is the plan itself
+            if line.starts_with("# ") {
+                break;
+            }
+            // The description runs from the title to the first field
+            if !line.starts_with("* __") {
+                if !in_fields && !line.trim().is_empty() {
+                    description.push(line.trim());
+                }
+                continue;
+            }
+            in_fields = true;
+            if let Some(value) = manifest_field(line, "License") {
+                self.licenses = License::parse_list(value);
+            } else if let Some(value) = manifest_field(line, "Source") {
+                self.source_url = markdown_link(value);
+            } else if let Some(value) = manifest_field(line, "SHA") {
+                self.source_sha256 = code_span(value);
+            } else if let Some(value) = manifest_field(line, "Upstream URL") {
+                self.upstream_url = markdown_link(value);
+            }
+        }
+        if !description.is_empty() {
+            self.description = Some(description.join(" "));
+        }
+    }
+
+    fn version(&self) -> &str { self.ident.version().unwrap_or_default() }
+
+    fn release(&self) -> &str { self.ident.release().unwrap_or_default() }
+
+    fn purl(&self) -> String {
+        format!("pkg:generic/{}/{}@{}?release={}",
+                self.ident.origin,
+                self.ident.name,
+                self.version(),
+                self.release())
+    }
+
+    fn cyclonedx(&self, kind: &str) -> Json {
+        let mut component = Map::new();
+        component.insert("type".into(), json!(kind));
+        component.insert("bom-ref".into(), json!(self.ident.to_string()));
+        component.insert("group".into(), json!(self.ident.origin));
+        component.insert("name".into(), json!(self.ident.name));
+        component.insert("version".into(), json!(self.version()));
+        if let Some(ref description) = self.description {
+            component.insert("description".into(), json!(description));
+        }
+        if let Some(ref checksum) = self.checksum {
+            component.insert("hashes".into(),
+                             json!([{ "alg": CHECKSUM_ALGORITHM, "content": checksum }]));
+        }
+        if !self.licenses.is_empty() {
+            let licenses: Vec<Json> = self.licenses.iter().map(License::cyclonedx).collect();
+            component.insert("licenses".into(), json!(licenses));
+        }
+        component.insert("purl".into(), json!(self.purl()));
+        let mut references = vec![];
+        if let Some(ref url) = self.source_url {
+            references.push(json!({ "type": "distribution", "url": url }));
+        }
+        if let Some(ref url) = self.upstream_url {
+            references.push(json!({ "type": "website", "url": url }));
+        }
+        if !references.is_empty() {
+            component.insert("externalReferences".into(), json!(references));
+        }
+        let mut properties = vec![json!({ "name": "habitat:release", "value": self.release() })];
+        if let Some(ref sha) = self.source_sha256 {
+            properties.push(json!({ "name": "habitat:source:sha256", "value": sha }));
+        }
+        component.insert("properties".into(), json!(properties));
+        Json::Object(component)
+    }
+
+    fn spdx_id(ident: &PackageIdent) -> String {
+        format!("SPDXRef-Package-{}", spdx_idstring(&ident.to_string()))
+    }
+
+    fn spdx(&self) -> Json {
+        let mut package = Map::new();
+        package.insert("SPDXID".into(), json!(Self::spdx_id(&self.ident)));
+        package.insert("name".into(),
+                       json!(format!("{}/{}", self.ident.origin, self.ident.name)));
+        package.insert("versionInfo".into(),
+                       json!(format!("{}/{}", self.version(), self.release())));
+        package.insert("supplier".into(),
+                       json!(format!("Organization: {}", self.ident.origin)));
+        package.insert("downloadLocation".into(),
+                       json!(self.source_url.as_ref().map_or("NOASSERTION", String::as_str)));
+        if let Some(ref url) = self.upstream_url {
+            package.insert("homepage".into(), json!(url));
+        }
+        package.insert("filesAnalyzed".into(), json!(false));
+        package.insert("licenseConcluded".into(), json!("NOASSERTION"));
+        package.insert("licenseDeclared".into(),
+                       json!(if self.licenses.is_empty() {
+                                 "NOASSERTION".to_string()
+                             } else {
+                                 self.licenses
+                                     .iter()
+                                     .map(License::spdx)
+                                     .collect::<Vec<_>>()
+                                     .join(" AND ")
+                             }));
+        package.insert("copyrightText".into(), json!("NOASSERTION"));
+        if let Some(ref checksum) = self.checksum {
+            package.insert("checksums".into(),
+                           json!([{ "algorithm": CHECKSUM_ALGORITHM,
+                                    "checksumValue": checksum }]));
+        }
+        if let Some(ref description) = self.description {
+            package.insert("description".into(), json!(description));
+        }
+        if let Some(ref sha) = self.source_sha256 {
+            package.insert("sourceInfo".into(),
+                           json!(format!("Built from a source archive with SHA256 {}", sha)));
+        }
+        package.insert("externalRefs".into(),
+                       json!([{ "referenceCategory": "PACKAGE-MANAGER",
+                                "referenceType": "purl",
+                                "referenceLocator": self.purl() }]));
+        Json::Object(package)
+    }
+}
+
+/// A license declared by a plan in `pkg_license`.
+#[derive(Debug, Eq, PartialEq)]
+enum License {
+    /// An SPDX license identifier, like `MIT`, or expression, like `MIT OR Apache-2.0`
+    Spdx(String),
+    /// The name of any other license, like `Boost Software License`
+    Other(String),
+}
+
+impl License {
+    fn new(name: &str) -> Self {
+        if license_exprs::validate_license_expr(name).is_ok() {
+            License::Spdx(name.to_string())
+        } else {
+            License::Other(name.to_string())
+        }
+    }
+
+    /// Parses the licenses of a `MANIFEST`, which are separated by `;`. Older plan builds
+    /// separated them with spaces, which can't be told apart from the spaces in license names, so
+    /// those are only split when every word is an SPDX license identifier.
+    fn parse_list(value: &str) -> Vec<License> {
+        let names: Vec<&str> = if value.contains(';') {
+            value.split(';')
+                 .map(str::trim)
+                 .filter(|name| !name.is_empty())
+                 .collect()
+        } else if value.split_whitespace()
+                       .all(|word| license_exprs::validate_license_expr(word).is_ok())
+        {
+            value.split_whitespace().collect()
+        } else {
+            vec![value]
+        };
+        names.into_iter().map(License::new).collect()
+    }
+
+    /// The identifier an SPDX document uses for a license which isn't on the SPDX license list.
+    fn license_ref(name: &str) -> String { format!("LicenseRef-{}", spdx_idstring(name)) }
+
+    fn spdx(&self) -> String {
+        match *self {
+            License::Spdx(ref expression) if expression.contains(' ') => {
+                format!("({})", expression)
+            }
+            License::Spdx(ref id) => id.clone(),
+            License::Other(ref name) => Self::license_ref(name),
+        }
+    }
+
+    fn cyclonedx(&self) -> Json {
+        match *self {
+            License::Spdx(ref expression) if expression.contains(' ') => {
+                json!({ "expression": expression })
+            }
+            License::Spdx(ref id) => json!({ "license": { "id": id } }),
+            License::Other(ref name) => json!({ "license": { "name": name } }),
+        }
+    }
+}
+
+/// SPDX identifiers may only contain letters, numbers, `.` and `-`.
+fn spdx_idstring(value: &str) -> String {
+    value.chars()
+         .map(|c| {
+             if c.is_ascii_alphanumeric() || c == '.' {
+                 c
+             } else {
+                 '-'
+             }
+         })
+         .collect()
+}
+
+/// The value of a `* __Name__: value` line of a `MANIFEST` header.
+fn manifest_field<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+    let prefix = format!("* __{}__:", name);
+    if line.starts_with(&prefix) {
+        Some(line[prefix.len()..].trim())
+    } else {
+        None
+    }
+}
+
+/// The target of a `[text](url)` link, or `None` for a placeholder.
+fn markdown_link(value: &str) -> Option<String> {
+    if !value.starts_with('[') || !value.ends_with(')') {
+        return None;
+    }
+    value.rfind("](")
+         .map(|start| value[start + 2..value.len() - 1].to_string())
+}
+
+/// The contents of a `` `code` `` span, or `None` for a placeholder.
+fn code_span(value: &str) -> Option<String> {
+    if value.len() > 2 && value.starts_with('`') && value.ends_with('`') {
+        Some(value[1..value.len() - 1].to_string())
+    } else {
+        None
+    }
+}
+
+fn cyclonedx(root: &Component, deps: &[Component], timestamp: &str) -> Json {
+    let dependencies: Vec<Json> =
+        iter::once(root).chain(deps)
+                        .map(|c| {
+                            let depends_on: Vec<String> =
+                                c.deps.iter().map(ToString::to_string).collect();
+                            json!({ "ref": c.ident.to_string(), "dependsOn": depends_on })
+                        })
+                        .collect();
+    let components: Vec<Json> = deps.iter().map(|c| c.cyclonedx("library")).collect();
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.4",
+        "serialNumber": format!("urn:uuid:{}", Uuid::new_v4()),
+        "version": 1,
+        "metadata": {
+            "timestamp": timestamp,
+            "tools": [{ "vendor": "Habitat", "name": "hab", "version": VERSION }],
+            "component": root.cyclonedx("application"),
+        },
+        "components": components,
+        "dependencies": dependencies,
+    })
+}
+
+fn spdx(root: &Component, deps: &[Component], timestamp: &str) -> Json {
+    let packages: Vec<Json> = iter::once(root).chain(deps).map(Component::spdx).collect();
+    let mut relationships = vec![json!({ "spdxElementId": "SPDXRef-DOCUMENT",
+                                         "relationshipType": "DESCRIBES",
+                                         "relatedSpdxElement": Component::spdx_id(&root.ident) })];
+    for component in iter::once(root).chain(deps) {
+        for dep in &component.deps {
+            relationships.push(json!({ "spdxElementId": Component::spdx_id(&component.ident),
+                                       "relationshipType": "DEPENDS_ON",
+                                       "relatedSpdxElement": Component::spdx_id(dep) }));
+        }
+    }
+    // Every `LicenseRef-` license has to be described in the document
+    let mut other_licenses = BTreeMap::new();
+    for component in iter::once(root).chain(deps) {
+        for license in &component.licenses {
+            if let License::Other(ref name) = *license {
+                other_licenses.entry(License::license_ref(name)).or_insert(name);
+            }
+        }
+    }
+    let other_licenses: Vec<Json> =
+        other_licenses.into_iter()
+                      .map(|(id, name)| {
+                          json!({ "licenseId": id, "name": name, "extractedText": name })
+                      })
+                      .collect();
+    json!({
+        "spdxVersion": "SPDX-2.3",
+        "dataLicense": "CC0-1.0",
+        "SPDXID": "SPDXRef-DOCUMENT",
+        "name": root.ident.to_string(),
+        "documentNamespace": format!("https://spdx.org/spdxdocs/{}-{}",
+                                     Component::spdx_id(&root.ident),
+                                     Uuid::new_v4()),
+        "creationInfo": {
+            "created": timestamp,
+            "creators": [format!("Tool: hab-{}", VERSION)],
+        },
+        "packages": packages,
+        "relationships": relationships,
+        "hasExtractedLicensingInfos": other_licenses,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const MANIFEST: &str = "# core / redis
+Persistent key-value database, with built-in net interface
+
+* __Maintainer__: The Habitat Maintainers <humans@habitat.sh>
+* __Version__: 4.0.14
+* __Release__: 20190319155852
+* __Target__: x86_64-linux
+* __Upstream URL__: [http://redis.io/](http://redis.io/)
+* __License__: BSD-3-Clause
+* __Source__: [http://download.redis.io/releases/redis-4.0.14.tar.gz](http://download.redis.io/releases/redis-4.0.14.tar.gz)
+* __SHA__: `1e1e18420a86cfb285933123b04a82e1ebda20bfb0a289472745a087587e93a7`
+* __Path__: `/hab/pkgs/core/redis/4.0.14/20190319155852`
+* __Build Dependencies__: `core/make core/gcc `
+* __Dependencies__: `core/glibc `
+* __Interpreters__: no interpreters or undefined
+
+# Plan
+
+## Plan Source
+
+```bash
+pkg_license=('MIT')
+```
+";
+
+    fn ident(s: &str) -> PackageIdent { PackageIdent::from_str(s).unwrap() }
+
+    fn redis() -> Component {
+        Component::new(ident("core/redis/4.0.14/20190319155852"),
+                       vec![ident("core/glibc/2.27/20190115002733")],
+                       Some(MANIFEST),
+                       Some("abc123".to_string()))
+    }
+
+    #[test]
+    fn reads_manifest_header() {
+        let c = redis();
+        assert_eq!(c.description.as_ref().map(String::as_str),
+                   Some("Persistent key-value database, with built-in net interface"));
+        assert_eq!(c.licenses, vec![License::Spdx("BSD-3-Clause".to_string())]);
+        assert_eq!(c.source_url.as_ref().map(String::as_str),
+                   Some("http://download.redis.io/releases/redis-4.0.14.tar.gz"));
+        assert_eq!(c.source_sha256.as_ref().map(String::as_str),
+                   Some("1e1e18420a86cfb285933123b04a82e1ebda20bfb0a289472745a087587e93a7"));
+        assert_eq!(c.upstream_url.as_ref().map(String::as_str),
+                   Some("http://redis.io/"));
+    }
+
+    #[test]
+    fn skips_manifest_placeholders() {
+        let manifest = "# core / glibc
+
+* __Upstream URL__: upstream project's website or home page is not defined
+* __License__:
+* __Source__: source URL not provided or required
+* __SHA__: SHA256 checksum not provided or required
+";
+        let c = Component::new(ident("core/glibc/2.27/20190115002733"),
+                               vec![],
+                               Some(manifest),
+                               None);
+        assert!(c.description.is_none());
+        assert!(c.licenses.is_empty());
+        assert!(c.source_url.is_none());
+        assert!(c.source_sha256.is_none());
+        assert!(c.upstream_url.is_none());
+    }
+
+    #[test]
+    fn licenses_are_separated_by_semicolons() {
+        assert_eq!(License::parse_list("Boost Software License; MIT OR Apache-2.0"),
+                   vec![License::Other("Boost Software License".to_string()),
+                        License::Spdx("MIT OR Apache-2.0".to_string())]);
+    }
+
+    #[test]
+    fn licenses_of_older_manifests_are_split_on_spaces_if_they_are_spdx_ids() {
+        assert_eq!(License::parse_list("Apache-2.0 MIT"),
+                   vec![License::Spdx("Apache-2.0".to_string()),
+                        License::Spdx("MIT".to_string())]);
+        assert_eq!(License::parse_list("Boost Software License"),
+                   vec![License::Other("Boost Software License".to_string())]);
+    }
+
+    #[test]
+    fn spdx_ids_are_sanitized() {
+        assert_eq!(Component::spdx_id(&ident("core/redis/4.0.14/20190319155852")),
+                   "SPDXRef-Package-core-redis-4.0.14-20190319155852");
+    }
+
+    #[test]
+    fn cyclonedx_document() {
+        let glibc = Component::new(ident("core/glibc/2.27/20190115002733"), vec![], None, None);
+        let doc = cyclonedx(&redis(), &[glibc], "2019-03-19T15:58:52Z");
+
+        assert_eq!(doc["bomFormat"], "CycloneDX");
+        let root = &doc["metadata"]["component"];
+        assert_eq!(root["purl"],
+                   "pkg:generic/core/redis@4.0.14?release=20190319155852");
+        assert_eq!(root["hashes"][0]["alg"], "BLAKE2b-256");
+        assert_eq!(root["licenses"][0]["license"]["id"], "BSD-3-Clause");
+        assert_eq!(doc["components"][0]["bom-ref"], "core/glibc/2.27/20190115002733");
+        assert_eq!(doc["dependencies"][0]["dependsOn"][0],
+                   "core/glibc/2.27/20190115002733");
+    }
+
+    #[test]
+    fn spdx_document() {
+        let glibc = Component::new(ident("core/glibc/2.27/20190115002733"), vec![], None, None);
+        let doc = spdx(&redis(), &[glibc], "2019-03-19T15:58:52Z");
+
+        assert_eq!(doc["spdxVersion"], "SPDX-2.3");
+        assert_eq!(doc["packages"][0]["licenseDeclared"], "BSD-3-Clause");
+        assert_eq!(doc["packages"][1]["licenseDeclared"], "NOASSERTION");
+        assert_eq!(doc["packages"][1]["downloadLocation"], "NOASSERTION");
+        assert_eq!(doc["relationships"][1]["relationshipType"], "DEPENDS_ON");
+        assert_eq!(doc["relationships"][1]["relatedSpdxElement"],
+                   "SPDXRef-Package-core-glibc-2.27-20190115002733");
+        assert_eq!(doc["hasExtractedLicensingInfos"], json!([]));
+    }
+
+    #[test]
+    fn other_licenses_are_license_refs() {
+        let mut boost = redis();
+        boost.licenses = License::parse_list("Boost Software License; MIT OR Apache-2.0");
+
+        let doc = cyclonedx(&boost, &[], "2019-03-19T15:58:52Z");
+        let licenses = &doc["metadata"]["component"]["licenses"];
+        assert_eq!(licenses[0]["license"]["name"], "Boost Software License");
+        assert_eq!(licenses[1]["expression"], "MIT OR Apache-2.0");
+
+        let doc = spdx(&boost, &[], "2019-03-19T15:58:52Z");
+        assert_eq!(doc["packages"][0]["licenseDeclared"],
+                   "LicenseRef-Boost-Software-License AND (MIT OR Apache-2.0)");
+        assert_eq!(doc["hasExtractedLicensingInfos"],
+                   json!([{ "licenseId": "LicenseRef-Boost-Software-License",
+                            "name": "Boost Software License",
+                            "extractedText": "Boost Software License" }]));
+    }
+}
@@ -3,19 +3,25 @@ use std::path::Path;
 use crate::{common::ui::{Status,
                          UIWriter,
                          UI},
-            hcore::crypto::{artifact,
+            hcore::crypto::{artifact::{self,
+                                       Compression},
                             SigKeyPair}};
 
 use crate::error::Result;
 
-pub fn start(ui: &mut UI, origin: &SigKeyPair, src: &Path, dst: &Path) -> Result<()> {
+pub fn start(ui: &mut UI,
+             origin: &SigKeyPair,
+             src: &Path,
+             dst: &Path,
+             compression: Compression)
+             -> Result<()> {
     ui.begin(format!("Signing {}", src.display()))?;
     ui.status(Status::Signing,
               format!("{} with {} to create {}",
                       src.display(),
                       &origin.name_with_rev(),
                       dst.display()))?;
-    artifact::sign(src, dst, origin)?;
+    artifact::sign_with_compression(src, dst, origin, compression)?;
     ui.end(format!("Signed artifact {}.", dst.display()))?;
     Ok(())
 }
@@ -2,18 +2,16 @@ use super::{ExecutionStrategy,
             Scope};
 use crate::error::{Error,
                    Result};
-use habitat_common::{package_graph::PackageGraph,
+use habitat_common::{command::package::gc,
+                     package_graph::PackageGraph,
                      ui::{Status,
                           UIWriter,
                           UI}};
-use habitat_core::{error as herror,
-                   fs as hfs,
-                   package::{list::temp_package_directory,
-                             Identifiable,
+use habitat_core::{fs as hfs,
+                   package::{Identifiable,
                              PackageIdent,
                              PackageInstall}};
-use std::{fs,
-          path::Path,
+use std::{path::Path,
           str::FromStr};
 
 /// Delete a package and all dependencies which are not used by other packages.
@@ -194,36 +192,9 @@ fn maybe_delete(ui: &mut UI,
     }
 }
 
-/// Delete empty parent directories from a given path. don't traverse above
+/// Delete a package and any empty parent directories. don't traverse above
 /// the `pkg_root_path`
 fn do_clean_delete(pkg_root_path: &Path, real_install_path: &Path) -> Result<bool> {
-    // This match will always return Ok(Path) as the install path is always 4 levels
-    // below the pkg_root_path
-    match real_install_path.parent() {
-        Some(real_install_base) => {
-            let temp_install_path = temp_package_directory(real_install_path)?.path()
-                                                                              .to_path_buf();
-            fs::rename(&real_install_path, &temp_install_path)?;
-            fs::remove_dir_all(&temp_install_path)?;
-
-            for p in real_install_base.ancestors() {
-                // Let's be safe and not rm below the package directories
-                if p == pkg_root_path {
-                    break;
-                }
-                match p.read_dir() {
-                    Ok(contents) => {
-                        // This will calculate the amount of items in the directory
-                        match contents.count() {
-                            0 => fs::remove_dir(&p)?,
-                            _ => break,
-                        }
-                    }
-                    Err(e) => return Err(Error::HabitatCore(herror::Error::IO(e))),
-                }
-            }
-            Ok(true)
-        }
-        None => unreachable!("Install path doesn't have a parent"),
-    }
+    gc::remove_package_dir(pkg_root_path, real_install_path)?;
+    Ok(true)
 }
@@ -1,18 +1,39 @@
 use serde_json::{self,
                  json,
                  Value as Json};
-use std::{fs::{create_dir_all,
+use std::{collections::HashMap,
+          fs::{create_dir_all,
                read_to_string,
                File},
           io::Write,
-          path::Path};
+          path::{Path,
+                 PathBuf},
+          str::FromStr};
 use toml::Value;
+use walkdir::WalkDir;
 
-use crate::{common::{templating::TemplateRenderer,
+use crate::{common::{templating::{config::CfgRenderer,
+                                  lint,
+                                  package::{Pkg,
+                                            PkgProxy},
+                                  TemplateEngine,
+                                  TemplateRenderer},
                      ui::{Status,
                           UIWriter,
                           UI}},
-            error::Result};
+            error::{Error,
+                    Result},
+            hcore::{fs,
+                    os::{process::{ResourceLimits,
+                                   ShutdownSignal,
+                                   ShutdownTimeout},
+                         sandbox::Sandbox,
+                         users},
+                    package::PackageIdent},
+            VERSION};
+
+/// The member id of the simulated Supervisor in the rendering context.
+const MEMBER_ID: &str = "00000000000000000000000000000000";
 
 #[allow(clippy::too_many_arguments)]
 pub fn start(ui: &mut UI,
@@ -20,27 +41,34 @@ pub fn start(ui: &mut UI,
              default_toml_path: &Path,
              user_toml_path: Option<&Path>,
              mock_data_path: Option<&Path>,
+             context_path: Option<&Path>,
              print: bool,
              render: bool,
              render_dir: &Path,
+             lint: bool,
              quiet: bool)
              -> Result<()> {
-    // Strip the file name out of our passed template
-    let file_name = Path::new(template_path.file_name().expect("valid template file"));
-
     if !quiet {
-        ui.begin(format!("Rendering: {} into: {} as: {}",
+        ui.begin(format!("Rendering: {} into: {}",
                          template_path.display(),
-                         render_dir.display(),
-                         file_name.display()))?;
+                         render_dir.display()))?;
         ui.br()?;
     }
 
-    // read our template from file
-    let template = read_to_string(&template_path)?;
+    // read the simulated census first, as it may define the package we're rendering
+    let context = match context_path {
+        Some(path) => {
+            if !quiet {
+                ui.begin(format!("Importing context: {}", path.display()))?;
+            }
+            serde_json::from_str(&read_to_string(path)?)?
+        }
+        None => json!({}),
+    };
+    let pkg = plan_pkg(template_path, &context);
 
-    // create a "data" json struct
-    let mut data = json!({});
+    // create a "data" json struct, holding a full rendering context for a standalone service
+    let mut data = default_context(&pkg);
 
     if !quiet {
         // import default.toml values, convert to JSON
@@ -49,7 +77,11 @@ pub fn start(ui: &mut UI,
 
     // we should always have a default.toml, would be nice to "autodiscover" based on package name,
     // for now assume we're working in the plan dir if --default-toml not passed
-    let default_toml = read_to_string(&default_toml_path)?;
+    let default_toml = if template_path.is_dir() && !default_toml_path.is_file() {
+        String::new()
+    } else {
+        read_to_string(&default_toml_path)?
+    };
 
     // merge default into data struct
     merge(&mut data, toml_to_json(&default_toml)?);
@@ -84,13 +116,157 @@ pub fn start(ui: &mut UI,
     // merge mock data into data
     merge(&mut data, serde_json::from_str(&mock_data)?);
 
+    // merge the simulated census last, filling in whatever its members leave out
+    merge(&mut data, context);
+    complete_members(&mut data);
+
+    let problems = if template_path.is_dir() {
+        render_plan(ui, template_path, &pkg, &data, print, render, render_dir, lint, quiet)?
+    } else {
+        // Strip the file name out of our passed template
+        let file_name = Path::new(template_path.file_name().expect("valid template file"));
+        let template = read_to_string(&template_path)?;
+        let engine = TemplateEngine::for_path(template_path, pkg.template_engine);
+        let (rendered, problems) =
+            render_template(ui, template_path, &template, engine, &data, print, lint, quiet)?;
+        if let (true, Some(rendered)) = (render, rendered) {
+            // Render our template file
+            let file_name = TemplateEngine::rendered_path(file_name);
+            create_with_template(ui, &render_dir, &file_name, &rendered, quiet)?;
+        }
+        problems
+    };
+
+    if !quiet {
+        ui.br()?;
+    }
+    if problems > 0 {
+        return Err(Error::TemplateLintFailed(problems));
+    }
+    Ok(())
+}
+
+/// Render the configuration templates and hooks of the plan at `plan_path`.
+///
+/// Returns the number of problems found while linting.
+#[allow(clippy::too_many_arguments)]
+fn render_plan(ui: &mut UI,
+               plan_path: &Path,
+               pkg: &Pkg,
+               data: &Json,
+               print: bool,
+               render: bool,
+               render_dir: &Path,
+               lint: bool,
+               quiet: bool)
+               -> Result<usize> {
+    let mut problems = 0;
+
+    let config_path = plan_path.join("config");
+    if config_path.is_dir() {
+        let mut failed = false;
+        for path in template_files(&config_path)? {
+            let template = read_to_string(&path)?;
+            let engine = TemplateEngine::for_path(&path, pkg.template_engine);
+            let (rendered, found) =
+                render_template(ui, &path, &template, engine, data, print, lint, quiet)?;
+            failed |= rendered.is_none();
+            problems += found;
+        }
+
+        // Templates which fail to render were already reported while linting
+        if render && !failed {
+            let config_dir = render_dir.join("config");
+            if !quiet {
+                ui.status(Status::Creating, format!("config: {}", config_dir.display()))?;
+            }
+            create_dir_all(&config_dir)?;
+            // the rendered files are owned by whoever renders them, not the service user
+            let mut owner = pkg.clone();
+            if let Some(user) = users::get_current_username() {
+                owner.svc_user = user;
+            }
+            if let Some(group) = users::get_current_groupname() {
+                owner.svc_group = group;
+            }
+            CfgRenderer::new(&config_path, pkg.template_engine)?.compile(&pkg.name,
+                                                                         &owner,
+                                                                         &config_dir,
+                                                                         data)?;
+        }
+    }
+
+    let hooks_path = plan_path.join("hooks");
+    if hooks_path.is_dir() {
+        for path in template_files(&hooks_path)? {
+            let template = read_to_string(&path)?;
+            let engine = TemplateEngine::for_path(&path, pkg.template_engine);
+            let (rendered, found) =
+                render_template(ui, &path, &template, engine, data, print, lint, quiet)?;
+            problems += found;
+            if let (true, Some(rendered)) = (render, rendered) {
+                let file_name = TemplateEngine::rendered_path(path.strip_prefix(&hooks_path)?);
+                create_with_template(ui, &render_dir.join("hooks"), file_name, &rendered, quiet)?;
+            }
+        }
+    }
+
+    Ok(problems)
+}
+
+/// Render a single template, printing it if requested.
+///
+/// When linting, undefined variables and rendering errors are reported instead of aborting, in
+/// which case the rendered template is `None` if it failed to render. Returns the rendered
+/// template and the number of problems found. Tera reports undefined variables as rendering
+/// errors, so only Handlebars templates are checked for them separately.
+#[allow(clippy::too_many_arguments)]
+fn render_template(ui: &mut UI,
+                   template_path: &Path,
+                   template: &str,
+                   engine: TemplateEngine,
+                   data: &Json,
+                   print: bool,
+                   lint: bool,
+                   quiet: bool)
+                   -> Result<(Option<String>, usize)> {
+    let mut problems = 0;
+    if lint && engine == TemplateEngine::Handlebars {
+        for undefined in lint::undefined_variables(template, data) {
+            ui.warn(format!("{}:{}: undefined variable '{}'",
+                            template_path.display(),
+                            undefined.line,
+                            undefined.path))?;
+            problems += 1;
+        }
+    }
+
     // create a template renderer
     let mut renderer = TemplateRenderer::new();
     // register our template
-    renderer.register_template_string(&template, &template)
-            .expect("Could not register template content");
-    // render our JSON override in our template.
-    let rendered_template = renderer.render(&template, &data)?;
+    let registered = match engine {
+        TemplateEngine::Handlebars => {
+            renderer.register_template_string(template, template)
+                    .map_err(|e| Error::TemplateRender(e.to_string()))
+        }
+        TemplateEngine::Tera => {
+            renderer.register_tera_template_string(template, template)
+                    .map_err(Error::from)
+        }
+    };
+    let rendered = match registered.and_then(|_| {
+                                       // render our JSON override in our template.
+                                       renderer.render(template, data).map_err(Error::from)
+                                   }) {
+        Ok(rendered) => rendered,
+        Err(e) => {
+            if !lint {
+                return Err(e);
+            }
+            ui.warn(format!("{}: {}", template_path.display(), e))?;
+            return Ok((None, problems + 1));
+        }
+    };
 
     if print {
         if !quiet {
@@ -99,7 +275,7 @@ pub fn start(ui: &mut UI,
                             &template_path.display()))?;
         }
 
-        println!("{}", rendered_template);
+        println!("{}", rendered);
 
         if !quiet {
             ui.warn(format!("========### End rendered template: {}",
@@ -107,15 +283,240 @@ pub fn start(ui: &mut UI,
         }
     }
 
-    if render {
-        // Render our template file
-        create_with_template(ui, &render_dir, &file_name, &rendered_template, quiet)?;
+    Ok((Some(rendered), problems))
+}
+
+/// All files below `dir`, in a stable order.
+fn template_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in WalkDir::new(dir).min_depth(1).sort_by(|a, b| a.file_name().cmp(b.file_name())) {
+        let entry = entry.map_err(|e| {
+                             e.into_io_error()
+                              .expect("walkdir doesn't follow links, so can't find a loop")
+                         })?;
+        if entry.file_type().is_file() {
+            files.push(entry.into_path());
+        }
     }
+    Ok(files)
+}
 
-    if !quiet {
-        ui.br()?;
+/// The package the templates are rendered for. Its identifier is taken from the context's
+/// `pkg.ident` if present, or else from the plan the templates belong to. Its template engine is
+/// always the plan's.
+fn plan_pkg(template_path: &Path, context: &Json) -> Pkg {
+    let ident = context["pkg"]["ident"].as_str()
+                                       .and_then(|ident| PackageIdent::from_str(ident).ok())
+                                       .or_else(|| plan_ident(template_path))
+                                       .unwrap_or_else(|| {
+                                           PackageIdent::new("local",
+                                                             "render",
+                                                             Some("0.0.0"),
+                                                             Some("19700101000000"))
+                                       });
+    let name = ident.name.clone();
+    let svc_user = context["pkg"]["svc_user"].as_str().unwrap_or("hab");
+    let svc_group = context["pkg"]["svc_group"].as_str().unwrap_or("hab");
+
+    Pkg { path: fs::pkg_install_path(&ident, None::<&Path>),
+          origin: ident.origin.clone(),
+          name: name.clone(),
+          svc_name: name.clone(),
+          version: ident.version.clone().unwrap_or_default(),
+          release: ident.release.clone().unwrap_or_default(),
+          ident,
+          deps: Vec::new(),
+          env: HashMap::<String, String>::new().into(),
+          exposes: Vec::new(),
+          exports: HashMap::new(),
+          svc_path: fs::svc_path(&name),
+          svc_config_path: fs::svc_config_path(&name),
+          svc_config_install_path: fs::svc_config_install_path(&name),
+          svc_data_path: fs::svc_data_path(&name),
+          svc_files_path: fs::svc_files_path(&name),
+          svc_static_path: fs::svc_static_path(&name),
+          svc_var_path: fs::svc_var_path(&name),
+          svc_pid_file: fs::svc_pid_file(&name),
+          svc_run: fs::svc_path(&name).join("run"),
+          svc_user: svc_user.to_string(),
+          svc_group: svc_group.to_string(),
+          shutdown_signal: ShutdownSignal::default(),
+          shutdown_timeout: ShutdownTimeout::default(),
+          hook_timeouts: HashMap::new(),
+          resource_limits: ResourceLimits::default(),
+          sandbox: Sandbox::default(),
+          template_engine: plan_template_engine(template_path).unwrap_or_default() }
+}
+
+/// Read `pkg_origin`, `pkg_name` and `pkg_version` from the plan the template at `path` belongs
+/// to, looking for a `plan.sh` or `plan.ps1` in `path` and its parent directories.
+fn plan_ident(path: &Path) -> Option<PackageIdent> {
+    let vars = plan_vars(path)?;
+    let origin = vars.get("pkg_origin")?;
+    let name = vars.get("pkg_name")?;
+    Some(PackageIdent::new(origin.as_str(),
+                           name.as_str(),
+                           vars.get("pkg_version").map(String::as_str),
+                           Some("19700101000000")))
+}
+
+/// Read `pkg_template_engine` from the plan the template at `path` belongs to.
+fn plan_template_engine(path: &Path) -> Option<TemplateEngine> {
+    plan_vars(path)?.get("pkg_template_engine")?.parse().ok()
+}
+
+/// The simple variable assignments of the plan the template at `path` belongs to, looking for a
+/// `plan.sh` or `plan.ps1` in `path` and its parent directories.
+fn plan_vars(path: &Path) -> Option<HashMap<String, String>> {
+    let plan = path.ancestors()
+                   .flat_map(|dir| vec![dir.join("plan.sh"), dir.join("plan.ps1")])
+                   .find(|plan| plan.is_file())?;
+    let content = read_to_string(plan).ok()?;
+
+    let mut vars = HashMap::new();
+    for line in content.lines() {
+        let line = line.trim().trim_start_matches('$');
+        if let Some(idx) = line.find('=') {
+            let value = line[idx + 1..].trim().trim_matches(|c: char| c == '"' || c == '\'');
+            vars.insert(line[..idx].trim().to_string(), value.to_string());
+        }
     }
-    Ok(())
+    Some(vars)
+}
+
+/// The system information of the simulated Supervisor.
+fn default_sys() -> Json {
+    json!({
+        "ip": "127.0.0.1",
+        "hostname": "localhost",
+        "gossip_ip": "0.0.0.0",
+        "gossip_port": 9638,
+        "http_gateway_ip": "0.0.0.0",
+        "http_gateway_port": 9631,
+        "ctl_gateway_ip": "127.0.0.1",
+        "ctl_gateway_port": 9632,
+        "member_id": MEMBER_ID,
+        "permanent": false,
+        "version": VERSION
+    })
+}
+
+/// A healthy, alive service group member, used to fill in any fields the members of a simulated
+/// census leave out.
+fn default_member() -> Json {
+    let mut sys = default_sys();
+    if let Json::Object(ref mut sys) = sys {
+        sys.remove("member_id");
+        sys.remove("permanent");
+        sys.remove("version");
+    }
+    json!({
+        "member_id": MEMBER_ID,
+        "alive": true,
+        "suspect": false,
+        "confirmed": false,
+        "departed": false,
+        "election_is_running": false,
+        "election_is_no_quorum": false,
+        "election_is_finished": false,
+        "update_election_is_running": false,
+        "update_election_is_no_quorum": false,
+        "update_election_is_finished": false,
+        "leader": false,
+        "follower": false,
+        "update_leader": false,
+        "update_follower": false,
+        "pkg": {},
+        "sys": sys,
+        "cfg": {},
+        "persistent": true,
+        "service": "",
+        "group": "default",
+        "org": null,
+        "application": null,
+        "environment": null
+    })
+}
+
+/// The rendering context of a standalone service which is the only member of its group and has
+/// no binds, as a Supervisor would provide it.
+fn default_context(pkg: &Pkg) -> Json {
+    let mut me = default_member();
+    me["service"] = json!(pkg.name);
+    me["pkg"] = json!({ "ident": pkg.ident.to_string() });
+
+    json!({
+        "sys": default_sys(),
+        "pkg": PkgProxy::new(pkg),
+        "cfg": {},
+        "svc": {
+            "service": pkg.name,
+            "group": "default",
+            "org": null,
+            "election_is_running": false,
+            "election_is_no_quorum": false,
+            "election_is_finished": false,
+            "update_election_is_running": false,
+            "update_election_is_no_quorum": false,
+            "update_election_is_finished": false,
+            "me": me,
+            "first": me,
+            "members": [me],
+            "leader": null,
+            "update_leader": null
+        },
+        "bind": {}
+    })
+}
+
+/// Fill in the fields members of the simulated census leave out, and the `first` and `leader`
+/// members of any group which doesn't define them. The leader is the member marked with
+/// `leader: true`, if any.
+fn complete_members(data: &mut Json) {
+    let complete = |member: &mut Json| {
+        if member.is_object() {
+            let mut completed = default_member();
+            merge(&mut completed, member.take());
+            *member = completed;
+        }
+    };
+
+    if let Some(members) = data["svc"]["members"].as_array_mut() {
+        members.iter_mut().for_each(complete);
+    }
+    if data["svc"]["leader"].is_null() {
+        data["svc"]["leader"] = marked_leader(&data["svc"]["members"]);
+    }
+    for key in &["me", "first", "leader", "update_leader"] {
+        complete(&mut data["svc"][key]);
+    }
+
+    if let Some(binds) = data["bind"].as_object_mut() {
+        for group in binds.values_mut().filter(|group| group.is_object()) {
+            if let Some(members) = group["members"].as_array_mut() {
+                members.iter_mut().for_each(complete);
+            }
+            if group["first"].is_null() {
+                group["first"] = group["members"][0].clone();
+            }
+            if group["leader"].is_null() {
+                group["leader"] = marked_leader(&group["members"]);
+            }
+            if group.get("members").is_none() {
+                group["members"] = json!([]);
+            }
+            complete(&mut group["first"]);
+            complete(&mut group["leader"]);
+        }
+    }
+}
+
+/// The first of `members` marked as the leader, or null if none is.
+fn marked_leader(members: &Json) -> Json {
+    members.as_array()
+           .and_then(|members| members.iter().find(|m| m["leader"] == true))
+           .cloned()
+           .unwrap_or(Json::Null)
 }
 
 fn toml_to_json(cfg: &str) -> Result<Json> {
@@ -149,9 +550,83 @@ fn create_with_template(ui: &mut UI,
         ui.status(Status::Creating, format!("file: {}", path.display()))?;
     }
 
-    create_dir_all(render_dir)?;
+    if let Some(dir) = path.parent() {
+        create_dir_all(dir)?;
+    }
 
     // Write file to disk
     File::create(path).and_then(|mut file| file.write(template.as_bytes()))?;
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn plan_ident_reads_the_plan() {
+        let plan = TempDir::new().unwrap();
+        fs::write(plan.path().join("plan.sh"),
+                  "pkg_name=redis\npkg_origin=core\npkg_version=\"4.0.14\"\n").unwrap();
+        fs::create_dir(plan.path().join("config")).unwrap();
+
+        let ident = plan_ident(&plan.path().join("config").join("redis.config")).unwrap();
+        assert_eq!(ident.to_string(), "core/redis/4.0.14/19700101000000");
+    }
+
+    #[test]
+    fn plan_template_engine_reads_the_plan() {
+        let plan = TempDir::new().unwrap();
+        fs::write(plan.path().join("plan.sh"), "pkg_name=redis\n").unwrap();
+        assert_eq!(plan_template_engine(&plan.path().join("redis.config")), None);
+
+        fs::write(plan.path().join("plan.sh"),
+                  "pkg_name=redis\npkg_template_engine=tera\n").unwrap();
+        assert_eq!(plan_template_engine(&plan.path().join("redis.config")),
+                   Some(TemplateEngine::Tera));
+    }
+
+    #[test]
+    fn complete_members_fills_in_the_census() {
+        let mut data = default_context(&plan_pkg(Path::new("/nonexistent"), &json!({})));
+        merge(&mut data,
+              json!({
+                  "bind": {
+                      "database": {
+                          "members": [
+                              { "sys": { "ip": "10.0.0.2" }, "leader": true },
+                              { "sys": { "ip": "10.0.0.3" } }
+                          ]
+                      }
+                  }
+              }));
+        complete_members(&mut data);
+
+        let database = &data["bind"]["database"];
+        assert_eq!(database["members"][0]["sys"]["ip"], "10.0.0.2");
+        assert_eq!(database["members"][0]["sys"]["hostname"], "localhost");
+        assert_eq!(database["members"][1]["alive"], true);
+        assert_eq!(database["members"][1]["leader"], false);
+        assert_eq!(database["first"]["sys"]["ip"], "10.0.0.2");
+        assert_eq!(database["leader"]["sys"]["ip"], "10.0.0.2");
+        assert_eq!(data["svc"]["me"]["service"], "render");
+        assert_eq!(data["svc"]["leader"], Json::Null);
+    }
+
+    #[test]
+    fn binds_render_from_the_census() {
+        let mut data = default_context(&plan_pkg(Path::new("/nonexistent"), &json!({})));
+        merge(&mut data,
+              json!({ "bind": { "database": { "members": [{ "sys": { "ip": "10.0.0.2" } }] } } }));
+        complete_members(&mut data);
+
+        let template = "{{#eachAlive bind.database.members as |member|}}\
+                        server {{member.sys.ip}}\
+                        {{/eachAlive}}";
+        let mut renderer = TemplateRenderer::new();
+        renderer.register_template_string("t", template).unwrap();
+        assert_eq!(renderer.render("t", &data).unwrap(), "server 10.0.0.2");
+    }
+}
@@ -61,6 +61,8 @@ pub enum Error {
     RootRequired,
     ScheduleStatus(api_client::Error),
     SubcommandNotSupported(String),
+    TemplateLintFailed(usize),
+    TemplateRender(String),
     UnsupportedExportFormat(String),
     TomlDeserializeError(toml::de::Error),
     TomlSerializeError(toml::ser::Error),
@@ -169,6 +171,10 @@ impl fmt::Display for Error {
             Error::SubcommandNotSupported(ref e) => {
                 format!("Subcommand `{}' not supported on this operating system", e)
             }
+            Error::TemplateLintFailed(count) => {
+                format!("Linting found {} problem(s) in the templates", count)
+            }
+            Error::TemplateRender(ref e) => format!("Failed to render template: {}", e),
             Error::UnsupportedExportFormat(ref e) => format!("Unsupported export format: {}", e),
             Error::TomlDeserializeError(ref e) => format!("Can't deserialize TOML: {}", e),
             Error::TomlSerializeError(ref e) => format!("Can't serialize TOML: {}", e),
@@ -239,6 +245,8 @@ impl error::Error for Error {
             }
             Error::ScheduleStatus(ref err) => err.description(),
             Error::SubcommandNotSupported(_) => "Subcommand not supported on this operating system",
+            Error::TemplateLintFailed(_) => "Linting found problems in the templates",
+            Error::TemplateRender(_) => "Failed to render template",
             Error::UnsupportedExportFormat(_) => "Unsupported export format",
             Error::TomlDeserializeError(_) => "Can't deserialize TOML",
             Error::TomlSerializeError(_) => "Can't serialize TOML",
@@ -30,11 +30,16 @@ use hab::{cli::{self,
 use habitat_common::{self as common,
                      cli::{cache_key_path_from_matches,
                            FS_ROOT},
-                     command::package::install::{InstallHookMode,
-                                                 InstallMode,
-                                                 InstallSource,
-                                                 LocalPackageUsage},
+                     command::package::{gc,
+                                        install::{InstallHookMode,
+                                                  InstallMode,
+                                                  InstallSource,
+                                                  LocalPackageUsage}},
                      output,
+                     templating::{config::{parse_patch,
+                                           patch_layer,
+                                           CfgExplanation},
+                                  hooks::history::HookRun},
                      types::ListenCtlAddr,
                      ui::{Status,
                           UIWriter,
@@ -64,12 +69,16 @@ use habitat_sup_client::{SrvClient,
                          SrvClientError};
 use habitat_sup_protocol::{self as sup_proto,
                            codec::*,
-                           ctl::ServiceBindList,
+                           ctl::{PackageIdentList,
+                                 ServiceBindList},
                            net::ErrCode,
                            types::*};
 use pbr;
-use std::{env,
-          ffi::OsString,
+use std::{collections::BTreeMap,
+          convert::TryFrom,
+          env,
+          ffi::{OsStr,
+                OsString},
           fs::File,
           io::{self,
                prelude::*,
@@ -101,6 +110,10 @@ lazy_static! {
              "pid",
              "group",]
     };
+    static ref STATUS_UPDATES_HEADER: Vec<&'static str> = { vec!["pending update", "held"] };
+    static ref HOOK_HISTORY_HEADER: Vec<&'static str> = {
+        vec!["hook", "run id", "started", "duration (ms)", "exit code", "timed out"]
+    };
 }
 
 fn main() {
@@ -236,14 +249,17 @@ fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
                 ("channels", Some(m)) => sub_pkg_channels(ui, m)?,
                 ("config", Some(m)) => sub_pkg_config(m)?,
                 ("dependencies", Some(m)) => sub_pkg_dependencies(m)?,
+                ("diff", Some(m)) => sub_pkg_diff(ui, m)?,
                 ("env", Some(m)) => sub_pkg_env(m)?,
                 ("exec", Some(m)) => sub_pkg_exec(m, &remaining_args)?,
                 ("export", Some(m)) => sub_pkg_export(ui, m)?,
+                ("gc", Some(m)) => sub_pkg_gc(ui, m)?,
                 ("hash", Some(m)) => sub_pkg_hash(m)?,
                 ("install", Some(m)) => sub_pkg_install(ui, m, feature_flags)?,
                 ("list", Some(m)) => sub_pkg_list(m)?,
                 ("path", Some(m)) => sub_pkg_path(m)?,
                 ("provides", Some(m)) => sub_pkg_provides(m)?,
+                ("sbom", Some(m)) => sub_pkg_sbom(m)?,
                 ("search", Some(m)) => sub_pkg_search(m)?,
                 ("sign", Some(m)) => sub_pkg_sign(ui, m)?,
                 ("uninstall", Some(m)) => sub_pkg_uninstall(ui, m)?,
@@ -290,6 +306,10 @@ fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
                 ("start", Some(m)) => sub_svc_start(m)?,
                 ("stop", Some(m)) => sub_svc_stop(m)?,
                 ("status", Some(m)) => sub_svc_status(m)?,
+                ("hooks", Some(m)) => sub_svc_hooks(m)?,
+                ("update", Some(m)) => sub_svc_update(m)?,
+                ("hold", Some(m)) => sub_svc_hold(m)?,
+                ("release", Some(m)) => sub_svc_release(m)?,
                 _ => unreachable!(),
             }
         }
@@ -515,6 +535,13 @@ fn sub_pkg_binds(m: &ArgMatches<'_>) -> Result<()> {
     Ok(())
 }
 
+fn sub_pkg_diff(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
+    let a = m.value_of("A").unwrap(); // Required via clap
+    let b = m.value_of("B").unwrap(); // Required via clap
+
+    command::pkg::diff::start(ui, a, b, &*FS_ROOT, m.is_present("TO_JSON"))
+}
+
 fn sub_pkg_dependencies(m: &ArgMatches<'_>) -> Result<()> {
     let ident = PackageIdent::from_str(m.value_of("PKG_IDENT").unwrap())?;
     let scope = if m.is_present("TRANSITIVE") {
@@ -596,6 +623,23 @@ fn sub_pkg_uninstall(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
                                    &excludes,
                                    &services)
 }
+
+fn sub_pkg_gc(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
+    let mode = if m.is_present("DRYRUN") {
+        gc::GcMode::DryRun
+    } else {
+        gc::GcMode::Run
+    };
+    let mut protected = excludes_from_matches(&m);
+    protected.extend(supervisor_services()?);
+    protected.extend(supervisor_specs(&*FS_ROOT)?);
+    let policy = gc::GcPolicy { keep_releases: value_t!(m, "KEEP", usize).unwrap(),
+                                protected };
+
+    gc::start(ui, &*FS_ROOT, &policy, mode)?;
+    Ok(())
+}
+
 fn sub_bldr_channel_create(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
     let url = bldr_url_from_matches(&m)?;
     let origin = origin_param_or_env(&m)?;
@@ -686,26 +730,36 @@ fn sub_plan_init(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
 fn sub_plan_render(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
     let template_path = Path::new(m.value_of("TEMPLATE_PATH").unwrap());
 
-    let default_toml_path = Path::new(m.value_of("DEFAULT_TOML").unwrap());
+    // A plan's own default.toml is used unless another one is given explicitly
+    let default_toml_path = if template_path.is_dir() && m.occurrences_of("DEFAULT_TOML") == 0 {
+        template_path.join("default.toml")
+    } else {
+        PathBuf::from(m.value_of("DEFAULT_TOML").unwrap())
+    };
 
     let user_toml_path = m.value_of("USER_TOML").map(Path::new);
 
     let mock_data_path = m.value_of("MOCK_DATA").map(Path::new);
 
+    let context_path = m.value_of("CONTEXT").map(Path::new);
+
     let print = m.is_present("PRINT");
     let render = !m.is_present("NO_RENDER");
+    let lint = m.is_present("LINT");
     let quiet = m.is_present("QUIET");
 
     let render_dir = Path::new(m.value_of("RENDER_DIR").unwrap());
 
     command::plan::render::start(ui,
                                  template_path,
-                                 default_toml_path,
+                                 &default_toml_path,
                                  user_toml_path,
                                  mock_data_path,
+                                 context_path,
                                  print,
                                  render,
                                  render_dir,
+                                 lint,
                                  quiet)
 }
 
@@ -784,6 +838,16 @@ fn sub_pkg_provides(m: &ArgMatches<'_>) -> Result<()> {
     command::pkg::provides::start(&filename, &*FS_ROOT, full_releases, full_paths)
 }
 
+fn sub_pkg_sbom(m: &ArgMatches<'_>) -> Result<()> {
+    let src = m.value_of("PKG_IDENT_OR_ARTIFACT").unwrap(); // Required via clap
+    let format = match m.value_of("FORMAT") {
+        Some("spdx") => command::pkg::sbom::SbomFormat::Spdx,
+        _ => command::pkg::sbom::SbomFormat::CycloneDx,
+    };
+
+    command::pkg::sbom::start(src, format, &*FS_ROOT)
+}
+
 fn sub_pkg_search(m: &ArgMatches<'_>) -> Result<()> {
     let url = bldr_url_from_matches(&m)?;
     let search_term = m.value_of("SEARCH_TERM").expect("required opt SEARCH_TERM");
@@ -801,13 +865,14 @@ fn sub_pkg_search(m: &ArgMatches<'_>) -> Result<()> {
 fn sub_pkg_sign(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
     let src = Path::new(m.value_of("SOURCE").unwrap()); // Required via clap
     let dst = Path::new(m.value_of("DEST").unwrap()); // Required via clap
+    let compression = m.value_of("COMPRESSION").unwrap_or("xz").parse()?;
     let cache_key_path = cache_key_path_from_matches(&m);
     init();
     let pair = SigKeyPair::get_latest_pair_for(&origin_param_or_env(&m)?,
                                                &cache_key_path,
                                                Some(&PairType::Secret))?;
 
-    command::pkg::sign::start(ui, &pair, &src, &dst)
+    command::pkg::sign::start(ui, &pair, &src, &dst, compression)
 }
 
 fn sub_pkg_upload(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
@@ -909,13 +974,19 @@ fn sub_svc_set(m: &ArgMatches<'_>) -> Result<()> {
     let mut validate = sup_proto::ctl::SvcValidateCfg::default();
     validate.service_group = Some(service_group.clone().into());
     let mut buf = Vec::with_capacity(sup_proto::butterfly::MAX_SVC_CFG_SIZE);
-    let cfg_len = match m.value_of("FILE") {
+    let mut cfg_len = match m.value_of("FILE") {
         Some("-") | None => io::stdin().read_to_end(&mut buf)?,
         Some(f) => {
             let mut file = File::open(f)?;
             file.read_to_end(&mut buf)?
         }
     };
+    if m.is_present("PATCH") {
+        // A patch travels as a TOML layer holding nothing but its operations
+        let patch = parse_patch(&String::from_utf8_lossy(&buf))?;
+        buf = toml::to_string(&patch_layer(&patch)?)?.into_bytes();
+        cfg_len = buf.len();
+    }
     if cfg_len > sup_proto::butterfly::MAX_SVC_CFG_SIZE {
         ui.fatal(format!("Configuration too large. Maximum size allowed is {} bytes.",
                          sup_proto::butterfly::MAX_SVC_CFG_SIZE))?;
@@ -997,12 +1068,14 @@ fn sub_svc_set(m: &ArgMatches<'_>) -> Result<()> {
 }
 
 fn sub_svc_config(m: &ArgMatches<'_>) -> Result<()> {
-    let ident = PackageIdent::from_str(m.value_of("PKG_IDENT").unwrap())?;
+    let (ident, instance) = service_ident_from_str(m.value_of("PKG_IDENT").unwrap())?;
     let cfg = config::load()?;
     let listen_ctl_addr = listen_ctl_addr_from_input(m)?;
     let secret_key = ctl_secret_key(&cfg)?;
     let mut msg = sup_proto::ctl::SvcGetDefaultCfg::default();
     msg.ident = Some(ident.into());
+    msg.instance = instance;
+    msg.explain = Some(m.is_present("EXPLAIN"));
     SrvClient::connect(&listen_ctl_addr, &secret_key).and_then(|conn| {
                                                          conn.call(msg).for_each(|reply| {
                           match reply.message_id() {
@@ -1010,7 +1083,56 @@ fn sub_svc_config(m: &ArgMatches<'_>) -> Result<()> {
                     let m = reply
                         .parse::<sup_proto::types::ServiceCfg>()
                         .map_err(SrvClientError::Decode)?;
-                    println!("{}", m.default.unwrap_or_default());
+                    match m.explanation {
+                        Some(explanation) => {
+                            let explanation: CfgExplanation = serde_json::from_str(&explanation)
+                                .map_err(|e| SrvClientError::from(io::Error::new(
+                                    io::ErrorKind::InvalidData,
+                                    e,
+                                )))?;
+                            print!("{}", explanation);
+                        }
+                        None => println!("{}", m.default.unwrap_or_default()),
+                    }
+                    Ok(())
+                }
+                "NetErr" => {
+                    let m = reply
+                        .parse::<sup_proto::net::NetErr>()
+                        .map_err(SrvClientError::Decode)?;
+                    Err(SrvClientError::from(m))
+                }
+                _ => Err(SrvClientError::from(io::Error::from(
+                    io::ErrorKind::UnexpectedEof,
+                ))),
+            }
+                      })
+                                                     })
+                                                     .wait()?;
+    Ok(())
+}
+
+fn sub_svc_hooks(m: &ArgMatches<'_>) -> Result<()> {
+    let (ident, instance) = service_ident_from_str(m.value_of("PKG_IDENT").unwrap())?;
+    let cfg = config::load()?;
+    let listen_ctl_addr = listen_ctl_addr_from_input(m)?;
+    let secret_key = ctl_secret_key(&cfg)?;
+    let msg = sup_proto::ctl::SvcHookHistory { ident: Some(ident.into()),
+                                               instance };
+    SrvClient::connect(&listen_ctl_addr, &secret_key).and_then(|conn| {
+                                                         conn.call(msg).for_each(|reply| {
+                          match reply.message_id() {
+                "HookHistory" => {
+                    let m = reply
+                        .parse::<sup_proto::types::HookHistory>()
+                        .map_err(SrvClientError::Decode)?;
+                    let histories: BTreeMap<String, Vec<HookRun>> =
+                        serde_json::from_str(&m.history.unwrap_or_default())
+                            .map_err(|e| SrvClientError::from(io::Error::new(
+                                io::ErrorKind::InvalidData,
+                                e,
+                            )))?;
+                    print_hook_history(&histories)?;
                     Ok(())
                 }
                 "NetErr" => {
@@ -1034,8 +1156,9 @@ fn sub_svc_load(m: &ArgMatches<'_>) -> Result<()> {
     let listen_ctl_addr = listen_ctl_addr_from_input(m)?;
     let secret_key = ctl_secret_key(&cfg)?;
     let mut msg = svc_load_from_input(m)?;
-    let ident: PackageIdent = m.value_of("PKG_IDENT").unwrap().parse()?;
+    let (ident, instance) = service_ident_from_str(m.value_of("PKG_IDENT").unwrap())?;
     msg.ident = Some(ident.into());
+    msg.instance = instance;
     SrvClient::connect(&listen_ctl_addr, &secret_key).and_then(|conn| {
                                                          conn.call(msg)
                                                              .for_each(|m| handle_ctl_reply(&m))
@@ -1045,7 +1168,7 @@ fn sub_svc_load(m: &ArgMatches<'_>) -> Result<()> {
 }
 
 fn sub_svc_unload(m: &ArgMatches<'_>) -> Result<()> {
-    let ident = PackageIdent::from_str(m.value_of("PKG_IDENT").unwrap())?;
+    let (ident, instance) = service_ident_from_str(m.value_of("PKG_IDENT").unwrap())?;
     let cfg = config::load()?;
     let listen_ctl_addr = listen_ctl_addr_from_input(m)?;
     let secret_key = ctl_secret_key(&cfg)?;
@@ -1053,7 +1176,8 @@ fn sub_svc_unload(m: &ArgMatches<'_>) -> Result<()> {
         parse_optional_arg::<ShutdownTimeout>("SHUTDOWN_TIMEOUT", m).map(u32::from);
 
     let msg = sup_proto::ctl::SvcUnload { ident: Some(ident.into()),
-                                          timeout_in_seconds };
+                                          timeout_in_seconds,
+                                          instance };
     SrvClient::connect(&listen_ctl_addr, &secret_key).and_then(|conn| {
                                                          conn.call(msg)
                                                              .for_each(|m| handle_ctl_reply(&m))
@@ -1063,12 +1187,13 @@ fn sub_svc_unload(m: &ArgMatches<'_>) -> Result<()> {
 }
 
 fn sub_svc_start(m: &ArgMatches<'_>) -> Result<()> {
-    let ident = PackageIdent::from_str(m.value_of("PKG_IDENT").unwrap())?;
+    let (ident, instance) = service_ident_from_str(m.value_of("PKG_IDENT").unwrap())?;
     let cfg = config::load()?;
     let listen_ctl_addr = listen_ctl_addr_from_input(m)?;
     let secret_key = ctl_secret_key(&cfg)?;
     let mut msg = sup_proto::ctl::SvcStart::default();
     msg.ident = Some(ident.into());
+    msg.instance = instance;
     SrvClient::connect(&listen_ctl_addr, &secret_key).and_then(|conn| {
                                                          conn.call(msg)
                                                              .for_each(|m| handle_ctl_reply(&m))
@@ -1081,9 +1206,12 @@ fn sub_svc_status(m: &ArgMatches<'_>) -> Result<()> {
     let cfg = config::load()?;
     let listen_ctl_addr = listen_ctl_addr_from_input(m)?;
     let secret_key = ctl_secret_key(&cfg)?;
+    let show_updates = m.is_present("UPDATES");
     let mut msg = sup_proto::ctl::SvcStatus::default();
     if let Some(pkg) = m.value_of("PKG_IDENT") {
-        msg.ident = Some(PackageIdent::from_str(pkg)?.into());
+        let (ident, instance) = service_ident_from_str(pkg)?;
+        msg.ident = Some(ident.into());
+        msg.instance = instance;
     }
 
     SrvClient::connect(&listen_ctl_addr, &secret_key).and_then(|conn| {
@@ -1098,13 +1226,13 @@ fn sub_svc_status(m: &ArgMatches<'_>) -> Result<()> {
                                 io::ErrorKind::UnexpectedEof,
                             )));
                         }
-                        Some(m) => print_svc_status(&mut out, &m, true)?,
+                        Some(m) => print_svc_status(&mut out, &m, true, show_updates)?,
                     }
                     Ok((out, rest))
                 })
                 .and_then(|(out, rest)| {
                     rest.fold(out, move |mut out, reply| {
-                        print_svc_status(&mut out, &reply, false)?;
+                        print_svc_status(&mut out, &reply, false, show_updates)?;
                         Ok::<_, SrvClientError>(out)
                     })
                 })
@@ -1118,7 +1246,7 @@ fn sub_svc_status(m: &ArgMatches<'_>) -> Result<()> {
 }
 
 fn sub_svc_stop(m: &ArgMatches<'_>) -> Result<()> {
-    let ident = PackageIdent::from_str(m.value_of("PKG_IDENT").unwrap())?;
+    let (ident, instance) = service_ident_from_str(m.value_of("PKG_IDENT").unwrap())?;
     let cfg = config::load()?;
     let listen_ctl_addr = listen_ctl_addr_from_input(m)?;
     let secret_key = ctl_secret_key(&cfg)?;
@@ -1126,7 +1254,53 @@ fn sub_svc_stop(m: &ArgMatches<'_>) -> Result<()> {
         parse_optional_arg::<ShutdownTimeout>("SHUTDOWN_TIMEOUT", m).map(u32::from);
 
     let msg = sup_proto::ctl::SvcStop { ident: Some(ident.into()),
-                                        timeout_in_seconds };
+                                        timeout_in_seconds,
+                                        instance };
+    SrvClient::connect(&listen_ctl_addr, &secret_key).and_then(|conn| {
+                                                         conn.call(msg)
+                                                             .for_each(|m| handle_ctl_reply(&m))
+                                                     })
+                                                     .wait()?;
+    Ok(())
+}
+
+fn sub_svc_update(m: &ArgMatches<'_>) -> Result<()> {
+    let (ident, instance) = service_ident_from_str(m.value_of("PKG_IDENT").unwrap())?;
+    let cfg = config::load()?;
+    let listen_ctl_addr = listen_ctl_addr_from_input(m)?;
+    let secret_key = ctl_secret_key(&cfg)?;
+    let msg = sup_proto::ctl::SvcUpdate { ident: Some(ident.into()),
+                                          instance };
+    SrvClient::connect(&listen_ctl_addr, &secret_key).and_then(|conn| {
+                                                         conn.call(msg)
+                                                             .for_each(|m| handle_ctl_reply(&m))
+                                                     })
+                                                     .wait()?;
+    Ok(())
+}
+
+fn sub_svc_hold(m: &ArgMatches<'_>) -> Result<()> {
+    let (ident, instance) = service_ident_from_str(m.value_of("PKG_IDENT").unwrap())?;
+    let cfg = config::load()?;
+    let listen_ctl_addr = listen_ctl_addr_from_input(m)?;
+    let secret_key = ctl_secret_key(&cfg)?;
+    let msg = sup_proto::ctl::SvcHold { ident: Some(ident.into()),
+                                        instance };
+    SrvClient::connect(&listen_ctl_addr, &secret_key).and_then(|conn| {
+                                                         conn.call(msg)
+                                                             .for_each(|m| handle_ctl_reply(&m))
+                                                     })
+                                                     .wait()?;
+    Ok(())
+}
+
+fn sub_svc_release(m: &ArgMatches<'_>) -> Result<()> {
+    let (ident, instance) = service_ident_from_str(m.value_of("PKG_IDENT").unwrap())?;
+    let cfg = config::load()?;
+    let listen_ctl_addr = listen_ctl_addr_from_input(m)?;
+    let secret_key = ctl_secret_key(&cfg)?;
+    let msg = sup_proto::ctl::SvcRelease { ident: Some(ident.into()),
+                                           instance };
     SrvClient::connect(&listen_ctl_addr, &secret_key).and_then(|conn| {
                                                          conn.call(msg)
                                                              .for_each(|m| handle_ctl_reply(&m))
@@ -1561,9 +1735,12 @@ fn handle_ctl_reply(reply: &SrvMessage) -> result::Result<(), SrvClientError> {
     Ok(())
 }
 
+/// Prints the status of a service. The pending update and whether the service is held are only
+/// printed with `show_updates`, after the columns scripts may depend on.
 fn print_svc_status<T>(out: &mut T,
                        reply: &SrvMessage,
-                       print_header: bool)
+                       print_header: bool,
+                       show_updates: bool)
                        -> result::Result<(), SrvClientError>
     where T: io::Write
 {
@@ -1602,8 +1779,19 @@ fn print_svc_status<T>(out: &mut T,
         }
     };
     if print_header {
-        writeln!(out, "{}", STATUS_HEADER.join("\t")).unwrap();
+        if show_updates {
+            writeln!(out,
+                     "{}\t{}",
+                     STATUS_HEADER.join("\t"),
+                     STATUS_UPDATES_HEADER.join("\t")).unwrap();
+        } else {
+            writeln!(out, "{}", STATUS_HEADER.join("\t")).unwrap();
+        }
     }
+    let service = match status.instance {
+        Some(ref instance) => format!("{}@{}", status.ident, instance),
+        None => status.ident.to_string(),
+    };
     // Composites were removed in 0.75 but people could be
     // depending on the exact format of this output even if they
     // never used composites. We don't want to break their tooling
@@ -1612,17 +1800,50 @@ fn print_svc_status<T>(out: &mut T,
     //
     // TODO: Remove this when we have a stable machine-readable alternative
     // that scripts could depend on
-    writeln!(out,
-             "{}\tstandalone\t{}\t{}\t{}\t{}\t{}",
-             status.ident,
-             DesiredState::from_str(&svc_desired_state)?,
-             ProcessState::from_str(&svc_state)?,
-             svc_elapsed,
-             svc_pid,
-             status.service_group,)?;
+    write!(out,
+           "{}\tstandalone\t{}\t{}\t{}\t{}\t{}",
+           service,
+           DesiredState::from_str(&svc_desired_state)?,
+           ProcessState::from_str(&svc_state)?,
+           svc_elapsed,
+           svc_pid,
+           status.service_group)?;
+    if show_updates {
+        let pending_update = status.pending_update
+                                   .map_or("<none>".to_string(), |ident| ident.to_string());
+        write!(out,
+               "\t{}\t{}",
+               pending_update,
+               status.held_release.is_some())?;
+    }
+    writeln!(out)?;
     Ok(())
 }
 
+/// Prints the runs of each hook, the latest run first.
+fn print_hook_history(histories: &BTreeMap<String, Vec<HookRun>>) -> io::Result<()> {
+    if histories.is_empty() {
+        println!("No hooks have run yet.");
+        return Ok(());
+    }
+    let mut out = TabWriter::new(io::stdout());
+    writeln!(out, "{}", HOOK_HISTORY_HEADER.join("\t"))?;
+    for (hook, runs) in histories {
+        for run in runs.iter().rev() {
+            writeln!(out,
+                     "{}\t{}\t{}\t{}\t{}\t{}",
+                     hook,
+                     run.run_id,
+                     run.started_at,
+                     run.duration_ms,
+                     run.exit_code
+                        .map_or_else(|| "<none>".to_string(), |c| c.to_string()),
+                     run.timed_out)?;
+        }
+    }
+    out.flush()
+}
+
 /// Check if we have a launcher/supervisor running out of this habitat root.
 /// If the launcher PID file exists then the supervisor is up and running
 fn launcher_is_running(fs_root_path: &Path) -> bool {
@@ -1650,7 +1871,10 @@ fn supervisor_services() -> Result<Vec<PackageIdent>> {
                                   let m =
                                       reply.parse::<sup_proto::types::ServiceStatus>()
                                            .map_err(SrvClientError::Decode)?;
-                                  out.push(m.ident.into());
+                                  let ident = PackageIdent::try_from(m.ident).map_err(|e| {
+                                      sup_proto::net::err(sup_proto::net::ErrCode::BadPayload, e)
+                                  })?;
+                                  out.push(ident);
                                   Ok(())
                               }
                               "NetOk" => Ok(()),
@@ -1670,6 +1894,47 @@ fn supervisor_services() -> Result<Vec<PackageIdent>> {
     Ok(out)
 }
 
+/// The idents of all the service specs loaded into the Supervisor running out of the habitat root
+/// under `fs_root`, whether or not their services are currently running, along with the releases
+/// those services are held at and the releases the Supervisor may roll them back to.
+fn supervisor_specs(fs_root: &Path) -> Result<Vec<PackageIdent>> {
+    // The Supervisor's default state path, relative to `fs_root` rather than to the root its own
+    // `sup_root` would use
+    let specs_path = fs_root.join("hab/sup/default/specs");
+    if !specs_path.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let mut out = vec![];
+    for entry in std::fs::read_dir(&specs_path)? {
+        let path = entry?.path();
+        if path.extension().and_then(OsStr::to_str) != Some("spec") {
+            continue;
+        }
+        let mut contents = String::new();
+        File::open(&path)?.read_to_string(&mut contents)?;
+        let spec = toml::from_str::<toml::Value>(&contents)?;
+        match spec.get("ident")
+                  .and_then(toml::Value::as_str)
+                  .map(PackageIdent::from_str)
+        {
+            Some(Ok(ident)) => out.push(ident),
+            _ => {
+                warn!("Skipping service spec without a valid ident, {}", path.display());
+                continue;
+            }
+        }
+        let releases = [spec.get("held_release"),
+                        spec.get("pinned_release"),
+                        spec.get("update_probation")
+                            .and_then(|probation| probation.get("from"))];
+        out.extend(releases.iter()
+                           .filter_map(|release| release.and_then(toml::Value::as_str))
+                           .filter_map(|release| PackageIdent::from_str(release).ok()));
+    }
+    Ok(out)
+}
+
 /// A Builder URL, but *only* if the user specified it via CLI args or
 /// the environment
 fn bldr_url_from_input(m: &ArgMatches<'_>) -> Option<String> {
@@ -1720,6 +1985,26 @@ fn get_health_check_interval_from_input(m: &ArgMatches<'_>)
      .map(HealthCheckInterval::into)
 }
 
+fn get_start_after_from_input(m: &ArgMatches<'_>) -> Result<Option<PackageIdentList>> {
+    match m.values_of("START_AFTER") {
+        Some(idents) => {
+            let mut list = PackageIdentList::default();
+            for ident in idents {
+                list.idents.push(PackageIdent::from_str(ident)?.into());
+            }
+            Ok(Some(list))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Splits a service as given on the command line, e.g. `core/redis@cache`, into its package
+/// identifier and its instance name, if it has one.
+fn service_ident_from_str(service: &str) -> Result<(PackageIdent, Option<String>)> {
+    let (ident, instance) = sup_proto::ctl::split_instance_name(service);
+    Ok((PackageIdent::from_str(ident)?, instance.map(str::to_string)))
+}
+
 #[cfg(target_os = "windows")]
 fn get_password_from_input(m: &ArgMatches) -> Result<Option<String>> {
     if let Some(password) = m.value_of("PASSWORD") {
@@ -1814,8 +2099,19 @@ fn svc_load_from_input(m: &ArgMatches) -> Result<sup_proto::ctl::SvcLoad> {
     msg.svc_encrypted_password = get_password_from_input(m)?;
     msg.health_check_interval = get_health_check_interval_from_input(m);
     msg.binding_mode = get_binding_mode_from_input(m).map(|v| v as i32);
+    if m.is_present("BIND_HEALTHY_ONLY") {
+        msg.bind_healthy_only = Some(true);
+    }
+    msg.start_after = get_start_after_from_input(m)?;
+    if m.is_present("REQUIRES_HEALTHY") {
+        msg.requires_healthy = Some(true);
+    }
     msg.topology = get_topology_from_input(m).map(|v| v as i32);
     msg.update_strategy = get_strategy_from_input(m).map(|v| v as i32);
+    msg.update_canaries = m.value_of("UPDATE_CANARIES").map(str::to_string);
+    msg.update_bake_period = parse_optional_arg::<u32>("UPDATE_BAKE_PERIOD", m);
+    msg.update_rollback_window = parse_optional_arg::<u32>("UPDATE_ROLLBACK_WINDOW", m);
+    msg.update_window = m.value_of("UPDATE_WINDOW").map(str::to_string);
     msg.shutdown_timeout =
         parse_optional_arg::<ShutdownTimeout>("SHUTDOWN_TIMEOUT", m).map(u32::from);
     Ok(msg)
@@ -1991,4 +2287,37 @@ mod test {
             assert_eq!(expected, actual);
         }
     }
+
+    mod supervisor_specs {
+        use super::*;
+        use std::fs;
+        use tempfile::TempDir;
+
+        #[test]
+        fn held_and_rollback_releases_are_protected() {
+            let fs_root = TempDir::new().unwrap();
+            let specs_path = fs_root.path().join("hab/sup/default/specs");
+            fs::create_dir_all(&specs_path).unwrap();
+            fs::write(specs_path.join("redis.spec"),
+                      r#"
+                ident = "core/redis"
+                held_release = "core/redis/4.0.14/20190319155852"
+                pinned_release = "core/redis/4.0.10/20180801003001"
+
+                [update_probation]
+                from = "core/redis/4.0.9/20180701003001"
+                to = "core/redis/4.0.14/20190319155852"
+                started_at = 1556000000
+                "#).unwrap();
+
+            let idents = supervisor_specs(fs_root.path()).unwrap();
+
+            let expected = ["core/redis",
+                            "core/redis/4.0.14/20190319155852",
+                            "core/redis/4.0.10/20180801003001",
+                            "core/redis/4.0.9/20180701003001"];
+            assert_eq!(idents.iter().map(ToString::to_string).collect::<Vec<_>>(),
+                       expected);
+        }
+    }
 }
@@ -1,7 +1,9 @@
 use crate::error::{Error,
                    Result};
 use habitat_common::types::UserInfo;
-use habitat_core::os::process::Pid;
+use habitat_core::os::{process::{Pid,
+                                 ResourceLimits},
+                       sandbox::Sandbox};
 use habitat_launcher_protocol::{self as protocol,
                                 Error as ProtocolError};
 use ipc_channel::ipc::{IpcOneShotServer,
@@ -120,6 +122,13 @@ impl LauncherCli {
     /// `username` and `groupname` are string names, while `uid` and
     /// `gid` are numeric IDs. Newer versions of the Launcher can
     /// accept either, but prefer numeric IDs.
+    ///
+    /// Older Launchers, and Launchers on hosts without cgroups v2, run the service without its
+    /// resource limits. Sandboxes are never silently dropped though: Launchers on platforms
+    /// other than Linux refuse to run a service with a sandbox, and a service an older Launcher
+    /// started without its sandbox, which it doesn't acknowledge, is terminated again and an
+    /// error returned.
+    #[allow(clippy::too_many_arguments)]
     pub fn spawn(&self,
                  id: &str,
                  bin: &Path,
@@ -128,7 +137,9 @@ impl LauncherCli {
                             groupname,
                             gid, }: UserInfo,
                  password: Option<&str>,
-                 env: Env)
+                 env: Env,
+                 resource_limits: &ResourceLimits,
+                 sandbox: &Sandbox)
                  -> Result<Pid> {
         // On Windows, we only expect user to be Some.
         //
@@ -143,10 +154,20 @@ impl LauncherCli {
                                     svc_group_id: gid,
                                     svc_password: password.map(str::to_string),
                                     env,
-                                    id: id.to_string() };
+                                    id: id.to_string(),
+                                    resource_limits: resource_limits_proto(resource_limits),
+                                    sandbox: sandbox_proto(sandbox) };
+        let sandboxed = msg.sandbox.is_some();
 
         Self::send(&self.tx, &msg)?;
         let reply = Self::recv::<protocol::SpawnOk>(&self.rx)?;
+        if sandboxed && !reply.sandboxed {
+            let pid = reply.pid as Pid;
+            if let Err(err) = self.terminate(pid) {
+                warn!("Unable to terminate unsandboxed process {}, {}", pid, err);
+            }
+            return Err(Error::SandboxNotApplied);
+        }
         Ok(reply.pid as Pid)
     }
 
@@ -157,3 +178,27 @@ impl LauncherCli {
         Ok(reply.exit_code)
     }
 }
+
+fn resource_limits_proto(limits: &ResourceLimits) -> Option<protocol::ResourceLimits> {
+    if limits.is_empty() {
+        return None;
+    }
+    Some(protocol::ResourceLimits { memory_max: limits.memory_max,
+                                    cpu_weight: limits.cpu_weight,
+                                    cpu_quota:  limits.cpu_quota,
+                                    pids_max:   limits.pids_max,
+                                    io_weight:  limits.io_weight, })
+}
+
+fn sandbox_proto(sandbox: &Sandbox) -> Option<protocol::Sandbox> {
+    if sandbox.is_empty() {
+        return None;
+    }
+    Some(protocol::Sandbox { private_tmp:        sandbox.private_tmp,
+                             read_only_pkgs:     sandbox.read_only_pkgs,
+                             no_new_privs:       sandbox.no_new_privs,
+                             limit_capabilities: Some(sandbox.capabilities.is_some()),
+                             capabilities:       sandbox.capabilities.clone().unwrap_or_default(),
+                             seccomp_profile:    sandbox.seccomp_profile.clone(),
+                             private_network:    sandbox.private_network, })
+}
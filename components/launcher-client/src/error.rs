@@ -13,6 +13,7 @@ pub enum Error {
     IPCBincode(String),
     IPCIO(io::ErrorKind),
     Protocol(protocol::Error),
+    SandboxNotApplied,
     Send(ipc_channel::Error),
 }
 
@@ -29,6 +30,12 @@ impl fmt::Display for Error {
             }
             Error::IPCIO(ref e) => format!("Unable to receive message from Launcher, {:?}", e),
             Error::Protocol(ref e) => format!("{}", e),
+            Error::SandboxNotApplied => {
+                String::from("The Launcher doesn't support sandboxes and started the service \
+                              without its sandbox, so it was stopped again. Stop the \
+                              Supervisor with `hab sup term` and start it again to run a \
+                              newer Launcher.")
+            }
             Error::Send(ref e) => format!("Unable to send to Launcher's pipe, {}", e),
         };
         write!(f, "{}", msg)
@@ -44,6 +51,7 @@ impl error::Error for Error {
             Error::IPCBincode(_) => "Unable to encode/decode message framing to/from Launcher",
             Error::IPCIO(_) => "Unable to receive message from Launcher",
             Error::Protocol(_) => "Received an error from Launcher",
+            Error::SandboxNotApplied => "The Launcher started the service without its sandbox",
             Error::Send(_) => "Unable to send to Launcher's pipe",
         }
     }
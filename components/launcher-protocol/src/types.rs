@@ -27,6 +27,8 @@ pub trait LauncherMessage
 }
 
 pub use generated::{ErrCode,
+                    ResourceLimits,
+                    Sandbox,
                     ShutdownMethod};
 
 // Now we're going to define our own set of structs to use internally, as well as conversion
@@ -124,14 +126,16 @@ impl From<Restart> for generated::Restart {
 
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct Spawn {
-    pub id:           String,
-    pub binary:       String,
-    pub svc_user:     Option<String>,
-    pub svc_group:    Option<String>,
-    pub svc_password: Option<String>,
-    pub env:          HashMap<String, String>,
-    pub svc_user_id:  Option<u32>,
-    pub svc_group_id: Option<u32>,
+    pub id:              String,
+    pub binary:          String,
+    pub svc_user:        Option<String>,
+    pub svc_group:       Option<String>,
+    pub svc_password:    Option<String>,
+    pub env:             HashMap<String, String>,
+    pub svc_user_id:     Option<u32>,
+    pub svc_group_id:    Option<u32>,
+    pub resource_limits: Option<ResourceLimits>,
+    pub sandbox:         Option<Sandbox>,
 }
 
 impl LauncherMessage for Spawn {
@@ -140,33 +144,39 @@ impl LauncherMessage for Spawn {
     const MESSAGE_ID: &'static str = "Spawn";
 
     fn from_proto(proto: generated::Spawn) -> Result<Self> {
-        Ok(Spawn { id:           proto.id.ok_or(Error::ProtocolMismatch("id"))?,
-                   binary:       proto.binary.ok_or(Error::ProtocolMismatch("binary"))?,
-                   svc_user:     proto.svc_user,
-                   svc_group:    proto.svc_group,
-                   svc_password: proto.svc_password,
-                   env:          proto.env,
-                   svc_user_id:  proto.svc_user_id,
-                   svc_group_id: proto.svc_group_id, })
+        Ok(Spawn { id:              proto.id.ok_or(Error::ProtocolMismatch("id"))?,
+                   binary:          proto.binary.ok_or(Error::ProtocolMismatch("binary"))?,
+                   svc_user:        proto.svc_user,
+                   svc_group:       proto.svc_group,
+                   svc_password:    proto.svc_password,
+                   env:             proto.env,
+                   svc_user_id:     proto.svc_user_id,
+                   svc_group_id:    proto.svc_group_id,
+                   resource_limits: proto.resource_limits,
+                   sandbox:         proto.sandbox, })
     }
 }
 
 impl From<Spawn> for generated::Spawn {
     fn from(value: Spawn) -> Self {
-        generated::Spawn { id:           Some(value.id),
-                           binary:       Some(value.binary),
-                           svc_user:     value.svc_user,
-                           svc_group:    value.svc_group,
-                           svc_password: value.svc_password,
-                           env:          value.env,
-                           svc_user_id:  value.svc_user_id,
-                           svc_group_id: value.svc_group_id, }
+        generated::Spawn { id:              Some(value.id),
+                           binary:          Some(value.binary),
+                           svc_user:        value.svc_user,
+                           svc_group:       value.svc_group,
+                           svc_password:    value.svc_password,
+                           env:             value.env,
+                           svc_user_id:     value.svc_user_id,
+                           svc_group_id:    value.svc_group_id,
+                           resource_limits: value.resource_limits,
+                           sandbox:         value.sandbox, }
     }
 }
 
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct SpawnOk {
-    pub pid: i64,
+    pub pid:       i64,
+    /// Whether the service runs in its sandbox, which older Launchers don't report.
+    pub sandboxed: bool,
 }
 
 impl LauncherMessage for SpawnOk {
@@ -175,12 +185,16 @@ impl LauncherMessage for SpawnOk {
     const MESSAGE_ID: &'static str = "SpawnOk";
 
     fn from_proto(proto: generated::SpawnOk) -> Result<Self> {
-        Ok(SpawnOk { pid: proto.pid.ok_or(Error::ProtocolMismatch("pid"))?, })
+        Ok(SpawnOk { pid:       proto.pid.ok_or(Error::ProtocolMismatch("pid"))?,
+                     sandboxed: proto.sandboxed.unwrap_or(false), })
     }
 }
 
 impl From<SpawnOk> for generated::SpawnOk {
-    fn from(value: SpawnOk) -> Self { generated::SpawnOk { pid: Some(value.pid), } }
+    fn from(value: SpawnOk) -> Self {
+        generated::SpawnOk { pid:       Some(value.pid),
+                             sandboxed: Some(value.sandboxed), }
+    }
 }
 
 #[derive(Clone, Debug, Default, PartialEq)]
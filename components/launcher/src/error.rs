@@ -16,6 +16,7 @@ pub enum Error {
     GroupNotFound(String),
     OpenPipe(io::Error),
     Protocol(protocol::Error),
+    Sandbox(String),
     Send(ipc_channel::Error),
     Spawn(io::Error),
     SupBinaryVersion,
@@ -39,6 +40,7 @@ impl fmt::Display for Error {
             Error::GroupNotFound(ref e) => format!("No GID for group '{}' could be found", e),
             Error::OpenPipe(ref e) => format!("Unable to open Launcher's comm channel, {}", e),
             Error::Protocol(ref e) => format!("{}", e),
+            Error::Sandbox(ref e) => format!("Unable to sandbox service, {}", e),
             Error::Send(ref e) => format!("Unable to send to Launcher's comm channel, {}", e),
             Error::Spawn(ref e) => format!("Unable to spawn process, {}", e),
             Error::SupBinaryVersion => "Unsupported Supervisor binary version".to_string(),
@@ -65,6 +67,7 @@ impl error::Error for Error {
             Error::ExecWait(_) => "OS Error while waiting on PID",
             Error::OpenPipe(_) => "Unable to open Launcher's pipe",
             Error::Protocol(_) => "Error with the Supervisor protocol",
+            Error::Sandbox(_) => "Unable to sandbox service",
             Error::Send(_) => "Unable to send to Launcher's pipe",
             Error::Spawn(_) => "Unable to spawn process",
             Error::SupBinaryVersion => "Unsupported Supervisor binary version",
@@ -92,6 +92,10 @@ impl Server {
         let mut pid_file = fs::File::create(&pid_file_path)?;
         write!(&mut pid_file, "{}", process::current_pid())?;
 
+        // This must happen before the Supervisor is spawned, see `delegate_cgroups`
+        #[cfg(unix)]
+        crate::service::delegate_cgroups();
+
         let ((rx, tx), supervisor, pipe) = Self::init(&args, false)?;
         Ok(Server { pid_file_path,
                     services: ServiceTable::default(),
@@ -22,10 +22,13 @@ impl Handler for RestartHandler {
         service.kill();
         match service.wait() {
             Ok(_status) => {
-                match service::run(service.take_args()) {
+                let args = service.take_args();
+                let sandboxed = args.sandbox.is_some();
+                match service::run(args) {
                     Ok(new_service) => {
                         let mut reply = protocol::SpawnOk::default();
                         reply.pid = new_service.id().into();
+                        reply.sandboxed = sandboxed;
                         services.insert(new_service);
                         Ok(reply)
                     }
@@ -11,10 +11,13 @@ impl Handler for SpawnHandler {
     type Reply = protocol::SpawnOk;
 
     fn handle(msg: Self::Message, services: &mut ServiceTable) -> HandleResult<Self::Reply> {
+        // Services are only run if their sandbox could be applied
+        let sandboxed = msg.sandbox.is_some();
         match service::run(msg) {
             Ok(service) => {
                 let mut reply = protocol::SpawnOk::default();
                 reply.pid = service.id().into();
+                reply.sandboxed = sandboxed;
                 services.insert(service);
                 Ok(reply)
             }
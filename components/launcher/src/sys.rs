@@ -5,3 +5,7 @@ pub mod service;
 #[cfg(windows)]
 #[path = "sys/windows/service.rs"]
 pub mod service;
+
+#[cfg(target_os = "linux")]
+#[path = "sys/linux/sandbox.rs"]
+pub mod sandbox;
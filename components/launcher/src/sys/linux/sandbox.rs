@@ -0,0 +1,523 @@
+//! Applies a service's sandbox, see `habitat_core::os::sandbox`.
+//!
+//! The sandbox is applied in the service's process between fork and exec, where nothing may be
+//! allocated, so everything it needs is prepared beforehand. Namespaces, mounts and the
+//! capability bounding set all need privileges the service's user doesn't have, so the process
+//! switches to that user itself once they are set up, rather than leaving it to `Command`, which
+//! does so before any `before_exec` closure runs.
+//!
+//! A service limited to some capabilities holds exactly those: the others are dropped from its
+//! bounding, effective, permitted and inheritable sets. Switching to a user other than root
+//! clears every capability, and exec does so again for such users, so the allowed ones are kept
+//! across the switch and raised as ambient capabilities, which survive exec.
+
+use crate::{core::{fs::{pkg_root_path,
+                        FS_ROOT_PATH},
+                   os::sandbox},
+            error::{Error,
+                    Result},
+            protocol};
+use libc::{self,
+           c_char,
+           c_int,
+           c_long,
+           c_short,
+           c_ulong,
+           c_void,
+           gid_t,
+           uid_t};
+use std::{env,
+          ffi::CString,
+          fs,
+          io,
+          mem,
+          os::unix::ffi::OsStrExt,
+          ptr};
+
+const LINUX_CAPABILITY_VERSION_3: u32 = 0x2008_0522;
+const PR_CAP_AMBIENT: c_int = 47;
+const PR_CAP_AMBIENT_RAISE: c_ulong = 2;
+
+const SECCOMP_MODE_FILTER: c_ulong = 2;
+const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
+const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
+/// The offsets of the system call number and the architecture in `struct seccomp_data`.
+const SECCOMP_DATA_NR: u32 = 0;
+const SECCOMP_DATA_ARCH: u32 = 4;
+
+const BPF_LD: u16 = 0x00;
+const BPF_W: u16 = 0x00;
+const BPF_ABS: u16 = 0x20;
+const BPF_JMP: u16 = 0x05;
+const BPF_JEQ: u16 = 0x10;
+const BPF_JGE: u16 = 0x30;
+const BPF_K: u16 = 0x00;
+const BPF_RET: u16 = 0x06;
+
+#[cfg(target_arch = "x86_64")]
+const AUDIT_ARCH: Option<u32> = Some(0xc000_003e);
+#[cfg(target_arch = "aarch64")]
+const AUDIT_ARCH: Option<u32> = Some(0xc000_00b7);
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+const AUDIT_ARCH: Option<u32> = None;
+/// The x32 ABI shares the architecture of x86_64, its system call numbers having this bit set.
+const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+
+/// The system calls denied by the `default` seccomp profile.
+const DEFAULT_DENIED_SYSCALLS: &[c_long] = &[libc::SYS_acct,
+                                             libc::SYS_add_key,
+                                             libc::SYS_adjtimex,
+                                             libc::SYS_bpf,
+                                             libc::SYS_clock_adjtime,
+                                             libc::SYS_clock_settime,
+                                             libc::SYS_delete_module,
+                                             libc::SYS_finit_module,
+                                             libc::SYS_init_module,
+                                             libc::SYS_kexec_file_load,
+                                             libc::SYS_kexec_load,
+                                             libc::SYS_keyctl,
+                                             libc::SYS_lookup_dcookie,
+                                             libc::SYS_mount,
+                                             libc::SYS_open_by_handle_at,
+                                             libc::SYS_perf_event_open,
+                                             libc::SYS_pivot_root,
+                                             libc::SYS_process_vm_readv,
+                                             libc::SYS_process_vm_writev,
+                                             libc::SYS_ptrace,
+                                             libc::SYS_quotactl,
+                                             libc::SYS_reboot,
+                                             libc::SYS_request_key,
+                                             libc::SYS_setns,
+                                             libc::SYS_settimeofday,
+                                             libc::SYS_swapoff,
+                                             libc::SYS_swapon,
+                                             libc::SYS_syslog,
+                                             libc::SYS_umount2,
+                                             libc::SYS_unshare,
+                                             libc::SYS_userfaultfd,
+                                             libc::SYS_vhangup];
+#[cfg(target_arch = "x86_64")]
+const ARCH_DENIED_SYSCALLS: &[c_long] = &[libc::SYS_ioperm, libc::SYS_iopl, libc::SYS_uselib];
+#[cfg(not(target_arch = "x86_64"))]
+const ARCH_DENIED_SYSCALLS: &[c_long] = &[];
+
+const IFNAMSIZ: usize = 16;
+const IFF_UP: c_short = 0x1;
+const SIOCGIFFLAGS: c_ulong = 0x8913;
+const SIOCSIFFLAGS: c_ulong = 0x8914;
+
+/// `struct __user_cap_header_struct`, which selects the process and version of `capset(2)`.
+#[repr(C)]
+struct CapUserHeader {
+    version: u32,
+    pid:     c_int,
+}
+
+/// `struct __user_cap_data_struct`, one half of the capability sets of a process, the first
+/// holding capabilities 0 to 31 and the second 32 to 63.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+struct CapUserData {
+    effective:   u32,
+    permitted:   u32,
+    inheritable: u32,
+}
+
+/// `struct sock_filter`, a BPF instruction.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+struct SockFilter {
+    code: u16,
+    jt:   u8,
+    jf:   u8,
+    k:    u32,
+}
+
+/// `struct sock_fprog`, a BPF program.
+#[repr(C)]
+struct SockFprog {
+    len:    u16,
+    filter: *const SockFilter,
+}
+
+/// `struct ifreq`, as used to get and set the flags of a network interface.
+#[repr(C)]
+struct IfReq {
+    name:     [c_char; IFNAMSIZ],
+    flags:    c_short,
+    _padding: [u8; 22],
+}
+
+/// A service's sandbox, prepared to be applied in its process.
+pub struct Sandbox {
+    private_tmp:          bool,
+    /// The package root to make read-only, if any.
+    read_only_pkgs:       Option<CString>,
+    private_network:      bool,
+    /// The capabilities the service is limited to, if any.
+    capabilities:         Option<Capabilities>,
+    no_new_privs:         bool,
+    seccomp_filter:       Option<Vec<SockFilter>>,
+    uid:                  uid_t,
+    gid:                  gid_t,
+}
+
+/// The capabilities a service is limited to, prepared to be set in its process.
+struct Capabilities {
+    /// The capabilities to drop from the bounding set.
+    dropped: Vec<c_ulong>,
+    /// The effective, permitted and inheritable sets, as passed to `capset(2)`.
+    sets:    [CapUserData; 2],
+    /// The capabilities to raise as ambient capabilities, which only services running as a user
+    /// other than root need.
+    ambient: Vec<c_ulong>,
+}
+
+impl Capabilities {
+    /// Limits a service running as `uid` to the `allowed` capabilities, `last` being the number
+    /// of the last capability the kernel knows.
+    fn new(allowed: &[u32], last: u32, uid: uid_t) -> Self {
+        let mut sets = [CapUserData::default(); 2];
+        for cap in allowed {
+            let set = &mut sets[*cap as usize / 32];
+            let bit = 1 << (cap % 32);
+            set.effective |= bit;
+            set.permitted |= bit;
+            set.inheritable |= bit;
+        }
+        let dropped = (0..=last).filter(|cap| !allowed.contains(cap))
+                                .map(c_ulong::from)
+                                .collect();
+        let ambient = if uid == 0 {
+            Vec::new()
+        } else {
+            allowed.iter().cloned().map(c_ulong::from).collect()
+        };
+        Capabilities { dropped,
+                       sets,
+                       ambient }
+    }
+}
+
+impl Sandbox {
+    /// Prepares the sandbox of a service which runs as the given user and group.
+    pub fn new(options: &protocol::Sandbox, uid: uid_t, gid: gid_t) -> Result<Self> {
+        let read_only_pkgs = if options.read_only_pkgs.unwrap_or(false) {
+            let pkgs = pkg_root_path(Some(&*FS_ROOT_PATH));
+            Some(CString::new(pkgs.as_os_str().as_bytes()).map_err(|e| {
+                                                              Error::Sandbox(e.to_string())
+                                                          })?)
+        } else {
+            None
+        };
+        let capabilities = if options.limit_capabilities.unwrap_or(false) {
+            let mut allowed = Vec::new();
+            for name in options.capabilities.iter() {
+                let cap = sandbox::capability(name).ok_or_else(|| {
+                              Error::Sandbox(format!("unknown capability {}", name))
+                          })?;
+                allowed.push(cap);
+            }
+            Some(Capabilities::new(&allowed, last_capability(), uid))
+        } else {
+            None
+        };
+        let seccomp_filter = match options.seccomp_profile {
+            Some(ref profile) => Some(seccomp_filter(profile)?),
+            None => None,
+        };
+        // The seccomp filter is installed once the process runs as the service's user, which may
+        // only install one if it can't gain privileges anymore
+        let no_new_privs = options.no_new_privs.unwrap_or(false) || seccomp_filter.is_some();
+        Ok(Sandbox { private_tmp: options.private_tmp.unwrap_or(false),
+                     read_only_pkgs,
+                     private_network: options.private_network.unwrap_or(false),
+                     capabilities,
+                     no_new_privs,
+                     seccomp_filter,
+                     uid,
+                     gid })
+    }
+
+    /// Applies the sandbox to the current process and switches to the service's user and group.
+    /// This runs in the forked process before it execs the service, so it must not allocate.
+    pub fn apply(&self) -> io::Result<()> {
+        unsafe {
+            if self.private_tmp || self.read_only_pkgs.is_some() {
+                check(libc::unshare(libc::CLONE_NEWNS))?;
+                // Keep our mounts from propagating to the host, while still seeing its mounts
+                check(libc::mount(ptr::null(),
+                                  c_str(b"/\0"),
+                                  ptr::null(),
+                                  libc::MS_REC | libc::MS_SLAVE,
+                                  ptr::null()))?;
+                if self.private_tmp {
+                    check(libc::mount(c_str(b"tmpfs\0"),
+                                      c_str(b"/tmp\0"),
+                                      c_str(b"tmpfs\0"),
+                                      libc::MS_NOSUID | libc::MS_NODEV,
+                                      c_str(b"mode=1777\0") as *const c_void))?;
+                }
+                if let Some(ref pkgs) = self.read_only_pkgs {
+                    // Bind mounts can only be made read-only by remounting them
+                    check(libc::mount(pkgs.as_ptr(),
+                                      pkgs.as_ptr(),
+                                      ptr::null(),
+                                      libc::MS_BIND | libc::MS_REC,
+                                      ptr::null()))?;
+                    check(libc::mount(ptr::null(),
+                                      pkgs.as_ptr(),
+                                      ptr::null(),
+                                      libc::MS_BIND | libc::MS_REMOUNT | libc::MS_RDONLY,
+                                      ptr::null()))?;
+                }
+            }
+            if self.private_network {
+                check(libc::unshare(libc::CLONE_NEWNET))?;
+                loopback_up()?;
+            }
+            if let Some(ref capabilities) = self.capabilities {
+                for cap in capabilities.dropped.iter() {
+                    prctl(libc::PR_CAPBSET_DROP, *cap, 0)?;
+                }
+                if !capabilities.ambient.is_empty() {
+                    prctl(libc::PR_SET_KEEPCAPS, 1, 0)?;
+                }
+            }
+            // What `Command` does when given a user and group, see the module documentation,
+            // except that failing to drop the Launcher's supplementary groups is an error. Only
+            // root has groups to drop; other users' groups are their own.
+            if libc::geteuid() == 0 {
+                check(libc::setgroups(0, ptr::null()))?;
+            }
+            check(libc::setgid(self.gid))?;
+            check(libc::setuid(self.uid))?;
+            if let Some(ref capabilities) = self.capabilities {
+                let header = CapUserHeader { version: LINUX_CAPABILITY_VERSION_3,
+                                             pid:     0, };
+                check(libc::syscall(libc::SYS_capset,
+                                    &header as *const CapUserHeader,
+                                    capabilities.sets.as_ptr()) as c_int)?;
+                for cap in capabilities.ambient.iter() {
+                    prctl(PR_CAP_AMBIENT, PR_CAP_AMBIENT_RAISE, *cap)?;
+                }
+            }
+            if self.no_new_privs {
+                prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0)?;
+            }
+            if let Some(ref filter) = self.seccomp_filter {
+                let program = SockFprog { len:    filter.len() as u16,
+                                          filter: filter.as_ptr(), };
+                prctl(libc::PR_SET_SECCOMP,
+                      SECCOMP_MODE_FILTER,
+                      &program as *const SockFprog as c_ulong)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Builds the BPF program of a seccomp profile, which denies the profile's system calls with
+/// `EPERM`, as well as any system call made through another architecture's ABI.
+fn seccomp_filter(profile: &str) -> Result<Vec<SockFilter>> {
+    if !sandbox::SECCOMP_PROFILES.contains(&profile) {
+        return Err(Error::Sandbox(format!("unknown seccomp profile {}", profile)));
+    }
+    let arch = AUDIT_ARCH.ok_or_else(|| {
+                             Error::Sandbox(format!("seccomp profiles are not supported on {}",
+                                                    env::consts::ARCH))
+                         })?;
+    let deny = statement(BPF_RET | BPF_K, SECCOMP_RET_ERRNO | libc::EPERM as u32);
+    let mut filter = vec![statement(BPF_LD | BPF_W | BPF_ABS, SECCOMP_DATA_ARCH),
+                          jump(BPF_JEQ, arch, 1, 0),
+                          deny,
+                          statement(BPF_LD | BPF_W | BPF_ABS, SECCOMP_DATA_NR)];
+    if cfg!(target_arch = "x86_64") {
+        filter.push(jump(BPF_JGE, X32_SYSCALL_BIT, 0, 1));
+        filter.push(deny);
+    }
+    for nr in DEFAULT_DENIED_SYSCALLS.iter().chain(ARCH_DENIED_SYSCALLS) {
+        filter.push(jump(BPF_JEQ, *nr as u32, 0, 1));
+        filter.push(deny);
+    }
+    filter.push(statement(BPF_RET | BPF_K, SECCOMP_RET_ALLOW));
+    Ok(filter)
+}
+
+fn statement(code: u16, k: u32) -> SockFilter { SockFilter { code, jt: 0, jf: 0, k } }
+
+/// A conditional jump, skipping `jt` instructions if the accumulator compares to `k` and `jf`
+/// instructions otherwise.
+fn jump(op: u16, k: u32, jt: u8, jf: u8) -> SockFilter {
+    SockFilter { code: BPF_JMP | op | BPF_K,
+                 jt,
+                 jf,
+                 k }
+}
+
+/// The number of the last capability the kernel knows, so that capabilities newer than
+/// `sandbox::CAPABILITIES` are dropped as well.
+fn last_capability() -> u32 {
+    let known = sandbox::CAPABILITIES.len() as u32 - 1;
+    fs::read_to_string("/proc/sys/kernel/cap_last_cap").ok()
+                                                        .and_then(|s| s.trim().parse().ok())
+                                                        .unwrap_or(known)
+}
+
+/// Brings up the loopback interface of a new network namespace, which starts out down.
+unsafe fn loopback_up() -> io::Result<()> {
+    let socket = libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0);
+    check(socket)?;
+    let mut request: IfReq = mem::zeroed();
+    request.name[0] = b'l' as c_char;
+    request.name[1] = b'o' as c_char;
+    let mut result = check(libc::ioctl(socket, SIOCGIFFLAGS as _, &mut request as *mut IfReq));
+    if result.is_ok() {
+        request.flags |= IFF_UP;
+        result = check(libc::ioctl(socket, SIOCSIFFLAGS as _, &request as *const IfReq));
+    }
+    libc::close(socket);
+    result
+}
+
+/// Calls `prctl(2)` with two arguments, passing zero for the ones it doesn't use.
+unsafe fn prctl(option: c_int, arg2: c_ulong, arg3: c_ulong) -> io::Result<()> {
+    let unused: c_ulong = 0;
+    check(libc::prctl(option, arg2, arg3, unused, unused))
+}
+
+fn c_str(bytes: &'static [u8]) -> *const c_char { bytes.as_ptr() as *const c_char }
+
+fn check(result: c_int) -> io::Result<()> {
+    if result == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+mod test {
+    use super::*;
+
+    const DENY: u32 = SECCOMP_RET_ERRNO | libc::EPERM as u32;
+
+    /// Runs the BPF program `filter` for a system call, returning the seccomp action.
+    fn run(filter: &[SockFilter], arch: u32, nr: u32) -> u32 {
+        let mut accumulator = 0;
+        let mut pc = 0;
+        loop {
+            let instruction = filter[pc];
+            pc += 1;
+            let code = instruction.code;
+            if code == BPF_LD | BPF_W | BPF_ABS {
+                accumulator = match instruction.k {
+                    SECCOMP_DATA_NR => nr,
+                    SECCOMP_DATA_ARCH => arch,
+                    k => panic!("Unexpected offset {} into seccomp_data", k),
+                };
+            } else if code == BPF_JMP | BPF_JEQ | BPF_K || code == BPF_JMP | BPF_JGE | BPF_K {
+                let taken = if code & BPF_JGE == BPF_JGE {
+                    accumulator >= instruction.k
+                } else {
+                    accumulator == instruction.k
+                };
+                pc += usize::from(if taken { instruction.jt } else { instruction.jf });
+            } else if code == BPF_RET | BPF_K {
+                return instruction.k;
+            } else {
+                panic!("Unexpected instruction {:?}", instruction);
+            }
+        }
+    }
+
+    const CAP_CHOWN: u32 = 0;
+    const CAP_NET_BIND_SERVICE: u32 = 10;
+    const CAP_SYSLOG: u32 = 34;
+
+    #[test]
+    fn a_root_service_holds_only_the_allowed_capabilities() {
+        let capabilities = Capabilities::new(&[CAP_NET_BIND_SERVICE, CAP_SYSLOG], 40, 0);
+
+        let low = 1 << CAP_NET_BIND_SERVICE;
+        let high = 1 << (CAP_SYSLOG - 32);
+        assert_eq!(capabilities.sets,
+                   [CapUserData { effective:   low,
+                                  permitted:   low,
+                                  inheritable: low, },
+                    CapUserData { effective:   high,
+                                  permitted:   high,
+                                  inheritable: high, }]);
+        assert_eq!(capabilities.dropped.len(), 39);
+        assert!(capabilities.dropped.contains(&c_ulong::from(CAP_CHOWN)));
+        assert!(!capabilities.dropped.contains(&c_ulong::from(CAP_NET_BIND_SERVICE)));
+        assert!(!capabilities.dropped.contains(&c_ulong::from(CAP_SYSLOG)));
+        assert!(capabilities.ambient.is_empty());
+    }
+
+    #[test]
+    fn a_non_root_service_is_granted_the_allowed_capabilities_as_ambient_ones() {
+        let capabilities = Capabilities::new(&[CAP_NET_BIND_SERVICE], 40, 1000);
+
+        let bit = 1 << CAP_NET_BIND_SERVICE;
+        assert_eq!(capabilities.sets[0],
+                   CapUserData { effective:   bit,
+                                 permitted:   bit,
+                                 inheritable: bit, });
+        assert_eq!(capabilities.sets[1], CapUserData::default());
+        assert_eq!(capabilities.dropped.len(), 40);
+        assert_eq!(capabilities.ambient,
+                   vec![c_ulong::from(CAP_NET_BIND_SERVICE)]);
+    }
+
+    #[test]
+    fn a_service_allowed_no_capabilities_holds_none() {
+        for uid in &[0, 1000] {
+            let capabilities = Capabilities::new(&[], 40, *uid);
+
+            assert_eq!(capabilities.sets, [CapUserData::default(); 2]);
+            assert_eq!(capabilities.dropped.len(), 41);
+            assert!(capabilities.ambient.is_empty());
+        }
+    }
+
+    fn default_filter() -> Vec<SockFilter> { seccomp_filter("default").unwrap() }
+
+    fn arch() -> u32 { AUDIT_ARCH.unwrap() }
+
+    #[test]
+    fn unknown_profiles_are_rejected() { assert!(seccomp_filter("lax").is_err()); }
+
+    #[test]
+    fn system_calls_of_other_architectures_are_denied() {
+        let other_arch = 0x4000_0003; // i386
+        assert_eq!(run(&default_filter(), other_arch, libc::SYS_read as u32), DENY);
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn x32_system_calls_are_denied() {
+        let nr = X32_SYSCALL_BIT | libc::SYS_read as u32;
+        assert_eq!(run(&default_filter(), arch(), nr), DENY);
+    }
+
+    #[test]
+    fn the_profile_system_calls_are_denied() {
+        let filter = default_filter();
+        for nr in DEFAULT_DENIED_SYSCALLS.iter().chain(ARCH_DENIED_SYSCALLS) {
+            assert_eq!(run(&filter, arch(), *nr as u32), DENY, "system call {}", nr);
+        }
+    }
+
+    #[test]
+    fn other_system_calls_are_allowed() {
+        let filter = default_filter();
+        for nr in &[libc::SYS_read, libc::SYS_write, libc::SYS_openat, libc::SYS_clone] {
+            assert_eq!(run(&filter, arch(), *nr as u32),
+                       SECCOMP_RET_ALLOW,
+                       "system call {}",
+                       nr);
+        }
+    }
+}
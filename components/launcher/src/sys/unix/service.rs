@@ -1,6 +1,10 @@
-use std::{io,
+use std::{fs::{self,
+               File},
+          io::{self,
+               Write},
           ops::Neg,
           os::unix::process::CommandExt,
+          path::PathBuf,
           process::{Child,
                     Command,
                     ExitStatus,
@@ -8,10 +12,15 @@ use std::{io,
           result};
 
 use crate::{core::os::{self,
+                       cgroups,
                        process::{signal,
+                                 ResourceLimits,
                                  Signal}},
             protocol::{self,
                        ShutdownMethod}};
+#[cfg(target_os = "linux")]
+use crate::sys::sandbox::Sandbox;
+use habitat_common::outputln;
 use libc;
 use time::{Duration,
            SteadyTime};
@@ -20,15 +29,24 @@ use crate::{error::{Error,
                     Result},
             service::Service};
 
-pub struct Process(Child);
+const NO_CGROUPS_ENVVAR: &str = "HAB_LAUNCH_NO_CGROUPS";
+/// Makes the Launcher manage cgroups even if its cgroup doesn't look delegated to it.
+const FORCE_CGROUPS_ENVVAR: &str = "HAB_LAUNCH_FORCE_CGROUPS";
+static LOGKEY: &'static str = "SV";
+
+pub struct Process {
+    child:  Child,
+    /// The cgroup the process runs in, removed along with the process.
+    cgroup: Option<PathBuf>,
+}
 
 impl Process {
-    pub fn id(&self) -> u32 { self.0.id() }
+    pub fn id(&self) -> u32 { self.child.id() }
 
     /// Attempt to gracefully terminate a process and then forcefully kill it after
     /// 8 seconds if it has not terminated.
     pub fn kill(&mut self) -> ShutdownMethod {
-        let mut pid_to_kill = self.0.id() as i32;
+        let mut pid_to_kill = self.child.id() as i32;
         // check the group of the process being killed
         // if it is the root process of the process group
         // we send our signals to the entire process group
@@ -64,9 +82,53 @@ impl Process {
         }
     }
 
-    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> { self.0.try_wait() }
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> { self.child.try_wait() }
+
+    pub fn wait(&mut self) -> io::Result<ExitStatus> { self.child.wait() }
+}
+
+impl Drop for Process {
+    fn drop(&mut self) {
+        if let Some(ref cgroup) = self.cgroup {
+            // This fails while processes the service started are still running in the cgroup,
+            // in which case the next run of the service reuses it.
+            if let Err(err) = fs::remove_dir(cgroup) {
+                debug!("Unable to remove cgroup {}, {}", cgroup.display(), err);
+            }
+        }
+    }
+}
 
-    pub fn wait(&mut self) -> io::Result<ExitStatus> { self.0.wait() }
+/// Moves the Launcher into its own cgroup so that each service can be run in its own cgroup, see
+/// `habitat_core::os::cgroups`. This must be called before the Supervisor is spawned.
+///
+/// Cgroups are only managed if the Launcher's cgroup was delegated to it, or if that is forced
+/// with `HAB_LAUNCH_FORCE_CGROUPS`, so as not to interfere with the cgroups of a service manager
+/// which didn't hand its cgroup over.
+pub fn delegate_cgroups() {
+    if !cfg!(target_os = "linux") {
+        return;
+    }
+    if crate::core::env::var(NO_CGROUPS_ENVVAR).is_ok() {
+        debug!("Not running services in cgroups, {} is set", NO_CGROUPS_ENVVAR);
+        return;
+    }
+    let force = crate::core::env::var(FORCE_CGROUPS_ENVVAR).is_ok();
+    match cgroups::delegate(force) {
+        Ok(services) => debug!("Running services in cgroups under {}", services.display()),
+        Err(ref err) if err.kind() == io::ErrorKind::PermissionDenied && !force => {
+            warn!("Not running services in cgroups, their resource limits will not be \
+                   enforced: {}. Delegate the cgroup to the Launcher, or set {} to use it \
+                   anyway.",
+                  err,
+                  FORCE_CGROUPS_ENVVAR)
+        }
+        Err(err) => {
+            warn!("Unable to set up cgroups for services, their resource limits will not be \
+                   enforced: {}",
+                  err)
+        }
+    }
 }
 
 pub fn run(msg: protocol::Spawn) -> Result<Service> {
@@ -93,19 +155,104 @@ pub fn run(msg: protocol::Spawn) -> Result<Service> {
     cmd.before_exec(owned_pgid);
     cmd.stdin(Stdio::null())
        .stdout(Stdio::piped())
-       .stderr(Stdio::piped())
-       .uid(uid)
-       .gid(gid);
+       .stderr(Stdio::piped());
+    let cgroup = match service_cgroup(&msg) {
+        Some((cgroup, procs)) => {
+            // The child joins the cgroup before it execs the service, so none of the service's
+            // processes ever run outside of it. The file was opened by the Launcher, so writing
+            // to it works after the user was switched, too.
+            cmd.before_exec(move || join_cgroup(&procs));
+            Some(cgroup)
+        }
+        None => None,
+    };
+    set_user(&mut cmd, &msg, uid, gid)?;
     for (key, val) in msg.env.iter() {
         cmd.env(key, val);
     }
     let mut child = cmd.spawn().map_err(Error::Spawn)?;
     let stdout = child.stdout.take();
     let stderr = child.stderr.take();
-    let process = Process(child);
+    let process = Process { child, cgroup };
     Ok(Service::new(msg, process, stdout, stderr))
 }
 
+/// Runs the service as the given user and group, in its sandbox if it has one. The sandbox
+/// switches to the user and group itself, once it is set up.
+#[cfg(target_os = "linux")]
+fn set_user(cmd: &mut Command, msg: &protocol::Spawn, uid: u32, gid: u32) -> Result<()> {
+    match msg.sandbox {
+        Some(ref options) => {
+            let sandbox = Sandbox::new(options, uid, gid)?;
+            cmd.before_exec(move || sandbox.apply());
+        }
+        None => {
+            cmd.uid(uid).gid(gid);
+        }
+    }
+    Ok(())
+}
+
+/// Runs the service as the given user and group. Sandboxes are only supported on Linux, so
+/// services with a sandbox are not run at all.
+#[cfg(not(target_os = "linux"))]
+fn set_user(cmd: &mut Command, msg: &protocol::Spawn, uid: u32, gid: u32) -> Result<()> {
+    if msg.sandbox.is_some() {
+        return Err(Error::Sandbox("sandboxes are only supported on Linux".to_string()));
+    }
+    cmd.uid(uid).gid(gid);
+    Ok(())
+}
+
+/// Moves the calling process into the cgroup whose `cgroup.procs` file is open as `procs`. Runs
+/// in the child between fork and exec, where it may only make async-signal-safe calls, which a
+/// single `write` is.
+fn join_cgroup(procs: &File) -> io::Result<()> {
+    let mut procs = procs;
+    procs.write_all(b"0")
+}
+
+/// Creates the cgroup to run the service in, applies the service's resource limits to it and
+/// opens its `cgroup.procs` file for the service to join it with. Returns `None` if the Launcher
+/// doesn't run services in cgroups, or the cgroup couldn't be set up.
+fn service_cgroup(msg: &protocol::Spawn) -> Option<(PathBuf, File)> {
+    let limits = msg.resource_limits
+                    .as_ref()
+                    .map(resource_limits)
+                    .unwrap_or_default();
+    let result = cgroups::services_cgroup().and_then(|services| {
+                                               cgroups::create_service_cgroup(&services,
+                                                                              &msg.id,
+                                                                              &limits)
+                                           })
+                                           .and_then(|cgroup| {
+                                               let procs = cgroups::open_procs(&cgroup)?;
+                                               Ok((cgroup, procs))
+                                           });
+    match result {
+        Ok(cgroup) => Some(cgroup),
+        Err(ref err) if limits.is_empty() => {
+            debug!("Not running {} in a cgroup, {}", msg.id, err);
+            None
+        }
+        Err(err) => {
+            outputln!(preamble msg.id,
+                      "Unable to set up a cgroup for the service, its resource limits will not \
+                       be enforced: {}",
+                      err);
+            None
+        }
+    }
+}
+
+fn resource_limits(limits: &protocol::ResourceLimits) -> ResourceLimits {
+    ResourceLimits { memory_max: limits.memory_max,
+                     cpu_weight: limits.cpu_weight,
+                     cpu_quota:  limits.cpu_quota,
+                     pids_max:   limits.pids_max,
+                     io_weight:  limits.io_weight, }
+}
+
 // we want the command to spawn processes in their own process group
 // and not the same group as the Launcher. Otherwise if a child process
 // sends SIGTERM to the group, the Launcher could be terminated.
@@ -115,6 +115,9 @@ impl Process {
 }
 
 pub fn run(msg: protocol::Spawn) -> Result<Service> {
+    if msg.sandbox.is_some() {
+        return Err(Error::Sandbox("sandboxes are only supported on Linux".to_string()));
+    }
     // Supervisors prior to version 0.53.0 pulled in beta versions of
     // powershell. The official 6.0.0 version of powershell changed
     // the name of the powershell binary to pwsh.exe. Here we will
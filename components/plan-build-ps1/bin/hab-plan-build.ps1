@@ -89,6 +89,19 @@ if (!(Test-Path Env:\HAB_BLDR_CHANNEL)) {
 $script:FALLBACK_CHANNEL = "stable"
 # The value of `$env:Path` on initial start of this program
 $script:INITIAL_PATH = "$env:Path"
+# The compression level to use when compressing harts (0..9)
+if (Test-Path Env:\HAB_HART_COMPRESSION_LEVEL) {
+    $script:HAB_HART_COMPRESSION_LEVEL = "$env:HAB_HART_COMPRESSION_LEVEL"
+} else {
+    $script:HAB_HART_COMPRESSION_LEVEL = "6"
+}
+# The compression to use for hart payloads (xz, zstd, or none). Anything other
+# than xz produces a HART-2 artifact which older Habitat clients cannot read.
+if (Test-Path Env:\HAB_HART_COMPRESSION) {
+    $script:HAB_HART_COMPRESSION = "$env:HAB_HART_COMPRESSION"
+} else {
+    $script:HAB_HART_COMPRESSION = "xz"
+}
 # The full target tuple this plan will be built for
 $script:pkg_target = "@@pkg_target@@"
 # The package's origin (i.e. acme)
@@ -118,6 +131,10 @@ $script:pkg_svc_run = ''
 $script:pkg_expose = @()
 # An associative array representing configuration data which should be gossiped to peers.
 $script:pkg_exports = @{}
+# The number of seconds each hook may run for, keyed by hook name.
+$script:pkg_hook_timeouts = @{}
+# The template engine the configuration templates and hooks are written for: handlebars or tera.
+$script:pkg_template_engine = ''
 # The user to run the service as
 $script:pkg_svc_user = "hab"
 # The group to run the service as
@@ -209,6 +226,8 @@ function _Exit-With {
 #
 # * `$_hab_cmd` (hab cli for signing, hashing, and possibly installing)
 # * `$_7z_cmd` (7z program for creating tarballs and compressing with xz)
+# * `$_zstd_cmd` (zstd program for compressing with zstd, only when
+#   `$HAB_HART_COMPRESSION` is `zstd`)
 #
 # Note that all of the commands noted above are considered internal
 # implementation details and are subject to change with little to no notice,
@@ -232,6 +251,22 @@ function _Get-SystemCommands {
         _Exit-With "We require 7z to create tar files & compress; aborting" 1
     }
     Write-Debug "Setting _7z_cmd=$_7z_cmd"
+
+    switch ($HAB_HART_COMPRESSION) {
+        "xz" { }
+        "none" { }
+        "zstd" {
+            if (_Check-Command zstd) {
+                $script:_zstd_cmd = (Get-Command zstd).Source
+            } else {
+                _Exit-With "We require zstd to compress artifacts with HAB_HART_COMPRESSION=zstd; aborting" 1
+            }
+            Write-Debug "Setting _zstd_cmd=$_zstd_cmd"
+        }
+        default {
+            _Exit-With "HAB_HART_COMPRESSION must be one of xz, zstd, or none (was: $HAB_HART_COMPRESSION); aborting" 1
+        }
+    }
 }
 
 
@@ -1766,6 +1801,9 @@ function Invoke-DefaultBuildService {
     if (Test-Path "${PLAN_CONTEXT}/hooks/run") {
         Write-BuildLine "Using run hook $PLAN_CONTEXT/hooks/run"
     }
+    elseif (Test-Path "${PLAN_CONTEXT}/hooks/run.tera") {
+        Write-BuildLine "Using run hook $PLAN_CONTEXT/hooks/run.tera"
+    }
     else {
         if ($pkg_svc_run -ne "") {
           Write-BuildLine "Writing $pkg_prefix/run script to run $pkg_svc_run"
@@ -1827,7 +1865,7 @@ $pkg_description
 * __Release__: $pkg_release
 * __Target__: $pkg_target
 * __Upstream URL__: $upstream_url_string
-* __License__: $($pkg_license -join ' ')
+* __License__: $($pkg_license -join '; ')
 * __Source__: [$pkg_source]($pkg_source)
 * __SHA__: $shasum_string
 * __Path__: ``$pkg_prefix``
@@ -1967,6 +2005,15 @@ function _Write-Metadata {
         "$pkg_shutdown_timeout_sec" |
             Out-File "$pkg_prefix\SHUTDOWN_TIMEOUT" -Encoding ascii
     }
+    if ($pkg_hook_timeouts) {
+        foreach ($timeout in $pkg_hook_timeouts.GetEnumerator()) {
+            "$($timeout.Key)=$($timeout.Value)" | Out-File "$pkg_prefix\HOOK_TIMEOUTS" -Encoding ascii -Append
+        }
+    }
+    if (-Not ([string]::IsNullOrEmpty($pkg_template_engine))) {
+        "$pkg_template_engine" |
+            Out-File "$pkg_prefix\TEMPLATE_ENGINE" -Encoding ascii
+    }
 
     # Generate the blake2b hashes of all the files in the package. This
     # is not in the resulting MANIFEST because MANIFEST is included!
@@ -1979,11 +2026,9 @@ function _Save-Artifact {
     $dir = Split-Path $pkg_artifact -Parent
     $file = ".$([io.path]::GetFileNameWithoutExtension($pkg_artifact))"
     $tarf = "$dir\${file}.tar"
-    $xzf = "${tarf}.xz"
 
     New-Item "$dir" -ItemType Directory -Force | Out-Null
-    Remove-Item "$tarf" -Force -ErrorAction SilentlyContinue
-    Remove-Item "$xzf" -Force -ErrorAction SilentlyContinue
+    Remove-Item "$tarf", "${tarf}.xz", "${tarf}.zst" -Force -ErrorAction SilentlyContinue
 
     # The 7z tar archiving via -spf does not produce an expandable
     # archive that is readable by hab pkg install. By replicating the
@@ -1998,9 +2043,25 @@ function _Save-Artifact {
     Copy-Item $pkg_prefix $tempPkg -Recurse
 
     & "$_7z_cmd" a -ttar "$tarf" $tempBase | Out-Null
-    & "$_7z_cmd" a -txz "$xzf" "$tarf" | Out-Null
-    & $HAB_BIN pkg sign --origin "$pkg_origin" "$xzf" "$pkg_artifact"
-    Remove-Item "$tarf", "$xzf" -Force
+    switch ($HAB_HART_COMPRESSION) {
+        "xz" {
+            $payload = "${tarf}.xz"
+            & "$_7z_cmd" a -txz "-mx=$HAB_HART_COMPRESSION_LEVEL" "$payload" "$tarf" | Out-Null
+        }
+        "zstd" {
+            $payload = "${tarf}.zst"
+            & "$_zstd_cmd" -q "-$HAB_HART_COMPRESSION_LEVEL" -T0 -o "$payload" "$tarf"
+        }
+        "none" {
+            $payload = "$tarf"
+        }
+    }
+    if ($HAB_HART_COMPRESSION -eq "xz") {
+        & $HAB_BIN pkg sign --origin "$pkg_origin" "$payload" "$pkg_artifact"
+    } else {
+        & $HAB_BIN pkg sign --origin "$pkg_origin" --compression "$HAB_HART_COMPRESSION" "$payload" "$pkg_artifact"
+    }
+    Remove-Item "$tarf", "$payload" -Force -ErrorAction SilentlyContinue
     Remove-Item $tempRoot -Recurse -Force
 }
 
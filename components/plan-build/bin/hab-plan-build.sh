@@ -193,6 +193,52 @@
 # pkg_exposes=(port)
 # ```
 #
+# ### pkg_hook_timeouts
+# An associative array of the number of seconds each hook may run for before the Supervisor
+# terminates it. Hooks which are not listed use the Supervisor's default timeout, and a value of
+# `0` lets a hook run forever.
+# ```
+# pkg_hook_timeouts=(
+#   [health-check]=10
+#   [init]=1800
+# )
+# ```
+#
+# ### pkg_template_engine
+# The template engine the package's configuration templates and hooks are written for, either
+# `handlebars` (the default) or `tera`. Individual templates can use Tera regardless by ending
+# their file name in `.tera`.
+# ```
+# pkg_template_engine=tera
+# ```
+#
+# ### pkg_resource_limits
+# An associative array of the resources the service may use. On Linux the Supervisor's Launcher
+# runs each service in its own cgroup (v2) to enforce these. `memory_max` is in bytes, `cpu_quota`
+# is a percentage of one CPU, `pids_max` is a number of processes and threads, and `cpu_weight`
+# and `io_weight` are shares relative to other services, from 1 to 10000 (the default is 100).
+# ```
+# pkg_resource_limits=(
+#   [memory_max]=536870912
+#   [cpu_quota]=150
+#   [pids_max]=512
+# )
+# ```
+#
+# ### pkg_sandbox
+# An associative array of the hardening the Supervisor's Launcher applies to the service on Linux
+# before starting it. `private_tmp`, `read_only_pkgs`, `no_new_privs` and `private_network` are
+# `true` or `false`, `capabilities` is a space separated allow-list of the capabilities the
+# service may hold, and `seccomp_profile` names the system call filter to apply (`default`).
+# ```
+# pkg_sandbox=(
+#   [private_tmp]=true
+#   [read_only_pkgs]=true
+#   [capabilities]="CAP_NET_BIND_SERVICE"
+#   [seccomp_profile]=default
+# )
+# ```
+#
 # ### pkg_binds
 # An associative array representing services which you depend on and the configuration keys that
 # you expect the service to export (by their `pkg_exports`). These binds *must* be set for the
@@ -363,6 +409,9 @@ INITIAL_PATH="$PATH"
 INITIAL_PWD="$(pwd)"
 # The compression level to use when compression harts (0..9)
 : "${HAB_HART_COMPRESSION_LEVEL:=6}"
+# The compression to use for hart payloads (xz, zstd, or none). Anything other
+# than xz produces a HART-2 artifact which older Habitat clients cannot read.
+: "${HAB_HART_COMPRESSION:=xz}"
 # The full target tuple this plan will be built for
 pkg_target='@@pkg_target@@'
 # The package's origin (i.e. acme)
@@ -388,6 +437,9 @@ pkg_exposes=()
 declare -A pkg_exports
 declare -A pkg_binds
 declare -A pkg_binds_optional
+declare -A pkg_hook_timeouts
+declare -A pkg_resource_limits
+declare -A pkg_sandbox
 # The user to run the service as
 pkg_svc_user=hab
 # The group to run the service as
@@ -632,6 +684,25 @@ _find_system_commands() {
   fi
   debug "Setting _xz_cmd=$_xz_cmd"
 
+  case "$HAB_HART_COMPRESSION" in
+    xz|none)
+      ;;
+    zstd)
+      if exists zstd; then
+        _zstd_cmd=$(command -v zstd)
+        if [[ "${HAB_NONINTERACTIVE:-}" == "true" ]]; then
+          _zstd_cmd="$_zstd_cmd --quiet"
+        fi
+      else
+        exit_with "We require zstd to compress artifacts with HAB_HART_COMPRESSION=zstd; aborting" 1
+      fi
+      debug "Setting _zstd_cmd=$_zstd_cmd"
+      ;;
+    *)
+      exit_with "HAB_HART_COMPRESSION must be one of xz, zstd, or none (was: $HAB_HART_COMPRESSION); aborting" 1
+      ;;
+  esac
+
   if exists hab; then
     _hab_cmd=$(command -v hab)
   else
@@ -1918,6 +1989,10 @@ do_default_install() {
 # * `$pkg_prefix/EXPOSES` - An array of `pkg_exports` for which ports that this package exposes
 # * `$pkg_prefix/BINDS` - A list of services you connect to and keys that you expect to be exported
 # * `$pkg_prefix/BINDS_OPTIONAL` - Same as `BINDS` but not required for the service to start
+# * `$pkg_prefix/HOOK_TIMEOUTS` - The number of seconds each hook may run for
+# * `$pkg_prefix/RESOURCE_LIMITS` - The resources the service may use
+# * `$pkg_prefix/SANDBOX` - The hardening applied to the service
+# * `$pkg_prefix/TEMPLATE_ENGINE` - The template engine the templates and hooks are written for
 # * `$pkg_prefix/FILES` - blake2b checksums of all files in the package
 # * `$pkg_prefix/LDFLAGS` - Any LDFLAGS for things that link against us
 # * `$pkg_prefix/LD_RUN_PATH` - The LD_RUN_PATH for things that link against us
@@ -1951,11 +2026,15 @@ _build_metadata() {
   _render_metadata_RUNTIME_ENVIRONMENT_PROVENANCE
   _render_metadata_SHUTDOWN_SIGNAL
   _render_metadata_SHUTDOWN_TIMEOUT
+  _render_metadata_HOOK_TIMEOUTS
+  _render_metadata_RESOURCE_LIMITS
+  _render_metadata_SANDBOX
+  _render_metadata_TEMPLATE_ENGINE
 
   # Only generate `SVC_USER` & `SVC_GROUP` files if this package is a service.
   # We determine this by checking if there is a `hooks/run` script and/or
   # a set `$pkg_svc_run` value.
-  if [[ -f "$PLAN_CONTEXT/hooks/run" || -n "${pkg_svc_run:-}" ]]; then
+  if [[ -f "$PLAN_CONTEXT/hooks/run" || -f "$PLAN_CONTEXT/hooks/run.tera" || -n "${pkg_svc_run:-}" ]]; then
     _render_metadata_SVC_USER
     _render_metadata_SVC_GROUP
   fi
@@ -2033,6 +2112,9 @@ do_default_build_service() {
   if [[ -f "${PLAN_CONTEXT}/hooks/run" ]]; then
     build_line "Using run hook ${PLAN_CONTEXT}/hooks/run"
     return 0
+  elif [[ -f "${PLAN_CONTEXT}/hooks/run.tera" ]]; then
+    build_line "Using run hook ${PLAN_CONTEXT}/hooks/run.tera"
+    return 0
   else
     if [[ -n "${pkg_svc_run}" ]]; then
       # We use chpst to ensure that the script works outside `hab-sup`
@@ -2122,6 +2204,12 @@ _build_manifest() {
     local _sha_string="\`$pkg_shasum\`"
   fi
 
+  # Licenses may contain spaces (e.g. 'Boost Software License'), so they are
+  # separated by semicolons
+  local _license_string
+  _license_string="$(printf "%s; " "${pkg_license[@]}")"
+  _license_string="${_license_string%; }"
+
   local _build_deps_string
   if [[ -z "${pkg_build_deps[*]}" ]]; then
     _build_deps_string="no build dependencies or undefined"
@@ -2184,7 +2272,7 @@ $pkg_description
 * __Release__: $pkg_release
 * __Target__: $pkg_target
 * __Upstream URL__: $_upstream_url_string
-* __License__: $(printf "%s " "${pkg_license[@]}")
+* __License__: $_license_string
 * __Source__: $_source_url_string
 * __SHA__: $_sha_string
 * __Path__: \`$pkg_prefix\`
@@ -2218,14 +2306,31 @@ _generate_artifact() {
   build_line "Generating package artifact"
   local tarf
   tarf="$(dirname "$pkg_artifact")/.$(basename "${pkg_artifact/%.${_artifact_ext}/.tar}")"
-  local xzf="${tarf}.xz"
+  local payload
 
   mkdir -pv "$(dirname "$pkg_artifact")"
-  rm -fv "$tarf" "$xzf" "$pkg_artifact"
+  rm -fv "$tarf" "${tarf}.xz" "${tarf}.zst" "$pkg_artifact"
   $_tar_cmd -cf "$tarf" "$pkg_prefix"
-  $_xz_cmd --compress -${HAB_HART_COMPRESSION_LEVEL} --threads=0 "$tarf"
-  $HAB_BIN pkg sign --origin $pkg_origin "$xzf" "$pkg_artifact"
-  rm -f "$tarf" "$xzf"
+  case "$HAB_HART_COMPRESSION" in
+    xz)
+      payload="${tarf}.xz"
+      $_xz_cmd --compress -${HAB_HART_COMPRESSION_LEVEL} --threads=0 "$tarf"
+      ;;
+    zstd)
+      payload="${tarf}.zst"
+      $_zstd_cmd -${HAB_HART_COMPRESSION_LEVEL} -T0 --rm -o "$payload" "$tarf"
+      ;;
+    none)
+      payload="$tarf"
+      ;;
+  esac
+  if [[ "$HAB_HART_COMPRESSION" == "xz" ]]; then
+    $HAB_BIN pkg sign --origin $pkg_origin "$payload" "$pkg_artifact"
+  else
+    $HAB_BIN pkg sign --origin $pkg_origin --compression "$HAB_HART_COMPRESSION" \
+      "$payload" "$pkg_artifact"
+  fi
+  rm -f "$tarf" "$payload"
 }
 
 _prepare_build_outputs() {
@@ -260,6 +260,26 @@ _render_metadata_SHUTDOWN_TIMEOUT() {
   fi
 }
 
+_render_metadata_HOOK_TIMEOUTS() {
+    _render_associative_array_file "${pkg_prefix}" HOOK_TIMEOUTS pkg_hook_timeouts
+}
+
+_render_metadata_RESOURCE_LIMITS() {
+    _render_associative_array_file "${pkg_prefix}" RESOURCE_LIMITS pkg_resource_limits
+}
+
+_render_metadata_SANDBOX() {
+    _render_associative_array_file "${pkg_prefix}" SANDBOX pkg_sandbox
+}
+
+_render_metadata_TEMPLATE_ENGINE() {
+  if [[ -n "${pkg_template_engine:-}" ]]; then
+    debug "Rendering TEMPLATE_ENGINE metadata file"
+    # shellcheck disable=2154
+    echo "$pkg_template_engine" > "$pkg_prefix"/TEMPLATE_ENGINE
+  fi
+}
+
 _render_metadata_TARGET() {
   debug "Rendering TARGET metadata file"
   # shellcheck disable=2154
@@ -11,3 +11,48 @@ use std::fmt;
 impl fmt::Display for ConsoleLine {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "{}", self.line) }
 }
+
+/// Whether `name` can name an instance of a service. Instance names end up in file and directory
+/// names, so they are restricted to letters, digits, `-` and `_`.
+pub fn valid_instance_name(name: &str) -> bool {
+    !name.is_empty()
+    && name.chars()
+           .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Splits a service as given on the command line, e.g. `core/redis@cache`, into its package
+/// identifier and its instance name, if it has one. Version constraints share the `@`
+/// separator, e.g. `core/redis@>=5.0@cache`, but always start with an operator, so only a
+/// trailing valid instance name is taken as one.
+pub fn split_instance_name(service: &str) -> (&str, Option<&str>) {
+    match service.rfind('@') {
+        Some(idx) if valid_instance_name(&service[idx + 1..]) => {
+            (&service[..idx], Some(&service[idx + 1..]))
+        }
+        _ => (service, None),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn instance_names_are_restricted_to_safe_characters() {
+        assert!(valid_instance_name("cache"));
+        assert!(valid_instance_name("cache-2_b"));
+        assert!(!valid_instance_name(""));
+        assert!(!valid_instance_name("../cache"));
+        assert!(!valid_instance_name("cache@2"));
+    }
+
+    #[test]
+    fn instance_names_are_split_from_version_constraints() {
+        assert_eq!(split_instance_name("core/redis"), ("core/redis", None));
+        assert_eq!(split_instance_name("core/redis@cache"), ("core/redis", Some("cache")));
+        assert_eq!(split_instance_name("core/redis@>=5.0,<6"), ("core/redis@>=5.0,<6", None));
+        assert_eq!(split_instance_name("core/redis@>=5.0@cache"),
+                   ("core/redis@>=5.0", Some("cache")));
+        assert_eq!(split_instance_name("core/redis@"), ("core/redis@", None));
+    }
+}
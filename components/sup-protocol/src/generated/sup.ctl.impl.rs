@@ -9,6 +9,9 @@ impl message::MessageStatic for Handshake {
 impl message::MessageStatic for ServiceBindList {
     const MESSAGE_ID: &'static str = "ServiceBindList";
 }
+impl message::MessageStatic for PackageIdentList {
+    const MESSAGE_ID: &'static str = "PackageIdentList";
+}
 impl message::MessageStatic for SupDepart {
     const MESSAGE_ID: &'static str = "SupDepart";
 }
@@ -39,6 +42,18 @@ impl message::MessageStatic for SvcStop {
 impl message::MessageStatic for SvcStatus {
     const MESSAGE_ID: &'static str = "SvcStatus";
 }
+impl message::MessageStatic for SvcHookHistory {
+    const MESSAGE_ID: &'static str = "SvcHookHistory";
+}
+impl message::MessageStatic for SvcUpdate {
+    const MESSAGE_ID: &'static str = "SvcUpdate";
+}
+impl message::MessageStatic for SvcHold {
+    const MESSAGE_ID: &'static str = "SvcHold";
+}
+impl message::MessageStatic for SvcRelease {
+    const MESSAGE_ID: &'static str = "SvcRelease";
+}
 impl message::MessageStatic for ConsoleLine {
     const MESSAGE_ID: &'static str = "ConsoleLine";
 }
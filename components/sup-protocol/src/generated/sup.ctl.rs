@@ -31,6 +31,14 @@ pub struct ServiceBindList {
     #[prost(message, repeated, tag="1")]
     pub binds: ::std::vec::Vec<super::types::ServiceBind>,
 }
+/// Wrapper type for a list of PackageIdents.
+#[derive(Clone, PartialEq, ::prost::Message)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PackageIdentList {
+    #[prost(message, repeated, tag="1")]
+    pub idents: ::std::vec::Vec<super::types::PackageIdent>,
+}
 #[derive(Clone, PartialEq, ::prost::Message)]
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
@@ -62,6 +70,13 @@ pub struct SvcGetDefaultCfg {
     /// Package identifier to target running service.
     #[prost(message, optional, tag="1")]
     pub ident: ::std::option::Option<super::types::PackageIdent>,
+    /// Explain where each value of the service's effective configuration came from, instead of
+    /// returning its default configuration.
+    #[prost(bool, optional, tag="2", default="false")]
+    pub explain: ::std::option::Option<bool>,
+    /// Instance of the package the service runs as, if several run on the Supervisor.
+    #[prost(string, optional, tag="3")]
+    pub instance: ::std::option::Option<String>,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 #[derive(Serialize, Deserialize)]
@@ -151,6 +166,33 @@ pub struct SvcLoad {
     /// The shutdown timeout for the service.
     #[prost(uint32, optional, tag="16")]
     pub shutdown_timeout: ::std::option::Option<u32>,
+    /// The number ("2") or percentage ("10%") of members a canary update updates first.
+    #[prost(string, optional, tag="17")]
+    pub update_canaries: ::std::option::Option<std::string::String>,
+    /// The number of seconds the canaries of a canary update run before the rest of the service
+    /// group is updated.
+    #[prost(uint32, optional, tag="18")]
+    pub update_bake_period: ::std::option::Option<u32>,
+    /// The number of seconds an updated release has to become healthy before it is rolled back.
+    #[prost(uint32, optional, tag="19")]
+    pub update_rollback_window: ::std::option::Option<u32>,
+    /// When updates may be applied to the service, as a crontab schedule followed by an optional
+    /// time zone. An empty string removes the update window.
+    #[prost(string, optional, tag="20")]
+    pub update_window: ::std::option::Option<std::string::String>,
+    /// Local services which the service is started after and stopped before.
+    #[prost(message, optional, tag="21")]
+    pub start_after: ::std::option::Option<PackageIdentList>,
+    /// Set to true to wait for the service's dependencies to be healthy before starting it.
+    #[prost(bool, optional, tag="22")]
+    pub requires_healthy: ::std::option::Option<bool>,
+    /// Set to true to only list the healthy members of bound service groups in templates.
+    #[prost(bool, optional, tag="23")]
+    pub bind_healthy_only: ::std::option::Option<bool>,
+    /// Name of the instance to load, for running several services of the same package on the
+    /// Supervisor. The service is known as `<name>@<instance>`.
+    #[prost(string, optional, tag="24")]
+    pub instance: ::std::option::Option<String>,
 }
 /// Request to unload a loaded service.
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -162,6 +204,9 @@ pub struct SvcUnload {
     /// Timeout in seconds before killing the service
     #[prost(uint32, optional, tag="3")]
     pub timeout_in_seconds: ::std::option::Option<u32>,
+    /// Instance of the package the service runs as, if several run on the Supervisor.
+    #[prost(string, optional, tag="4")]
+    pub instance: ::std::option::Option<String>,
 }
 /// Request to start a loaded and stopped service.
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -170,6 +215,9 @@ pub struct SvcUnload {
 pub struct SvcStart {
     #[prost(message, optional, tag="1")]
     pub ident: ::std::option::Option<super::types::PackageIdent>,
+    /// Instance of the package the service runs as, if several run on the Supervisor.
+    #[prost(string, optional, tag="2")]
+    pub instance: ::std::option::Option<String>,
 }
 /// Request to stop a loaded and started service.
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -181,6 +229,9 @@ pub struct SvcStop {
     /// Timeout in seconds before killing the service
     #[prost(uint32, optional, tag="3")]
     pub timeout_in_seconds: ::std::option::Option<u32>,
+    /// Instance of the package the service runs as, if several run on the Supervisor.
+    #[prost(string, optional, tag="4")]
+    pub instance: ::std::option::Option<String>,
 }
 /// Request to retrieve the service status of one or all services.
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -191,6 +242,57 @@ pub struct SvcStatus {
     /// left blank then all services will report their status.
     #[prost(message, optional, tag="1")]
     pub ident: ::std::option::Option<super::types::PackageIdent>,
+    /// If specified along with the identifier, only the status of this instance is returned.
+    #[prost(string, optional, tag="2")]
+    pub instance: ::std::option::Option<String>,
+}
+/// Request to retrieve the latest runs of the hooks of a service.
+#[derive(Clone, PartialEq, ::prost::Message)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SvcHookHistory {
+    /// Package identifier to target running service.
+    #[prost(message, optional, tag="1")]
+    pub ident: ::std::option::Option<super::types::PackageIdent>,
+    /// Instance of the package the service runs as, if several run on the Supervisor.
+    #[prost(string, optional, tag="2")]
+    pub instance: ::std::option::Option<String>,
+}
+/// Request to apply the pending update of a service now, regardless of its update window.
+#[derive(Clone, PartialEq, ::prost::Message)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SvcUpdate {
+    /// Package identifier to target running service.
+    #[prost(message, optional, tag="1")]
+    pub ident: ::std::option::Option<super::types::PackageIdent>,
+    /// Instance of the package the service runs as, if several run on the Supervisor.
+    #[prost(string, optional, tag="2")]
+    pub instance: ::std::option::Option<String>,
+}
+/// Request to hold a service at the release it is running, stopping its updates.
+#[derive(Clone, PartialEq, ::prost::Message)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SvcHold {
+    /// Package identifier to target running service.
+    #[prost(message, optional, tag="1")]
+    pub ident: ::std::option::Option<super::types::PackageIdent>,
+    /// Instance of the package the service runs as, if several run on the Supervisor.
+    #[prost(string, optional, tag="2")]
+    pub instance: ::std::option::Option<String>,
+}
+/// Request to release the hold of a service, resuming its updates.
+#[derive(Clone, PartialEq, ::prost::Message)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SvcRelease {
+    /// Package identifier to target running service.
+    #[prost(message, optional, tag="1")]
+    pub ident: ::std::option::Option<super::types::PackageIdent>,
+    /// Instance of the package the service runs as, if several run on the Supervisor.
+    #[prost(string, optional, tag="2")]
+    pub instance: ::std::option::Option<String>,
 }
 /// A reply to various requests which contains a pre-formatted console line.
 #[derive(Clone, PartialEq, ::prost::Message)]
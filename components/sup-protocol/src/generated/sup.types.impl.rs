@@ -24,3 +24,6 @@ impl message::MessageStatic for ServiceStatus {
 impl message::MessageStatic for HealthCheckInterval {
     const MESSAGE_ID: &'static str = "HealthCheckInterval";
 }
+impl message::MessageStatic for HookHistory {
+    const MESSAGE_ID: &'static str = "HookHistory";
+}
@@ -19,6 +19,10 @@ pub struct PackageIdent {
     pub version: ::std::option::Option<std::string::String>,
     #[prost(string, optional, tag="4")]
     pub release: ::std::option::Option<std::string::String>,
+    /// Comma-separated version comparators, e.g. ">=1.0.2,<1.1". Only set when
+    /// neither version nor release are.
+    #[prost(string, optional, tag="5")]
+    pub version_constraint: ::std::option::Option<std::string::String>,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 #[derive(Serialize, Deserialize)]
@@ -51,6 +55,10 @@ pub struct ServiceCfg {
     pub format: ::std::option::Option<i32>,
     #[prost(string, optional, tag="2")]
     pub default: ::std::option::Option<std::string::String>,
+    /// JSON document describing the source of each value of the effective configuration, if it
+    /// was requested with `SvcGetDefaultCfg.explain`.
+    #[prost(string, optional, tag="3")]
+    pub explanation: ::std::option::Option<std::string::String>,
 }
 pub mod service_cfg {
     #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
@@ -86,6 +94,15 @@ pub struct ServiceStatus {
     pub service_group: ServiceGroup,
     #[prost(enumeration="DesiredState", optional, tag="5")]
     pub desired_state: ::std::option::Option<i32>,
+    /// The update which was found for the service but waits for its update window to open.
+    #[prost(message, optional, tag="6")]
+    pub pending_update: ::std::option::Option<PackageIdent>,
+    /// The release the service is held at, if its updates are on hold.
+    #[prost(message, optional, tag="7")]
+    pub held_release: ::std::option::Option<PackageIdent>,
+    /// The instance of the package the service runs as, if it has one.
+    #[prost(string, optional, tag="8")]
+    pub instance: ::std::option::Option<String>,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 #[derive(Serialize, Deserialize)]
@@ -94,6 +111,15 @@ pub struct HealthCheckInterval {
     #[prost(uint64, required, tag="1")]
     pub seconds: u64,
 }
+/// The latest runs of the hooks of a service.
+#[derive(Clone, PartialEq, ::prost::Message)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct HookHistory {
+    /// JSON document holding the latest runs of each hook, keyed by hook name.
+    #[prost(string, optional, tag="1")]
+    pub history: ::std::option::Option<std::string::String>,
+}
 /// Encapsulate all possible sources we can install packages from.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
@@ -141,6 +167,7 @@ pub enum UpdateStrategy {
     None = 0,
     AtOnce = 1,
     Rolling = 2,
+    Canary = 3,
 }
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
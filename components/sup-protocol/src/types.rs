@@ -8,7 +8,8 @@
 include!("generated/sup.types.rs");
 include!("generated/sup.types.impl.rs");
 
-use std::{fmt,
+use std::{convert::TryFrom,
+          fmt,
           str::FromStr};
 
 use crate::core::{self,
@@ -59,7 +60,14 @@ impl fmt::Display for PackageIdent {
                 write!(f, "{}/{}/{}/{}", self.origin, self.name, version, release,)
             }
             (Some(ref version), None) => write!(f, "{}/{}/{}", self.origin, self.name, version,),
-            (None, Some(_)) | (None, None) => write!(f, "{}/{}", self.origin, self.name),
+            (None, Some(_)) | (None, None) => {
+                match self.version_constraint {
+                    Some(ref constraint) => {
+                        write!(f, "{}/{}@{}", self.origin, self.name, constraint)
+                    }
+                    None => write!(f, "{}/{}", self.origin, self.name),
+                }
+            }
         }
     }
 }
@@ -206,16 +214,27 @@ impl From<core::service::HealthCheckInterval> for HealthCheckInterval {
 
 impl From<package::PackageIdent> for PackageIdent {
     fn from(ident: package::PackageIdent) -> Self {
-        Self { origin:  ident.origin,
-               name:    ident.name,
-               version: ident.version,
-               release: ident.release, }
+        Self { origin:             ident.origin,
+               name:               ident.name,
+               version:            ident.version,
+               release:            ident.release,
+               version_constraint: ident.version_constraint.map(|c| c.to_string()), }
     }
 }
 
-impl Into<package::PackageIdent> for PackageIdent {
-    fn into(self) -> package::PackageIdent {
-        package::PackageIdent::new(self.origin, self.name, self.version, self.release)
+impl TryFrom<PackageIdent> for package::PackageIdent {
+    type Error = core::Error;
+
+    /// Fails if the version constraint is invalid, rather than dropping it and leaving the
+    /// identifier unconstrained.
+    fn try_from(value: PackageIdent) -> core::Result<Self> {
+        let mut ident =
+            package::PackageIdent::new(value.origin, value.name, value.version, value.release);
+        ident.version_constraint = match value.version_constraint {
+            Some(constraint) => Some(constraint.parse()?),
+            None => None,
+        };
+        Ok(ident)
     }
 }
 
@@ -317,6 +336,7 @@ impl UpdateStrategy {
             UpdateStrategy::None => "none",
             UpdateStrategy::AtOnce => "at-once",
             UpdateStrategy::Rolling => "rolling",
+            UpdateStrategy::Canary => "canary",
         }
     }
 }
@@ -329,6 +349,7 @@ impl FromStr for UpdateStrategy {
             "none" => Ok(UpdateStrategy::None),
             "at-once" => Ok(UpdateStrategy::AtOnce),
             "rolling" => Ok(UpdateStrategy::Rolling),
+            "canary" => Ok(UpdateStrategy::Canary),
             _ => Err(net::err(ErrCode::InvalidPayload, "Invalid update strategy.")),
         }
     }
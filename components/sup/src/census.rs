@@ -1,9 +1,11 @@
-use crate::error::Error;
+use crate::{error::Error,
+            manager::service::HealthCheckResult};
 use habitat_butterfly::{member::{Health,
                                  Member,
                                  MemberList,
                                  Membership},
-                        rumor::{election::{Election as ElectionRumor,
+                        rumor::{election::{CanaryRollout,
+                                           Election as ElectionRumor,
                                            ElectionStatus as ElectionStatusRumor,
                                            ElectionUpdate as ElectionUpdateRumor},
                                 service::{Service as ServiceRumor,
@@ -311,6 +313,8 @@ pub struct CensusGroup {
     pub update_election_status: ElectionStatus,
     pub leader_id:              Option<MemberId>,
     pub service_config:         Option<ServiceConfig>,
+    /// The canary update the update leader is running, if any.
+    pub canary_rollout:         Option<CanaryRollout>,
 
     local_member_id:       MemberId,
     population:            BTreeMap<MemberId, CensusMember>,
@@ -329,6 +333,7 @@ impl CensusGroup {
                       leader_id:              None,
                       update_leader_id:       None,
                       service_config:         None,
+                      canary_rollout:         None,
                       service_files:          HashMap::new(),
                       changed_service_files:  Vec::new(), }
     }
@@ -363,6 +368,23 @@ impl CensusGroup {
             .filter(|cm| cm.alive() || cm.suspect())
     }
 
+    /// Same as `active_members`, but leaves out members whose
+    /// latest health check failed, see `CensusMember::healthy`.
+    pub fn healthy_members(&self) -> impl Iterator<Item = &CensusMember> {
+        self.active_members().filter(|cm| cm.healthy())
+    }
+
+    /// The releases of the package any member of the group must not run, because a member rolled
+    /// back an update to them.
+    pub fn blocked_releases(&self) -> Vec<&PackageIdent> {
+        let mut releases = self.members()
+                               .flat_map(|cm| cm.blocked_releases.iter())
+                               .collect::<Vec<_>>();
+        releases.sort();
+        releases.dedup();
+        releases
+    }
+
     pub fn changed_service_files(&self) -> Vec<&ServiceFile> {
         self.changed_service_files
             .iter()
@@ -434,6 +456,7 @@ impl CensusGroup {
 
     fn update_from_election_update_rumor(&mut self, election: &ElectionUpdateRumor) {
         self.update_leader_id = None;
+        self.canary_rollout = election.canary.clone();
         for census_member in self.population.values_mut() {
             if census_member.update_from_election_update_rumor(election) {
                 self.update_leader_id = Some(census_member.member_id.clone());
@@ -526,7 +549,7 @@ impl Serialize for CensusGroup {
     fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
         where S: Serializer
     {
-        let mut strukt = serializer.serialize_struct("census_group", 10)?;
+        let mut strukt = serializer.serialize_struct("census_group", 11)?;
         strukt.serialize_field("service_group", &self.service_group)?;
         strukt.serialize_field("election_status", &self.election_status)?;
         strukt.serialize_field("update_election_status", &self.update_election_status)?;
@@ -542,6 +565,7 @@ impl Serialize for CensusGroup {
 
         strukt.serialize_field("population", &new_pop)?;
         strukt.serialize_field("update_leader_id", &self.update_leader_id)?;
+        strukt.serialize_field("canary_rollout", &self.canary_rollout)?;
         strukt.serialize_field("changed_service_files", &self.changed_service_files)?;
         strukt.serialize_field("service_files", &self.service_files)?;
         strukt.end()
@@ -579,6 +603,10 @@ pub struct CensusMember {
     suspect:   bool,
     confirmed: bool,
     departed:  bool,
+    health:    HealthCheckResult,
+    /// Releases of the package this member gossips as blocked, see `CensusGroup::blocked_releases`.
+    #[serde(skip)]
+    blocked_releases: Vec<PackageIdent>,
     // Maps must be represented last in a serializable struct for the current version of the toml
     // crate. Additionally, this deserialization method is required to correct any ordering issues
     // with the table being serialized - https://docs.rs/toml/0.4.0/toml/ser/fn.tables_last.html
@@ -603,6 +631,11 @@ impl CensusMember {
             Err(err) => warn!("Received a bad package ident from gossip data, err={}", err),
         };
         self.sys = rumor.sys.clone();
+        self.health = rumor.health.into();
+        self.blocked_releases = rumor.blocked_releases
+                                     .iter()
+                                     .filter_map(|release| PackageIdent::from_str(release).ok())
+                                     .collect();
         self.cfg = toml::from_slice(&rumor.cfg).unwrap_or_default();
     }
 
@@ -665,6 +698,24 @@ impl CensusMember {
     pub fn confirmed(&self) -> bool { self.confirmed }
 
     pub fn departed(&self) -> bool { self.departed }
+
+    /// The result of the latest health check of this member's
+    /// service, as gossiped by the member.
+    pub fn health(&self) -> HealthCheckResult { self.health }
+
+    /// Whether this member's service is not known to be unhealthy. Members whose health is
+    /// `Unknown`, either because they are gossiped by a Supervisor which doesn't gossip health or
+    /// because their service hasn't been checked yet, count as healthy, so that mixing old and new
+    /// Supervisors in a service group doesn't empty the binds of services which only bind to
+    /// healthy members.
+    pub fn healthy(&self) -> bool {
+        match self.health {
+            HealthCheckResult::Ok | HealthCheckResult::Unknown => true,
+            HealthCheckResult::Warning
+            | HealthCheckResult::Critical
+            | HealthCheckResult::Timeout => false,
+        }
+    }
 }
 
 /// This data structure just wraps the CensusMember and allows us to tweak the serialization logic.
@@ -678,7 +729,7 @@ impl<'a> Serialize for CensusMemberProxy<'a> {
     fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
         where S: Serializer
     {
-        let mut strukt = serializer.serialize_struct("census_member", 24)?;
+        let mut strukt = serializer.serialize_struct("census_member", 25)?;
         strukt.serialize_field("member_id", &self.0.member_id)?;
         strukt.serialize_field("pkg", &self.0.pkg)?;
 
@@ -712,6 +763,7 @@ impl<'a> Serialize for CensusMemberProxy<'a> {
         strukt.serialize_field("suspect", &self.0.suspect)?;
         strukt.serialize_field("confirmed", &self.0.confirmed)?;
         strukt.serialize_field("departed", &self.0.departed)?;
+        strukt.serialize_field("health", &self.0.health.to_string())?;
         strukt.serialize_field("cfg", &self.0.cfg)?;
         strukt.end()
     }
@@ -868,6 +920,8 @@ mod tests {
                        suspect: health == Health::Suspect,
                        confirmed: health == Health::Confirmed,
                        departed: health == Health::Departed,
+                       health: HealthCheckResult::Unknown,
+                       blocked_releases: Vec::new(),
                        cfg: toml::value::Table::new(), }
     }
 
@@ -894,6 +948,57 @@ mod tests {
         assert!(active_members.next().is_none());
     }
 
+    #[test]
+    fn healthy_members_leaves_out_inactive_and_unhealthy_members() {
+        let mut healthy = test_census_member("healthy-one", Health::Alive);
+        healthy.health = HealthCheckResult::Ok;
+        let mut critical = test_census_member("critical-one", Health::Alive);
+        critical.health = HealthCheckResult::Critical;
+        let mut confirmed = test_census_member("confirmed-one", Health::Confirmed);
+        confirmed.health = HealthCheckResult::Ok;
+        let unknown = test_census_member("unknown-one", Health::Alive);
+
+        let sg: ServiceGroup =
+            "test-service.default".parse()
+                                  .expect("This should be a valid service group");
+
+        let mut census_group = CensusGroup::new(sg, &"healthy-one".to_string());
+        for member in vec![healthy, critical, confirmed, unknown] {
+            census_group.population
+                        .insert(member.member_id.clone(), member);
+        }
+
+        let mut healthy_members: Vec<&str> = census_group.healthy_members()
+                                                          .map(|cm| cm.member_id.as_str())
+                                                          .collect();
+        healthy_members.sort();
+        assert_eq!(healthy_members, vec!["healthy-one", "unknown-one"]);
+    }
+
+    #[test]
+    fn blocked_releases_are_collected_from_all_members() {
+        let release = |s: &str| PackageIdent::from_str(s).unwrap();
+        let mut one = test_census_member("one", Health::Alive);
+        one.blocked_releases = vec![release("core/redis/4.0.14/20190415155205")];
+        let mut two = test_census_member("two", Health::Confirmed);
+        two.blocked_releases = vec![release("core/redis/4.0.14/20190415155205"),
+                                    release("core/redis/5.0.4/20190501000000")];
+
+        let sg: ServiceGroup =
+            "test-service.default".parse()
+                                  .expect("This should be a valid service group");
+
+        let mut census_group = CensusGroup::new(sg, &"one".to_string());
+        for member in vec![one, two] {
+            census_group.population
+                        .insert(member.member_id.clone(), member);
+        }
+
+        assert_eq!(census_group.blocked_releases(),
+                   vec![&release("core/redis/4.0.14/20190415155205"),
+                        &release("core/redis/5.0.4/20190501000000")]);
+    }
+
     fn assert_eq_member_ids(cm: Option<&CensusMember>, id: Option<&str>) {
         assert_eq!(cm.map(|cm| cm.member_id.as_str()), id);
     }
@@ -345,6 +345,42 @@ impl SrvHandler {
                                        commands::service_status(state, req, m.clone())
                                    }))
             }
+            "SvcHookHistory" => {
+                let m = msg.parse::<protocol::ctl::SvcHookHistory>()
+                           .map_err(HandlerError::from)?;
+                Ok(CtlCommand::new(ctl_sender,
+                                   msg.transaction(),
+                                   move |state, req, _action_sender| {
+                                       commands::service_hook_history(state, req, m.clone())
+                                   }))
+            }
+            "SvcUpdate" => {
+                let m = msg.parse::<protocol::ctl::SvcUpdate>()
+                           .map_err(HandlerError::from)?;
+                Ok(CtlCommand::new(ctl_sender,
+                                   msg.transaction(),
+                                   move |state, req, _action_sender| {
+                                       commands::service_update(state, req, m.clone())
+                                   }))
+            }
+            "SvcHold" => {
+                let m = msg.parse::<protocol::ctl::SvcHold>()
+                           .map_err(HandlerError::from)?;
+                Ok(CtlCommand::new(ctl_sender,
+                                   msg.transaction(),
+                                   move |state, req, _action_sender| {
+                                       commands::service_hold(state, req, m.clone())
+                                   }))
+            }
+            "SvcRelease" => {
+                let m = msg.parse::<protocol::ctl::SvcRelease>()
+                           .map_err(HandlerError::from)?;
+                Ok(CtlCommand::new(ctl_sender,
+                                   msg.transaction(),
+                                   move |state, req, _action_sender| {
+                                       commands::service_release(state, req, m.clone())
+                                   }))
+            }
             "SupDepart" => {
                 let m = msg.parse::<protocol::ctl::SupDepart>()
                            .map_err(HandlerError::from)?;
@@ -35,6 +35,7 @@ pub type Result<T> = result::Result<T, Error>;
 pub enum Error {
     Departed,
     BadAddress(String),
+    BadConfigDriftPolicy(String),
     BadDataFile(PathBuf, io::Error),
     BadDataPath(PathBuf, io::Error),
     BadDesiredState(String),
@@ -57,6 +58,7 @@ pub enum Error {
     HabitatCore(habitat_core::Error),
     InvalidBinds(Vec<String>),
     InvalidCertFile(PathBuf),
+    InvalidInstanceName(String),
     InvalidKeyFile(PathBuf),
     InvalidKeyParameter(String),
     InvalidPidFile,
@@ -111,6 +113,9 @@ impl fmt::Display for Error {
         let content = match self {
             Error::APIClient(ref err) => err.to_string(),
             Error::BadAddress(ref err) => format!("Unable to bind to address {}.", err),
+            Error::BadConfigDriftPolicy(ref policy) => {
+                format!("Unknown config drift policy '{}'", policy)
+            }
             Error::Departed => "This Supervisor has been manually departed.\n\nFor the safety of \
                                 the system, this Supervisor cannot be started (if we did, we \
                                 would risk the services on this machine behaving badly without \
@@ -163,6 +168,11 @@ impl fmt::Display for Error {
             Error::GroupNotFound(ref e) => format!("No GID for group '{}' could be found", e),
             Error::InvalidBinds(ref e) => format!("Invalid bind(s), {}", e.join(", ")),
             Error::InvalidCertFile(ref path) => format!("Invalid cert file: {}", path.display()),
+            Error::InvalidInstanceName(ref name) => {
+                format!("Invalid instance name '{}': instance names may only contain letters, \
+                         digits, '-' and '_'",
+                        name)
+            }
             Error::InvalidKeyFile(ref path) => format!("Invalid key file: {}", path.display()),
             Error::InvalidKeyParameter(ref e) => {
                 format!("Invalid parameter for key generation: {:?}", e)
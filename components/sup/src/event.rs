@@ -23,11 +23,13 @@ mod stream_impl;
 mod types;
 
 pub(crate) use self::types::ServiceMetadata;
-use self::types::{EventMessage,
+use self::types::{ConfigDriftEvent,
+                  EventMessage,
                   EventMetadata,
                   HealthCheckEvent,
                   ServiceStartedEvent,
                   ServiceStoppedEvent,
+                  ServiceUpdateRolledBackEvent,
                   ServiceUpdateStartedEvent};
 use crate::manager::{service::{HealthCheckResult,
                                Service},
@@ -43,6 +45,7 @@ use habitat_core::{env::Config as EnvConfig,
 use state::Container;
 use std::{net::SocketAddr,
           num::ParseIntError,
+          path::PathBuf,
           str::FromStr,
           sync::Once,
           time::Duration};
@@ -190,6 +193,34 @@ pub fn service_update_started(service: &Service, update: &PackageIdent) {
     }
 }
 
+/// Send an event when a Service is rolled back from a failed update.
+pub fn service_update_rolled_back(service: &Service,
+                                  failed: &PackageIdent,
+                                  restored: &PackageIdent,
+                                  reason: &str) {
+    if stream_initialized() {
+        publish(ServiceUpdateRolledBackEvent { event_metadata:         None,
+                                               service_metadata:
+                                                   Some(service.to_service_metadata()),
+                                               failed_package_ident:   failed.to_string(),
+                                               restored_package_ident: restored.to_string(),
+                                               reason:                 reason.to_string(), });
+    }
+}
+
+/// Send an event when rendered configuration files or hooks of a Service were modified outside of
+/// the Supervisor. `restored` is `true` if they are being rendered again.
+pub fn config_drift(service: &Service, paths: &[PathBuf], restored: bool) {
+    if stream_initialized() {
+        publish(ConfigDriftEvent { event_metadata: None,
+                                   service_metadata: Some(service.to_service_metadata()),
+                                   paths: paths.iter()
+                                               .map(|p| p.display().to_string())
+                                               .collect(),
+                                   restored });
+    }
+}
+
 // Takes metadata directly, rather than a `&Service` like other event
 // functions, because of how the asynchronous health checking
 // currently works. Revisit when async/await + Pin is all stabilized.
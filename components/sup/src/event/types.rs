@@ -19,6 +19,7 @@ impl Into<HealthCheckResult> for DomainHealthCheckResult {
             DomainHealthCheckResult::Warning => HealthCheckResult::Warning,
             DomainHealthCheckResult::Critical => HealthCheckResult::Critical,
             DomainHealthCheckResult::Unknown => HealthCheckResult::Unknown,
+            DomainHealthCheckResult::Timeout => HealthCheckResult::Timeout,
         }
     }
 }
@@ -50,6 +51,7 @@ impl Service {
             }
             DomainUpdateStrategy::AtOnce => UpdateStrategy::AtOnce,
             DomainUpdateStrategy::Rolling => UpdateStrategy::Rolling,
+            DomainUpdateStrategy::Canary => UpdateStrategy::Canary,
         };
 
         Some(UpdateConfig { strategy: strategy.into(),
@@ -104,3 +106,5 @@ event_msg_impl!(ServiceStartedEvent);
 event_msg_impl!(ServiceStoppedEvent);
 event_msg_impl!(ServiceUpdateStartedEvent);
 event_msg_impl!(HealthCheckEvent);
+event_msg_impl!(ConfigDriftEvent);
+event_msg_impl!(ServiceUpdateRolledBackEvent);
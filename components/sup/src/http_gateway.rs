@@ -1,5 +1,6 @@
 use crate::manager::{self,
-                     service::{HealthCheckHook,
+                     service::{spec,
+                               HealthCheckHook,
                                HealthCheckResult}};
 use actix_web::{dev::{Body,
                       Service,
@@ -19,11 +20,13 @@ use futures::future::{ok,
                       Either,
                       Future};
 use habitat_common::{self,
-                     templating::hooks,
+                     templating::hooks::{self,
+                                         history},
                      types::HttpListenAddr,
                      FeatureFlag};
 use habitat_core::{crypto,
                    env as henv,
+                   fs,
                    service::ServiceGroup};
 use prometheus::{self,
                  CounterVec,
@@ -87,7 +90,9 @@ impl Into<StatusCode> for HealthCheckResult {
     fn into(self) -> StatusCode {
         match self {
             HealthCheckResult::Ok | HealthCheckResult::Warning => StatusCode::OK,
-            HealthCheckResult::Critical => StatusCode::SERVICE_UNAVAILABLE,
+            HealthCheckResult::Critical | HealthCheckResult::Timeout => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
             HealthCheckResult::Unknown => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -282,12 +287,21 @@ fn services_routes() -> Scope {
     web::scope("/services").route("", web::get().to(services))
                            .route("/{svc}/{group}", web::get().to(service_without_org))
                            .route("/{svc}/{group}/config", web::get().to(config_without_org))
+                           .service(web::resource("/{svc}/{group}/config/explain")
+                                        .route(web::get().to(config_explain_without_org))
+                                        .wrap_fn(redact_http_middleware))
                            .route("/{svc}/{group}/health", web::get().to(health_without_org))
+                           .route("/{svc}/{group}/hooks", web::get().to(hooks_without_org))
                            .route("/{svc}/{group}/{org}", web::get().to(service_with_org))
                            .route("/{svc}/{group}/{org}/config",
                                   web::get().to(config_with_org))
+                           .service(web::resource("/{svc}/{group}/{org}/config/explain")
+                                        .route(web::get().to(config_explain_with_org))
+                                        .wrap_fn(redact_http_middleware))
                            .route("/{svc}/{group}/{org}/health",
                                   web::get().to(health_with_org))
+                           .route("/{svc}/{group}/{org}/hooks",
+                                  web::get().to(hooks_with_org))
 }
 
 fn routes() -> Scope {
@@ -363,6 +377,39 @@ fn config(svc: String, group: String, org: Option<&str>, state: &AppState) -> Ht
     }
 }
 
+#[allow(clippy::needless_pass_by_value)]
+fn config_explain_with_org(path: Path<(String, String, String)>,
+                           state: Data<AppState>)
+                           -> HttpResponse {
+    let (svc, group, org) = path.into_inner();
+    config_explain(svc, group, Some(&org), &state)
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn config_explain_without_org(path: Path<(String, String)>, state: Data<AppState>) -> HttpResponse {
+    let (svc, group) = path.into_inner();
+    config_explain(svc, group, None, &state)
+}
+
+fn config_explain(svc: String,
+                  group: String,
+                  org: Option<&str>,
+                  state: &AppState)
+                  -> HttpResponse {
+    let service_group = match ServiceGroup::new(None, svc, group, org) {
+        Ok(sg) => sg,
+        Err(_) => return HttpResponse::BadRequest().finish(),
+    };
+
+    let gateway_state = &state.gateway_state
+                              .read()
+                              .expect("GatewayState lock is poisoned");
+    match gateway_state.cfg_explanation_data.get(&service_group) {
+        Some(explanation) => HttpResponse::Ok().json(explanation),
+        None => HttpResponse::NotFound().finish(),
+    }
+}
+
 #[allow(clippy::needless_pass_by_value)]
 fn health_with_org(path: Path<(String, String, String)>, state: Data<AppState>) -> HttpResponse {
     let (svc, group, org) = path.into_inner();
@@ -408,6 +455,45 @@ fn health(svc: String, group: String, org: Option<&str>, state: &AppState) -> Ht
     }
 }
 
+#[allow(clippy::needless_pass_by_value)]
+fn hooks_with_org(path: Path<(String, String, String)>, state: Data<AppState>) -> HttpResponse {
+    let (svc, group, org) = path.into_inner();
+    hooks(svc, group, Some(&org), &state)
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn hooks_without_org(path: Path<(String, String)>, state: Data<AppState>) -> HttpResponse {
+    let (svc, group) = path.into_inner();
+    hooks(svc, group, None, &state)
+}
+
+fn hooks(svc: String, group: String, org: Option<&str>, state: &AppState) -> HttpResponse {
+    let service_group = match ServiceGroup::new(None, svc, group, org) {
+        Ok(sg) => sg,
+        Err(_) => return HttpResponse::BadRequest().finish(),
+    };
+
+    let service = {
+        let data = &state.gateway_state
+                         .read()
+                         .expect("GatewayState lock is poisoned")
+                         .services_data;
+        service_from_services(&service_group, &data)
+    };
+    let service_name = match service {
+        Some(service) => spec::service_name(service_group.service(), service["instance"].as_str()),
+        None => return HttpResponse::NotFound().finish(),
+    };
+
+    match history::read_histories(&fs::svc_logs_path(service_name)) {
+        Ok(histories) => HttpResponse::Ok().json(histories),
+        Err(err) => {
+            error!("Unable to read hook history of {}: {}", service_group, err);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
 #[allow(clippy::needless_pass_by_value)]
 fn service_with_org(path: Path<(String, String, String)>, state: Data<AppState>) -> HttpResponse {
     let (svc, group, org) = path.into_inner();
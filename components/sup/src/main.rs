@@ -28,7 +28,8 @@ use crate::sup::{cli::cli,
 use clap::ArgMatches;
 use hab::cli::parse_optional_arg;
 use habitat_common::{cli::cache_key_path_from_matches,
-                     command::package::install::InstallSource,
+                     command::package::{gc::DEFAULT_KEEP_RELEASES,
+                                        install::InstallSource},
                      liveliness_checker,
                      output::{self,
                               OutputFormat,
@@ -43,13 +44,15 @@ use habitat_core::crypto::dpapi::encrypt;
 use habitat_core::{crypto::{self,
                             SymKey},
                    os::process::ShutdownTimeout,
+                   package::PackageIdent,
                    url::{bldr_url_from_env,
                          default_bldr_url},
                    ChannelIdent};
 use habitat_launcher_client::{LauncherCli,
                               ERR_NO_RETRY_EXCODE};
 use habitat_sup_protocol::{self as sup_proto,
-                           ctl::ServiceBindList,
+                           ctl::{PackageIdentList,
+                                 ServiceBindList},
                            types::{ApplicationEnvironment,
                                    BindingMode,
                                    ServiceBind,
@@ -64,7 +67,8 @@ use std::{env,
                  PathBuf},
           process,
           str::{self,
-                FromStr}};
+                FromStr},
+          time::Duration};
 #[cfg(test)]
 use tempfile::TempDir;
 
@@ -265,6 +269,12 @@ fn mgrcfg_from_sup_run_matches(m: &ArgMatches,
         }),
         feature_flags,
         event_stream_config,
+        pkg_gc_interval: m.value_of("PKG_GC_INTERVAL")
+            .and_then(|s| s.parse().ok())
+            .map(Duration::from_secs),
+        pkg_gc_keep: m.value_of("PKG_GC_KEEP")
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(DEFAULT_KEEP_RELEASES),
     };
 
     Ok(cfg)
@@ -394,6 +404,19 @@ fn get_binding_mode_from_input(m: &ArgMatches) -> Option<BindingMode> {
      .and_then(|b| BindingMode::from_str(b).ok())
 }
 
+fn get_start_after_from_input(m: &ArgMatches) -> Result<Option<PackageIdentList>> {
+    match m.values_of("START_AFTER") {
+        Some(idents) => {
+            let mut list = PackageIdentList::default();
+            for ident in idents {
+                list.idents.push(PackageIdent::from_str(ident)?.into());
+            }
+            Ok(Some(list))
+        }
+        None => Ok(None),
+    }
+}
+
 fn get_config_from_input(m: &ArgMatches) -> Option<String> {
     if let Some(ref config_from) = m.value_of("CONFIG_DIR") {
         warn!("");
@@ -462,8 +485,19 @@ fn svc_load_from_input(m: &ArgMatches) -> Result<sup_proto::ctl::SvcLoad> {
     msg.group = get_group_from_input(m);
     msg.svc_encrypted_password = get_password_from_input(m)?;
     msg.binding_mode = get_binding_mode_from_input(m).map(|v| v as i32);
+    if m.is_present("BIND_HEALTHY_ONLY") {
+        msg.bind_healthy_only = Some(true);
+    }
+    msg.start_after = get_start_after_from_input(m)?;
+    if m.is_present("REQUIRES_HEALTHY") {
+        msg.requires_healthy = Some(true);
+    }
     msg.topology = get_topology_from_input(m).map(|v| v as i32);
     msg.update_strategy = get_strategy_from_input(m).map(|v| v as i32);
+    msg.update_canaries = m.value_of("UPDATE_CANARIES").map(str::to_string);
+    msg.update_bake_period = parse_optional_arg::<u32>("UPDATE_BAKE_PERIOD", m);
+    msg.update_rollback_window = parse_optional_arg::<u32>("UPDATE_ROLLBACK_WINDOW", m);
+    msg.update_window = m.value_of("UPDATE_WINDOW").map(str::to_string);
     msg.shutdown_timeout =
         parse_optional_arg::<ShutdownTimeout>("SHUTDOWN_TIMEOUT", m).map(u32::from);
     Ok(msg)
@@ -512,6 +546,24 @@ mod test {
             assert_eq!(config.auto_update, false);
         }
 
+        #[test]
+        fn pkg_gc_should_be_set() {
+            let config =
+                config_from_cmd_str("hab-sup run --pkg-gc-interval 3600 --pkg-gc-keep 3");
+            assert_eq!(config.pkg_gc_interval, Some(Duration::from_secs(3600)));
+            assert_eq!(config.pkg_gc_keep, 3);
+
+            let config = config_from_cmd_str("hab-sup run");
+            assert_eq!(config.pkg_gc_interval, None);
+            assert_eq!(config.pkg_gc_keep, DEFAULT_KEEP_RELEASES);
+        }
+
+        #[test]
+        fn pkg_gc_interval_must_not_be_zero() {
+            let cmd_vec = cmd_vec_from_cmd_str("hab-sup run --pkg-gc-interval 0");
+            assert!(cli(no_feature_flags()).get_matches_from_safe(cmd_vec).is_err());
+        }
+
         #[test]
         fn update_url_should_be_set() {
             let config = config_from_cmd_str("hab-sup run -u http://fake.example.url");
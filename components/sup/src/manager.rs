@@ -3,14 +3,17 @@ pub mod service;
 #[macro_use]
 mod debug;
 pub mod commands;
+mod dependencies;
 mod file_watcher;
 mod peer_watcher;
 mod periodic;
+mod pkg_gc;
 mod self_updater;
 mod service_updater;
 mod spec_dir;
 mod spec_watcher;
 pub(crate) mod sys;
+mod update_probation;
 mod user_config_watcher;
 
 use self::{action::{ShutdownInput,
@@ -22,13 +25,17 @@ use self::{action::{ShutdownInput,
                      DesiredState,
                      HealthCheckResult,
                      Service,
+                     ServiceName,
                      ServiceProxy,
                      ServiceSpec,
                      Topology},
-           service_updater::ServiceUpdater,
+           service_updater::{ServiceUpdater,
+                             UpdateAction},
            spec_dir::SpecDir,
            spec_watcher::SpecWatcher,
            sys::Sys,
+           update_probation::{Outcome,
+                              UpdateProbation},
            user_config_watcher::UserConfigWatcher};
 use crate::{census::{CensusRing,
                      CensusRingProxy},
@@ -54,6 +61,7 @@ use habitat_butterfly::{member::Member,
                         trace::Trace};
 use habitat_common::{liveliness_checker,
                      outputln,
+                     templating::config::CfgExplanation,
                      types::{GossipListenAddr,
                              HttpListenAddr,
                              ListenCtlAddr},
@@ -102,6 +110,7 @@ use std::{collections::{HashMap,
                Read,
                Write},
           iter::IntoIterator,
+          mem,
           net::SocketAddr,
           path::{Path,
                  PathBuf},
@@ -127,6 +136,11 @@ use winapi::{shared::minwindef::PDWORD,
              um::processthreadsapi};
 
 const MEMBER_ID_FILE: &str = "MEMBER_ID";
+/// How often the resource usage of services is read for the `/services` endpoint.
+const RESOURCE_USAGE_INTERVAL_SECS: i64 = 10;
+/// How often the dependencies of services waiting for them are checked again, besides whenever
+/// the census changes.
+const DEPENDENCY_CHECK_INTERVAL_SECS: i64 = 5;
 pub const PROC_LOCK_FILE: &str = "LOCK";
 
 static LOGKEY: &'static str = "MR";
@@ -253,6 +267,8 @@ pub struct ManagerConfig {
     pub tls_config:          Option<TLSConfig>,
     pub feature_flags:       FeatureFlag,
     pub event_stream_config: Option<EventStreamConfig>,
+    pub pkg_gc_interval:     Option<StdDuration>,
+    pub pkg_gc_keep:         usize,
 }
 
 #[derive(Clone, Debug)]
@@ -277,15 +293,35 @@ impl ManagerConfig {
         spec.to_file(self.spec_path_for(spec))
     }
 
-    /// Given a `PackageIdent`, return current spec if it exists.
-    pub fn spec_for_ident(&self, ident: &PackageIdent) -> Option<ServiceSpec> {
-        let default_spec = ServiceSpec::default_for(ident.clone());
+    /// Given a `PackageIdent` and the instance of the package, if any, return current spec if
+    /// it exists.
+    pub fn spec_for_ident(&self,
+                          ident: &PackageIdent,
+                          instance: Option<&str>)
+                          -> Option<ServiceSpec> {
+        let mut default_spec = ServiceSpec::default_for(ident.clone());
+        default_spec.instance = instance.map(str::to_string);
         let spec_file = self.spec_path_for(&default_spec);
 
         // JC: This mimics the logic from when we had composites.  But
         // should we check for Err ?
         ServiceSpec::from_file(&spec_file).ok()
     }
+
+    /// Applies `change` to the spec of the service `spec` is the spec of, as it is on disk, and
+    /// saves it. Does nothing if the service has no spec on disk anymore.
+    pub fn update_spec_for<F>(&self, spec: &ServiceSpec, change: F) -> Result<()>
+        where F: FnOnce(&mut ServiceSpec)
+    {
+        let instance = spec.instance.as_ref().map(String::as_str);
+        match self.spec_for_ident(&spec.ident, instance) {
+            Some(mut spec) => {
+                change(&mut spec);
+                self.save_spec_for(&spec)
+            }
+            None => Ok(()),
+        }
+    }
 }
 
 /// Once a formerly-busy service is no longer doing something
@@ -355,7 +391,7 @@ impl ReconciliationFlag {
 pub struct ManagerState {
     /// The configuration used to instantiate this Manager instance
     pub cfg: ManagerConfig,
-    pub services: Arc<RwLock<HashMap<PackageIdent, Service>>>,
+    pub services: Arc<RwLock<HashMap<ServiceName, Service>>>,
     pub gateway_state: Arc<RwLock<GatewayState>>,
 }
 
@@ -372,6 +408,9 @@ pub struct GatewayState {
     /// Data returned by /services/<SERVICE_NAME>/<GROUP_NAME>/health
     /// endpoint
     pub health_check_data: HashMap<ServiceGroup, HealthCheckResult>,
+    /// Data returned by /services/<SERVICE_NAME>/<GROUP_NAME>/config/explain
+    /// endpoint
+    pub cfg_explanation_data: HashMap<ServiceGroup, CfgExplanation>,
 }
 
 pub struct Manager {
@@ -396,7 +435,7 @@ pub struct Manager {
     spec_dir:            SpecDir,
     organization:        Option<String>,
     self_updater:        Option<SelfUpdater>,
-    service_states:      HashMap<PackageIdent, Timespec>,
+    service_states:      HashMap<ServiceName, Timespec>,
     sys:                 Arc<Sys>,
     http_disable:        bool,
 
@@ -413,8 +452,17 @@ pub struct Manager {
     // that point, we might need / want to change from a HashSet to
     // something else (maybe a HashMap?) in order to cleanly manage
     // the different operations.
-    busy_services: Arc<Mutex<HashSet<PackageIdent>>>,
+    busy_services: Arc<Mutex<HashSet<ServiceName>>>,
     services_need_reconciliation: ReconciliationFlag,
+    /// The package each service ran before it was stopped for an update, keyed by service name.
+    /// Consumed when the updated service is started again, so it can run its post-update hook.
+    updated_services: HashMap<ServiceName, PackageIdent>,
+    /// Services which were updated and are rolled back unless the new release becomes healthy,
+    /// keyed by service name.
+    update_probations: HashMap<ServiceName, UpdateProbation>,
+    /// Services which are not started until their dependencies are ready, keyed by service name,
+    /// with what they are waiting for.
+    waiting_for_dependencies: HashMap<ServiceName, String>,
 
     feature_flags: FeatureFlag,
 }
@@ -467,7 +515,7 @@ impl Manager {
         let cfg_static = cfg.clone();
         let self_updater = if cfg.auto_update {
             if current.fully_qualified() {
-                Some(SelfUpdater::new(current, cfg.update_url, cfg.update_channel))
+                Some(SelfUpdater::new(current.clone(), cfg.update_url, cfg.update_channel))
             } else {
                 warn!("Supervisor version not fully qualified, unable to start self-updater");
                 None
@@ -510,6 +558,14 @@ impl Manager {
 
         let spec_watcher = SpecWatcher::run(&spec_dir)?;
 
+        if let Some(interval) = cfg.pkg_gc_interval {
+            pkg_gc::spawn(interval,
+                          cfg.pkg_gc_keep,
+                          current.clone(),
+                          services.clone(),
+                          spec_dir.clone());
+        }
+
         if cfg.feature_flags.contains(FeatureFlag::EVENT_STREAM) {
             // Putting configuration of the stream behind a feature
             // flag for now. If the flag isn't set, just don't
@@ -552,6 +608,9 @@ impl Manager {
                      http_disable: cfg.http_disable,
                      busy_services: Arc::new(Mutex::new(HashSet::new())),
                      services_need_reconciliation: ReconciliationFlag::new(false),
+                     updated_services: HashMap::new(),
+                     update_probations: HashMap::new(),
+                     waiting_for_dependencies: HashMap::new(),
                      feature_flags: cfg.feature_flags })
     }
 
@@ -642,7 +701,7 @@ impl Manager {
         // back to us. Since we consume and deconstruct the spec in `Service::new()` which
         // `Service::load()` eventually delegates to we just can't have that. We should clean
         // this up in the future.
-        let service = match Service::load(self.sys.clone(),
+        let mut service = match Service::load(self.sys.clone(),
                                           spec.clone(),
                                           self.fs_cfg.clone(),
                                           self.organization.as_ref().map(|org| &**org),
@@ -657,6 +716,20 @@ impl Manager {
                 return;
             }
         };
+        if let Some(from) = self.updated_services.remove(&spec.service_name()) {
+            if from != service.pkg.ident {
+                service.updated_from = Some(from);
+            }
+        }
+        if update_probation::start(&mut self.update_probations, &mut service) {
+            let probation = service.update_probation.clone();
+            if let Err(err) = self.state
+                                  .cfg
+                                  .update_spec_for(spec, |spec| spec.update_probation = probation)
+            {
+                outputln!("Unable to save the update probation of {}, {}", &spec.ident, err);
+            }
+        }
 
         if let Ok(package) =
             PackageInstall::load(&service.pkg.ident, Some(Path::new(&*FS_ROOT_PATH)))
@@ -709,7 +782,7 @@ impl Manager {
             .services
             .write()
             .expect("Services lock is poisoned!")
-            .insert(service.spec_ident.clone(), service);
+            .insert(service.service_name().to_string(), service);
     }
 
     // If we ever need to modify this function, it would be an excellent opportunity to
@@ -727,6 +800,8 @@ impl Manager {
         let service_hist = RUN_LOOP_DURATION.with_label_values(&["service"]);
         let mut next_cpu_measurement = SteadyTime::now();
         let mut cpu_start = ProcessTime::now();
+        let mut next_resource_usage_check = SteadyTime::now();
+        let mut next_dependency_check = SteadyTime::now();
 
         let mut runtime =
             RuntimeBuilder::new().name_prefix("tokio-")
@@ -980,8 +1055,18 @@ impl Manager {
             // Takes into account filesystem events in the specs
             // directory, as well as whether or not we need to
             // reexamine specs after finishing some asynchronous
-            // operation on a service.
-            if self.spec_watcher.has_events() || self.services_need_reconciliation.is_set() {
+            // operation on a service, or services waiting for their
+            // dependencies, which are checked again when the census
+            // changes and every so often.
+            let check_dependencies = !self.waiting_for_dependencies.is_empty()
+                                     && (self.census_ring.changed()
+                                         || SteadyTime::now() >= next_dependency_check);
+            if self.spec_watcher.has_events()
+               || self.services_need_reconciliation.is_set()
+               || check_dependencies
+            {
+                next_dependency_check =
+                    SteadyTime::now() + TimeDuration::seconds(DEPENDENCY_CHECK_INTERVAL_SECS);
                 // This call *must* come first. If some other future
                 // happens to complete before we get done spawning our
                 // current batch of futures, it could set the flag to
@@ -1058,6 +1143,14 @@ impl Manager {
                 next_cpu_measurement = SteadyTime::now() + TimeDuration::seconds(1);
                 cpu_start = ProcessTime::now();
             }
+
+            // Resource usage changes all the time, so rather than whenever it changes, it is
+            // refreshed (and the services state persisted) on an interval
+            if SteadyTime::now() >= next_resource_usage_check {
+                self.refresh_resource_usage();
+                next_resource_usage_check =
+                    SteadyTime::now() + TimeDuration::seconds(RESOURCE_USAGE_INTERVAL_SECS);
+            }
         }; // end main loop
 
         // When we make it down here, we've broken out of the main
@@ -1092,9 +1185,23 @@ impl Manager {
                                    .write()
                                    .expect("Services lock is poisoned!");
 
-                for (_ident, svc) in svcs.drain() {
-                    runtime.spawn(self.stop(svc));
+                // Stop services before the services they depend on,
+                // one batch after the other.
+                let specs: Vec<ServiceSpec> = svcs.values().map(Service::to_spec).collect();
+                let mut shutdown: Box<dyn Future<Item = (), Error = ()> + Send> =
+                    Box::new(future::ok(()));
+                for batch in dependencies::stop_batches(specs) {
+                    let stops: Vec<_> = batch.iter()
+                                             .filter_map(|spec| {
+                                                 svcs.remove(&spec.service_name())
+                                             })
+                                             .map(|svc| self.stop_lazily(svc))
+                                             .collect();
+                    shutdown = Box::new(shutdown.and_then(move |_| {
+                                                    future::join_all(stops).map(|_| ())
+                                                }));
                 }
+                runtime.spawn(shutdown);
             }
         }
 
@@ -1134,28 +1241,165 @@ impl Manager {
                                      .services
                                      .write()
                                      .expect("Services lock is poisoned!");
-        let idents_to_restart: Vec<_> = state_services.iter()
+        // Releases to block, with the release to pin the service to instead, if any
+        let mut rollbacks = Vec::new();
+        let mut passed_probations = Vec::new();
+        for service in state_services.values_mut() {
+            match update_probation::check(&mut self.update_probations, service) {
+                Some(Outcome::Passed) => passed_probations.push(service.to_spec()),
+                Some(Outcome::Failed { from, to }) => {
+                    rollbacks.push((service.to_spec(), to, Some(from)))
+                }
+                None => {}
+            }
+        }
+        // Releases that other members of the service group blocked
+        let mut adopted_blocks = Vec::new();
+        // Services with an update that waits for the update window
+        let mut pending_updates = Vec::new();
+        let census_ring = &self.census_ring;
+        let idents_to_restart: Vec<_> = state_services.iter_mut()
             .filter_map(|(current_ident, service)| {
+                if let Some(census_group) = census_ring.census_group_for(&service.service_group) {
+                    let releases: Vec<PackageIdent> =
+                        census_group.blocked_releases()
+                                    .into_iter()
+                                    .filter(|release| {
+                                        release.satisfies(&service.spec_ident)
+                                        && !service.blocked_releases.contains(release)
+                                    })
+                                    .cloned()
+                                    .collect();
+                    if !releases.is_empty() {
+                        service.blocked_releases.extend(releases.iter().cloned());
+                        adopted_blocks.push((service.to_spec(), releases));
+                    }
+                }
                 if service.needs_restart {
-                    Some(current_ident.clone())
-                } else if let Some(new_ident) =
-                    updater.check_for_updated_package_mlr(&service, &self.census_ring)
-                {
-                    outputln!("Updating from {} to {}", current_ident, new_ident);
-                    event::service_update_started(&service, &new_ident);
-                    Some(current_ident.clone())
-                } else {
-                    trace!("No update found for {}", current_ident);
-                    None
+                    return Some((current_ident.clone(), false));
+                }
+                if service.blocked_releases.contains(&service.pkg.ident) {
+                    outputln!("Rolling back {}, its release is blocked in the service group",
+                              current_ident);
+                    return Some((current_ident.clone(), false));
                 }
+                // Held services stay at their release until they are released
+                if service.held_release.is_some() {
+                    return None;
+                }
+                match updater.check_for_updated_package_mlr(&service, census_ring) {
+                    Some(UpdateAction::Update(ref new_ident))
+                        if service.blocked_releases.contains(new_ident) =>
+                    {
+                        trace!("Ignoring blocked release {} for {}", new_ident, current_ident)
+                    }
+                    Some(UpdateAction::Update(new_ident)) => {
+                        if service.pending_update.as_ref() != Some(&new_ident)
+                           && !service.apply_update_now
+                           && !service.update_window_is_open()
+                        {
+                            outputln!("Update of {} to {} is pending until the update window \
+                                       opens", current_ident, new_ident);
+                            // The pending update is saved in the spec and the service pinned
+                            // to its release, so that a restart doesn't apply the update early
+                            service.pending_update = Some(new_ident);
+                            service.pinned_release = Some(service.pkg.ident.clone());
+                            pending_updates.push(service.to_spec());
+                        } else {
+                            service.pending_update = Some(new_ident);
+                        }
+                    }
+                    Some(UpdateAction::RollBack(release)) => {
+                        outputln!("Rolling back {} from {}", current_ident, release);
+                        rollbacks.push((service.to_spec(), release, None));
+                        return None;
+                    }
+                    None => trace!("No update found for {}", current_ident),
+                }
+                // Updates wait for the update window, unless they were asked to be applied now
+                if !service.apply_update_now && !service.update_window_is_open() {
+                    return None;
+                }
+                let new_ident = service.pending_update.take()?;
+                outputln!("Updating from {} to {}", current_ident, new_ident);
+                event::service_update_started(&service, &new_ident);
+                Some((current_ident.clone(), true))
             })
             .collect();
 
+        // Rolling back is done by blocking the bad release in the service's spec and pinning the
+        // service to the release it ran before the update. The spec watcher notices the change
+        // and the service is restarted on the pinned release. The blocked release is gossiped
+        // with the service, so the rest of the service group blocks it as well.
+        for (service_spec, release, from) in rollbacks {
+            let result = self.state.cfg.update_spec_for(&service_spec, |spec| {
+                if !spec.blocked_releases.contains(&release) {
+                    spec.blocked_releases.push(release);
+                }
+                if from.is_some() {
+                    spec.pinned_release = from;
+                }
+                spec.update_probation = None;
+            });
+            if let Err(err) = result {
+                outputln!("Unable to block release for {}: {}", service_spec.ident, err);
+            }
+        }
+        for (service_spec, releases) in adopted_blocks {
+            let result = self.state.cfg.update_spec_for(&service_spec, |spec| {
+                for release in releases {
+                    if !spec.blocked_releases.contains(&release) {
+                        spec.blocked_releases.push(release);
+                    }
+                }
+            });
+            if let Err(err) = result {
+                outputln!("Unable to block release for {}: {}", service_spec.ident, err);
+            }
+        }
+        for service_spec in pending_updates {
+            let result = self.state.cfg.update_spec_for(&service_spec, |spec| {
+                spec.pending_update = service_spec.pending_update.clone();
+                spec.pinned_release = service_spec.pinned_release.clone();
+            });
+            if let Err(err) = result {
+                outputln!("Unable to save the pending update of {}: {}",
+                          service_spec.ident,
+                          err);
+            }
+        }
+        for service_spec in passed_probations {
+            let result = self.state.cfg.update_spec_for(&service_spec, |spec| {
+                spec.update_probation = None;
+            });
+            if let Err(err) = result {
+                outputln!("Unable to save the update probation of {}: {}",
+                          service_spec.ident,
+                          err);
+            }
+        }
+
         let mut services_to_restart = Vec::with_capacity(idents_to_restart.len());
-        for current_ident in idents_to_restart {
+        for (current_ident, updated) in idents_to_restart {
             // unwrap is safe because we've to the write lock, and we
             // know there's a value present at this key.
-            services_to_restart.push(state_services.remove(&current_ident).unwrap());
+            let service = state_services.remove(&current_ident).unwrap();
+            if updated {
+                self.updated_services.insert(service.service_name().to_string(),
+                                             service.pkg.ident.clone());
+                // An update replaces the release the service was pinned to, by a rollback or
+                // while the update was pending
+                if service.pinned_release.is_some() {
+                    let result = self.state.cfg.update_spec_for(&service.to_spec(), |spec| {
+                        spec.pinned_release = None;
+                        spec.pending_update = None;
+                    });
+                    if let Err(err) = result {
+                        outputln!("Unable to unpin {}: {}", service.spec_ident, err);
+                    }
+                }
+            }
+            services_to_restart.push(service);
         }
         services_to_restart
     }
@@ -1215,16 +1459,16 @@ impl Manager {
                            .expect("Services lock is poisoned!")
                            .values_mut()
         {
-            service_states.insert(service.spec_ident.clone(), service.last_state_change());
-            active_services.push(service.spec_ident.clone());
+            service_states.insert(service.service_name().to_string(), service.last_state_change());
+            active_services.push(service.service_name().to_string());
         }
 
         for loaded in self.spec_dir
                           .specs()
                           .iter()
-                          .filter(|s| !active_services.contains(&s.ident))
+                          .filter(|s| !active_services.contains(&s.service_name()))
         {
-            service_states.insert(loaded.ident.clone(), Timespec::new(0, 0));
+            service_states.insert(loaded.service_name(), Timespec::new(0, 0));
         }
 
         if service_states != self.service_states {
@@ -1270,6 +1514,18 @@ impl Manager {
             .butterfly_data = json;
     }
 
+    fn refresh_resource_usage(&self) {
+        for service in self.state
+                           .services
+                           .read()
+                           .expect("Services lock is poisoned!")
+                           .values()
+        {
+            service.refresh_resource_usage();
+        }
+        self.persist_services_state();
+    }
+
     fn persist_services_state(&self) {
         let config_rendering = if self.feature_flags.contains(FeatureFlag::REDACT_HTTP) {
             ConfigRendering::Redacted
@@ -1281,8 +1537,7 @@ impl Manager {
                            .services
                            .read()
                            .expect("Services lock is poisoned!");
-        let existing_idents: Vec<PackageIdent> =
-            services.values().map(|s| s.spec_ident.clone()).collect();
+        let existing_names: Vec<ServiceName> = services.keys().cloned().collect();
 
         // Services that are not active but are being watched for changes
         // These would include stopped persistent services or other
@@ -1291,7 +1546,7 @@ impl Manager {
             self.spec_dir
                 .specs()
                 .iter()
-                .filter(|spec| !existing_idents.contains(&spec.ident))
+                .filter(|spec| !existing_names.contains(&spec.service_name()))
                 .flat_map(|spec| {
                     Service::load(self.sys.clone(),
                                   spec.clone(),
@@ -1311,12 +1566,27 @@ impl Manager {
 
         services_to_render.extend(watched_service_proxies);
 
+        let cfg_explanations =
+            services.values()
+                    .filter_map(|s| {
+                        match s.cfg.explain() {
+                            Ok(explanation) => Some((s.service_group.clone(), explanation)),
+                            Err(err) => {
+                                outputln!(preamble s.service_group,
+                                          "Unable to explain configuration, {}", err);
+                                None
+                            }
+                        }
+                    })
+                    .collect();
+
         let json = serde_json::to_string(&services_to_render).unwrap();
-        self.state
-            .gateway_state
-            .write()
-            .expect("GatewayState lock is poisoned")
-            .services_data = json;
+        let mut gateway_state = self.state
+                                    .gateway_state
+                                    .write()
+                                    .expect("GatewayState lock is poisoned");
+        gateway_state.services_data = json;
+        gateway_state.cfg_explanation_data = cfg_explanations;
     }
 
     /// Check if any elections need restarting.
@@ -1345,18 +1615,36 @@ impl Manager {
         self.stop_with_config(service, shutdown_config)
     }
 
+    /// Like `stop`, but doesn't begin stopping the service until the
+    /// returned future is polled.
+    fn stop_lazily(&self, service: Service) -> impl Future<Item = (), Error = ()> + Send {
+        let shutdown_config = ShutdownConfig::new_from_service(&service);
+        let user_config_watcher = Arc::clone(&self.user_config_watcher);
+        let updater = Arc::clone(&self.updater);
+        let busy_services = Arc::clone(&self.busy_services);
+        let services_need_reconciliation = self.services_need_reconciliation.clone();
+        future::lazy(move || {
+            Self::service_stop_future(service,
+                                      shutdown_config,
+                                      user_config_watcher,
+                                      updater,
+                                      busy_services,
+                                      services_need_reconciliation)
+        })
+    }
+
     /// Remove the given service from the manager.
     fn service_stop_future(mut service: Service,
                            shutdown_config: ShutdownConfig,
                            user_config_watcher: Arc<RwLock<UserConfigWatcher>>,
                            updater: Arc<Mutex<ServiceUpdater>>,
-                           busy_services: Arc<Mutex<HashSet<PackageIdent>>>,
+                           busy_services: Arc<Mutex<HashSet<ServiceName>>>,
                            services_need_reconciliation: ReconciliationFlag)
                            -> impl Future<Item = (), Error = ()> {
         // JW TODO: Update service rumor to remove service from
         // cluster
         // TODO (CM): But only if we're not going down for a restart.
-        let ident = service.spec_ident.clone();
+        let name = service.service_name().to_string();
         let stop_it = service.stop(shutdown_config).then(move |_| {
                                                        event::service_stopped(&service);
                                                        user_config_watcher.write()
@@ -1368,7 +1656,7 @@ impl Manager {
                                                               .remove(&service);
                                                        Ok(())
                                                    });
-        Self::wrap_async_service_operation(ident,
+        Self::wrap_async_service_operation(name,
                                            busy_services,
                                            services_need_reconciliation,
                                            stop_it)
@@ -1388,8 +1676,8 @@ impl Manager {
     /// As more service operations (e.g., hooks) become asynchronous,
     /// we'll need to wrap those operations in this logic to ensure
     /// consistent operation.
-    fn wrap_async_service_operation<F>(ident: PackageIdent,
-                                       busy_services: Arc<Mutex<HashSet<PackageIdent>>>,
+    fn wrap_async_service_operation<F>(name: ServiceName,
+                                       busy_services: Arc<Mutex<HashSet<ServiceName>>>,
                                        services_need_reconciliation: ReconciliationFlag,
                                        fut: F)
                                        -> impl Future<Item = (), Error = ()>
@@ -1397,22 +1685,22 @@ impl Manager {
     {
         // TODO (CM): can't wait for the Pinning API :(
         let busy_services_2 = Arc::clone(&busy_services);
-        let ident_2 = ident.clone();
+        let name_2 = name.clone();
 
         future::lazy(move || {
             trace!("Flagging '{:?}' as busy, pending an asynchronous operation",
-                   ident);
+                   name);
             busy_services.lock()
                          .expect("busy_services lock is poisoned")
-                         .insert(ident);
+                         .insert(name);
             Ok(())
         }).and_then(|_| fut)
           .and_then(move |_| {
               trace!("Removing 'busy' flag for '{:?}'; asynchronous operation over",
-                     ident_2);
+                     name_2);
               busy_services_2.lock()
                              .expect("busy_services lock is poisoned")
-                             .remove(&ident_2);
+                             .remove(&name_2);
               services_need_reconciliation.set();
               Ok(())
           })
@@ -1442,7 +1730,7 @@ impl Manager {
             .services
             .write()
             .expect("Services lock is poisoned")
-            .remove(&spec.ident)
+            .remove(&spec.service_name())
     }
 
     /// Start, stop, or restart services to bring what's running in
@@ -1460,38 +1748,60 @@ impl Manager {
     fn operations_into_futures_mlw<O>(&mut self, ops: O) -> Vec<impl Future<Item = (), Error = ()>>
         where O: IntoIterator<Item = ServiceOperation>
     {
-        ops.into_iter()
-           .filter_map(|op| {
-               match op {
-                   ServiceOperation::Stop(spec)
-                   | ServiceOperation::Restart { to_stop: spec, .. } => {
-                       // Yes, Stop and Restart both turn into
-                       // "stop"... Once we've finished stopping, we'll
-                       // end up re-examining the spec file on disk; if
-                       // we should be running, we'll start up again.
-                       //
-                       // This may change in the future, once service
-                       // start can be performed asynchronously in a
-                       // future; then we could just chain that future
-                       // onto the end of the stop one for a *real*
-                       // restart future.
-                       let f = self.remove_service_from_state(&spec)
-                                   .map(|service| self.stop(service));
-                       if f.is_none() {
-                           // We really don't expect this to happen....
-                           outputln!("Tried to remove service for {} but could not find it \
-                                      running, skipping",
-                                     &spec.ident);
-                       }
-                       f
-                   }
-                   ServiceOperation::Start(spec) => {
-                       self.add_service_mlw(&spec);
-                       None // No future to return (currently synchronous!)
-                   }
-               }
-           })
-           .collect()
+        let mut stops = Vec::new();
+        let mut starts = Vec::new();
+        for op in ops {
+            match op {
+                ServiceOperation::Stop(spec) | ServiceOperation::Restart { to_stop: spec, .. } => {
+                    // Yes, Stop and Restart both turn into
+                    // "stop"... Once we've finished stopping, we'll
+                    // end up re-examining the spec file on disk; if
+                    // we should be running, we'll start up again.
+                    //
+                    // This may change in the future, once service
+                    // start can be performed asynchronously in a
+                    // future; then we could just chain that future
+                    // onto the end of the stop one for a *real*
+                    // restart future.
+                    match self.remove_service_from_state(&spec) {
+                        Some(service) => stops.push(self.stop(service)),
+                        None => {
+                            // We really don't expect this to happen....
+                            outputln!("Tried to remove service for {} but could not find it \
+                                       running, skipping",
+                                      &spec.ident);
+                        }
+                    }
+                }
+                ServiceOperation::Start(spec) => starts.push(spec),
+            }
+        }
+        // Starts are synchronous, so starting services after their
+        // dependencies lets a dependent service start in the same pass.
+        let mut previously_waiting = mem::replace(&mut self.waiting_for_dependencies,
+                                                  HashMap::new());
+        let starts = dependencies::start_order(starts);
+        for spec in &starts {
+            let unmet = {
+                let services = self.state
+                                   .services
+                                   .read()
+                                   .expect("Services lock is poisoned");
+                let cycle = dependencies::cycle_members(spec, &starts);
+                dependencies::unmet_dependency(spec, &cycle, &services, &self.census_ring)
+            };
+            match unmet {
+                Some(reason) => {
+                    let name = spec.service_name();
+                    if previously_waiting.remove(&name).as_ref() != Some(&reason) {
+                        outputln!("Waiting to start {} until {}", name, reason);
+                    }
+                    self.waiting_for_dependencies.insert(name, reason);
+                }
+                None => self.add_service_mlw(spec),
+            }
+        }
+        stops
     }
 
     /// Determine what services we need to start, stop, or restart in
@@ -1516,7 +1826,7 @@ impl Manager {
         let on_disk_specs = self.spec_dir
                                 .specs()
                                 .into_iter()
-                                .filter(|s| !busy_services.contains(&s.ident));
+                                .filter(|s| !busy_services.contains(&s.service_name()));
 
         Self::specs_to_operations(currently_running_specs, on_disk_specs)
     }
@@ -1539,14 +1849,13 @@ impl Manager {
         }
 
         for rs in currently_running_specs {
-            svc_states.insert(rs.ident.clone(),
+            svc_states.insert(rs.service_name(),
                               ServiceState { running: Some(rs),
                                              disk:    None, });
         }
 
         for ds in on_disk_specs {
-            let ident = ds.ident.clone();
-            svc_states.entry(ident)
+            svc_states.entry(ds.service_name())
                       .or_insert_with(ServiceState::default)
                       .disk = Some((ds.desired_state, ds));
         }
@@ -1693,7 +2002,7 @@ habitat_core::env_config_int!(/// Represents how many threads to start for our m
                               num_cpus::get().max(1));
 
 #[derive(Debug)]
-struct SuitabilityLookup(Arc<RwLock<HashMap<PackageIdent, Service>>>);
+struct SuitabilityLookup(Arc<RwLock<HashMap<ServiceName, Service>>>);
 
 impl Suitability for SuitabilityLookup {
     fn get(&self, service_group: &str) -> u64 {
@@ -1827,7 +2136,8 @@ fn track_memory_stats() {}
 #[cfg(test)]
 mod test {
     use super::*;
-    use habitat_common::cli::FS_ROOT;
+    use habitat_common::{cli::FS_ROOT,
+                         command::package::gc::DEFAULT_KEEP_RELEASES};
     use habitat_core::fs::cache_key_path;
     use habitat_sup_protocol::STATE_PATH_PREFIX;
     use std::path::PathBuf;
@@ -1868,7 +2178,9 @@ mod test {
                             watch_peer_file:     None,
                             tls_config:          None,
                             feature_flags:       FeatureFlag::empty(),
-                            event_stream_config: None, }
+                            event_stream_config: None,
+                            pkg_gc_interval:     None,
+                            pkg_gc_keep:         DEFAULT_KEEP_RELEASES, }
         }
     }
 
@@ -7,15 +7,19 @@ use crate::{ctl_gateway::CtlRequest,
                       service::{spec::{IntoServiceSpec,
                                        ServiceSpec},
                                 DesiredState,
-                                ProcessState},
+                                ProcessState,
+                                Service},
+                      spec_dir::SpecDir,
                       ManagerState},
             util};
 use habitat_butterfly as butterfly;
 use habitat_common::{command::package::install::InstallSource,
                      outputln,
-                     templating::package::Pkg,
+                     templating::{hooks::history,
+                                  package::Pkg},
                      ui::UIWriter};
-use habitat_core::{package::{Identifiable,
+use habitat_core::{fs,
+                   package::{Identifiable,
                              PackageIdent,
                              PackageTarget},
                    service::ServiceGroup,
@@ -25,7 +29,8 @@ use habitat_sup_protocol::{self as protocol,
                                  ErrCode,
                                  NetResult}};
 use serde_json;
-use std::{fmt,
+use std::{convert::TryFrom,
+          fmt,
           result};
 use time::{self,
            Duration as TimeDuration,
@@ -38,18 +43,31 @@ pub fn service_cfg(mgr: &ManagerState,
                    req: &mut CtlRequest,
                    opts: protocol::ctl::SvcGetDefaultCfg)
                    -> NetResult<()> {
-    let ident: PackageIdent = opts.ident.ok_or_else(err_update_client)?.into();
+    let ident: PackageIdent = ident_from(opts.ident)?;
+    let instance = opts.instance.as_ref().map(String::as_str);
     let mut msg = protocol::types::ServiceCfg { format:
                                                     Some(protocol::types::service_cfg::Format::Toml
                                                          as i32),
-                                                default: None, };
+                                                default: None,
+                                                explanation: None, };
     for service in mgr.services
                       .read()
                       .expect("Services lock is poisoned")
                       .values()
     {
-        if service.pkg.ident.satisfies(&ident) {
-            if let Some(ref cfg) = service.cfg.default {
+        if is_service(service, &ident, instance) {
+            if opts.explain.unwrap_or(false) {
+                let explanation = service.cfg.explain().map_err(|e| {
+                                                           net::err(ErrCode::Internal,
+                                                                    e.to_string())
+                                                       })?;
+                msg.explanation =
+                    Some(serde_json::to_string(&explanation).map_err(|e| {
+                                                               net::err(ErrCode::Internal,
+                                                                        e.to_string())
+                                                           })?);
+                req.reply_complete(msg);
+            } else if let Some(ref cfg) = service.cfg.default {
                 msg.default =
                     Some(toml::to_string_pretty(&toml::value::Value::Table(cfg.clone())).unwrap());
                 req.reply_complete(msg);
@@ -57,7 +75,8 @@ pub fn service_cfg(mgr: &ManagerState,
             return Ok(());
         }
     }
-    Err(net::err(ErrCode::NotFound, format!("Service not loaded, {}", ident)))
+    Err(net::err(ErrCode::NotFound,
+                 format!("Service not loaded, {}", qualified(&ident, instance))))
 }
 
 pub fn service_cfg_validate(_mgr: &ManagerState,
@@ -184,7 +203,7 @@ pub fn service_load(mgr: &ManagerState,
                     req: &mut CtlRequest,
                     opts: &protocol::ctl::SvcLoad)
                     -> NetResult<()> {
-    let ident: PackageIdent = opts.ident.clone().ok_or_else(err_update_client)?.into();
+    let ident: PackageIdent = ident_from(opts.ident.clone())?;
     let bldr_url = opts.bldr_url
                        .clone()
                        .unwrap_or_else(|| protocol::DEFAULT_BLDR_URL.to_string());
@@ -193,11 +212,19 @@ pub fn service_load(mgr: &ManagerState,
                            .map(ChannelIdent::from)
                            .unwrap_or_default();
     let force = opts.force.unwrap_or(false);
+    let instance = opts.instance.as_ref().map(String::as_str);
+    if let Some(instance) = instance {
+        if !protocol::ctl::valid_instance_name(instance) {
+            return Err(net::err(ErrCode::BadPayload,
+                                Error::InvalidInstanceName(instance.to_string())));
+        }
+    }
     let source = InstallSource::Ident(ident.clone(), PackageTarget::active_target());
-    match mgr.cfg.spec_for_ident(source.as_ref()) {
+    match mgr.cfg.spec_for_ident(source.as_ref(), instance) {
         None => {
             let mut spec = ServiceSpec::default();
-            opts.into_spec(&mut spec);
+            opts.into_spec(&mut spec).map_err(|e| net::err(ErrCode::BadPayload, e))?;
+            check_group_is_free(mgr, &spec)?;
 
             // We don't have any record of this thing; let's set it up!
             //
@@ -208,7 +235,8 @@ pub fn service_load(mgr: &ManagerState,
             util::pkg::satisfy_or_install(req, &source, &bldr_url, &bldr_channel)?;
 
             mgr.cfg.save_spec_for(&spec)?;
-            req.info(format!("The {} service was successfully loaded", spec.ident))?;
+            req.info(format!("The {} service was successfully loaded",
+                             qualified(&spec.ident, instance)))?;
         }
         Some(mut spec) => {
             // We've seen this service  before. Thus `load`
@@ -222,10 +250,11 @@ pub fn service_load(mgr: &ManagerState,
                 return Err(net::err(ErrCode::Conflict,
                                     format!("Service already loaded, unload '{}' \
                                              and try again",
-                                            ident)));
+                                            qualified(&ident, instance))));
             }
 
-            opts.into_spec(&mut spec);
+            opts.into_spec(&mut spec).map_err(|e| net::err(ErrCode::BadPayload, e))?;
+            check_group_is_free(mgr, &spec)?;
 
             // Only install if we don't have something
             // locally; otherwise you could potentially
@@ -236,7 +265,8 @@ pub fn service_load(mgr: &ManagerState,
             util::pkg::satisfy_or_install(req, &source, &spec.bldr_url, &spec.channel)?;
 
             mgr.cfg.save_spec_for(&spec)?;
-            req.info(format!("The {} service was successfully loaded", spec.ident))?;
+            req.info(format!("The {} service was successfully loaded",
+                             qualified(&spec.ident, instance)))?;
         }
     }
     req.reply_complete(net::ok());
@@ -248,8 +278,12 @@ pub fn service_unload(mgr: &ManagerState,
                       opts: protocol::ctl::SvcUnload,
                       action_sender: &ActionSender)
                       -> NetResult<()> {
-    let ident: PackageIdent = opts.ident.clone().ok_or_else(err_update_client)?.into();
-    if let Some(service_spec) = mgr.cfg.spec_for_ident(&ident) {
+    let ident: PackageIdent = ident_from(opts.ident.clone())?;
+    let name = qualified(&ident, opts.instance.as_ref().map(String::as_str));
+    if let Some(service_spec) =
+        mgr.cfg
+           .spec_for_ident(&ident, opts.instance.as_ref().map(String::as_str))
+    {
         let shutdown_input = opts.into();
         let action = SupervisorAction::UnloadService { service_spec,
                                                        shutdown_input };
@@ -257,7 +291,7 @@ pub fn service_unload(mgr: &ManagerState,
 
         // JW TODO: Change this to unloaded from unloading when the Supervisor waits for
         // the work to complete.
-        req.info(format!("Unloading {}", ident))?;
+        req.info(format!("Unloading {}", name))?;
         req.reply_complete(net::ok());
         Ok(())
     } else {
@@ -269,8 +303,10 @@ pub fn service_start(mgr: &ManagerState,
                      req: &mut CtlRequest,
                      opts: protocol::ctl::SvcStart)
                      -> NetResult<()> {
-    let ident = opts.ident.ok_or_else(err_update_client)?.into();
-    match mgr.cfg.spec_for_ident(&ident) {
+    let ident = ident_from(opts.ident)?;
+    let instance = opts.instance.as_ref().map(String::as_str);
+    let name = qualified(&ident, instance);
+    match mgr.cfg.spec_for_ident(&ident, instance) {
         Some(mut spec) => {
             if spec.desired_state == DesiredState::Down {
                 spec.desired_state = DesiredState::Up;
@@ -280,11 +316,11 @@ pub fn service_start(mgr: &ManagerState,
                 // synchronously control services from the ctl gateway.
                 req.info(format!("Supervisor starting {}. See the Supervisor output for more \
                                   details.",
-                                 &ident))?;
+                                 &name))?;
             }
         }
         None => {
-            return Err(net::err(ErrCode::NotFound, format!("Service not loaded, {}", &ident)));
+            return Err(net::err(ErrCode::NotFound, format!("Service not loaded, {}", &name)));
         }
     };
     req.reply_complete(net::ok());
@@ -296,8 +332,11 @@ pub fn service_stop(mgr: &ManagerState,
                     opts: protocol::ctl::SvcStop,
                     action_sender: &ActionSender)
                     -> NetResult<()> {
-    let ident: PackageIdent = opts.ident.clone().ok_or_else(err_update_client)?.into();
-    match mgr.cfg.spec_for_ident(&ident) {
+    let ident: PackageIdent = ident_from(opts.ident.clone())?;
+    let name = qualified(&ident, opts.instance.as_ref().map(String::as_str));
+    match mgr.cfg
+             .spec_for_ident(&ident, opts.instance.as_ref().map(String::as_str))
+    {
         Some(service_spec) => {
             if service_spec.desired_state == DesiredState::Up {
                 let shutdown_input = opts.into();
@@ -309,11 +348,11 @@ pub fn service_stop(mgr: &ManagerState,
                 // synchronously control services from the ctl gateway.
                 req.info(format!("Supervisor stopping {}. See the Supervisor output for more \
                                   details.",
-                                 &ident))?;
+                                 &name))?;
             }
         }
         None => {
-            return Err(net::err(ErrCode::NotFound, format!("Service not loaded, {}", &ident)));
+            return Err(net::err(ErrCode::NotFound, format!("Service not loaded, {}", &name)));
         }
     };
 
@@ -358,43 +397,228 @@ pub fn service_status(mgr: &ManagerState,
         serde_json::from_str(&services_data).map_err(Error::ServiceDeserializationError)?;
 
     if let Some(ident) = opts.ident {
-        for status in statuses {
-            if status.pkg.ident.satisfies(&ident) {
-                let msg: protocol::types::ServiceStatus = status.into();
-                req.reply_complete(msg);
-                return Ok(());
-            }
+        let ident = ident_from(Some(ident))?;
+        let instance = opts.instance;
+        // Without an instance, the status of every instance of the package is returned
+        let statuses: Vec<ServiceStatus> =
+            statuses.into_iter()
+                    .filter(|status| {
+                        status.pkg.ident.satisfies(&ident)
+                        && (instance.is_none() || status.instance == instance)
+                    })
+                    .collect();
+        if statuses.is_empty() {
+            let name = qualified(&ident, instance.as_ref().map(String::as_str));
+            return Err(net::err(ErrCode::NotFound, format!("Service not loaded, {}", name)));
         }
-        return Err(net::err(ErrCode::NotFound, format!("Service not loaded, {}", ident)));
+        reply_statuses(req, statuses);
+        return Ok(());
     }
 
     // We're not dealing with a single service, but with all of them.
     if statuses.is_empty() {
         req.reply_complete(net::ok());
     } else {
-        let mut list = statuses.into_iter().peekable();
-        while let Some(status) = list.next() {
-            let msg: protocol::types::ServiceStatus = status.into();
-            if list.peek().is_some() {
-                req.reply_partial(msg);
-            } else {
-                req.reply_complete(msg);
-            }
+        reply_statuses(req, statuses);
+    }
+    Ok(())
+}
+
+pub fn service_hook_history(mgr: &ManagerState,
+                            req: &mut CtlRequest,
+                            opts: protocol::ctl::SvcHookHistory)
+                            -> NetResult<()> {
+    let ident: PackageIdent = ident_from(opts.ident)?;
+    let instance = opts.instance.as_ref().map(String::as_str);
+    for service in mgr.services
+                      .read()
+                      .expect("Services lock is poisoned")
+                      .values()
+    {
+        if is_service(service, &ident, instance) {
+            let logs_dir = fs::svc_logs_path(service.service_name());
+            let histories = history::read_histories(&logs_dir).map_err(|e| {
+                                                                   net::err(ErrCode::Internal,
+                                                                            e.to_string())
+                                                               })?;
+            let history = serde_json::to_string(&histories).unwrap();
+            req.reply_complete(protocol::types::HookHistory { history: Some(history) });
+            return Ok(());
         }
     }
+    Err(net::err(ErrCode::NotFound,
+                 format!("Service not loaded, {}", qualified(&ident, instance))))
+}
+
+pub fn service_update(mgr: &ManagerState,
+                      req: &mut CtlRequest,
+                      opts: protocol::ctl::SvcUpdate)
+                      -> NetResult<()> {
+    let ident: PackageIdent = ident_from(opts.ident)?;
+    let instance = opts.instance.as_ref().map(String::as_str);
+    let name = qualified(&ident, instance);
+    let mut services = mgr.services.write().expect("Services lock is poisoned");
+    let service = services.values_mut()
+                          .find(|service| is_service(service, &ident, instance))
+                          .ok_or_else(|| {
+                              net::err(ErrCode::NotFound, format!("Service not loaded, {}", name))
+                          })?;
+    match service.pending_update {
+        Some(ref update) => {
+            service.apply_update_now = true;
+            req.info(format!("Supervisor updating {} to {}. See the Supervisor output for more \
+                              details.",
+                             name, update))?;
+        }
+        None => {
+            return Err(net::err(ErrCode::NotFound, format!("No pending update for {}", name)));
+        }
+    }
+    req.reply_complete(net::ok());
     Ok(())
 }
 
+pub fn service_hold(mgr: &ManagerState,
+                    req: &mut CtlRequest,
+                    opts: protocol::ctl::SvcHold)
+                    -> NetResult<()> {
+    let ident: PackageIdent = ident_from(opts.ident)?;
+    set_held_release(mgr, req, &ident, opts.instance.as_ref().map(String::as_str), true)
+}
+
+pub fn service_release(mgr: &ManagerState,
+                       req: &mut CtlRequest,
+                       opts: protocol::ctl::SvcRelease)
+                       -> NetResult<()> {
+    let ident: PackageIdent = ident_from(opts.ident)?;
+    set_held_release(mgr, req, &ident, opts.instance.as_ref().map(String::as_str), false)
+}
+
 ////////////////////////////////////////////////////////////////////////
 // Private helper functions
 fn err_update_client() -> net::NetErr { net::err(ErrCode::UpdateClient, "client out of date") }
 
+/// Converts the package identifier of a request, rejecting an invalid version constraint rather
+/// than ignoring it.
+fn ident_from(ident: Option<protocol::types::PackageIdent>) -> NetResult<PackageIdent> {
+    let ident = ident.ok_or_else(err_update_client)?;
+    PackageIdent::try_from(ident).map_err(|e| net::err(ErrCode::BadPayload, e))
+}
+
+/// Whether `service` runs the package `ident` as the instance `instance`.
+fn is_service(service: &Service, ident: &PackageIdent, instance: Option<&str>) -> bool {
+    service.pkg.ident.satisfies(ident) && service.instance.as_ref().map(String::as_str) == instance
+}
+
+/// Formats the package identifier qualified by the instance, the way services are named on the
+/// command line, e.g. `core/redis@cache`.
+fn qualified(ident: &PackageIdent, instance: Option<&str>) -> String {
+    match instance {
+        Some(instance) => format!("{}@{}", ident, instance),
+        None => ident.to_string(),
+    }
+}
+
+/// Instances of a package are told apart in the census by their service group, so an instance
+/// can't join a group another instance of the package was loaded into.
+fn check_group_is_free(mgr: &ManagerState, spec: &ServiceSpec) -> NetResult<()> {
+    let specs = match SpecDir::new(mgr.cfg.sup_root().join("specs")) {
+        Ok(spec_dir) => spec_dir.specs(),
+        Err(_) => return Ok(()),
+    };
+    let taken_by = specs.into_iter().find(|other| {
+                                         other.ident.name == spec.ident.name
+                                         && other.group == spec.group
+                                         && other.application_environment
+                                            == spec.application_environment
+                                         && other.service_name() != spec.service_name()
+                                     });
+    match taken_by {
+        Some(other) => {
+            Err(net::err(ErrCode::Conflict,
+                         format!("The {} service is already loaded into the group {}, load {} \
+                                  into another group with --group",
+                                 other.service_name(),
+                                 spec.group,
+                                 spec.service_name())))
+        }
+        None => Ok(()),
+    }
+}
+
+fn reply_statuses(req: &mut CtlRequest, statuses: Vec<ServiceStatus>) {
+    let mut list = statuses.into_iter().peekable();
+    while let Some(status) = list.next() {
+        let msg: protocol::types::ServiceStatus = status.into();
+        if list.peek().is_some() {
+            req.reply_partial(msg);
+        } else {
+            req.reply_complete(msg);
+        }
+    }
+}
+
+/// Holds the service at its current release, or releases it. The hold is saved in the spec file
+/// and set on the running service right away, so the service is not restarted.
+fn set_held_release(mgr: &ManagerState,
+                    req: &mut CtlRequest,
+                    ident: &PackageIdent,
+                    instance: Option<&str>,
+                    hold: bool)
+                    -> NetResult<()> {
+    let name = qualified(ident, instance);
+    let mut services = mgr.services.write().expect("Services lock is poisoned");
+    let service = services.values_mut()
+                          .find(|service| is_service(service, ident, instance))
+                          .ok_or_else(|| {
+                              net::err(ErrCode::NotFound, format!("Service not loaded, {}", name))
+                          })?;
+    let held_release = if hold {
+        Some(service.pkg.ident.clone())
+    } else {
+        None
+    };
+    if service.held_release == held_release {
+        let msg = if hold {
+            format!("{} is already held at {}", name, service.pkg.ident)
+        } else {
+            format!("{} is not held", name)
+        };
+        req.info(msg)?;
+        req.reply_complete(net::ok());
+        return Ok(());
+    }
+    let mut spec = mgr.cfg
+                      .spec_for_ident(&service.spec_ident, instance)
+                      .ok_or_else(|| {
+                          net::err(ErrCode::NotFound, format!("Service not loaded, {}", name))
+                      })?;
+    spec.held_release = held_release.clone();
+    mgr.cfg.save_spec_for(&spec)?;
+    service.held_release = held_release;
+    if hold {
+        service.pending_update = None;
+        service.apply_update_now = false;
+        req.info(format!("{} is held at {}", name, service.pkg.ident))?;
+    } else {
+        req.info(format!("{} was released and is updated again", name))?;
+    }
+    req.reply_complete(net::ok());
+    Ok(())
+}
+
 #[derive(Deserialize)]
 struct ServiceStatus {
-    pkg:           Pkg,
-    process:       ProcessStatus,
-    service_group: ServiceGroup,
-    desired_state: DesiredState,
+    pkg:            Pkg,
+    process:        ProcessStatus,
+    service_group:  ServiceGroup,
+    desired_state:  DesiredState,
+    #[serde(default)]
+    pending_update: Option<PackageIdent>,
+    #[serde(default)]
+    held_release:   Option<PackageIdent>,
+    #[serde(default)]
+    instance:       Option<String>,
 }
 
 impl fmt::Display for ServiceStatus {
@@ -412,6 +636,9 @@ impl From<ServiceStatus> for protocol::types::ServiceStatus {
         proto.process = Some(other.process.into());
         proto.service_group = other.service_group.into();
         proto.desired_state = Some(other.desired_state.into());
+        proto.pending_update = other.pending_update.map(Into::into);
+        proto.held_release = other.held_release.map(Into::into);
+        proto.instance = other.instance;
         proto
     }
 }
@@ -0,0 +1,285 @@
+//! Orders the services of a Supervisor by their dependencies.
+//!
+//! A service depends on the local services listed in its `start_after` and on the local services
+//! providing the groups it binds to. It is started once these are running, or healthy if it
+//! `requires_healthy`, and it is stopped before them when the Supervisor shuts down. Bound groups
+//! which are not provided by a local service are left to the service's binding mode, unless the
+//! service requires healthy dependencies, in which case it waits for the group to have a healthy
+//! member.
+//!
+//! Services which depend on each other in a cycle can't wait for each other, so they are started
+//! regardless of the other services of the cycle, once their other dependencies are ready.
+
+use crate::{census::CensusRing,
+            manager::service::{HealthCheckResult,
+                               Service,
+                               ServiceName,
+                               ServiceSpec}};
+use habitat_core::{package::Identifiable,
+                   service::ServiceGroup};
+use std::collections::HashMap;
+
+static LOGKEY: &str = "DP";
+
+/// Sorts the specs so that each comes after the specs it depends on.
+pub fn start_order(specs: Vec<ServiceSpec>) -> Vec<ServiceSpec> {
+    batches(specs).into_iter().flatten().collect()
+}
+
+/// Groups the specs into batches to stop one after the other, so that each service is stopped
+/// before the services it depends on. The services of a batch can be stopped at once.
+pub fn stop_batches(specs: Vec<ServiceSpec>) -> Vec<Vec<ServiceSpec>> {
+    let mut batches = batches(specs);
+    batches.reverse();
+    batches
+}
+
+/// Returns the specs of `specs` which are in a dependency cycle with `spec`, i.e. which `spec`
+/// depends on and which depend on `spec`, directly or through other specs.
+pub fn cycle_members<'a>(spec: &ServiceSpec, specs: &'a [ServiceSpec]) -> Vec<&'a ServiceSpec> {
+    let dependents: Vec<ServiceName> = reachable(spec, specs, |from, to| depends_on(to, from))
+        .into_iter()
+        .map(ServiceSpec::service_name)
+        .collect();
+    reachable(spec, specs, depends_on).into_iter()
+                                      .filter(|dependency| {
+                                          let name = dependency.service_name();
+                                          name != spec.service_name() && dependents.contains(&name)
+                                      })
+                                      .collect()
+}
+
+/// The specs of `specs` which can be reached from `spec` by following `edge`.
+fn reachable<'a, F>(spec: &ServiceSpec, specs: &'a [ServiceSpec], edge: F) -> Vec<&'a ServiceSpec>
+    where F: Fn(&ServiceSpec, &ServiceSpec) -> bool
+{
+    let mut reached: Vec<&ServiceSpec> = Vec::new();
+    let mut frontier = vec![spec];
+    while let Some(from) = frontier.pop() {
+        for to in specs {
+            if edge(from, to)
+               && !reached.iter()
+                          .any(|r| r.service_name() == to.service_name())
+            {
+                reached.push(to);
+                frontier.push(to);
+            }
+        }
+    }
+    reached
+}
+
+/// Returns what the service of `spec` has to wait for before it can be started, or `None` if its
+/// dependencies are ready. Dependencies on the services of `cycle`, the specs `spec` is in a
+/// dependency cycle with, are ignored.
+pub fn unmet_dependency(spec: &ServiceSpec,
+                        cycle: &[&ServiceSpec],
+                        services: &HashMap<ServiceName, Service>,
+                        census_ring: &CensusRing)
+                        -> Option<String> {
+    let ready = |service: &Service| {
+        !spec.requires_healthy || service.health_check_result() == HealthCheckResult::Ok
+    };
+    let condition = if spec.requires_healthy {
+        "healthy"
+    } else {
+        "running"
+    };
+    for ident in &spec.start_after {
+        if cycle.iter().any(|member| member.ident.satisfies(ident)) {
+            continue;
+        }
+        // Every instance of the package is a dependency
+        let dependencies: Vec<&Service> = services.values()
+                                                  .filter(|service| {
+                                                      service.spec_ident.satisfies(ident)
+                                                      || service.pkg.ident.satisfies(ident)
+                                                  })
+                                                  .collect();
+        if dependencies.is_empty() || !dependencies.into_iter().all(ready) {
+            return Some(format!("{} is {}", ident, condition));
+        }
+    }
+    for bind in &spec.binds {
+        let group = bind.service_group();
+        if cycle.iter().any(|member| provides_bind(member, group)) {
+            continue;
+        }
+        let provider = services.values()
+                               .find(|service| provides(&service.service_group, group));
+        match provider {
+            Some(service) if ready(service) => {}
+            Some(_) => return Some(format!("{} is healthy", group)),
+            None if spec.requires_healthy && !has_healthy_members(census_ring, group) => {
+                return Some(format!("{} has a healthy member", group));
+            }
+            None => {}
+        }
+    }
+    None
+}
+
+/// Whether the service group `provider` provides the bound group `group`.
+fn provides(provider: &ServiceGroup, group: &ServiceGroup) -> bool {
+    provider.service() == group.service() && provider.group() == group.group()
+}
+
+fn has_healthy_members(census_ring: &CensusRing, group: &ServiceGroup) -> bool {
+    census_ring.census_group_for(group)
+               .map_or(false, |g| g.healthy_members().count() > 0)
+}
+
+/// Whether the service of `spec` depends on the service of `other`.
+fn depends_on(spec: &ServiceSpec, other: &ServiceSpec) -> bool {
+    spec.service_name() != other.service_name()
+    && (spec.start_after
+            .iter()
+            .any(|ident| other.ident.satisfies(ident))
+        || spec.binds
+               .iter()
+               .any(|bind| provides_bind(other, bind.service_group())))
+}
+
+/// Whether the service of `spec` provides the bound group `group`.
+fn provides_bind(spec: &ServiceSpec, group: &ServiceGroup) -> bool {
+    spec.ident.name == group.service() && spec.group == group.group()
+}
+
+/// Groups the specs into batches which only depend on the batches before them. Specs which
+/// depend on each other in a cycle end up in the last batch.
+fn batches(mut specs: Vec<ServiceSpec>) -> Vec<Vec<ServiceSpec>> {
+    let mut batches = Vec::new();
+    while !specs.is_empty() {
+        let ready: Vec<bool> = specs.iter()
+                                    .map(|spec| !specs.iter().any(|other| depends_on(spec, other)))
+                                    .collect();
+        if !ready.contains(&true) {
+            let names: Vec<String> = specs.iter().map(ServiceSpec::service_name).collect();
+            outputln!("Services depend on each other in a cycle, starting them regardless of \
+                       each other: {}",
+                      names.join(", "));
+            batches.push(specs);
+            break;
+        }
+        let mut ready = ready.into_iter();
+        let (batch, rest) = specs.into_iter()
+                                 .partition(|_| ready.next().unwrap_or(false));
+        batches.push(batch);
+        specs = rest;
+    }
+    batches
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use habitat_core::{package::PackageIdent,
+                       service::ServiceBind};
+    use std::str::FromStr;
+
+    fn spec(ident: &str) -> ServiceSpec {
+        ServiceSpec::default_for(PackageIdent::from_str(ident).unwrap())
+    }
+
+    fn idents(specs: &[ServiceSpec]) -> Vec<String> {
+        specs.iter().map(|spec| spec.ident.to_string()).collect()
+    }
+
+    fn instance(ident: &str, instance: &str) -> ServiceSpec {
+        let mut spec = spec(ident);
+        spec.instance = Some(instance.to_string());
+        spec.group = instance.to_string();
+        spec
+    }
+
+    #[test]
+    fn services_start_after_their_dependencies() {
+        let mut app = spec("core/app");
+        app.start_after = vec![PackageIdent::from_str("core/cache").unwrap()];
+        app.binds = vec![ServiceBind::from_str("database:postgresql.default").unwrap()];
+        let mut cache = spec("core/cache");
+        cache.start_after = vec![PackageIdent::from_str("core/postgresql").unwrap()];
+        let postgresql = spec("core/postgresql");
+
+        let order = start_order(vec![app, cache, postgresql]);
+        assert_eq!(idents(&order), vec!["core/postgresql", "core/cache", "core/app"]);
+    }
+
+    #[test]
+    fn services_stop_before_their_dependencies() {
+        let mut app = spec("core/app");
+        app.binds = vec![ServiceBind::from_str("database:postgresql.default").unwrap()];
+        let mut worker = spec("core/worker");
+        worker.start_after = vec![PackageIdent::from_str("core/postgresql").unwrap()];
+        let postgresql = spec("core/postgresql");
+
+        let batches = stop_batches(vec![postgresql, app, worker]);
+        assert_eq!(batches.len(), 2);
+        assert_eq!(idents(&batches[0]), vec!["core/app", "core/worker"]);
+        assert_eq!(idents(&batches[1]), vec!["core/postgresql"]);
+    }
+
+    #[test]
+    fn binds_to_other_groups_are_not_dependencies() {
+        let mut app = spec("core/app");
+        app.binds = vec![ServiceBind::from_str("database:postgresql.other").unwrap()];
+        let postgresql = spec("core/postgresql");
+
+        assert_eq!(stop_batches(vec![app, postgresql]).len(), 1);
+    }
+
+    #[test]
+    fn services_in_a_cycle_are_started_last() {
+        let mut a = spec("core/a");
+        a.start_after = vec![PackageIdent::from_str("core/b").unwrap()];
+        let mut b = spec("core/b");
+        b.start_after = vec![PackageIdent::from_str("core/a").unwrap()];
+        let c = spec("core/c");
+
+        let order = start_order(vec![a, b, c]);
+        assert_eq!(idents(&order), vec!["core/c", "core/a", "core/b"]);
+    }
+
+    #[test]
+    fn services_in_a_cycle_do_not_wait_for_each_other() {
+        let mut a = spec("core/a");
+        a.start_after = vec![PackageIdent::from_str("core/b").unwrap()];
+        let mut b = spec("core/b");
+        b.binds = vec![ServiceBind::from_str("a:a.default").unwrap()];
+        b.requires_healthy = true;
+        let mut c = spec("core/c");
+        c.start_after = vec![PackageIdent::from_str("core/a").unwrap()];
+        let specs = vec![a, b, c];
+        let services = HashMap::new();
+        let census_ring = CensusRing::new("member");
+
+        let a_cycle = cycle_members(&specs[0], &specs);
+        assert_eq!(a_cycle.len(), 1);
+        assert_eq!(a_cycle[0].ident.to_string(), "core/b");
+        assert_eq!(unmet_dependency(&specs[0], &a_cycle, &services, &census_ring),
+                   None);
+        assert_eq!(unmet_dependency(&specs[0], &[], &services, &census_ring),
+                   Some("core/b is running".to_string()));
+
+        let b_cycle = cycle_members(&specs[1], &specs);
+        assert_eq!(unmet_dependency(&specs[1], &b_cycle, &services, &census_ring),
+                   None);
+
+        // Depending on a cycle doesn't make a service part of it
+        let c_cycle = cycle_members(&specs[2], &specs);
+        assert!(c_cycle.is_empty());
+        assert_eq!(unmet_dependency(&specs[2], &c_cycle, &services, &census_ring),
+                   Some("core/a is running".to_string()));
+    }
+
+    #[test]
+    fn instances_start_after_the_instances_they_bind_to() {
+        let mut replica = instance("core/redis", "replica");
+        replica.binds = vec![ServiceBind::from_str("leader:redis.primary").unwrap()];
+        let primary = instance("core/redis", "primary");
+
+        let order = start_order(vec![replica, primary]);
+        let names: Vec<String> = order.iter().map(ServiceSpec::service_name).collect();
+        assert_eq!(names, vec!["redis@primary", "redis@replica"]);
+    }
+}
@@ -0,0 +1,83 @@
+//! Periodically removes installed packages which are no longer needed, when the Supervisor is
+//! started with `--pkg-gc-interval`.
+
+use super::{service::{Service,
+                     ServiceName},
+            spec_dir::SpecDir};
+use habitat_common::{command::package::gc::{self,
+                                            GcMode,
+                                            GcPolicy},
+                     outputln,
+                     ui::UI};
+use habitat_core::{fs::FS_ROOT_PATH,
+                   package::PackageIdent};
+use std::{collections::HashMap,
+          path::Path,
+          sync::{Arc,
+                 RwLock},
+          thread,
+          time::Duration};
+
+static LOGKEY: &str = "GC";
+
+/// Spawn a thread which collects unused packages every `interval`.
+///
+/// Besides the newest `keep_releases` of each package, the packages of every loaded service spec
+/// and running service, the releases they are held at or could be rolled back to, and the
+/// running Supervisor itself, are always kept. The thread doesn't report to the liveliness checker
+/// since it sleeps far longer than the alive threshold.
+pub fn spawn(interval: Duration,
+             keep_releases: usize,
+             current: PackageIdent,
+             services: Arc<RwLock<HashMap<ServiceName, Service>>>,
+             spec_dir: SpecDir) {
+    thread::Builder::new().name("pkg-gc".to_string())
+                          .spawn(move || {
+                              loop {
+                                  thread::sleep(interval);
+                                  collect(keep_releases, &current, &services, &spec_dir);
+                              }
+                          })
+                          .expect("Unable to start pkg-gc thread");
+}
+
+fn collect(keep_releases: usize,
+           current: &PackageIdent,
+           services: &RwLock<HashMap<ServiceName, Service>>,
+           spec_dir: &SpecDir) {
+    let mut protected = vec![current.clone()];
+    for service in services.read()
+                           .expect("Services lock is poisoned!")
+                           .values()
+    {
+        protected.push(service.spec_ident.clone());
+        protected.push(service.pkg.ident.clone());
+        protected.extend(service.held_release.clone());
+        protected.extend(service.pinned_release.clone());
+        protected.extend(service.update_probation.iter().map(|p| p.from.clone()));
+    }
+    for spec in spec_dir.specs() {
+        protected.extend(spec.held_release);
+        protected.extend(spec.pinned_release);
+        protected.extend(spec.update_probation.map(|p| p.from));
+        protected.push(spec.ident);
+    }
+
+    let policy = GcPolicy { keep_releases,
+                            protected };
+    // We don't want anything in here to print
+    match gc::start(&mut UI::with_sinks(),
+                    Path::new(&*FS_ROOT_PATH),
+                    &policy,
+                    GcMode::Run)
+    {
+        Ok(summary) => {
+            if !summary.removed.is_empty() {
+                outputln!("Removed {} unused packages, freeing {} bytes",
+                          summary.removed.len(),
+                          summary.bytes_freed);
+            }
+        }
+        Err(err) => warn!("Unable to collect unused packages, {}", err),
+    }
+}
@@ -9,6 +9,7 @@
 // system, and we'd use separate internal types for our core logic.
 
 mod context;
+mod drift;
 mod health;
 mod hook_runner;
 mod hooks;
@@ -18,6 +19,8 @@ mod supervisor;
 mod terminator;
 
 use self::{context::RenderContext,
+           drift::{ConfigDrift,
+                   ConfigDriftPolicy},
            hook_runner::HookRunner,
            hooks::{HookCompileTable,
                    HookTable},
@@ -26,13 +29,16 @@ pub use self::{health::HealthCheckResult,
                hooks::HealthCheckHook,
                spec::{DesiredState,
                       IntoServiceSpec,
-                      ServiceSpec}};
+                      ServiceName,
+                      ServiceSpec,
+                      UpdateProbationSpec}};
 use crate::{census::{CensusGroup,
                      CensusRing,
                      ElectionStatus,
                      ServiceFile},
             error::{Error,
                     Result},
+            event,
             manager::{FsCfg,
                       GatewayState,
                       ShutdownConfig,
@@ -52,16 +58,22 @@ use habitat_common::{outputln,
                                   hooks::Hook}};
 use habitat_core::{crypto::hash,
                    fs::{atomic_write,
+                        pkg_root_path,
                         svc_hooks_path,
                         SvcDir,
                         FS_ROOT_PATH},
-                   os::process::ShutdownTimeout,
-                   package::{metadata::Bind,
+                   os::{process::{ResourceLimits,
+                                  ShutdownTimeout},
+                        sandbox::Sandbox},
+                   package::{list,
+                             metadata::Bind,
                              PackageIdent,
                              PackageInstall},
-                   service::{HealthCheckInterval,
+                   service::{CanaryCount,
+                             HealthCheckInterval,
                              ServiceBind,
-                             ServiceGroup},
+                             ServiceGroup,
+                             UpdateWindow},
                    ChannelIdent};
 use habitat_launcher_client::LauncherCli;
 use habitat_sup_protocol::types::BindingMode;
@@ -74,7 +86,8 @@ use serde::{ser::SerializeStruct,
             Serialize,
             Serializer};
 use std::{self,
-          collections::HashSet,
+          collections::{BTreeMap,
+                        HashSet},
           fmt,
           fs,
           ops::Deref,
@@ -97,8 +110,21 @@ lazy_static! {
         register_histogram_vec!("hab_sup_hook_duration_seconds",
                                 "The time it takes for a hook to run",
                                 &["hook"]).unwrap();
+    static ref SUPERVISOR_UPDATE_WINDOW: Option<UpdateWindow> =
+        SupervisorUpdateWindow::configured_value().into();
 }
 
+habitat_core::env_config!(
+    /// The update window of services which do not have one of their own, written like the
+    /// `update_window` of a service spec.
+    SupervisorUpdateWindow,
+    Option<UpdateWindow>,
+    HAB_UPDATE_WINDOW,
+    None,
+    habitat_core::Error,
+    s,
+    Ok(SupervisorUpdateWindow(Some(s.parse()?))));
+
 /// When evaluating whether a particular service group can satisfy a
 /// bind of the Service, there are several states it can be
 /// in. Depending on which point in the lifecycle of the Service we
@@ -126,13 +152,21 @@ enum BindStatus<'a> {
 struct TemplateUpdate {
     hooks:                 HookCompileTable,
     config_changed:        bool,
+    // A renewed secret may render to the same content, but the service still has to pick up the
+    // new lease, so this counts as a configuration change.
+    secrets_renewed:       bool,
     have_reconfigure_hook: bool,
 }
 
 impl TemplateUpdate {
-    fn new(hooks: HookCompileTable, config_changed: bool, have_reconfigure_hook: bool) -> Self {
+    fn new(hooks: HookCompileTable,
+           config_changed: bool,
+           secrets_renewed: bool,
+           have_reconfigure_hook: bool)
+           -> Self {
         Self { hooks,
                config_changed,
+               secrets_renewed,
                have_reconfigure_hook }
     }
 
@@ -141,42 +175,75 @@ impl TemplateUpdate {
     /// A restart is needed under the following conditions:
     /// 1. the `run` or `post-run` hooks have changed. A restart is limited to these hooks
     /// because they are the only hooks that can impact the execution of the service.
-    /// 2. `/config` changed and there is no `reconfigure` hook
+    /// 2. `/config` changed or a secret was renewed, and there is no `reconfigure` hook
     fn needs_restart(&self) -> bool {
         self.hooks.run_changed()
         || self.hooks.post_run_changed()
-        || (!self.have_reconfigure_hook && self.config_changed)
+        || (!self.have_reconfigure_hook && (self.config_changed || self.secrets_renewed))
     }
 
     /// Returns `true` if the service needs to be reconfigured.
     ///
-    /// A reconfigure is needed if `/config` or the `reconfigure` hook changed.
+    /// A reconfigure is needed if `/config` or the `reconfigure` hook changed, or if a secret was
+    /// renewed.
     fn needs_reconfigure(&self) -> bool {
-        self.config_changed || self.hooks.reconfigure_changed() || self.hooks.reload_changed()
+        self.config_changed
+        || self.secrets_renewed
+        || self.hooks.reconfigure_changed()
+        || self.hooks.reload_changed()
     }
 }
 
 #[derive(Debug)]
 pub struct Service {
-    pub service_group:       ServiceGroup,
-    pub bldr_url:            String,
-    pub channel:             ChannelIdent,
-    pub desired_state:       DesiredState,
-    pub spec_file:           PathBuf,
-    pub spec_ident:          PackageIdent,
-    pub topology:            Topology,
-    pub update_strategy:     UpdateStrategy,
-    pub cfg:                 Cfg,
-    pub pkg:                 Pkg,
-    pub sys:                 Arc<Sys>,
-    pub initialized:         bool,
-    pub user_config_updated: bool,
-    pub shutdown_timeout:    Option<ShutdownTimeout>,
+    pub service_group:          ServiceGroup,
+    pub bldr_url:               String,
+    pub channel:                ChannelIdent,
+    pub desired_state:          DesiredState,
+    pub spec_file:              PathBuf,
+    pub spec_ident:             PackageIdent,
+    /// The instance of the package this service runs as, see `ServiceSpec::instance`.
+    pub instance:               Option<String>,
+    pub topology:               Topology,
+    pub update_strategy:        UpdateStrategy,
+    pub update_canaries:        CanaryCount,
+    pub update_bake_period:     u32,
+    pub update_rollback_window: u32,
+    pub update_window:          Option<UpdateWindow>,
+    /// An update which was found while the update window was closed and is applied once it
+    /// opens, see `ServiceSpec::pending_update`.
+    pub pending_update:         Option<PackageIdent>,
+    /// Set to apply the pending update right away, regardless of the update window.
+    pub apply_update_now:       bool,
+    /// Releases of the package this service must not run, see `ServiceSpec::blocked_releases`.
+    pub blocked_releases:       Vec<PackageIdent>,
+    /// The release this service is held at, see `ServiceSpec::held_release`.
+    pub held_release:           Option<PackageIdent>,
+    /// The release this service is kept at until it is updated, see
+    /// `ServiceSpec::pinned_release`.
+    pub pinned_release:         Option<PackageIdent>,
+    /// The update this service is on probation for, see `manager::update_probation`.
+    pub update_probation:       Option<UpdateProbationSpec>,
+    pub cfg:                    Cfg,
+    pub pkg:                    Pkg,
+    pub sys:                    Arc<Sys>,
+    pub initialized:            bool,
+    pub user_config_updated:    bool,
+    pub shutdown_timeout:       Option<ShutdownTimeout>,
+    /// The package this service ran before it was updated, if this instance of the service was
+    /// started by an update. The `post-update` hook runs once the updated service has started,
+    /// after which this is cleared, so that later renders of the templates don't see the update.
+    pub updated_from:           Option<PackageIdent>,
     // TODO (DM): This flag is a temporary hack to signal to the `Manager` that this service needs
     // to be restarted. As we continue refactoring lifecycle hooks this flag should be removed.
     pub needs_restart: bool,
+    /// Set along with `needs_restart` if the service's process died unexpectedly.
+    pub crashed: bool,
 
     config_renderer: CfgRenderer,
+    /// The hashes of the configuration files and hooks as they were last rendered, used to
+    /// detect edits made outside of the Supervisor.
+    config_drift: ConfigDrift,
     // Note: This field is really only needed for serializing a
     // Service in the gateway (see ServiceProxy's Serialize
     // implementation). Ideally, we could get rid of this, since we're
@@ -188,6 +255,8 @@ pub struct Service {
     // hook, we need to wrap some Arc<Mutex<_>> protection around it
     // :(
     health_check_result: Arc<Mutex<HealthCheckResult>>,
+    /// The health check result last gossiped in the service rumor.
+    gossiped_health: HealthCheckResult,
     last_election_status: ElectionStatus,
     /// The mapping of bind name to a service group, specified by the
     /// user when the service definition was loaded into the Supervisor.
@@ -201,6 +270,12 @@ pub struct Service {
     /// Controls how the presence or absence of bound service groups
     /// impacts the service's start-up.
     binding_mode: BindingMode,
+    /// Whether bound service groups only list their healthy members
+    /// in templates.
+    bind_healthy_only: bool,
+    /// The local services this service is started after, see `ServiceSpec::start_after`.
+    start_after: Vec<PackageIdent>,
+    requires_healthy: bool,
     /// Binds specified by the user that are currently mapped to
     /// service groups that do _not_ satisfy the bind's contract, as
     /// defined in the service's current package.
@@ -219,6 +294,13 @@ pub struct Service {
     supervisor: Arc<Mutex<Supervisor>>,
     svc_encrypted_password: Option<String>,
     health_check_interval: HealthCheckInterval,
+    /// The hook timeouts given in the service spec. These have already been merged into `pkg`,
+    /// but are kept separately so the spec can be recreated.
+    hook_timeouts: BTreeMap<String, u32>,
+    /// The resource limits given in the service spec, kept like `hook_timeouts`.
+    resource_limits: ResourceLimits,
+    /// The sandboxing options given in the service spec, kept like `hook_timeouts`.
+    sandbox: Sandbox,
 
     gateway_state: Arc<RwLock<GatewayState>>,
 
@@ -239,7 +321,12 @@ impl Service {
            -> Result<Service> {
         spec.validate(&package)?;
         let all_pkg_binds = package.all_binds()?;
-        let pkg = Pkg::from_install(&package)?;
+        let mut pkg = Pkg::from_install_for_service(&package, &spec.service_name())?;
+        // Hook timeouts in the spec take precedence over those set in the package
+        pkg.hook_timeouts.extend(spec.hook_timeouts.clone());
+        // As do its resource limits and sandboxing options
+        pkg.resource_limits.merge(&spec.resource_limits);
+        pkg.sandbox.merge(&spec.sandbox);
         let spec_file = manager_fs_cfg.specs_path.join(spec.file_name());
         let service_group = ServiceGroup::new(spec.application_environment.as_ref(),
                                               &pkg.name,
@@ -249,37 +336,60 @@ impl Service {
         let hooks_root = Self::hooks_root(&pkg, spec.config_from.as_ref());
         Ok(Service { sys,
                      cfg: Cfg::new(&pkg, spec.config_from.as_ref())?,
-                     config_renderer: CfgRenderer::new(&config_root)?,
+                     config_renderer: CfgRenderer::new(&config_root, pkg.template_engine)?,
+                     config_drift: ConfigDrift::configured(),
                      bldr_url: spec.bldr_url,
                      channel: spec.channel,
                      desired_state: spec.desired_state,
                      health_check_result: Default::default(),
-                     hooks: HookTable::load(&pkg.name,
+                     gossiped_health: Default::default(),
+                     hooks: HookTable::load(&pkg.svc_name,
                                             &hooks_root,
-                                            svc_hooks_path(&service_group.service())),
+                                            svc_hooks_path(&pkg.svc_name),
+                                            pkg.template_engine),
                      initialized: false,
                      last_election_status: ElectionStatus::None,
                      user_config_updated: false,
                      needs_restart: false,
+                     crashed: false,
+                     updated_from: None,
                      manager_fs_cfg,
-                     supervisor: Arc::new(Mutex::new(Supervisor::new(&service_group))),
+                     supervisor: Arc::new(Mutex::new(Supervisor::new(&service_group,
+                                                                     &pkg.svc_pid_file))),
                      pkg,
                      service_group,
                      binds: spec.binds,
                      all_pkg_binds,
                      unsatisfied_binds: HashSet::new(),
                      binding_mode: spec.binding_mode,
+                     bind_healthy_only: spec.bind_healthy_only,
+                     start_after: spec.start_after,
+                     requires_healthy: spec.requires_healthy,
                      spec_ident: spec.ident,
+                     instance: spec.instance,
                      spec_file,
                      topology: spec.topology,
                      update_strategy: spec.update_strategy,
+                     update_canaries: spec.update_canaries,
+                     update_bake_period: spec.update_bake_period,
+                     update_rollback_window: spec.update_rollback_window,
+                     update_window: spec.update_window,
+                     pending_update: spec.pending_update,
+                     apply_update_now: false,
+                     blocked_releases: spec.blocked_releases,
+                     held_release: spec.held_release,
+                     pinned_release: spec.pinned_release,
+                     update_probation: spec.update_probation,
                      config_from: spec.config_from,
                      svc_encrypted_password: spec.svc_encrypted_password,
                      health_check_interval: spec.health_check_interval,
                      gateway_state,
                      health_check_handle: None,
                      post_run_handle: None,
-                     shutdown_timeout: spec.shutdown_timeout })
+                     shutdown_timeout: spec.shutdown_timeout,
+                     hook_timeouts: spec.hook_timeouts,
+                     resource_limits: spec.resource_limits,
+                     sandbox: spec.sandbox })
     }
 
     /// Returns the config root given the package and optional config-from path.
@@ -304,7 +414,7 @@ impl Service {
                 -> Result<Service> {
         // The package for a spec should already be installed.
         let fs_root_path = Path::new(&*FS_ROOT_PATH);
-        let package = PackageInstall::load(&spec.ident, Some(fs_root_path))?;
+        let package = Self::load_package(&spec, fs_root_path)?;
         Ok(Self::new(sys,
                      &package,
                      spec,
@@ -313,10 +423,80 @@ impl Service {
                      gateway_state)?)
     }
 
+    /// Loads the release `spec` is held at or, if it is not held, the release it is pinned to.
+    /// Without either, or if that release is not installed anymore, this loads the latest
+    /// installed release satisfying the identifier of `spec` which is not one of its blocked
+    /// releases.
+    fn load_package(spec: &ServiceSpec, fs_root_path: &Path) -> Result<PackageInstall> {
+        if let Some(held) = spec.held_release
+                                .as_ref()
+                                .filter(|held| held.satisfies(&spec.ident))
+        {
+            match PackageInstall::load(held, Some(fs_root_path)) {
+                Ok(package) => return Ok(package),
+                Err(err) => {
+                    outputln!("Unable to load held release {} of {}, ignoring the hold: {}",
+                              held, spec.ident, err);
+                }
+            }
+        } else if let Some(pinned) =
+            spec.pinned_release
+                .as_ref()
+                .filter(|pinned| {
+                    pinned.satisfies(&spec.ident) && !spec.blocked_releases.contains(pinned)
+                })
+        {
+            match PackageInstall::load(pinned, Some(fs_root_path)) {
+                Ok(package) => return Ok(package),
+                Err(err) => {
+                    outputln!("Unable to load release {} of {} the service is pinned to, \
+                               ignoring the pin: {}",
+                              pinned, spec.ident, err);
+                }
+            }
+        }
+        if spec.blocked_releases.is_empty() {
+            return Ok(PackageInstall::load(&spec.ident, Some(fs_root_path))?);
+        }
+        let installed =
+            list::package_list_for_ident(&pkg_root_path(Some(fs_root_path)), &spec.ident)?;
+        let latest = installed.into_iter()
+                              .filter(|ident| {
+                                  ident.satisfies(&spec.ident)
+                                  && !spec.blocked_releases.contains(ident)
+                              })
+                              .fold(None, |latest: Option<PackageIdent>, ident| {
+                                  match latest {
+                                      Some(l) => Some(if l >= ident { l } else { ident }),
+                                      None => Some(ident),
+                                  }
+                              });
+        match latest {
+            Some(ident) => Ok(PackageInstall::load(&ident, Some(fs_root_path))?),
+            None => Err(habitat_core::Error::PackageNotFound(spec.ident.clone()).into()),
+        }
+    }
+
+    /// Whether updates may be applied to this service now, according to its own update window or,
+    /// if it has none, the Supervisor's.
+    pub fn update_window_is_open(&self) -> bool {
+        self.update_window
+            .as_ref()
+            .or_else(|| SUPERVISOR_UPDATE_WINDOW.as_ref())
+            .map_or(true, UpdateWindow::is_open)
+    }
+
+    /// The result of the latest health check of this service.
+    pub fn health_check_result(&self) -> HealthCheckResult {
+        *self.health_check_result
+             .lock()
+             .expect("Health check result lock poisoned")
+    }
+
     /// Create the service path for this package.
     pub fn create_svc_path(&self) -> Result<()> {
         debug!("{}, Creating svc paths", self.service_group);
-        SvcDir::new(&self.pkg.name, &self.pkg.svc_user, &self.pkg.svc_group).create()?;
+        SvcDir::new(&self.pkg.svc_name, &self.pkg.svc_user, &self.pkg.svc_group).create()?;
         Ok(())
     }
 
@@ -436,12 +616,20 @@ impl Service {
 
         let service_group = self.service_group.clone();
         let gs = Arc::clone(&self.gateway_state);
+        let supervisor = Arc::clone(&self.supervisor);
 
-        let f = self.supervisor
-                    .lock()
-                    .expect("Couldn't lock supervisor")
-                    .stop(shutdown_config)
-                    .and_then(move |_| {
+        // The pre-stop hook runs while the service is still up, e.g. to drain connections. The
+        // service is stopped regardless of how the hook exits.
+        let pre_stop = match self.pre_stop() {
+            Some(hook) => future::Either::A(hook.into_future().map(|_exitvalue| ())),
+            None => future::Either::B(future::ok(())),
+        };
+        let f = pre_stop.then(move |_| {
+                            supervisor.lock()
+                                      .expect("Couldn't lock supervisor")
+                                      .stop(shutdown_config)
+                        })
+                        .and_then(move |_| {
                         gs.write()
                           .expect("GatewayState lock is poisoned")
                           .health_check_data
@@ -469,6 +657,14 @@ impl Service {
             .state_entered
     }
 
+    /// Reads what the service's processes currently use, for the `/services` endpoint.
+    pub fn refresh_resource_usage(&self) {
+        self.supervisor
+            .lock()
+            .expect("Couldn't lock supervisor")
+            .refresh_resource_usage();
+    }
+
     /// Performs updates and executes hooks.
     ///
     /// Returns `true` if the service was marked to be restarted or reconfigured.
@@ -504,7 +700,7 @@ impl Service {
             self.file_updated();
         }
 
-        match self.topology {
+        let hooks_changed = match self.topology {
             Topology::Standalone => self.execute_hooks(launcher, executor, &template_update),
             Topology::Leader => {
                 let census_group =
@@ -551,11 +747,22 @@ impl Service {
                     }
                 }
             }
-        }
+        };
+
+        // Gossip changes in health, so that other members can tell
+        // healthy members of this service group from unhealthy ones.
+        let health = self.health_check_result();
+        let health_changed = health != self.gossiped_health;
+        self.gossiped_health = health;
+        hooks_changed || health_changed
     }
 
+    /// The name of this service on the Supervisor, see `ServiceSpec::service_name`.
+    pub fn service_name(&self) -> &str { &self.pkg.svc_name }
+
     pub fn to_spec(&self) -> ServiceSpec {
         let mut spec = ServiceSpec::default_for(self.spec_ident.clone());
+        spec.instance = self.instance.clone();
         spec.group = self.service_group.group().to_string();
         if let Some(appenv) = self.service_group.application_environment() {
             spec.application_environment = Some(appenv)
@@ -564,14 +771,29 @@ impl Service {
         spec.channel = self.channel.clone();
         spec.topology = self.topology;
         spec.update_strategy = self.update_strategy;
+        spec.update_canaries = self.update_canaries;
+        spec.update_bake_period = self.update_bake_period;
+        spec.update_rollback_window = self.update_rollback_window;
+        spec.update_window = self.update_window.clone();
+        spec.pending_update = self.pending_update.clone();
+        spec.blocked_releases = self.blocked_releases.clone();
+        spec.held_release = self.held_release.clone();
+        spec.pinned_release = self.pinned_release.clone();
+        spec.update_probation = self.update_probation.clone();
         spec.binds = self.binds.clone();
         spec.binding_mode = self.binding_mode;
+        spec.bind_healthy_only = self.bind_healthy_only;
+        spec.start_after = self.start_after.clone();
+        spec.requires_healthy = self.requires_healthy;
         spec.config_from = self.config_from.clone();
         if let Some(ref password) = self.svc_encrypted_password {
             spec.svc_encrypted_password = Some(password.clone())
         }
         spec.health_check_interval = self.health_check_interval;
         spec.shutdown_timeout = self.shutdown_timeout;
+        spec.hook_timeouts = self.hook_timeouts.clone();
+        spec.resource_limits = self.resource_limits.clone();
+        spec.sandbox = self.sandbox.clone();
         spec
     }
 
@@ -655,7 +877,12 @@ impl Service {
         match census_ring.census_group_for(service_bind.service_group()) {
             None => BindStatus::NotPresent,
             Some(group) => {
-                if group.active_members().count() == 0 {
+                let members = if self.bind_healthy_only {
+                    group.healthy_members().count()
+                } else {
+                    group.active_members().count()
+                };
+                if members == 0 {
                     BindStatus::Empty
                 } else {
                     match self.unsatisfied_bind_exports(group, service_bind.name()) {
@@ -746,6 +973,8 @@ impl Service {
                        .expect("Service update failed; unable to find own service group");
         let cfg_updated_from_rumors = self.update_gossip(census_group);
         let template_data_changed = cfg_updated_from_rumors || self.user_config_updated;
+        let secrets_expired = self.secrets_expired();
+        let config_drifted = self.config_drifted();
 
         if self.user_config_updated {
             if let Err(e) = self.cfg.reload_user() {
@@ -755,16 +984,63 @@ impl Service {
             self.user_config_updated = false;
         }
 
-        if template_data_changed || census_ring.changed() {
+        if template_data_changed || secrets_expired || config_drifted || census_ring.changed() {
             let ctx = self.render_context(census_ring);
-            TemplateUpdate::new(self.compile_hooks(&ctx),
-                                self.compile_configuration(&ctx),
-                                self.hooks.reconfigure.is_some() || self.hooks.reload.is_some())
+            let have_reconfigure_hook =
+                self.hooks.reconfigure.is_some() || self.hooks.reload.is_some();
+            let update = TemplateUpdate::new(self.compile_hooks(&ctx),
+                                             self.compile_configuration(&ctx),
+                                             secrets_expired,
+                                             have_reconfigure_hook);
+            let mut rendered = self.config_renderer
+                                   .rendered_paths(&self.pkg.svc_config_path);
+            rendered.extend(self.hooks.rendered_paths());
+            self.config_drift.record(rendered);
+            update
         } else {
             TemplateUpdate::default()
         }
     }
 
+    /// Returns `true` if a secret used by the configuration or hook templates needs to be
+    /// refreshed, in which case the templates are rendered again and the service is reconfigured.
+    fn secrets_expired(&self) -> bool {
+        let config = self.config_renderer.secrets_expired();
+        let hooks = self.hooks.secrets_expired();
+        if config || hooks {
+            outputln!(preamble self.service_group,
+                      "Secret lease needs to be renewed; rendering templates and reconfiguring");
+        }
+        config || hooks
+    }
+
+    /// Checks the rendered configuration files and hooks for edits made outside of the
+    /// Supervisor, once every `HAB_CONFIG_DRIFT_CHECK_PERIOD_SECS`. Newly drifted files are
+    /// reported according to `HAB_CONFIG_DRIFT_POLICY`.
+    ///
+    /// Returns `true` if the templates need to be rendered again to restore the drifted files.
+    fn config_drifted(&mut self) -> bool {
+        if !self.config_drift.check_due() {
+            return false;
+        }
+        let drifted = self.config_drift.check();
+        if drifted.is_empty() {
+            return false;
+        }
+        for path in &drifted {
+            outputln!(preamble self.service_group,
+                      "{} was modified outside of the Supervisor",
+                      path.display());
+        }
+        let restore = self.config_drift.policy() == ConfigDriftPolicy::Restore;
+        event::config_drift(self, &drifted, restore);
+        if restore {
+            outputln!(preamble self.service_group,
+                      "Restoring modified files; rendering templates again");
+        }
+        restore
+    }
+
     pub fn to_rumor(&self, incarnation: u64) -> ServiceRumor {
         let exported = match self.cfg.to_exported(&self.pkg) {
             Ok(exported) => Some(exported),
@@ -781,6 +1057,11 @@ impl Service {
                                           self.sys.as_sys_info().clone(),
                                           exported);
         rumor.incarnation = incarnation;
+        rumor.health = self.health_check_result().into();
+        rumor.blocked_releases = self.blocked_releases
+                                     .iter()
+                                     .map(PackageIdent::to_string)
+                                     .collect();
         rumor
     }
 
@@ -843,6 +1124,40 @@ impl Service {
         }
     }
 
+    fn pre_stop(&self) -> Option<HookRunner<hooks::PreStopHook>> {
+        self.hooks.pre_stop.as_ref().map(|hook| {
+                                        HookRunner::new(Arc::clone(&hook),
+                                                        self.service_group.clone(),
+                                                        self.pkg.clone(),
+                                                        self.svc_encrypted_password.clone())
+                                    })
+    }
+
+    /// Run the post-update hook if this service was started by an update.
+    fn post_update(&mut self, executor: &TaskExecutor) {
+        let from = match self.updated_from.take() {
+            Some(from) => from,
+            None => return,
+        };
+        if let Some(ref hook) = self.hooks.post_update {
+            outputln!(preamble self.service_group,
+                      "Running post-update hook for the update from {} to {}",
+                      from,
+                      self.pkg.ident);
+            let hook_runner = HookRunner::new(Arc::clone(&hook),
+                                              self.service_group.clone(),
+                                              self.pkg.clone(),
+                                              self.svc_encrypted_password.clone());
+            let service_group = self.service_group.clone();
+            executor.spawn(hook_runner.into_future()
+                                      .map(|_exitvalue| ())
+                                      .map_err(move |e| {
+                                          outputln!(preamble service_group,
+                                                    "Post-update hook failed to run: {}", e);
+                                      }));
+        }
+    }
+
     fn post_stop(&self) -> Option<HookRunner<hooks::PostStopHook>> {
         self.hooks.post_stop.as_ref().map(|hook| {
                                          HookRunner::new(Arc::clone(&hook),
@@ -957,6 +1272,7 @@ impl Service {
             if self.initialized {
                 self.start(launcher, executor);
                 self.post_run(executor);
+                self.post_update(executor);
             }
         } else {
             // If the service is initialized and the process is not running, the process
@@ -966,6 +1282,7 @@ impl Service {
                 // schedule the `stop` future, but the `Manager` wraps the `stop` future with
                 // additional functionality. Can we refactor to make this flag unnecessary?
                 self.needs_restart = true;
+                self.crashed = !up;
                 return true;
             } else if template_update.needs_reconfigure() {
                 // Only reconfigure if we did NOT restart the service
@@ -1017,14 +1334,19 @@ impl Service {
         // Unsatisfied binds are filtered out; you only get bind
         // information in the render context if they actually satisfy
         // the contract!
-        RenderContext::new(&self.service_group,
-                           &self.sys,
-                           &self.pkg,
-                           &self.cfg,
-                           census,
-                           self.binds
-                               .iter()
-                               .filter(|b| !self.unsatisfied_binds.contains(b)))
+        let ctx = RenderContext::new(&self.service_group,
+                                     &self.sys,
+                                     &self.pkg,
+                                     &self.cfg,
+                                     census,
+                                     self.binds
+                                         .iter()
+                                         .filter(|b| !self.unsatisfied_binds.contains(b)),
+                                     self.bind_healthy_only);
+        match self.updated_from {
+            Some(ref from) => ctx.with_update(from, &self.pkg.ident),
+            None => ctx,
+        }
     }
 
     // Returns `false` if the write fails.
@@ -1147,9 +1469,9 @@ impl<'a> Serialize for ServiceProxy<'a> {
         where S: Serializer
     {
         let num_fields: usize = if self.config_rendering == ConfigRendering::Full {
-            27
+            31
         } else {
-            26
+            30
         };
 
         let s = &self.service;
@@ -1164,13 +1486,17 @@ impl<'a> Serialize for ServiceProxy<'a> {
         }
 
         strukt.serialize_field("channel", &s.channel)?;
+        strukt.serialize_field("config_drift", s.config_drift.drifted())?;
         strukt.serialize_field("config_from", &s.config_from)?;
         strukt.serialize_field("desired_state", &s.desired_state)?;
         strukt.serialize_field("health_check", &s.health_check_result)?;
+        strukt.serialize_field("held_release", &s.held_release)?;
         strukt.serialize_field("hooks", &s.hooks)?;
         strukt.serialize_field("initialized", &s.initialized)?;
+        strukt.serialize_field("instance", &s.instance)?;
         strukt.serialize_field("last_election_status", &s.last_election_status)?;
         strukt.serialize_field("manager_fs_cfg", &s.manager_fs_cfg)?;
+        strukt.serialize_field("pending_update", &s.pending_update)?;
 
         let pkg_proxy = PkgProxy::new(&s.pkg);
         strukt.serialize_field("pkg", &pkg_proxy)?;
@@ -1201,8 +1527,10 @@ mod tests {
     use habitat_common::types::{GossipListenAddr,
                                 HttpListenAddr,
                                 ListenCtlAddr};
+    use habitat_core::package::PackageTarget;
     use serde_json;
     use std::str::FromStr;
+    use tempfile::TempDir;
 
     fn initialize_test_service() -> Service {
         let listen_ctl_addr =
@@ -1238,6 +1566,65 @@ mod tests {
                                                                           load, but it didn't")
     }
 
+    /// Installs empty releases of core/tree/1.7.0 under `fs_root`.
+    fn install_tree_releases(fs_root: &Path, releases: &[&str]) {
+        for release in releases {
+            let path = pkg_root_path(Some(fs_root)).join("core/tree/1.7.0")
+                                                   .join(release);
+            fs::create_dir_all(&path).unwrap();
+            fs::write(path.join("TARGET"),
+                      PackageTarget::active_target().to_string()).unwrap();
+        }
+    }
+
+    #[test]
+    fn a_restart_does_not_apply_a_pending_update() {
+        let fs_root = TempDir::new().unwrap();
+        install_tree_releases(fs_root.path(), &["20180609045201", "20190609045201"]);
+        let running = PackageIdent::from_str("core/tree/1.7.0/20180609045201").unwrap();
+        let pending = PackageIdent::from_str("core/tree/1.7.0/20190609045201").unwrap();
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("core/tree").unwrap());
+        assert_eq!(Service::load_package(&spec, fs_root.path()).unwrap().ident,
+                   pending);
+
+        // The update was found while the update window was closed, and the Supervisor is
+        // restarted before it opens
+        spec.pending_update = Some(pending.clone());
+        spec.pinned_release = Some(running.clone());
+        let spec_file = fs_root.path().join("tree.spec");
+        spec.to_file(&spec_file).unwrap();
+        let spec = ServiceSpec::from_file(&spec_file).unwrap();
+
+        assert_eq!(spec.pending_update, Some(pending));
+        assert_eq!(Service::load_package(&spec, fs_root.path()).unwrap().ident,
+                   running);
+    }
+
+    #[test]
+    fn a_renewed_secret_reconfigures_the_service_even_if_nothing_rendered_differently() {
+        let with_hook = TemplateUpdate::new(HookCompileTable::default(), false, true, true);
+        assert!(with_hook.needs_reconfigure());
+        assert!(!with_hook.needs_restart());
+
+        let without_hook = TemplateUpdate::new(HookCompileTable::default(), false, true, false);
+        assert!(without_hook.needs_restart());
+
+        let unchanged = TemplateUpdate::new(HookCompileTable::default(), false, false, true);
+        assert!(!unchanged.needs_reconfigure());
+        assert!(!unchanged.needs_restart());
+    }
+
+    #[test]
+    fn the_update_is_forgotten_once_the_post_update_hook_ran() {
+        let mut service = initialize_test_service();
+        service.updated_from =
+            Some(PackageIdent::from_str("core/tree/1.6.0/20170609045201").unwrap());
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+
+        service.post_update(&runtime.executor());
+        assert_eq!(service.updated_from, None);
+    }
+
     #[test]
     fn service_proxy_conforms_to_the_schema() {
         let service = initialize_test_service();
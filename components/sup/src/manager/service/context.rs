@@ -70,11 +70,13 @@ use toml;
 /// as required.
 #[derive(Clone, Debug, Serialize)]
 pub struct RenderContext<'a> {
-    sys:  SystemInfo<'a>,
-    pkg:  Package<'a>,
-    cfg:  Cow<'a, Cfg>,
-    svc:  Svc<'a>,
-    bind: Binds<'a>,
+    sys:    SystemInfo<'a>,
+    pkg:    Package<'a>,
+    cfg:    Cow<'a, Cfg>,
+    svc:    Svc<'a>,
+    bind:   Binds<'a>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    update: Option<Update>,
 }
 
 impl<'a> RenderContext<'a> {
@@ -87,22 +89,35 @@ impl<'a> RenderContext<'a> {
     /// is already complex, and exactly what we need. Because of the
     /// nature of `Cfg`s behavior, we should be safe relying on that
     /// implementation for the foreseeable future.
+    ///
+    /// If `bind_healthy_only` is set, bound service groups only list
+    /// their healthy members.
     pub fn new<T>(service_group: &ServiceGroup,
                   sys: &'a Sys,
                   pkg: &'a Pkg,
                   cfg: &'a Cfg,
                   census: &'a CensusRing,
-                  bindings: T)
+                  bindings: T,
+                  bind_healthy_only: bool)
                   -> RenderContext<'a>
         where T: Iterator<Item = &'a ServiceBind>
     {
         let census_group = census.census_group_for(&service_group)
                                  .expect("Census Group missing from list!");
-        RenderContext { sys:  SystemInfo::from_sys(sys),
-                        pkg:  Package::from_pkg(pkg),
-                        cfg:  Cow::Borrowed(cfg),
-                        svc:  Svc::new(census_group),
-                        bind: Binds::new(bindings, census), }
+        RenderContext { sys:    SystemInfo::from_sys(sys),
+                        pkg:    Package::from_pkg(pkg),
+                        cfg:    Cow::Borrowed(cfg),
+                        svc:    Svc::new(census_group),
+                        bind:   Binds::new(bindings, census, bind_healthy_only),
+                        update: None, }
+    }
+
+    /// Expose the package the service was updated from, along with the package it runs now, for
+    /// the benefit of the `post-update` hook.
+    pub fn with_update(mut self, from: &PackageIdent, to: &PackageIdent) -> Self {
+        self.update = Some(Update { from: from.to_string(),
+                                    to:   to.to_string(), });
+        self
     }
 
     // Exposed only for logging... can probably do this another way.
@@ -151,6 +166,17 @@ impl<'a> SystemInfo<'a> {
 
 ////////////////////////////////////////////////////////////////////////
 
+/// The packages involved in the most recent update of the service.
+///
+/// Exposed to users under the `update` key, which is only present after an update.
+#[derive(Clone, Debug, Serialize)]
+struct Update {
+    from: String,
+    to:   String,
+}
+
+////////////////////////////////////////////////////////////////////////
+
 /// Templating proxy fro a `manager::service::Pkg` struct.
 ///
 /// Currently exposed to users under the `pkg` key.
@@ -350,13 +376,13 @@ impl<'a> Serialize for Svc<'a> {
 struct Binds<'a>(HashMap<String, BindGroup<'a>>);
 
 impl<'a> Binds<'a> {
-    fn new<T>(bindings: T, census: &'a CensusRing) -> Self
+    fn new<T>(bindings: T, census: &'a CensusRing, healthy_only: bool) -> Self
         where T: Iterator<Item = &'a ServiceBind>
     {
         let mut map = HashMap::default();
         for bind in bindings {
             if let Some(group) = census.census_group_for(&bind.service_group()) {
-                map.insert(bind.name().to_string(), BindGroup::new(group));
+                map.insert(bind.name().to_string(), BindGroup::new(group, healthy_only));
             }
         }
         Binds(map)
@@ -371,10 +397,11 @@ struct BindGroup<'a> {
 }
 
 impl<'a> BindGroup<'a> {
-    fn new(group: &'a CensusGroup) -> Self {
+    fn new(group: &'a CensusGroup, healthy_only: bool) -> Self {
         BindGroup { first:   select_first(group),
                     leader:  group.leader().map(|m| SvcMember::from_census_member(m)),
                     members: group.active_members()
+                                  .filter(|m| !healthy_only || m.healthy())
                                   .map(|m| SvcMember::from_census_member(m))
                                   .collect(), }
     }
@@ -411,6 +438,7 @@ struct SvcMember<'a> {
     suspect: Cow<'a, bool>,
     confirmed: Cow<'a, bool>,
     departed: Cow<'a, bool>,
+    health: Cow<'a, String>,
     cfg: Cow<'a, toml::value::Table>,
 }
 
@@ -444,6 +472,7 @@ impl<'a> SvcMember<'a> {
                     suspect:   Cow::Owned(c.suspect()),
                     confirmed: Cow::Owned(c.confirmed()),
                     departed:  Cow::Owned(c.departed()),
+                    health:    Cow::Owned(c.health().to_string()),
 
                     cfg: Cow::Borrowed(&c.cfg), }
     }
@@ -509,6 +538,7 @@ impl<'a> Serialize for SvcMember<'a> {
         map.serialize_entry("suspect", &self.suspect)?;
         map.serialize_entry("confirmed", &self.confirmed)?;
         map.serialize_entry("departed", &self.departed)?;
+        map.serialize_entry("health", &self.health)?;
 
         map.serialize_entry("cfg", &self.cfg)?;
 
@@ -646,6 +676,7 @@ two = 2
                     suspect: Cow::Owned(false),
                     confirmed: Cow::Owned(false),
                     departed: Cow::Owned(false),
+                    health: Cow::Owned("OK".into()),
                     cfg: Cow::Owned(toml::value::Table::new()), }
     }
 
@@ -743,7 +774,8 @@ two = 2
                         pkg,
                         cfg: Cow::Owned(cfg),
                         svc,
-                        bind: binds }
+                        bind: binds,
+                        update: None }
     }
 
     /// Render the given template string using the given context,
@@ -802,6 +834,19 @@ two = 2
         assert_valid(&j, "render_context_schema.json");
     }
 
+    #[test]
+    fn update_is_valid_and_renders_correctly() {
+        let from = PackageIdent::new("core", "foo", Some("1.0.0"), Some("20180701125610"));
+        let to = PackageIdent::new("core", "foo", Some("1.1.0"), Some("20180801125610"));
+        let ctx = default_render_context().with_update(&from, &to);
+        let j = serde_json::to_string(&ctx).expect("can't serialize to JSON");
+        assert_valid(&j, "render_context_schema.json");
+
+        let output = render("{{update.from}} -> {{update.to}}", &ctx);
+        assert_eq!(output,
+                   "core/foo/1.0.0/20180701125610 -> core/foo/1.1.0/20180801125610");
+    }
+
     #[test]
     fn no_leader_renders_correctly() {
         let ctx = default_render_context();
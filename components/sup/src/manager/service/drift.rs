@@ -0,0 +1,217 @@
+//! Detection of out-of-band edits to the configuration files and hooks the Supervisor renders for
+//! a service.
+//!
+//! Whenever the templates of a service are rendered, the hash of every rendered file is
+//! recorded. Every `HAB_CONFIG_DRIFT_CHECK_PERIOD_SECS` seconds the files are hashed again, and
+//! any file that was modified or removed since the Supervisor wrote it has drifted. What the
+//! Supervisor does about it is governed by `HAB_CONFIG_DRIFT_POLICY`.
+
+use crate::error::Error;
+use habitat_core::crypto::hash;
+use std::{collections::{BTreeMap,
+                        BTreeSet},
+          path::PathBuf,
+          result,
+          str::FromStr,
+          time::{Duration,
+                 Instant}};
+
+habitat_core::env_config_duration!(
+    /// How often the rendered files of each service are checked for out-of-band edits.
+    ConfigDriftCheckPeriod,
+    HAB_CONFIG_DRIFT_CHECK_PERIOD_SECS => from_secs,
+    Duration::from_secs(60));
+
+/// What the Supervisor does when a rendered file has drifted.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ConfigDriftPolicy {
+    /// Rendered files are not checked.
+    Off,
+    /// Drifted files are logged, sent out as an event and listed in the HTTP gateway.
+    Report,
+    /// Drifted files are reported, then rendered again. The service is reconfigured or
+    /// restarted just as if its configuration had changed.
+    Restore,
+}
+
+impl Default for ConfigDriftPolicy {
+    fn default() -> Self { ConfigDriftPolicy::Report }
+}
+
+impl FromStr for ConfigDriftPolicy {
+    type Err = Error;
+
+    fn from_str(value: &str) -> result::Result<Self, Self::Err> {
+        match value.to_lowercase().as_ref() {
+            "off" => Ok(ConfigDriftPolicy::Off),
+            "report" => Ok(ConfigDriftPolicy::Report),
+            "restore" => Ok(ConfigDriftPolicy::Restore),
+            _ => Err(Error::BadConfigDriftPolicy(value.to_string())),
+        }
+    }
+}
+
+impl habitat_core::env::Config for ConfigDriftPolicy {
+    const ENVVAR: &'static str = "HAB_CONFIG_DRIFT_POLICY";
+}
+
+/// The hashes of the files rendered for a service, as the Supervisor last wrote them, along with
+/// the files that no longer match.
+#[derive(Debug)]
+pub struct ConfigDrift {
+    policy:     ConfigDriftPolicy,
+    period:     Duration,
+    hashes:     BTreeMap<PathBuf, String>,
+    drifted:    BTreeSet<PathBuf>,
+    last_check: Option<Instant>,
+}
+
+impl ConfigDrift {
+    pub fn new(policy: ConfigDriftPolicy, period: Duration) -> Self {
+        ConfigDrift { policy,
+                      period,
+                      hashes: BTreeMap::new(),
+                      drifted: BTreeSet::new(),
+                      last_check: None }
+    }
+
+    /// Creates a `ConfigDrift` using the policy and check period from the environment.
+    pub fn configured() -> Self {
+        Self::new(ConfigDriftPolicy::configured_value(),
+                  ConfigDriftCheckPeriod::configured_value().into())
+    }
+
+    pub fn policy(&self) -> ConfigDriftPolicy { self.policy }
+
+    /// Records the current content of `paths` as the content the Supervisor rendered, replacing
+    /// whatever was recorded before. Files that cannot be read are not tracked.
+    pub fn record<I>(&mut self, paths: I)
+        where I: IntoIterator<Item = PathBuf>
+    {
+        self.hashes = paths.into_iter()
+                           .filter_map(|path| hash::hash_file(&path).ok().map(|h| (path, h)))
+                           .collect();
+        self.drifted.clear();
+    }
+
+    /// Returns `true` if the policy calls for checks and the check period has passed since the
+    /// last check.
+    pub fn check_due(&self) -> bool {
+        self.policy != ConfigDriftPolicy::Off
+        && self.last_check
+               .map_or(true, |last| last.elapsed() >= self.period)
+    }
+
+    /// Hashes the recorded files again and returns those that drifted since the last check.
+    /// Files that drifted earlier and still differ are not returned again; files that were put
+    /// back the way they were rendered no longer count as drifted.
+    pub fn check(&mut self) -> Vec<PathBuf> {
+        self.last_check = Some(Instant::now());
+        let mut newly_drifted = Vec::new();
+        for (path, rendered_hash) in &self.hashes {
+            let drifted = match hash::hash_file(path) {
+                Ok(current_hash) => current_hash != *rendered_hash,
+                Err(_) => true,
+            };
+            if !drifted {
+                self.drifted.remove(path);
+            } else if self.drifted.insert(path.clone()) {
+                newly_drifted.push(path.clone());
+            }
+        }
+        newly_drifted
+    }
+
+    /// The files that currently differ from what the Supervisor rendered.
+    pub fn drifted(&self) -> &BTreeSet<PathBuf> { &self.drifted }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn parse_config_drift_policy() {
+        assert_eq!("off".parse::<ConfigDriftPolicy>().unwrap(),
+                   ConfigDriftPolicy::Off);
+        assert_eq!("Report".parse::<ConfigDriftPolicy>().unwrap(),
+                   ConfigDriftPolicy::Report);
+        assert_eq!("RESTORE".parse::<ConfigDriftPolicy>().unwrap(),
+                   ConfigDriftPolicy::Restore);
+        assert!("ignore".parse::<ConfigDriftPolicy>().is_err());
+    }
+
+    #[test]
+    fn detects_modified_and_removed_files_once() {
+        let tmp = TempDir::new().expect("create temp dir");
+        let config = tmp.path().join("config.toml");
+        let hook = tmp.path().join("run");
+        fs::write(&config, "port = 80").unwrap();
+        fs::write(&hook, "#!/bin/sh").unwrap();
+
+        let mut drift = ConfigDrift::new(ConfigDriftPolicy::Report, Duration::from_secs(60));
+        drift.record(vec![config.clone(), hook.clone()]);
+        assert!(drift.check().is_empty());
+
+        fs::write(&config, "port = 8080").unwrap();
+        assert_eq!(drift.check(), vec![config.clone()]);
+        // Still drifted, but already reported
+        assert!(drift.check().is_empty());
+
+        fs::remove_file(&hook).unwrap();
+        assert_eq!(drift.check(), vec![hook.clone()]);
+        assert_eq!(drift.drifted().len(), 2);
+    }
+
+    #[test]
+    fn reverted_files_are_no_longer_drifted() {
+        let tmp = TempDir::new().expect("create temp dir");
+        let config = tmp.path().join("config.toml");
+        fs::write(&config, "port = 80").unwrap();
+
+        let mut drift = ConfigDrift::new(ConfigDriftPolicy::Report, Duration::from_secs(60));
+        drift.record(vec![config.clone()]);
+        fs::write(&config, "port = 8080").unwrap();
+        assert_eq!(drift.check(), vec![config.clone()]);
+
+        fs::write(&config, "port = 80").unwrap();
+        assert!(drift.check().is_empty());
+        assert!(drift.drifted().is_empty());
+    }
+
+    #[test]
+    fn recording_clears_drift() {
+        let tmp = TempDir::new().expect("create temp dir");
+        let config = tmp.path().join("config.toml");
+        fs::write(&config, "port = 80").unwrap();
+
+        let mut drift = ConfigDrift::new(ConfigDriftPolicy::Report, Duration::from_secs(60));
+        drift.record(vec![config.clone()]);
+        fs::write(&config, "port = 8080").unwrap();
+        drift.check();
+
+        drift.record(vec![config.clone()]);
+        assert!(drift.drifted().is_empty());
+        assert!(drift.check().is_empty());
+    }
+
+    #[test]
+    fn check_is_due_after_the_period() {
+        let mut drift = ConfigDrift::new(ConfigDriftPolicy::Report, Duration::from_secs(60));
+        assert!(drift.check_due());
+        drift.check();
+        assert!(!drift.check_due());
+
+        let mut drift = ConfigDrift::new(ConfigDriftPolicy::Restore, Duration::from_secs(0));
+        drift.check();
+        assert!(drift.check_due());
+    }
+
+    #[test]
+    fn check_is_never_due_when_off() {
+        let drift = ConfigDrift::new(ConfigDriftPolicy::Off, Duration::from_secs(0));
+        assert!(!drift.check_due());
+    }
+}
@@ -10,6 +10,7 @@ use futures::{future::{self,
                        Future,
                        Loop},
               IntoFuture};
+use habitat_butterfly::rumor::service::ServiceHealth;
 use habitat_common::templating::package::Pkg;
 use habitat_core::service::{HealthCheckInterval,
                             ServiceGroup};
@@ -28,12 +29,27 @@ pub enum HealthCheckResult {
     Warning,
     Critical,
     Unknown,
+    /// The health check hook was terminated because it ran for longer than its timeout.
+    Timeout,
 }
 
 impl Default for HealthCheckResult {
     fn default() -> HealthCheckResult { HealthCheckResult::Unknown }
 }
 
+impl HealthCheckResult {
+    /// Whether the service is failing its health check, which it is when the hook reports it as
+    /// critical or runs for longer than its timeout.
+    pub fn is_failing(self) -> bool {
+        match self {
+            HealthCheckResult::Critical | HealthCheckResult::Timeout => true,
+            HealthCheckResult::Ok | HealthCheckResult::Warning | HealthCheckResult::Unknown => {
+                false
+            }
+        }
+    }
+}
+
 /// Convert health check hook exit codes into `HealthCheckResult` statuses.
 impl From<i8> for HealthCheckResult {
     fn from(value: i8) -> HealthCheckResult {
@@ -47,6 +63,30 @@ impl From<i8> for HealthCheckResult {
     }
 }
 
+/// Convert health check results into the health gossiped in the service rumor. Timeouts are
+/// gossiped as critical, just like they are reported by the HTTP gateway.
+impl From<HealthCheckResult> for ServiceHealth {
+    fn from(value: HealthCheckResult) -> ServiceHealth {
+        match value {
+            HealthCheckResult::Ok => ServiceHealth::Ok,
+            HealthCheckResult::Warning => ServiceHealth::Warning,
+            HealthCheckResult::Critical | HealthCheckResult::Timeout => ServiceHealth::Critical,
+            HealthCheckResult::Unknown => ServiceHealth::Unknown,
+        }
+    }
+}
+
+impl From<ServiceHealth> for HealthCheckResult {
+    fn from(value: ServiceHealth) -> HealthCheckResult {
+        match value {
+            ServiceHealth::Ok => HealthCheckResult::Ok,
+            ServiceHealth::Warning => HealthCheckResult::Warning,
+            ServiceHealth::Critical => HealthCheckResult::Critical,
+            ServiceHealth::Unknown => HealthCheckResult::Unknown,
+        }
+    }
+}
+
 impl fmt::Display for HealthCheckResult {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let msg = match *self {
@@ -54,6 +94,7 @@ impl fmt::Display for HealthCheckResult {
             HealthCheckResult::Warning => "WARNING",
             HealthCheckResult::Critical => "CRITICAL",
             HealthCheckResult::Unknown => "UNKNOWN",
+            HealthCheckResult::Timeout => "TIMEOUT",
         };
         write!(f, "{}", msg)
     }
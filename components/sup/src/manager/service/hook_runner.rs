@@ -89,8 +89,8 @@ impl<H: Hook + Sync + 'static> IntoFuture for HookRunner<H> {
         // name in the thread name (e.g. "HC" for "health_check", "I"
         // for "init", etc.
 
-        // TODO (CM): May want to consider adding a configurable
-        // timeout to how long this hook is allowed to run.
+        // The hook enforces its own timeout (see `Hook::run`), so this
+        // thread can't outlive a hung hook for long.
         let handle_result =
             thread::Builder::new().name(format!("{}-{}", H::file_name(), self.service_group))
                                   .spawn(move || {
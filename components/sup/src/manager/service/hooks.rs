@@ -6,6 +6,7 @@ use habitat_common::{outputln,
                                           HookOutput,
                                           RenderPair},
                                   package::Pkg,
+                                  TemplateEngine,
                                   TemplateRenderer}};
 #[cfg(windows)]
 use habitat_core::os::process::windows_child::ExitStatus;
@@ -19,6 +20,9 @@ use std::{self,
           sync::Arc};
 
 static LOGKEY: &'static str = "HK";
+/// The number of seconds a health check hook may run for unless its package or service spec sets
+/// a timeout.
+const HEALTH_CHECK_TIMEOUT_SECS: u32 = 30;
 
 #[derive(Debug, Serialize)]
 pub struct FileUpdatedHook {
@@ -63,6 +67,11 @@ impl Hook for HealthCheckHook {
 
     fn file_name() -> &'static str { "health-check" }
 
+    /// Health checks run every few seconds, so one which hangs for long is as good as failed.
+    fn default_timeout() -> u32 { HEALTH_CHECK_TIMEOUT_SECS }
+
+    fn timed_out_value() -> Self::ExitValue { health::HealthCheckResult::Timeout }
+
     fn new(package_name: &str, pair: RenderPair) -> Self {
         HealthCheckHook { render_pair:     pair,
                           stdout_log_path: hooks::stdout_log_path::<Self>(package_name),
@@ -424,6 +433,92 @@ impl Hook for PostStopHook {
     fn stderr_log_path(&self) -> &Path { &self.stderr_log_path }
 }
 
+#[derive(Debug, Serialize)]
+pub struct PreStopHook {
+    render_pair:     RenderPair,
+    stdout_log_path: PathBuf,
+    stderr_log_path: PathBuf,
+}
+
+impl Hook for PreStopHook {
+    type ExitValue = bool;
+
+    fn file_name() -> &'static str { "pre-stop" }
+
+    fn new(package_name: &str, pair: RenderPair) -> Self {
+        PreStopHook { render_pair:     pair,
+                      stdout_log_path: hooks::stdout_log_path::<Self>(package_name),
+                      stderr_log_path: hooks::stderr_log_path::<Self>(package_name), }
+    }
+
+    fn handle_exit<'a>(&self, pkg: &Pkg, _: &'a HookOutput, status: ExitStatus) -> Self::ExitValue {
+        let pkg_name = &pkg.name;
+        match status.code() {
+            Some(0) => true,
+            Some(code) => {
+                outputln!(preamble pkg_name, "Pre stop failed! '{}' exited with \
+                    status code {}; stopping the service anyway", Self::file_name(), code);
+                false
+            }
+            None => {
+                Self::output_termination_message(pkg_name, status);
+                false
+            }
+        }
+    }
+
+    fn path(&self) -> &Path { &self.render_pair.path }
+
+    fn renderer(&self) -> &TemplateRenderer { &self.render_pair.renderer }
+
+    fn stdout_log_path(&self) -> &Path { &self.stdout_log_path }
+
+    fn stderr_log_path(&self) -> &Path { &self.stderr_log_path }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PostUpdateHook {
+    render_pair:     RenderPair,
+    stdout_log_path: PathBuf,
+    stderr_log_path: PathBuf,
+}
+
+impl Hook for PostUpdateHook {
+    type ExitValue = bool;
+
+    fn file_name() -> &'static str { "post-update" }
+
+    fn new(package_name: &str, pair: RenderPair) -> Self {
+        PostUpdateHook { render_pair:     pair,
+                         stdout_log_path: hooks::stdout_log_path::<Self>(package_name),
+                         stderr_log_path: hooks::stderr_log_path::<Self>(package_name), }
+    }
+
+    fn handle_exit<'a>(&self, pkg: &Pkg, _: &'a HookOutput, status: ExitStatus) -> Self::ExitValue {
+        let pkg_name = &pkg.name;
+        match status.code() {
+            Some(0) => true,
+            Some(code) => {
+                outputln!(preamble pkg_name, "Post update failed! '{}' exited with \
+                    status code {}", Self::file_name(), code);
+                false
+            }
+            None => {
+                Self::output_termination_message(pkg_name, status);
+                false
+            }
+        }
+    }
+
+    fn path(&self) -> &Path { &self.render_pair.path }
+
+    fn renderer(&self) -> &TemplateRenderer { &self.render_pair.renderer }
+
+    fn stdout_log_path(&self) -> &Path { &self.stdout_log_path }
+
+    fn stderr_log_path(&self) -> &Path { &self.stderr_log_path }
+}
+
 /// A lookup of hooks that have changed after compilation.
 #[derive(Default)]
 pub struct HookCompileTable {
@@ -435,7 +530,9 @@ pub struct HookCompileTable {
     suitability:  bool,
     run:          bool,
     post_run:     bool,
+    pre_stop:     bool,
     post_stop:    bool,
+    post_update:  bool,
 }
 
 impl HookCompileTable {
@@ -459,7 +556,9 @@ impl HookCompileTable {
                    suitability,
                    run,
                    post_run,
-                   post_stop, } => {
+                   pre_stop,
+                   post_stop,
+                   post_update, } => {
                 *health_check
                 || *init
                 || *file_updated
@@ -468,7 +567,9 @@ impl HookCompileTable {
                 || *suitability
                 || *run
                 || *post_run
+                || *pre_stop
                 || *post_stop
+                || *post_update
             }
         }
     }
@@ -486,30 +587,45 @@ pub struct HookTable {
     pub suitability:  Option<SuitabilityHook>,
     pub run:          Option<RunHook>,
     pub post_run:     Option<Arc<PostRunHook>>,
+    pub pre_stop:     Option<Arc<PreStopHook>>,
     pub post_stop:    Option<Arc<PostStopHook>>,
+    pub post_update:  Option<Arc<PostUpdateHook>>,
 }
 
 impl HookTable {
     /// Read all available hook templates from the table's package directory into the table.
-    pub fn load<P, T>(package_name: &str, templates: T, hooks_path: P) -> Self
+    /// Hook templates without a `.tera` extension are written for `engine`.
+    pub fn load<P, T>(package_name: &str,
+                      templates: T,
+                      hooks_path: P,
+                      engine: TemplateEngine)
+                      -> Self
         where P: AsRef<Path>,
               T: AsRef<Path>
     {
         let mut table = HookTable::default();
         if let Ok(meta) = std::fs::metadata(templates.as_ref()) {
             if meta.is_dir() {
-                table.file_updated = FileUpdatedHook::load(package_name, &hooks_path, &templates);
+                let hooks = hooks_path.as_ref();
+                let templates = templates.as_ref();
+                table.file_updated =
+                    FileUpdatedHook::load(package_name, hooks, templates, engine);
                 table.health_check =
-                    HealthCheckHook::load(package_name, &hooks_path, &templates).map(Arc::new);
-                table.suitability = SuitabilityHook::load(package_name, &hooks_path, &templates);
-                table.init = InitHook::load(package_name, &hooks_path, &templates);
-                table.reload = ReloadHook::load(package_name, &hooks_path, &templates);
-                table.reconfigure = ReconfigureHook::load(package_name, &hooks_path, &templates);
-                table.run = RunHook::load(package_name, &hooks_path, &templates);
+                    HealthCheckHook::load(package_name, hooks, templates, engine).map(Arc::new);
+                table.suitability = SuitabilityHook::load(package_name, hooks, templates, engine);
+                table.init = InitHook::load(package_name, hooks, templates, engine);
+                table.reload = ReloadHook::load(package_name, hooks, templates, engine);
+                table.reconfigure =
+                    ReconfigureHook::load(package_name, hooks, templates, engine);
+                table.run = RunHook::load(package_name, hooks, templates, engine);
                 table.post_run =
-                    PostRunHook::load(package_name, &hooks_path, &templates).map(Arc::new);
+                    PostRunHook::load(package_name, hooks, templates, engine).map(Arc::new);
+                table.pre_stop =
+                    PreStopHook::load(package_name, hooks, templates, engine).map(Arc::new);
                 table.post_stop =
-                    PostStopHook::load(package_name, &hooks_path, &templates).map(Arc::new);
+                    PostStopHook::load(package_name, hooks, templates, engine).map(Arc::new);
+                table.post_update =
+                    PostUpdateHook::load(package_name, hooks, templates, engine).map(Arc::new);
             }
         }
         debug!("{}, Hooks loaded, destination={}, templates={}",
@@ -549,12 +665,55 @@ impl HookTable {
         if let Some(ref hook) = self.post_run {
             changed.post_run = self.compile_one(hook.as_ref(), service_group, ctx);
         }
+        if let Some(ref hook) = self.pre_stop {
+            changed.pre_stop = self.compile_one(hook.as_ref(), service_group, ctx);
+        }
         if let Some(ref hook) = self.post_stop {
             changed.post_stop = self.compile_one(hook.as_ref(), service_group, ctx);
         }
+        if let Some(ref hook) = self.post_update {
+            changed.post_update = self.compile_one(hook.as_ref(), service_group, ctx);
+        }
         changed
     }
 
+    /// Returns `true` once the lease of a secret used by any of the hooks needs to be renewed.
+    pub fn secrets_expired(&self) -> bool {
+        [self.file_updated.as_ref().map(|h| h.renderer()),
+         self.health_check.as_ref().map(|h| h.renderer()),
+         self.init.as_ref().map(|h| h.renderer()),
+         self.reload.as_ref().map(|h| h.renderer()),
+         self.reconfigure.as_ref().map(|h| h.renderer()),
+         self.suitability.as_ref().map(|h| h.renderer()),
+         self.run.as_ref().map(|h| h.renderer()),
+         self.post_run.as_ref().map(|h| h.renderer()),
+         self.pre_stop.as_ref().map(|h| h.renderer()),
+         self.post_stop.as_ref().map(|h| h.renderer()),
+         self.post_update.as_ref().map(|h| h.renderer())].iter()
+                                                         .flatten()
+                                                         .fold(false, |expired, renderer| {
+                                                             renderer.secrets_expired() | expired
+                                                         })
+    }
+
+    /// The paths the loaded hooks are compiled to.
+    pub fn rendered_paths(&self) -> Vec<PathBuf> {
+        [self.file_updated.as_ref().map(rendered_path),
+         self.health_check.as_ref().map(|h| rendered_path(h.as_ref())),
+         self.init.as_ref().map(rendered_path),
+         self.reload.as_ref().map(rendered_path),
+         self.reconfigure.as_ref().map(rendered_path),
+         self.suitability.as_ref().map(rendered_path),
+         self.run.as_ref().map(rendered_path),
+         self.post_run.as_ref().map(|h| rendered_path(h.as_ref())),
+         self.pre_stop.as_ref().map(|h| rendered_path(h.as_ref())),
+         self.post_stop.as_ref().map(|h| rendered_path(h.as_ref())),
+         self.post_update.as_ref().map(|h| rendered_path(h.as_ref()))].iter()
+                                                                       .flatten()
+                                                                       .cloned()
+                                                                       .collect()
+    }
+
     fn compile_one<H, T>(&self, hook: &H, service_group: &str, ctx: &T) -> bool
         where H: Hook,
               T: Serialize
@@ -570,6 +729,10 @@ impl HookTable {
     }
 }
 
+/// The path a hook is compiled to. This is where `Hook::compile` writes, which may differ from
+/// `Hook::path` if the hook template uses a deprecated file name.
+fn rendered_path<H: Hook>(hook: &H) -> PathBuf { hook.path().with_file_name(H::file_name()) }
+
 #[cfg(test)]
 mod tests {
     use super::{super::RenderContext,
@@ -623,7 +786,9 @@ mod tests {
                       ReconfigureHook
                       RunHook
                       SuitabilityHook
-                      PostStopHook);
+                      PreStopHook
+                      PostStopHook
+                      PostUpdateHook);
 
     fn hook_templates_path() -> PathBuf {
         PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests")
@@ -721,12 +886,15 @@ mod tests {
 
         let bindings = iter::empty::<&ServiceBind>();
 
-        let ctx = RenderContext::new(&service_group, &sys, &pkg, &cfg, &ring, bindings);
+        let ctx = RenderContext::new(&service_group, &sys, &pkg, &cfg, &ring, bindings, false);
 
         // END RENDER CONTEXT SETUP
         ////////////////////////////////////////////////////////////////////////
 
-        let hook_table = HookTable::load(&service_group, &template_path, &hooks_path);
+        let hook_table = HookTable::load(&service_group,
+                                         &template_path,
+                                         &hooks_path,
+                                         TemplateEngine::Handlebars);
         assert!(hook_table.compile(&service_group, &ctx).changed());
 
         // Verify init hook
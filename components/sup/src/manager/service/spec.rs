@@ -4,19 +4,25 @@ use super::{BindingMode,
 use crate::error::{Error,
                    Result};
 use habitat_core::{fs::atomic_write,
-                   os::process::ShutdownTimeout,
+                   os::{process::{ResourceLimits,
+                                  ShutdownTimeout},
+                        sandbox::Sandbox},
                    package::{PackageIdent,
                              PackageInstall},
                    service::{ApplicationEnvironment,
+                             CanaryCount,
                              HealthCheckInterval,
-                             ServiceBind},
+                             ServiceBind,
+                             UpdateWindow},
                    url::DEFAULT_BLDR_URL,
                    util::serde_string,
                    ChannelIdent};
 use habitat_sup_protocol;
 use serde::{self,
             Deserialize};
-use std::{collections::HashSet,
+use std::{collections::{BTreeMap,
+                        HashSet},
+          convert::TryFrom,
           fmt,
           fs::{self,
                File},
@@ -30,6 +36,8 @@ use std::{collections::HashSet,
 use toml;
 
 static DEFAULT_GROUP: &str = "default";
+const DEFAULT_UPDATE_BAKE_PERIOD: u32 = 300;
+const DEFAULT_UPDATE_ROLLBACK_WINDOW: u32 = 0;
 const SPEC_FILE_EXT: &str = "spec";
 
 #[derive(Copy, Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
@@ -86,15 +94,81 @@ pub fn deserialize_application_environment<'de, D>(
     }
 }
 
+fn serialize_idents<S>(idents: &[PackageIdent], s: S) -> result::Result<S::Ok, S::Error>
+    where S: serde::Serializer
+{
+    s.collect_seq(idents.iter().map(PackageIdent::to_string))
+}
+
+fn deserialize_idents<'de, D>(d: D) -> result::Result<Vec<PackageIdent>, D::Error>
+    where D: serde::Deserializer<'de>
+{
+    let idents: Vec<String> = Vec::deserialize(d)?;
+    idents.iter()
+          .map(|s| PackageIdent::from_str(s).map_err(serde::de::Error::custom))
+          .collect()
+}
+
+fn serialize_optional_ident<S>(ident: &Option<PackageIdent>,
+                               s: S)
+                               -> result::Result<S::Ok, S::Error>
+    where S: serde::Serializer
+{
+    match *ident {
+        Some(ref ident) => s.serialize_some(&ident.to_string()),
+        None => s.serialize_none(),
+    }
+}
+
+fn deserialize_optional_ident<'de, D>(d: D) -> result::Result<Option<PackageIdent>, D::Error>
+    where D: serde::Deserializer<'de>
+{
+    let s: Option<String> = Option::deserialize(d)?;
+    s.map(|s| PackageIdent::from_str(&s).map_err(serde::de::Error::custom))
+     .transpose()
+}
+
+/// An update a service is on probation for, saved in its spec so that the probation survives a
+/// restart of the Supervisor. See `manager::update_probation`.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct UpdateProbationSpec {
+    /// The release the service ran before the update, which it is rolled back to if the update
+    /// fails.
+    #[serde(with = "serde_string")]
+    pub from:       PackageIdent,
+    /// The release the service was updated to.
+    #[serde(with = "serde_string")]
+    pub to:         PackageIdent,
+    /// When the probation started, in seconds since the Unix epoch.
+    pub started_at: u64,
+}
+
 pub trait IntoServiceSpec {
-    fn into_spec(&self, spec: &mut ServiceSpec);
+    fn into_spec(&self, spec: &mut ServiceSpec) -> Result<()>;
+}
+
+/// The name a service goes by on a Supervisor: the name of its package, qualified by the
+/// instance name if the service has one, e.g. `redis@cache`.
+pub type ServiceName = String;
+
+/// Returns the name of the service running the package named `package_name` as the instance
+/// `instance`.
+pub fn service_name(package_name: &str, instance: Option<&str>) -> ServiceName {
+    match instance {
+        Some(instance) => format!("{}@{}", package_name, instance),
+        None => package_name.to_string(),
+    }
 }
 
 impl IntoServiceSpec for habitat_sup_protocol::ctl::SvcLoad {
-    fn into_spec(&self, spec: &mut ServiceSpec) {
-        spec.ident = self.ident.clone().unwrap().into();
+    fn into_spec(&self, spec: &mut ServiceSpec) -> Result<()> {
+        spec.ident = PackageIdent::try_from(self.ident.clone().unwrap())?;
+        spec.instance = self.instance.clone();
+        // Instances are told apart in the census by their service group, so an instance is
+        // placed in a group named after it unless told otherwise.
         spec.group = self.group
                          .clone()
+                         .or_else(|| self.instance.clone())
                          .unwrap_or_else(|| DEFAULT_GROUP.to_string());
         if let Some(ref app_env) = self.application_environment {
             spec.application_environment = Some(app_env.clone().into());
@@ -111,6 +185,28 @@ impl IntoServiceSpec for habitat_sup_protocol::ctl::SvcLoad {
         if let Some(update_strategy) = self.update_strategy {
             spec.update_strategy = UpdateStrategy::from_i32(update_strategy).unwrap_or_default();
         }
+        if let Some(ref canaries) = self.update_canaries {
+            match canaries.parse() {
+                Ok(canaries) => spec.update_canaries = canaries,
+                Err(err) => warn!("Ignoring update canaries: {}", err),
+            }
+        }
+        if let Some(bake_period) = self.update_bake_period {
+            spec.update_bake_period = bake_period;
+        }
+        if let Some(rollback_window) = self.update_rollback_window {
+            spec.update_rollback_window = rollback_window;
+        }
+        if let Some(ref window) = self.update_window {
+            if window.trim().is_empty() {
+                spec.update_window = None;
+            } else {
+                match window.parse() {
+                    Ok(window) => spec.update_window = Some(window),
+                    Err(err) => warn!("Ignoring update window: {}", err),
+                }
+            }
+        }
         if let Some(ref list) = self.binds {
             spec.binds =
                 list.binds
@@ -124,6 +220,19 @@ impl IntoServiceSpec for habitat_sup_protocol::ctl::SvcLoad {
         if let Some(binding_mode) = self.binding_mode {
             spec.binding_mode = BindingMode::from_i32(binding_mode).unwrap_or_default();
         }
+        if let Some(ref list) = self.start_after {
+            spec.start_after = list.idents
+                                   .iter()
+                                   .cloned()
+                                   .map(PackageIdent::try_from)
+                                   .collect::<result::Result<_, _>>()?;
+        }
+        if let Some(bind_healthy_only) = self.bind_healthy_only {
+            spec.bind_healthy_only = bind_healthy_only;
+        }
+        if let Some(requires_healthy) = self.requires_healthy {
+            spec.requires_healthy = requires_healthy;
+        }
         if let Some(ref config_from) = self.config_from {
             spec.config_from = Some(PathBuf::from(config_from));
         }
@@ -134,6 +243,7 @@ impl IntoServiceSpec for habitat_sup_protocol::ctl::SvcLoad {
             spec.health_check_interval = interval.seconds.into()
         }
         spec.shutdown_timeout = self.shutdown_timeout.map(ShutdownTimeout::from);
+        Ok(())
     }
 }
 
@@ -142,6 +252,9 @@ impl IntoServiceSpec for habitat_sup_protocol::ctl::SvcLoad {
 pub struct ServiceSpec {
     #[serde(with = "serde_string")]
     pub ident: PackageIdent,
+    /// Tells this service apart from other services of the same package on the Supervisor.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
     pub group: String,
     #[serde(deserialize_with = "deserialize_application_environment",
             skip_serializing_if = "Option::is_none")]
@@ -152,12 +265,77 @@ pub struct ServiceSpec {
     pub update_strategy: UpdateStrategy,
     pub binds: Vec<ServiceBind>,
     pub binding_mode: BindingMode,
+    /// Whether bound service groups only list their healthy members in templates.
+    pub bind_healthy_only: bool,
+    /// Local services this service is started after and stopped before. Local services providing
+    /// the groups it binds to are treated the same way.
+    #[serde(serialize_with = "serialize_idents",
+            deserialize_with = "deserialize_idents",
+            skip_serializing_if = "Vec::is_empty")]
+    pub start_after: Vec<PackageIdent>,
+    /// Whether the service waits for its dependencies to be healthy, rather than just running,
+    /// before it is started.
+    pub requires_healthy: bool,
     pub config_from: Option<PathBuf>,
     #[serde(with = "serde_string")]
     pub desired_state: DesiredState,
     pub shutdown_timeout: Option<ShutdownTimeout>,
-    pub health_check_interval: HealthCheckInterval,
     pub svc_encrypted_password: Option<String>,
+    /// The number or percentage of members the canary update strategy updates first.
+    #[serde(with = "serde_string")]
+    pub update_canaries: CanaryCount,
+    /// The number of seconds the canaries must stay healthy before the canary update strategy
+    /// updates the rest of the service group.
+    pub update_bake_period: u32,
+    /// The number of seconds an updated release has to become healthy before it is rolled back
+    /// to the release that ran before it. A value of 0, the default, disables rollbacks.
+    pub update_rollback_window: u32,
+    /// When updates may be applied to the service. Updates found while the window is closed
+    /// wait until it opens. If not set, the Supervisor's update window applies.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub update_window: Option<UpdateWindow>,
+    /// The release the service is updated to once its update window opens. While an update is
+    /// pending, the service is pinned to the release it runs.
+    #[serde(serialize_with = "serialize_optional_ident",
+            deserialize_with = "deserialize_optional_ident",
+            skip_serializing_if = "Option::is_none")]
+    pub pending_update: Option<PackageIdent>,
+    /// Releases of the package which must not be run, e.g. because a canary update rolled them
+    /// back. The service runs the latest installed release which is not listed here.
+    #[serde(serialize_with = "serialize_idents",
+            deserialize_with = "deserialize_idents",
+            skip_serializing_if = "Vec::is_empty")]
+    pub blocked_releases: Vec<PackageIdent>,
+    /// The release the service is held at. Held services are not updated and keep running this
+    /// release, even if newer ones are installed.
+    #[serde(serialize_with = "serialize_optional_ident",
+            deserialize_with = "deserialize_optional_ident",
+            skip_serializing_if = "Option::is_none")]
+    pub held_release: Option<PackageIdent>,
+    /// The release the Supervisor keeps the service at until the next update is applied, e.g.
+    /// the release it rolled back to, or the release it runs while an update is pending. Unlike
+    /// a held release, it doesn't keep the service from being updated.
+    #[serde(serialize_with = "serialize_optional_ident",
+            deserialize_with = "deserialize_optional_ident",
+            skip_serializing_if = "Option::is_none")]
+    pub pinned_release: Option<PackageIdent>,
+    // The fields below are written as TOML tables, which must follow all plain values.
+    pub health_check_interval: HealthCheckInterval,
+    /// The update the service is on probation for, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub update_probation: Option<UpdateProbationSpec>,
+    /// The number of seconds each hook may run for, keyed by hook file name. These take
+    /// precedence over the timeouts set in the package.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub hook_timeouts: BTreeMap<String, u32>,
+    /// The resources the service may use. Limits set here take precedence over those set in the
+    /// package.
+    #[serde(skip_serializing_if = "ResourceLimits::is_empty")]
+    pub resource_limits: ResourceLimits,
+    /// The hardening applied to the service's process. Options set here take precedence over
+    /// those set in the package.
+    #[serde(skip_serializing_if = "Sandbox::is_empty")]
+    pub sandbox: Sandbox,
 }
 
 impl ServiceSpec {
@@ -203,7 +381,12 @@ impl ServiceSpec {
         Ok(())
     }
 
-    pub fn file_name(&self) -> String { format!("{}.{}", &self.ident.name, SPEC_FILE_EXT) }
+    /// The name of the service, which names its spec file and its `svc` directory.
+    pub fn service_name(&self) -> ServiceName {
+        service_name(&self.ident.name, self.instance.as_ref().map(String::as_str))
+    }
+
+    pub fn file_name(&self) -> String { format!("{}.{}", self.service_name(), SPEC_FILE_EXT) }
 
     /// Validates that all required package binds are present in service binds and all remaining
     /// service binds are optional package binds.
@@ -253,6 +436,7 @@ impl ServiceSpec {
 impl Default for ServiceSpec {
     fn default() -> Self {
         ServiceSpec { ident:                   PackageIdent::default(),
+                      instance:                None,
                       group:                   DEFAULT_GROUP.to_string(),
                       application_environment: None,
                       bldr_url:                DEFAULT_BLDR_URL.to_string(),
@@ -261,11 +445,26 @@ impl Default for ServiceSpec {
                       update_strategy:         UpdateStrategy::default(),
                       binds:                   Vec::default(),
                       binding_mode:            BindingMode::Strict,
+                      bind_healthy_only:       false,
+                      start_after:             Vec::new(),
+                      requires_healthy:        false,
                       config_from:             None,
                       desired_state:           DesiredState::default(),
                       health_check_interval:   HealthCheckInterval::default(),
                       svc_encrypted_password:  None,
-                      shutdown_timeout:        None, }
+                      shutdown_timeout:        None,
+                      update_canaries:         CanaryCount::default(),
+                      update_bake_period:      DEFAULT_UPDATE_BAKE_PERIOD,
+                      update_rollback_window:  DEFAULT_UPDATE_ROLLBACK_WINDOW,
+                      update_window:           None,
+                      pending_update:          None,
+                      blocked_releases:        Vec::new(),
+                      held_release:            None,
+                      pinned_release:          None,
+                      update_probation:        None,
+                      hook_timeouts:           BTreeMap::new(),
+                      resource_limits:         ResourceLimits::default(),
+                      sandbox:                 Sandbox::default(), }
     }
 }
 
@@ -277,6 +476,13 @@ impl FromStr for ServiceSpec {
         if spec.ident == PackageIdent::default() {
             return Err(Error::MissingRequiredIdent);
         }
+        if let Some(ref instance) = spec.instance {
+            if !habitat_sup_protocol::ctl::valid_instance_name(instance) {
+                return Err(Error::InvalidInstanceName(instance.clone()));
+            }
+        }
+        spec.resource_limits.validate()?;
+        spec.sandbox.validate()?;
         Ok(spec)
     }
 }
@@ -330,12 +536,37 @@ mod test {
             bldr_url = "http://example.com/depot"
             topology = "leader"
             update_strategy = "rolling"
+            update_window = "0-29 2 * * *"
+            pending_update = "origin/name/1.2.4/20170224130020"
+            blocked_releases = ["origin/name/1.2.3/20170223130020"]
+            held_release = "origin/name/1.2.2/20170222130020"
+            pinned_release = "origin/name/1.2.1/20170221130020"
+            start_after = ["origin/db", "origin/cache/1.0.0"]
+            requires_healthy = true
             binds = ["cache:redis.cache@acmecorp", "db:postgres.app@acmecorp"]
+            bind_healthy_only = true
             config_from = "/only/for/development"
 
             [health_check_interval]
             secs = 5
             nanos = 0
+
+            [hook_timeouts]
+            health-check = 10
+            reconfigure = 0
+
+            [update_probation]
+            from = "origin/name/1.2.1/20170221130020"
+            to = "origin/name/1.2.3/20170223130020"
+            started_at = 1556000000
+
+            [resource_limits]
+            memory_max = 536870912
+            cpu_quota = 50
+
+            [sandbox]
+            private_tmp = true
+            capabilities = ["CAP_NET_BIND_SERVICE"]
             "#;
         let spec = ServiceSpec::from_str(toml).unwrap();
 
@@ -347,6 +578,27 @@ mod test {
         assert_eq!(spec.bldr_url, String::from("http://example.com/depot"));
         assert_eq!(spec.topology, Topology::Leader);
         assert_eq!(spec.update_strategy, UpdateStrategy::Rolling);
+        assert_eq!(spec.update_canaries, CanaryCount::default());
+        assert_eq!(spec.update_bake_period, DEFAULT_UPDATE_BAKE_PERIOD);
+        assert_eq!(spec.update_rollback_window, DEFAULT_UPDATE_ROLLBACK_WINDOW);
+        assert_eq!(spec.update_window, Some(UpdateWindow::from_str("0-29 2 * * *").unwrap()));
+        assert_eq!(spec.pending_update,
+                   Some(PackageIdent::from_str("origin/name/1.2.4/20170224130020").unwrap()));
+        assert_eq!(spec.blocked_releases,
+                   vec![PackageIdent::from_str("origin/name/1.2.3/20170223130020").unwrap()]);
+        assert_eq!(spec.held_release,
+                   Some(PackageIdent::from_str("origin/name/1.2.2/20170222130020").unwrap()));
+        let pinned_release = PackageIdent::from_str("origin/name/1.2.1/20170221130020").unwrap();
+        assert_eq!(spec.pinned_release, Some(pinned_release.clone()));
+        assert_eq!(spec.update_probation,
+                   Some(UpdateProbationSpec { from:       pinned_release,
+                                              to:         spec.ident.clone(),
+                                              started_at: 1_556_000_000, }));
+        assert_eq!(spec.start_after,
+                   vec![PackageIdent::from_str("origin/db").unwrap(),
+                        PackageIdent::from_str("origin/cache/1.0.0").unwrap()]);
+        assert!(spec.requires_healthy);
+        assert!(spec.bind_healthy_only);
         assert_eq!(spec.binds,
                    vec![ServiceBind::from_str("cache:redis.cache@acmecorp").unwrap(),
                         ServiceBind::from_str("db:postgres.app@acmecorp").unwrap(),]);
@@ -354,6 +606,31 @@ mod test {
                    Some(PathBuf::from("/only/for/development")));
         assert_eq!(spec.health_check_interval,
                    HealthCheckInterval::from_str("5").unwrap());
+        assert_eq!(spec.hook_timeouts["health-check"], 10);
+        assert_eq!(spec.hook_timeouts["reconfigure"], 0);
+        assert_eq!(spec.resource_limits,
+                   ResourceLimits { memory_max: Some(536_870_912),
+                                    cpu_quota: Some(50),
+                                    ..Default::default() });
+        assert_eq!(spec.sandbox,
+                   Sandbox { private_tmp: Some(true),
+                             capabilities: Some(vec!["CAP_NET_BIND_SERVICE".to_string()]),
+                             ..Default::default() });
+    }
+
+    #[test]
+    fn service_spec_from_str_canary() {
+        let toml = r#"
+            ident = "origin/name/1.2.3/20170223130020"
+            update_strategy = "canary"
+            update_canaries = "2"
+            update_bake_period = 60
+            "#;
+        let spec = ServiceSpec::from_str(toml).unwrap();
+
+        assert_eq!(spec.update_strategy, UpdateStrategy::Canary);
+        assert_eq!(spec.update_canaries, CanaryCount::Members(2));
+        assert_eq!(spec.update_bake_period, 60);
     }
 
     #[test]
@@ -371,6 +648,36 @@ mod test {
         }
     }
 
+    #[test]
+    fn service_spec_from_str_invalid_resource_limits() {
+        let toml = r#"
+            ident = "origin/name/1.2.3/20170223130020"
+
+            [resource_limits]
+            cpu_weight = 0
+            "#;
+
+        match ServiceSpec::from_str(toml) {
+            Err(HabitatCore(habitat_core::Error::InvalidResourceLimit(_))) => (),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn service_spec_from_str_invalid_sandbox() {
+        let toml = r#"
+            ident = "origin/name/1.2.3/20170223130020"
+
+            [sandbox]
+            seccomp_profile = "everything"
+            "#;
+
+        match ServiceSpec::from_str(toml) {
+            Err(HabitatCore(habitat_core::Error::InvalidSandboxOption(_))) => (),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
     #[test]
     fn service_spec_from_str_invalid_topology() {
         let toml = r#"
@@ -410,9 +717,22 @@ mod test {
 
     #[test]
     fn service_spec_to_toml_string() {
+        let mut hook_timeouts = BTreeMap::new();
+        hook_timeouts.insert("health-check".to_string(), 10);
+        let blocked_release = PackageIdent::from_str("origin/name/1.2.4/20170224130020").unwrap();
+        let update_window = UpdateWindow::from_str("* 2-4 * * sat,sun Europe/Berlin").unwrap();
+        let pending_update = PackageIdent::from_str("origin/name/1.2.5/20170225130020").unwrap();
+        let held_release = PackageIdent::from_str("origin/name/1.2.3/20170223130020").unwrap();
+        let start_after = PackageIdent::from_str("origin/db").unwrap();
+        let pinned_release = PackageIdent::from_str("origin/name/1.2.2/20170222130020").unwrap();
+        let update_probation =
+            UpdateProbationSpec { from:       pinned_release.clone(),
+                                  to:         blocked_release.clone(),
+                                  started_at: 1_556_000_000, };
         let spec =
             ServiceSpec { ident:
                               PackageIdent::from_str("origin/name/1.2.3/20170223130020").unwrap(),
+                          instance:                Some(String::from("nightly")),
                           group:                   String::from("jobs"),
                           application_environment:
                               Some(ApplicationEnvironment::from_str("theinternet.preprod").unwrap()),
@@ -425,14 +745,30 @@ mod test {
                 ServiceBind::from_str("db:postgres.app@acmecorp").unwrap(),
             ],
                           binding_mode:            BindingMode::Relaxed,
+                          bind_healthy_only:       true,
+                          start_after:             vec![start_after],
+                          requires_healthy:        true,
                           health_check_interval:   HealthCheckInterval::from_str("123").unwrap(),
                           config_from:             Some(PathBuf::from("/only/for/development")),
                           desired_state:           DesiredState::Down,
                           svc_encrypted_password:  None,
-                          shutdown_timeout:        Some(ShutdownTimeout::from_str("10").unwrap()), };
+                          shutdown_timeout:        Some(ShutdownTimeout::from_str("10").unwrap()),
+                          update_canaries:         CanaryCount::Percent(25),
+                          update_bake_period:      60,
+                          update_rollback_window:  0,
+                          update_window:           Some(update_window),
+                          pending_update:          Some(pending_update),
+                          blocked_releases:        vec![blocked_release],
+                          held_release:            Some(held_release),
+                          pinned_release:          Some(pinned_release),
+                          update_probation:        Some(update_probation),
+                          hook_timeouts,
+                          resource_limits:         ResourceLimits::default(),
+                          sandbox:                 Sandbox::default(), };
         let toml = spec.to_toml_string().unwrap();
 
         assert!(toml.contains(r#"ident = "origin/name/1.2.3/20170223130020""#,));
+        assert!(toml.contains(r#"instance = "nightly""#));
         assert!(toml.contains(r#"group = "jobs""#));
         assert!(toml.contains(r#"application_environment = "theinternet.preprod""#,));
         assert!(toml.contains(r#"bldr_url = "http://example.com/depot""#));
@@ -448,6 +784,23 @@ mod test {
         assert!(toml.contains(r#"secs = 123"#));
         assert!(toml.contains(r#"nanos = 0"#));
         assert!(toml.contains(r#"shutdown_timeout = 10"#));
+        assert!(toml.contains(r#"update_canaries = "25%""#));
+        assert!(toml.contains(r#"update_bake_period = 60"#));
+        assert!(toml.contains(r#"update_rollback_window = 0"#));
+        assert!(toml.contains(r#"update_window = "* 2-4 * * sat,sun Europe/Berlin""#));
+        assert!(toml.contains(r#"pending_update = "origin/name/1.2.5/20170225130020""#));
+        assert!(toml.contains(r#"blocked_releases = ["origin/name/1.2.4/20170224130020"]"#));
+        assert!(toml.contains(r#"held_release = "origin/name/1.2.3/20170223130020""#));
+        assert!(toml.contains(r#"pinned_release = "origin/name/1.2.2/20170222130020""#));
+        assert!(toml.contains(r#"[update_probation]"#));
+        assert!(toml.contains(r#"from = "origin/name/1.2.2/20170222130020""#));
+        assert!(toml.contains(r#"to = "origin/name/1.2.4/20170224130020""#));
+        assert!(toml.contains(r#"started_at = 1556000000"#));
+        assert!(toml.contains(r#"start_after = ["origin/db"]"#));
+        assert!(toml.contains(r#"requires_healthy = true"#));
+        assert!(toml.contains(r#"bind_healthy_only = true"#));
+        assert!(toml.contains(r#"[hook_timeouts]"#));
+        assert!(toml.contains(r#"health-check = 10"#));
     }
 
     #[test]
@@ -588,6 +941,7 @@ mod test {
         let spec =
             ServiceSpec { ident:
                               PackageIdent::from_str("origin/name/1.2.3/20170223130020").unwrap(),
+                          instance:                None,
                           group:                   String::from("jobs"),
                           application_environment:
                               Some(ApplicationEnvironment::from_str("theinternet.preprod").unwrap()),
@@ -600,11 +954,26 @@ mod test {
                 ServiceBind::from_str("db:postgres.app@acmecorp").unwrap(),
             ],
                           binding_mode:            BindingMode::Relaxed,
+                          bind_healthy_only:       false,
+                          start_after:             Vec::new(),
+                          requires_healthy:        false,
                           health_check_interval:   HealthCheckInterval::from_str("23").unwrap(),
                           config_from:             Some(PathBuf::from("/only/for/development")),
                           desired_state:           DesiredState::Down,
                           svc_encrypted_password:  None,
-                          shutdown_timeout:        Some(ShutdownTimeout::default()), };
+                          shutdown_timeout:        Some(ShutdownTimeout::default()),
+                          update_canaries:         CanaryCount::default(),
+                          update_bake_period:      DEFAULT_UPDATE_BAKE_PERIOD,
+                          update_rollback_window:  DEFAULT_UPDATE_ROLLBACK_WINDOW,
+                          update_window:           None,
+                          pending_update:          None,
+                          blocked_releases:        Vec::new(),
+                          held_release:            None,
+                          pinned_release:          None,
+                          update_probation:        None,
+                          hook_timeouts:           BTreeMap::new(),
+                          resource_limits:         ResourceLimits::default(),
+                          sandbox:                 Sandbox::default(), };
         spec.to_file(&path).unwrap();
         let toml = string_from_file(path);
 
@@ -625,6 +994,43 @@ mod test {
         assert!(toml.contains(r#"nanos = 0"#));
     }
 
+    #[test]
+    fn service_spec_round_trips_through_a_file() {
+        let tmpdir = TempDir::new().unwrap();
+        let path = tmpdir.path().join("name.spec");
+        let ident = PackageIdent::from_str("origin/name/1.2.3/20170223130020").unwrap();
+        let previous_release = PackageIdent::from_str("origin/name/1.2.2/20170222130020").unwrap();
+        let mut spec = ServiceSpec::default_for(ident);
+        spec.instance = Some(String::from("nightly"));
+        spec.bind_healthy_only = true;
+        spec.start_after = vec![PackageIdent::from_str("origin/db").unwrap()];
+        spec.requires_healthy = true;
+        spec.health_check_interval = HealthCheckInterval::from(30);
+        spec.svc_encrypted_password = Some(String::from("secret"));
+        spec.shutdown_timeout = Some(ShutdownTimeout::from(10));
+        spec.update_canaries = CanaryCount::Percent(25);
+        spec.update_bake_period = 60;
+        spec.update_rollback_window = 600;
+        spec.update_window =
+            Some(UpdateWindow::from_str("* 2-4 * * sat,sun Europe/Berlin").unwrap());
+        spec.pending_update =
+            Some(PackageIdent::from_str("origin/name/1.2.5/20170225130020").unwrap());
+        spec.blocked_releases =
+            vec![PackageIdent::from_str("origin/name/1.2.4/20170224130020").unwrap()];
+        spec.held_release = Some(spec.ident.clone());
+        spec.pinned_release = Some(previous_release.clone());
+        spec.update_probation =
+            Some(UpdateProbationSpec { from:       previous_release,
+                                       to:         spec.ident.clone(),
+                                       started_at: 1_556_000_000, });
+        spec.hook_timeouts.insert("health-check".to_string(), 10);
+        spec.resource_limits.memory_max = Some(1 << 30);
+        spec.sandbox.private_tmp = Some(true);
+        spec.to_file(&path).unwrap();
+
+        assert_eq!(ServiceSpec::from_file(&path).unwrap(), spec);
+    }
+
     #[test]
     fn service_spec_to_file_invalid_ident() {
         let tmpdir = TempDir::new().unwrap();
@@ -651,6 +1057,69 @@ mod test {
         assert_eq!(String::from("hoopa.spec"), spec.file_name());
     }
 
+    #[test]
+    fn service_spec_file_name_with_instance() {
+        let mut spec =
+            ServiceSpec::default_for(PackageIdent::from_str("origin/hoopa/1.2.3").unwrap());
+        spec.instance = Some(String::from("cache"));
+
+        assert_eq!(String::from("hoopa@cache"), spec.service_name());
+        assert_eq!(String::from("hoopa@cache.spec"), spec.file_name());
+    }
+
+    #[test]
+    fn service_spec_from_str_with_instance() {
+        let toml = r#"
+            ident = "origin/redis"
+            instance = "sessions"
+            group = "sessions"
+            "#;
+        let spec = ServiceSpec::from_str(toml).unwrap();
+
+        assert_eq!(spec.instance, Some(String::from("sessions")));
+        assert_eq!(spec.service_name(), String::from("redis@sessions"));
+    }
+
+    #[test]
+    fn service_spec_from_str_invalid_instance() {
+        let toml = r#"
+            ident = "origin/redis"
+            instance = "../cache"
+            "#;
+
+        match ServiceSpec::from_str(toml) {
+            Err(Error::InvalidInstanceName(ref name)) => assert_eq!(name, "../cache"),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn instances_default_to_a_group_named_after_them() {
+        let mut msg = habitat_sup_protocol::ctl::SvcLoad::default();
+        msg.ident = Some(PackageIdent::from_str("core/redis").unwrap().into());
+        msg.instance = Some(String::from("cache"));
+        let mut spec = ServiceSpec::default();
+        msg.into_spec(&mut spec).unwrap();
+
+        assert_eq!(spec.group, String::from("cache"));
+        assert_eq!(spec.service_name(), String::from("redis@cache"));
+    }
+
+    #[test]
+    fn invalid_version_constraints_are_rejected() {
+        let mut msg = habitat_sup_protocol::ctl::SvcLoad::default();
+        let mut ident: habitat_sup_protocol::types::PackageIdent =
+            PackageIdent::from_str("core/redis").unwrap().into();
+        ident.version_constraint = Some(String::from("!=4"));
+        msg.ident = Some(ident);
+        let mut spec = ServiceSpec::default();
+
+        match msg.into_spec(&mut spec) {
+            Err(Error::HabitatCore(habitat_core::Error::InvalidVersionConstraint(_))) => (),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
     fn testing_package_install() -> PackageInstall {
         let ident = if cfg!(target_os = "linux") {
             PackageIdent::new("test-bind",
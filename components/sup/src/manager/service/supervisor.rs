@@ -16,8 +16,10 @@ use habitat_common::{outputln,
 #[cfg(unix)]
 use habitat_core::os::users;
 use habitat_core::{fs,
-                   os::process::{self,
-                                 Pid},
+                   os::{cgroups::{self,
+                                  ResourceUsage},
+                        process::{self,
+                                  Pid}},
                    service::ServiceGroup};
 use habitat_launcher_client::LauncherCli;
 use serde::{ser::SerializeStruct,
@@ -40,15 +42,19 @@ pub struct Supervisor {
     pub state_entered: Timespec,
     pid:               Option<Pid>,
     pid_file:          PathBuf,
+    /// What the service's processes used when last checked, if the Launcher runs the service in
+    /// its own cgroup.
+    resource_usage: Option<ResourceUsage>,
 }
 
 impl Supervisor {
-    pub fn new(service_group: &ServiceGroup) -> Supervisor {
-        Supervisor { preamble:      service_group.to_string(),
-                     state:         ProcessState::Down,
-                     state_entered: time::get_time(),
-                     pid:           None,
-                     pid_file:      fs::svc_pid_file(service_group.service()), }
+    pub fn new(service_group: &ServiceGroup, pid_file: &Path) -> Supervisor {
+        Supervisor { preamble:       service_group.to_string(),
+                     state:          ProcessState::Down,
+                     state_entered:  time::get_time(),
+                     pid:            None,
+                     pid_file:       pid_file.to_path_buf(),
+                     resource_usage: None, }
     }
 
     /// Check if the child process is running
@@ -162,13 +168,22 @@ impl Supervisor {
                                  &pkg.svc_run,
                                  user_info,
                                  svc_password, // Windows optional
-                                 (*pkg.env).clone())?;
+                                 (*pkg.env).clone(),
+                                 &pkg.resource_limits,
+                                 &pkg.sandbox)?;
         self.pid = Some(pid);
         self.create_pidfile()?;
         self.change_state(ProcessState::Up);
         Ok(())
     }
 
+    /// Reads what the service's processes currently use from the service's cgroup.
+    pub fn refresh_resource_usage(&mut self) {
+        self.resource_usage = self.pid
+                                  .and_then(cgroups::service_cgroup_of)
+                                  .map(|cgroup| cgroups::resource_usage(&cgroup));
+    }
+
     pub fn status(&self) -> (bool, String) {
         let status = format!("{}: {} for {}",
                              self.preamble,
@@ -247,6 +262,7 @@ impl Serialize for Supervisor {
         strukt.serialize_field("pid", &self.pid)?;
         strukt.serialize_field("state", &self.state)?;
         strukt.serialize_field("state_entered", &self.state_entered.sec)?;
+        strukt.serialize_field("resource_usage", &self.resource_usage)?;
         strukt.end()
     }
 }
@@ -1,4 +1,8 @@
-use crate::{census::CensusRing,
+mod canary;
+
+use self::canary::CanaryState;
+use crate::{census::{CensusGroup,
+                     CensusRing},
             manager::{periodic::Periodic,
                       service::{Service,
                                 Topology,
@@ -31,9 +35,20 @@ const PERIOD_BYPASS_CHECK_ENVVAR: &str = "HAB_UPDATE_STRATEGY_FREQUENCY_BYPASS_C
 
 type UpdaterStateList = HashMap<ServiceGroup, UpdaterState>;
 
+/// What the `ServiceUpdater` wants done with a service.
+#[derive(Debug, PartialEq)]
+pub enum UpdateAction {
+    /// Restart the service, which has a newer package installed.
+    Update(PackageIdent),
+    /// Stop running the given release and go back to the latest release before it, because a
+    /// canary update was halted.
+    RollBack(PackageIdent),
+}
+
 enum UpdaterState {
     AtOnce(Receiver<PackageInstall>, Sender<()>),
     Rolling(RollingState),
+    Canary(CanaryState),
 }
 
 enum RollingState {
@@ -94,6 +109,12 @@ impl ServiceUpdater {
                     .or_insert(UpdaterState::Rolling(RollingState::AwaitingElection));
                 true
             }
+            UpdateStrategy::Canary => {
+                self.states
+                    .entry(service.service_group.clone())
+                    .or_insert(UpdaterState::Canary(CanaryState::AwaitingElection));
+                true
+            }
         }
     }
 
@@ -132,6 +153,7 @@ impl ServiceUpdater {
                     _ => {}
                 }
             }
+            Some(UpdaterState::Canary(cs)) => cs.stop(),
             None => {
                 warn!("Tried to remove {} from the ServiceUpdater, but it wasn't found.",
                       service);
@@ -141,7 +163,8 @@ impl ServiceUpdater {
 
     /// See if the given service has an update. Returns the identifier
     /// of the newly-updated service if a new version was installed,
-    /// thus signalling that the service should be restarted.
+    /// thus signalling that the service should be restarted, or the
+    /// release the service has to roll back from.
     // If we ever need to modify this function, it would be an excellent opportunity to
     // simplify the redundant aspects and remove this allow(clippy::cognitive_complexity),
     // but changing it in the absence of other necessity seems like too much risk for the
@@ -157,7 +180,7 @@ impl ServiceUpdater {
                                          // our
                                          // service.
                                          census_ring: &CensusRing)
-                                         -> Option<PackageIdent> {
+                                         -> Option<UpdateAction> {
         debug!("Checking for updated package!");
 
         // TODO (CM): can we do without this?
@@ -167,7 +190,7 @@ impl ServiceUpdater {
             Some(&mut UpdaterState::AtOnce(ref mut rx, ref mut kill_tx)) => {
                 match rx.try_recv() {
                     Ok(package) => {
-                        return Some(package.ident.clone());
+                        return Some(UpdateAction::Update(package.ident.clone()));
                     }
                     Err(TryRecvError::Empty) => return None,
                     Err(TryRecvError::Disconnected) => {
@@ -181,28 +204,7 @@ impl ServiceUpdater {
 
             Some(&mut UpdaterState::Rolling(ref mut st @ RollingState::AwaitingElection)) => {
                 if let Some(census_group) = census_ring.census_group_for(&service.service_group) {
-                    if service.topology == Topology::Leader {
-                        debug!("Rolling Update, determining proper suitability because we're in \
-                                a leader topology");
-                        match (census_group.me(), census_group.leader()) {
-                            (Some(me), Some(leader)) => {
-                                let suitability = if me.member_id == leader.member_id {
-                                    u64::min_value()
-                                } else {
-                                    u64::max_value()
-                                };
-                                self.butterfly
-                                    .start_update_election_mlr(&service.service_group,
-                                                               suitability,
-                                                               0);
-                                *st = RollingState::InElection
-                            }
-                            _ => return None,
-                        }
-                    } else {
-                        debug!("Rolling update, using default suitability");
-                        self.butterfly
-                            .start_update_election_mlr(&service.service_group, 0, 0);
+                    if start_update_election_mlr(&self.butterfly, service, census_group) {
                         *st = RollingState::InElection;
                     }
                 }
@@ -287,6 +289,11 @@ impl ServiceUpdater {
                                             debug!("We're in an update but it's not our turn");
                                             return None;
                                         }
+                                        let blocked = &service.blocked_releases;
+                                        if leader.pkg.iter().any(|p| blocked.contains(p)) {
+                                            debug!("We're in an update to a blocked release");
+                                            return None;
+                                        }
                                         debug!("We're in an update and it's our turn");
                                         let (kill_tx, kill_rx) = channel();
                                         let rx = Worker::new(service).start(&service.service_group,
@@ -334,9 +341,45 @@ impl ServiceUpdater {
                     *state = FollowerState::Waiting;
                 }
             }
+            Some(&mut UpdaterState::Canary(ref mut state)) => {
+                return state.check_mlr(&self.butterfly, service, census_ring);
+            }
             None => {}
         }
-        ident
+        ident.map(UpdateAction::Update)
+    }
+}
+
+/// Starts the update election of the service group of `service`, returning whether it was
+/// started. In a leader topology, the leader of the service group is the least suitable update
+/// leader, so that it is updated last.
+///
+/// # Locking
+/// * `MemberList::entries` (read) This method must not be called while any MemberList::entries
+///   lock is held.
+fn start_update_election_mlr(butterfly: &habitat_butterfly::Server,
+                             service: &Service,
+                             census_group: &CensusGroup)
+                             -> bool {
+    if service.topology == Topology::Leader {
+        debug!("Update election, determining proper suitability because we're in a leader \
+                topology");
+        match (census_group.me(), census_group.leader()) {
+            (Some(me), Some(leader)) => {
+                let suitability = if me.member_id == leader.member_id {
+                    u64::min_value()
+                } else {
+                    u64::max_value()
+                };
+                butterfly.start_update_election_mlr(&service.service_group, suitability, 0);
+                true
+            }
+            _ => false,
+        }
+    } else {
+        debug!("Update election, using default suitability");
+        butterfly.start_update_election_mlr(&service.service_group, 0, 0);
+        true
     }
 }
 
@@ -358,6 +401,8 @@ struct Worker {
     spec_ident:  PackageIdent,
     builder_url: String,
     channel:     ChannelIdent,
+    /// Releases which are never updated to.
+    blocked:     Vec<PackageIdent>,
 }
 
 impl Periodic for Worker {
@@ -379,7 +424,8 @@ impl Worker {
         Worker { current:     service.pkg.ident.clone(),
                  spec_ident:  service.spec_ident.clone(),
                  builder_url: service.bldr_url.clone(),
-                 channel:     service.channel.clone(), }
+                 channel:     service.channel.clone(),
+                 blocked:     service.blocked_releases.clone(), }
     }
 
     /// Start a new update worker.
@@ -509,7 +555,10 @@ impl Worker {
                                          &self.channel)
                 {
                     Ok(maybe_newer_package) => {
-                        if self.current < *maybe_newer_package.ident() {
+                        if self.blocked.contains(maybe_newer_package.ident()) {
+                            debug!("Package found {} is blocked",
+                                   maybe_newer_package.ident());
+                        } else if self.current < *maybe_newer_package.ident() {
                             outputln!("Updating from {} to {}",
                                       self.current,
                                       maybe_newer_package.ident());
@@ -552,7 +601,8 @@ mod tests {
                                                                  .expect("Can't parse ident!"),
                  spec_ident:  "core/testing".parse().expect("Can't parse ident!"),
                  builder_url: String::from("https://bldr.habitat.sh"),
-                 channel:     ChannelIdent::stable(), }
+                 channel:     ChannelIdent::stable(),
+                 blocked:     Vec::new(), }
     }
 
     #[test]
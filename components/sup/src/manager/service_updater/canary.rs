@@ -0,0 +1,392 @@
+//! The canary update strategy.
+//!
+//! A canary update is run by the winner of the update election of a service group, which
+//! publishes its progress in its update election rumor:
+//!
+//! 1. The update leader polls for a newer package. Once it has installed one, it picks the
+//!    canaries: itself and the alive members after it in the census, `update_canaries` members in
+//!    all. It publishes the package and the canaries as `Baking`, and updates itself.
+//! 2. The other canaries update to the package. Once all of them run it, the update leader waits
+//!    for `update_bake_period` seconds, then publishes the rollout as `Promoted`, and the rest of
+//!    the service group updates.
+//!
+//! While the rollout is baking, every canary watches its own health check. A canary whose health
+//! check turns critical or times out rolls back: it blocks the release in its spec, so it is
+//! restarted with the release it ran before. The update leader notices that a canary left the
+//! package, or that its own health check is failing, and publishes the rollout as `Halted`, which
+//! rolls back the remaining canaries.
+
+use super::{start_update_election_mlr,
+            FollowerState,
+            UpdateAction,
+            Worker};
+use crate::{census::{CensusGroup,
+                     CensusRing},
+            manager::service::Service};
+use habitat_butterfly::{self,
+                        rumor::election::{CanaryRollout,
+                                          CanaryStage}};
+use habitat_common::outputln;
+use habitat_core::package::{PackageIdent,
+                            PackageInstall};
+use std::{collections::HashSet,
+          str::FromStr,
+          sync::mpsc::{channel,
+                       Receiver,
+                       Sender,
+                       TryRecvError},
+          time::{Duration,
+                 Instant}};
+
+static LOGKEY: &'static str = "SU";
+
+pub(super) enum CanaryState {
+    AwaitingElection,
+    InElection,
+    Leader(LeaderState),
+    Follower(FollowerState),
+}
+
+/// Current canary update state of the update leader.
+#[derive(Default)]
+pub(super) struct LeaderState {
+    /// Polls for a newer package while no rollout is in progress.
+    worker:       Option<(Receiver<PackageInstall>, Sender<()>)>,
+    /// The canaries which were seen running the package of the rollout.
+    updated:      HashSet<String>,
+    /// When all canaries first ran the package of the rollout.
+    baking_since: Option<Instant>,
+}
+
+impl CanaryState {
+    /// Stops any update worker.
+    pub fn stop(self) {
+        let kill_tx = match self {
+            CanaryState::Leader(LeaderState { worker: Some((_rx, kill_tx)),
+                                              .. }) => kill_tx,
+            CanaryState::Follower(FollowerState::Updating(_rx, kill_tx)) => kill_tx,
+            _ => return,
+        };
+        if kill_tx.send(()).is_err() {
+            debug!("Tried to kill the updater thread but it's already dead.");
+        }
+    }
+
+    /// # Locking
+    /// * `MemberList::entries` (read) This method must not be called while any MemberList::entries
+    ///   lock is held.
+    pub fn check_mlr(&mut self,
+                     butterfly: &habitat_butterfly::Server,
+                     service: &Service,
+                     census_ring: &CensusRing)
+                     -> Option<UpdateAction> {
+        let census_group = census_ring.census_group_for(&service.service_group)?;
+        if let CanaryState::AwaitingElection = *self {
+            if start_update_election_mlr(butterfly, service, census_group) {
+                *self = CanaryState::InElection;
+            }
+            return None;
+        }
+        let is_leader = match (census_group.me(), census_group.update_leader()) {
+            (Some(me), Some(leader)) => me.member_id == leader.member_id,
+            _ => return None,
+        };
+        match *self {
+            CanaryState::Leader(_) if is_leader => {}
+            CanaryState::Follower(_) if !is_leader => {}
+            _ if is_leader => {
+                debug!("We're the update leader");
+                self.replace(CanaryState::Leader(LeaderState::default()));
+            }
+            _ => {
+                debug!("We're an update follower");
+                self.replace(CanaryState::Follower(FollowerState::Waiting));
+            }
+        }
+        match *self {
+            CanaryState::Leader(ref mut state) => state.check(butterfly, service, census_group),
+            CanaryState::Follower(ref mut state) => check_follower(state, service, census_group),
+            _ => None,
+        }
+    }
+
+    fn replace(&mut self, state: CanaryState) {
+        std::mem::replace(self, state).stop();
+    }
+}
+
+impl LeaderState {
+    fn check(&mut self,
+             butterfly: &habitat_butterfly::Server,
+             service: &Service,
+             census_group: &CensusGroup)
+             -> Option<UpdateAction> {
+        let rollout = match census_group.canary_rollout {
+            Some(ref rollout) => rollout,
+            None => {
+                if census_group.active_members()
+                               .any(|m| m.pkg.as_ref() != Some(&service.pkg.ident))
+                {
+                    // An earlier update leader may have left the service group in the middle of
+                    // a rollout. Bring the rest of the group in line with us before updating.
+                    publish(butterfly,
+                            service,
+                            &service.pkg.ident,
+                            CanaryStage::Promoted,
+                            Vec::new());
+                    return None;
+                }
+                return self.poll(butterfly, service, census_group);
+            }
+        };
+        let pkg = rollout_package(rollout)?;
+        match rollout.stage {
+            CanaryStage::Baking => {
+                self.check_baking(butterfly, service, census_group, rollout, pkg)
+            }
+            CanaryStage::Promoted => {
+                if census_group.active_members()
+                               .any(|m| m.pkg.as_ref() != Some(&pkg))
+                {
+                    debug!("Update leader still waiting for followers...");
+                    return None;
+                }
+                self.poll(butterfly, service, census_group)
+            }
+            CanaryStage::Halted => {
+                if service.pkg.ident == pkg {
+                    return Some(UpdateAction::RollBack(pkg));
+                }
+                self.poll(butterfly, service, census_group)
+            }
+        }
+    }
+
+    fn check_baking(&mut self,
+                    butterfly: &habitat_butterfly::Server,
+                    service: &Service,
+                    census_group: &CensusGroup,
+                    rollout: &CanaryRollout,
+                    pkg: PackageIdent)
+                    -> Option<UpdateAction> {
+        if service.pkg.ident != pkg {
+            outputln!(preamble service.service_group,
+                      "Halting the canary update to {}, which the update leader isn't running",
+                      pkg);
+            publish(butterfly, service, &pkg, CanaryStage::Halted, rollout.members.clone());
+            return None;
+        }
+        let health = service.health_check_result();
+        if health.is_failing() {
+            outputln!(preamble service.service_group,
+                      "Halting the canary update to {}, the update leader's health check is {}",
+                      pkg,
+                      health);
+            publish(butterfly, service, &pkg, CanaryStage::Halted, rollout.members.clone());
+            return Some(UpdateAction::RollBack(pkg));
+        }
+
+        let mut all_updated = true;
+        for canary in census_group.active_members()
+                                  .filter(|m| rollout.members.contains(&m.member_id))
+        {
+            if canary.pkg.as_ref() == Some(&pkg) {
+                self.updated.insert(canary.member_id.clone());
+            } else if self.updated.contains(&canary.member_id) {
+                outputln!(preamble service.service_group,
+                          "Halting the canary update to {}, canary {} rolled back",
+                          pkg,
+                          canary.member_id);
+                publish(butterfly, service, &pkg, CanaryStage::Halted, rollout.members.clone());
+                return Some(UpdateAction::RollBack(pkg));
+            } else {
+                all_updated = false;
+            }
+        }
+        if !all_updated {
+            debug!("Update leader still waiting for canaries to update...");
+            return None;
+        }
+
+        let since = *self.baking_since.get_or_insert_with(Instant::now);
+        if since.elapsed() >= Duration::from_secs(service.update_bake_period.into()) {
+            outputln!(preamble service.service_group,
+                      "Canaries stayed healthy on {}, updating the service group",
+                      pkg);
+            publish(butterfly,
+                    service,
+                    &pkg,
+                    CanaryStage::Promoted,
+                    rollout.members.clone());
+        }
+        None
+    }
+
+    /// Polls for a newer package. Once one is installed, starts a rollout with it.
+    fn poll(&mut self,
+            butterfly: &habitat_butterfly::Server,
+            service: &Service,
+            census_group: &CensusGroup)
+            -> Option<UpdateAction> {
+        self.updated.clear();
+        self.baking_since = None;
+        let package = match self.worker {
+            Some((ref mut rx, ref mut kill_tx)) => {
+                match rx.try_recv() {
+                    Ok(package) => package,
+                    Err(TryRecvError::Empty) => return None,
+                    Err(TryRecvError::Disconnected) => {
+                        debug!("Service Updater worker has died; restarting...");
+                        let (ktx, krx) = channel();
+                        *rx = Worker::new(service).start(&service.service_group, None, krx);
+                        *kill_tx = ktx;
+                        return None;
+                    }
+                }
+            }
+            None => {
+                let (kill_tx, kill_rx) = channel();
+                let rx = Worker::new(service).start(&service.service_group, None, kill_rx);
+                self.worker = Some((rx, kill_tx));
+                return None;
+            }
+        };
+        self.worker = None;
+
+        let member_ids = census_group.members()
+                                     .filter(|m| m.alive())
+                                     .map(|m| m.member_id.as_str())
+                                     .collect::<Vec<_>>();
+        let count = service.update_canaries.of(member_ids.len());
+        let canaries = choose_canaries(&member_ids, &census_group.me()?.member_id, count);
+        outputln!(preamble service.service_group,
+                  "Starting a canary update to {} on {}",
+                  package.ident,
+                  canaries.join(", "));
+        publish(butterfly,
+                service,
+                &package.ident,
+                CanaryStage::Baking,
+                canaries);
+        Some(UpdateAction::Update(package.ident))
+    }
+}
+
+fn check_follower(state: &mut FollowerState,
+                  service: &Service,
+                  census_group: &CensusGroup)
+                  -> Option<UpdateAction> {
+    let rollout = census_group.canary_rollout.as_ref()?;
+    let pkg = rollout_package(rollout)?;
+    let is_canary = census_group.me()
+                                .map_or(false, |me| rollout.members.contains(&me.member_id));
+    let running = service.pkg.ident == pkg;
+
+    let mut ident = None;
+    match *state {
+        FollowerState::Updating(ref mut rx, ref mut kill_tx) => {
+            if rollout.stage == CanaryStage::Halted {
+                debug!("Canary update was halted, no longer updating");
+                if kill_tx.send(()).is_err() {
+                    debug!("Tried to kill the updater thread but it's already dead.");
+                }
+            } else {
+                match rx.try_recv() {
+                    Ok(package) => ident = Some(package.ident),
+                    Err(TryRecvError::Empty) => return None,
+                    Err(TryRecvError::Disconnected) => {
+                        debug!("Service Updater worker has died; restarting...");
+                        let (ktx, krx) = channel();
+                        *rx = Worker::new(service).start(&service.service_group, Some(pkg), krx);
+                        *kill_tx = ktx;
+                        return None;
+                    }
+                }
+            }
+        }
+        FollowerState::Waiting => {
+            let should_update = match rollout.stage {
+                CanaryStage::Baking => is_canary,
+                CanaryStage::Promoted => true,
+                CanaryStage::Halted => false,
+            };
+            if running {
+                let health = service.health_check_result();
+                return match rollout.stage {
+                    CanaryStage::Baking if is_canary && health.is_failing() => {
+                        outputln!(preamble service.service_group,
+                                  "Canary health check is {} on {}, rolling back",
+                                  health,
+                                  pkg);
+                        Some(UpdateAction::RollBack(pkg))
+                    }
+                    CanaryStage::Halted if is_canary => {
+                        outputln!(preamble service.service_group,
+                                  "Canary update to {} was halted, rolling back",
+                                  pkg);
+                        Some(UpdateAction::RollBack(pkg))
+                    }
+                    _ => None,
+                };
+            }
+            if !should_update || service.blocked_releases.contains(&pkg) {
+                return None;
+            }
+            debug!("We're in a canary update and it's our turn");
+            let (kill_tx, kill_rx) = channel();
+            let rx = Worker::new(service).start(&service.service_group, Some(pkg), kill_rx);
+            *state = FollowerState::Updating(rx, kill_tx);
+            return None;
+        }
+    }
+    *state = FollowerState::Waiting;
+    ident.map(UpdateAction::Update)
+}
+
+fn rollout_package(rollout: &CanaryRollout) -> Option<PackageIdent> {
+    match PackageIdent::from_str(&rollout.pkg) {
+        Ok(ident) => Some(ident),
+        Err(err) => {
+            warn!("Received a bad package ident in a canary rollout, err={}", err);
+            None
+        }
+    }
+}
+
+fn publish(butterfly: &habitat_butterfly::Server,
+           service: &Service,
+           pkg: &PackageIdent,
+           stage: CanaryStage,
+           canaries: Vec<String>) {
+    let rollout = CanaryRollout::new(pkg.to_string(), stage, canaries);
+    if !butterfly.publish_canary_rollout(&service.service_group, rollout) {
+        debug!("Not the winner of a finished update election, can't publish the canary rollout");
+    }
+}
+
+/// Picks `count` canaries from `member_ids`, in order: the update leader, then the members after
+/// it, wrapping around to the first member.
+fn choose_canaries(member_ids: &[&str], leader_id: &str, count: usize) -> Vec<String> {
+    let start = member_ids.iter().position(|id| *id == leader_id).unwrap_or(0);
+    member_ids.iter()
+              .cycle()
+              .skip(start)
+              .take(count.max(1).min(member_ids.len()))
+              .map(|id| id.to_string())
+              .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canaries_start_with_the_update_leader() {
+        let members = ["a", "b", "c", "d", "e"];
+        assert_eq!(choose_canaries(&members, "b", 2), vec!["b", "c"]);
+        assert_eq!(choose_canaries(&members, "d", 3), vec!["d", "e", "a"]);
+        assert_eq!(choose_canaries(&members, "a", 0), vec!["a"]);
+        assert_eq!(choose_canaries(&members, "c", 10).len(), 5);
+        assert!(choose_canaries(&[], "a", 1).is_empty());
+    }
+}
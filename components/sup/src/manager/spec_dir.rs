@@ -69,16 +69,18 @@ impl SpecDir {
                 }
             };
 
+            let service_name = spec.service_name();
             specs.push(match spec_file.file_stem().and_then(OsStr::to_str) {
-                           Some(stem) if stem == spec.ident.name => spec,
+                           Some(stem) if stem == service_name => spec,
                            Some(_) => {
                                outputln!("Error when loading service spec file '{}' (File name \
-                                          does not match ident name '{}' from ident = \"{}\", it \
-                                          should be called '{}.{}'). This file will be skipped.",
+                                          does not match service name '{}' from ident = \"{}\", \
+                                          it should be called '{}.{}'). This file will be \
+                                          skipped.",
                                          spec_file.display(),
-                                         &spec.ident.name,
+                                         &service_name,
                                          &spec.ident,
-                                         &spec.ident.name,
+                                         &service_name,
                                          SPEC_FILE_EXT);
                                continue;
                            }
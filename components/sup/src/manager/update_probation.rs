@@ -0,0 +1,286 @@
+//! Watches services that were just updated and rolls them back if the new release does not become
+//! healthy.
+//!
+//! When a service starts on a release it was updated to, it is put on probation for its
+//! `update_rollback_window`. The update passes as soon as a health check reports the service as
+//! `OK` or `WARNING`. It fails if the window runs out before that happens, or if the service's
+//! process dies `CRASH_LOOP_LIMIT` times within the window. A failed release is added to the
+//! service's blocked releases, and the service is pinned to the release it ran before and
+//! restarted on it. The blocked release is gossiped with the service, so that the rest of the
+//! service group doesn't update to it either.
+//!
+//! Probations are saved in the service's spec, so a probation which is interrupted by a restart
+//! of the Supervisor goes on where it left off.
+
+use crate::{event,
+            manager::service::{HealthCheckResult,
+                               Service,
+                               ServiceName,
+                               UpdateProbationSpec}};
+use habitat_core::package::PackageIdent;
+use std::{collections::HashMap,
+          fmt,
+          time::{Duration,
+                 Instant,
+                 SystemTime,
+                 UNIX_EPOCH}};
+
+static LOGKEY: &str = "UP";
+
+/// The number of times the process of an updated service may die within the rollback window
+/// before the update is considered to have failed.
+const CRASH_LOOP_LIMIT: u32 = 3;
+
+/// Why an update was considered to have failed.
+#[derive(Debug, PartialEq)]
+pub enum Failure {
+    /// The process died this many times.
+    CrashLoop(u32),
+    /// No health check reported the service as healthy; this was the last result.
+    Unhealthy(HealthCheckResult),
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Failure::CrashLoop(crashes) => write!(f, "the service crashed {} times", crashes),
+            Failure::Unhealthy(result) => {
+                write!(f,
+                       "the service did not become healthy in time (last health check: {})",
+                       result)
+            }
+        }
+    }
+}
+
+/// How the probation of a service ended.
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    Passed,
+    /// The service has to be rolled back to `from`, and `to` blocked.
+    Failed {
+        from: PackageIdent,
+        to:   PackageIdent,
+    },
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Verdict {
+    /// The update has neither passed nor failed yet.
+    Pending,
+    Passed,
+    Failed(Failure),
+}
+
+#[derive(Debug)]
+pub struct UpdateProbation {
+    /// The release the service ran before the update.
+    from:    PackageIdent,
+    /// The release the service was updated to.
+    to:      PackageIdent,
+    started: Instant,
+    window:  Duration,
+    crashes: u32,
+}
+
+impl UpdateProbation {
+    pub fn new(from: PackageIdent, to: PackageIdent, window: Duration) -> Self {
+        UpdateProbation { from,
+                          to,
+                          started: Instant::now(),
+                          window,
+                          crashes: 0 }
+    }
+
+    /// Resumes the probation saved in a service's spec, e.g. after a restart of the Supervisor.
+    /// The time that passed since the probation started counts against the window.
+    fn resume(saved: &UpdateProbationSpec, window: Duration) -> Self {
+        let elapsed = Duration::from_secs(unix_time().saturating_sub(saved.started_at));
+        let now = Instant::now();
+        UpdateProbation { from: saved.from.clone(),
+                          to: saved.to.clone(),
+                          started: now.checked_sub(elapsed).unwrap_or(now),
+                          window,
+                          crashes: 0 }
+    }
+
+    /// The probation as it is saved in the service's spec.
+    fn to_spec(&self) -> UpdateProbationSpec {
+        let elapsed = self.started.elapsed().as_secs();
+        UpdateProbationSpec { from:       self.from.clone(),
+                              to:         self.to.clone(),
+                              started_at: unix_time().saturating_sub(elapsed), }
+    }
+
+    /// Records that the service's process died unexpectedly.
+    pub fn crashed(&mut self) { self.crashes += 1; }
+
+    fn check(&self, health: HealthCheckResult) -> Verdict { self.check_at(Instant::now(), health) }
+
+    fn check_at(&self, now: Instant, health: HealthCheckResult) -> Verdict {
+        if self.crashes >= CRASH_LOOP_LIMIT {
+            return Verdict::Failed(Failure::CrashLoop(self.crashes));
+        }
+        match health {
+            HealthCheckResult::Ok | HealthCheckResult::Warning => Verdict::Passed,
+            _ if now.duration_since(self.started) >= self.window => {
+                Verdict::Failed(Failure::Unhealthy(health))
+            }
+            _ => Verdict::Pending,
+        }
+    }
+}
+
+/// Starts a probation for a service that was started on the release it was updated to, unless
+/// rollbacks are disabled for it, or resumes the probation saved in its spec. Returns whether the
+/// probation in the service's spec changed, in which case the spec has to be saved.
+pub fn start(probations: &mut HashMap<ServiceName, UpdateProbation>,
+             service: &mut Service)
+             -> bool {
+    let name = service.service_name().to_string();
+    let window = Duration::from_secs(service.update_rollback_window.into());
+    match service.updated_from {
+        Some(ref from) if service.update_rollback_window > 0 => {
+            let probation = UpdateProbation::new(from.clone(), service.pkg.ident.clone(), window);
+            service.update_probation = Some(probation.to_spec());
+            probations.insert(name, probation);
+            true
+        }
+        _ => {
+            // A probation only covers the release it was started for; a service loaded again on
+            // another release is not on probation anymore.
+            if probations.get(&name)
+                         .map_or(false, |p| p.to != service.pkg.ident)
+            {
+                probations.remove(&name);
+            }
+            let saved = match service.update_probation {
+                Some(ref saved)
+                    if saved.to == service.pkg.ident && service.update_rollback_window > 0 =>
+                {
+                    saved
+                }
+                Some(_) => {
+                    service.update_probation = None;
+                    return true;
+                }
+                None => return false,
+            };
+            if !probations.contains_key(&name) {
+                outputln!(preamble service.service_group,
+                          "Resuming the probation of the update to {}", saved.to);
+                probations.insert(name, UpdateProbation::resume(saved, window));
+            }
+            false
+        }
+    }
+}
+
+/// Checks the probation of the service, if it is on one, and returns how it ended if it did. The
+/// probation is removed from the service, whose spec has to be saved then.
+pub fn check(probations: &mut HashMap<ServiceName, UpdateProbation>,
+             service: &mut Service)
+             -> Option<Outcome> {
+    let verdict = {
+        let probation = probations.get_mut(service.service_name())?;
+        if service.crashed {
+            probation.crashed();
+        }
+        probation.check(service.health_check_result())
+    };
+    match verdict {
+        Verdict::Pending => None,
+        Verdict::Passed => {
+            let probation = probations.remove(service.service_name())?;
+            outputln!(preamble service.service_group,
+                      "Update to {} is healthy", probation.to);
+            service.update_probation = None;
+            Some(Outcome::Passed)
+        }
+        Verdict::Failed(failure) => {
+            let probation = probations.remove(service.service_name())?;
+            outputln!(preamble service.service_group,
+                      "Update to {} failed because {}; rolling back to {}",
+                      probation.to, failure, probation.from);
+            event::service_update_rolled_back(service,
+                                              &probation.to,
+                                              &probation.from,
+                                              &failure.to_string());
+            service.update_probation = None;
+            Some(Outcome::Failed { from: probation.from,
+                                   to:   probation.to, })
+        }
+    }
+}
+
+/// The current time, in seconds since the Unix epoch.
+fn unix_time() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH)
+                     .map(|elapsed| elapsed.as_secs())
+                     .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    fn probation() -> UpdateProbation {
+        UpdateProbation::new(PackageIdent::from_str("core/redis/4.0.14/20190319155852").unwrap(),
+                             PackageIdent::from_str("core/redis/4.0.14/20190415155205").unwrap(),
+                             Duration::from_secs(60))
+    }
+
+    #[test]
+    fn a_healthy_update_passes() {
+        let probation = probation();
+        assert_eq!(probation.check_at(probation.started, HealthCheckResult::Unknown),
+                   Verdict::Pending);
+        assert_eq!(probation.check_at(probation.started, HealthCheckResult::Ok),
+                   Verdict::Passed);
+        assert_eq!(probation.check_at(probation.started, HealthCheckResult::Warning),
+                   Verdict::Passed);
+    }
+
+    #[test]
+    fn an_update_fails_when_it_stays_unhealthy_for_the_window() {
+        let probation = probation();
+        let later = probation.started + Duration::from_secs(30);
+        assert_eq!(probation.check_at(later, HealthCheckResult::Critical),
+                   Verdict::Pending);
+        let later = probation.started + Duration::from_secs(60);
+        assert_eq!(probation.check_at(later, HealthCheckResult::Critical),
+                   Verdict::Failed(Failure::Unhealthy(HealthCheckResult::Critical)));
+    }
+
+    #[test]
+    fn a_resumed_probation_keeps_its_deadline() {
+        let saved = UpdateProbationSpec { from:       probation().from,
+                                          to:         probation().to,
+                                          started_at: unix_time() - 45, };
+        let resumed = UpdateProbation::resume(&saved, Duration::from_secs(60));
+        let later = Instant::now() + Duration::from_secs(10);
+        assert_eq!(resumed.check_at(later, HealthCheckResult::Critical),
+                   Verdict::Pending);
+        let later = Instant::now() + Duration::from_secs(20);
+        assert_eq!(resumed.check_at(later, HealthCheckResult::Critical),
+                   Verdict::Failed(Failure::Unhealthy(HealthCheckResult::Critical)));
+
+        let saved_again = resumed.to_spec();
+        assert!(saved_again.started_at >= saved.started_at
+                && saved_again.started_at <= saved.started_at + 1);
+    }
+
+    #[test]
+    fn an_update_fails_when_it_crashes_repeatedly() {
+        let mut probation = probation();
+        for _ in 0..CRASH_LOOP_LIMIT - 1 {
+            probation.crashed();
+        }
+        assert_eq!(probation.check_at(probation.started, HealthCheckResult::Unknown),
+                   Verdict::Pending);
+        probation.crashed();
+        assert_eq!(probation.check_at(probation.started, HealthCheckResult::Ok),
+                   Verdict::Failed(Failure::CrashLoop(CRASH_LOOP_LIMIT)));
+    }
+}
@@ -4,7 +4,8 @@ use crate::manager::service::Service;
 use habitat_common::{liveliness_checker,
                      outputln,
                      templating::config::UserConfigPath};
-use habitat_core::{fs::USER_CONFIG_FILE,
+use habitat_core::{fs::{USER_CONFIG_FILE,
+                        USER_CONFIG_PATCH_FILE},
                    service::ServiceGroup};
 use std::{collections::HashMap,
           io,
@@ -24,6 +25,9 @@ use std::{collections::HashMap,
           time::Duration};
 
 static LOGKEY: &'static str = "UCW";
+/// The files in a service's user config directory which make up its user configuration. Each is
+/// watched by its own worker.
+const WATCHED_FILES: [&str; 2] = [USER_CONFIG_FILE, USER_CONFIG_PATCH_FILE];
 
 // This trait exists to ease the testing of functions that receive a Service. Creating Services
 // requires a lot of ceremony, so we work around this with this trait.
@@ -34,7 +38,7 @@ pub trait Serviceable {
 }
 
 impl Serviceable for Service {
-    fn name(&self) -> &str { &self.pkg.name }
+    fn name(&self) -> &str { self.service_name() }
 
     fn user_config_path(&self) -> &UserConfigPath { &self.cfg.user_config_path }
 
@@ -49,13 +53,13 @@ struct WorkerState {
     // size 1, as we are only interested in the fact that there were
     // events, not how many there were.
     have_events: Receiver<()>,
-    // This sender is used by the watcher to notify a worker to stop
-    // running.  It is an async channel because we never want the
+    // These senders are used by the watcher to notify the workers to
+    // stop running.  They are async channels because we never want the
     // UserConfigWatcher to block, even if the receiver end of the
     // channel somehow dies and/or fails to consume the message.
-    stop_running: Sender<()>,
+    stop_running: Vec<Sender<()>>,
     // This receiver is used by the watcher tests to be notified when
-    // a worker finished setting up the watcher and is about to
+    // the workers finished setting up the watcher and are about to
     // starting looping it.
     //
     // Silence the dead code warnings from rustc, because it is only
@@ -82,8 +86,8 @@ impl UserConfigWatcher {
         // return value, which we need to return the error from `Worker::run`.
         let mut states = self.states.lock().expect("states lock was poisoned");
         if states.get(service.name()).is_none() {
-            let user_toml_path = match service.user_config_path() {
-                UserConfigPath::Recommended(ref p) => p.join(USER_CONFIG_FILE),
+            let user_config_dir = match service.user_config_path() {
+                UserConfigPath::Recommended(ref p) => p,
                 UserConfigPath::Deprecated(ref p) => {
                     outputln!(
                         preamble service.service_group(),
@@ -99,16 +103,22 @@ impl UserConfigWatcher {
             // are not interested in the events themselves, but only whether at least one has
             // happened.
             let (events_tx, events_rx) = sync_channel(1);
-            let (running_tx, running_rx) = channel();
-            let (watching_tx, watching_rx) = sync_channel(1);
-
-            Worker::run(user_toml_path, events_tx, running_rx, watching_tx)?;
-
-            outputln!(preamble service.service_group(), "Watching {}", USER_CONFIG_FILE);
+            let (watching_tx, watching_rx) = sync_channel(WATCHED_FILES.len());
+            let mut stop_running = Vec::with_capacity(WATCHED_FILES.len());
+
+            for file in WATCHED_FILES.iter() {
+                let (running_tx, running_rx) = channel();
+                Worker::run(user_config_dir.join(file),
+                            events_tx.clone(),
+                            running_rx,
+                            watching_tx.clone())?;
+                stop_running.push(running_tx);
+                outputln!(preamble service.service_group(), "Watching {}", file);
+            }
 
-            let state = WorkerState { have_events:      events_rx,
-                                      stop_running:     running_tx,
-                                      started_watching: watching_rx, };
+            let state = WorkerState { have_events: events_rx,
+                                      stop_running,
+                                      started_watching: watching_rx };
 
             states.insert(service.name().to_owned(), state);
         }
@@ -124,10 +134,12 @@ impl UserConfigWatcher {
                                  .expect("states lock was poisoned")
                                  .remove(service.name())
         {
-            if let Err(e) = state.stop_running.send(()) {
-                debug!("Error stopping user-config watcher thread for service {}: {:?}",
-                       service.name(),
-                       e);
+            for stop_running in state.stop_running {
+                if let Err(e) = stop_running.send(()) {
+                    debug!("Error stopping user-config watcher thread for service {}: {:?}",
+                           service.name(),
+                           e);
+                }
             }
         }
     }
@@ -324,17 +336,36 @@ mod tests {
         assert!(wait_for_events(&ucm, &service));
     }
 
+    #[test]
+    fn events_present_after_adding_patch() {
+        let service = TestService::default();
+        let mut ucm = UserConfigWatcher::new();
+        ucm.add(&service).expect("adding service");
+        assert!(wait_for_watcher(&ucm, &service));
+
+        File::create(service.user_config_path()
+                            .get_path()
+                            .join(USER_CONFIG_PATCH_FILE)).expect("creating file");
+
+        assert!(wait_for_events(&ucm, &service));
+    }
+
     fn wait_for_watcher<T: Serviceable>(ucm: &UserConfigWatcher, service: &T) -> bool {
         let start = Instant::now();
         let timeout = Duration::from_secs(10);
+        let mut started = 0;
 
         while start.elapsed() < timeout {
             let states = ucm.states.lock().expect("states lock was poisoned");
             let state = states.get(service.name()).expect("service added");
             match state.started_watching.try_recv() {
                 Ok(_) => {
-                    println!("Received data on the start_watching channel. Returning true.");
-                    return true;
+                    println!("Received data on the start_watching channel.");
+                    started += 1;
+                    if started == WATCHED_FILES.len() {
+                        return true;
+                    }
+                    continue;
                 }
                 Err(TryRecvError::Empty) => {
                     println!("Received nothing on the start_watching channel. Returning ().");